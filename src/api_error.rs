@@ -0,0 +1,238 @@
+//! Structured error bodies returned by the API
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Error details parsed from a 4xx response body
+///
+/// Understands per-field errors such as
+/// `{"errors": {"order_products[0].product_code": ["not found"]}}`, lists of
+/// `{"field", "message", "code"}` objects and top-level `message`/`code` or
+/// `error` members. The raw body is always kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ApiErrorBody {
+    /// Overall error message
+    pub message: Option<String>,
+    /// Machine-readable error code
+    pub code: Option<String>,
+    /// Messages per field, keyed by the path the server reported
+    pub errors: BTreeMap<String, Vec<String>>,
+    /// The body as received
+    pub raw: String,
+}
+
+impl ApiErrorBody {
+    /// Parse a response body, keeping only the raw text if it is not JSON
+    pub fn parse(raw: impl Into<String>) -> Self {
+        let raw = raw.into();
+        let mut body = Self::default();
+        if let Ok(Value::Object(members)) = serde_json::from_str::<Value>(&raw) {
+            body.message = members
+                .get("message")
+                .or_else(|| members.get("error_description"))
+                .and_then(text);
+            body.code = members.get("code").and_then(text);
+            match members.get("error") {
+                Some(Value::Object(error)) => {
+                    body.message = body.message.or_else(|| error.get("message").and_then(text));
+                    body.code = body.code.or_else(|| error.get("code").and_then(text));
+                }
+                Some(error) => body.message = body.message.or_else(|| text(error)),
+                None => {}
+            }
+            if let Some(errors) = members.get("errors") {
+                body.collect_errors(errors);
+            }
+        }
+        body.raw = raw;
+        body
+    }
+    
+    /// Messages reported for `field`
+    pub fn field_errors(&self, field: &str) -> &[String] {
+        self.errors.get(field).map_or(&[], Vec::as_slice)
+    }
+    
+    /// Whether the server reported any per-field errors
+    pub fn has_field_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+    
+    /// The error code, if the server sent one
+    pub fn error_code(&self) -> Option<ApiErrorCode> {
+        self.code.as_deref().map(ApiErrorCode::from)
+    }
+    
+    fn collect_errors(&mut self, errors: &Value) {
+        match errors {
+            Value::Object(fields) => {
+                for (field, messages) in fields {
+                    let messages: Vec<String> = match messages {
+                        Value::Array(messages) => messages.iter().filter_map(text).collect(),
+                        message => text(message).into_iter().collect(),
+                    };
+                    self.errors.entry(field.clone()).or_default().extend(messages);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    let Some(message) = item.get("message").and_then(text).or_else(|| text(item)) else {
+                        continue;
+                    };
+                    match item.get("field").and_then(text) {
+                        Some(field) => self.errors.entry(field).or_default().push(message),
+                        None => self.message = self.message.take().or(Some(message)),
+                    }
+                    if self.code.is_none() {
+                        self.code = item.get("code").and_then(text);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl From<&str> for ApiErrorBody {
+    fn from(raw: &str) -> Self {
+        Self::parse(raw)
+    }
+}
+
+impl From<String> for ApiErrorBody {
+    fn from(raw: String) -> Self {
+        Self::parse(raw)
+    }
+}
+
+impl fmt::Display for ApiErrorBody {
+    /// The message, else the field errors, else the raw body
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(message) = &self.message {
+            return f.write_str(message);
+        }
+        if self.errors.is_empty() {
+            return f.write_str(&self.raw);
+        }
+        let fields: Vec<String> = self
+            .errors
+            .iter()
+            .map(|(field, messages)| format!("{}: {}", field, messages.join(", ")))
+            .collect();
+        f.write_str(&fields.join("; "))
+    }
+}
+
+/// Machine error codes documented by the API
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ApiErrorCode {
+    /// `PRODUCT_NOT_FOUND`: a product code does not exist
+    ProductNotFound,
+    /// `INSUFFICIENT_STOCK`: not enough stock for an ordered quantity
+    InsufficientStock,
+    /// `CREDIT_LIMIT_EXCEEDED`: the order would exceed the account's credit limit
+    CreditLimitExceeded,
+    /// `DUPLICATE_ORDER_REFERENCE`: the `customer_order_reference` was already used
+    DuplicateOrderReference,
+    /// A code this version of the client does not know
+    Unknown(String),
+}
+
+impl ApiErrorCode {
+    /// The code as sent by the server
+    pub fn as_str(&self) -> &str {
+        match self {
+            ApiErrorCode::ProductNotFound => "PRODUCT_NOT_FOUND",
+            ApiErrorCode::InsufficientStock => "INSUFFICIENT_STOCK",
+            ApiErrorCode::CreditLimitExceeded => "CREDIT_LIMIT_EXCEEDED",
+            ApiErrorCode::DuplicateOrderReference => "DUPLICATE_ORDER_REFERENCE",
+            ApiErrorCode::Unknown(code) => code,
+        }
+    }
+}
+
+impl From<&str> for ApiErrorCode {
+    /// Known codes are matched ignoring case
+    fn from(code: &str) -> Self {
+        [
+            ApiErrorCode::ProductNotFound,
+            ApiErrorCode::InsufficientStock,
+            ApiErrorCode::CreditLimitExceeded,
+            ApiErrorCode::DuplicateOrderReference,
+        ]
+        .into_iter()
+        .find(|known| known.as_str().eq_ignore_ascii_case(code))
+        .unwrap_or_else(|| ApiErrorCode::Unknown(code.to_string()))
+    }
+}
+
+impl fmt::Display for ApiErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A string, or a number rendered as one
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_field_errors() {
+        let body = ApiErrorBody::parse(
+            r#"{"errors": {"order_products[0].product_code": ["not found"], "customer_order_reference": "too long"}}"#,
+        );
+        assert_eq!(body.field_errors("order_products[0].product_code"), ["not found"]);
+        assert_eq!(body.field_errors("customer_order_reference"), ["too long"]);
+        assert!(body.field_errors("quantity").is_empty());
+        assert_eq!(
+            body.to_string(),
+            "customer_order_reference: too long; order_products[0].product_code: not found"
+        );
+    }
+    
+    #[test]
+    fn test_message_and_code() {
+        let body = ApiErrorBody::parse(
+            r#"{"message": "Validation failed", "code": 1042, "errors": [{"field": "quantity", "message": "must be positive"}]}"#,
+        );
+        assert_eq!(body.message.as_deref(), Some("Validation failed"));
+        assert_eq!(body.code.as_deref(), Some("1042"));
+        assert_eq!(body.field_errors("quantity"), ["must be positive"]);
+        assert_eq!(body.to_string(), "Validation failed");
+        
+        let nested = ApiErrorBody::parse(r#"{"error": {"message": "Unknown product", "code": "PRODUCT_NOT_FOUND"}}"#);
+        assert_eq!(nested.code.as_deref(), Some("PRODUCT_NOT_FOUND"));
+        assert_eq!(nested.message.as_deref(), Some("Unknown product"));
+    }
+    
+    #[test]
+    fn test_error_codes() {
+        let body = ApiErrorBody::parse(r#"{"code": "INSUFFICIENT_STOCK", "message": "Only 3 left"}"#);
+        assert_eq!(body.error_code(), Some(ApiErrorCode::InsufficientStock));
+        assert_eq!(ApiErrorCode::from("credit_limit_exceeded"), ApiErrorCode::CreditLimitExceeded);
+        assert_eq!(ApiErrorCode::from("ACCOUNT_ON_HOLD"), ApiErrorCode::Unknown("ACCOUNT_ON_HOLD".to_string()));
+        assert_eq!(ApiErrorCode::Unknown("ACCOUNT_ON_HOLD".to_string()).to_string(), "ACCOUNT_ON_HOLD");
+        assert_eq!(ApiErrorBody::parse("Bad Gateway").error_code(), None);
+    }
+    
+    #[test]
+    fn test_plain_text() {
+        let body = ApiErrorBody::parse("Bearer abc123");
+        assert_eq!(body.message, None);
+        assert!(!body.has_field_errors());
+        assert_eq!(body.to_string(), "Bearer abc123");
+        assert_eq!(body.raw, "Bearer abc123");
+    }
+}
@@ -0,0 +1,350 @@
+//! Audit trail of every call sent to the API
+
+use crate::error::{Error, Result};
+use crate::options::Endpoint;
+use crate::redact;
+use crate::response::ApiResponse;
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime};
+
+/// Evidence of a single call, written once it has succeeded or finally failed
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AuditRecord {
+    /// When the call started
+    pub timestamp: SystemTime,
+    /// The endpoint called
+    pub endpoint: Endpoint,
+    /// HTTP method
+    pub method: Method,
+    /// Request URL
+    pub url: String,
+    /// URL relative to the client's base URL, or the full URL for other origins
+    pub path: String,
+    /// Id sent in the `X-Request-Id` header
+    pub request_id: String,
+    /// Id the server reported for the request
+    pub server_request_id: Option<String>,
+    /// Hex SHA-256 of the request body as sent, unset for streaming bodies
+    pub body_sha256: Option<String>,
+    /// JSON request body with credentials and personal data redacted
+    pub body: Option<String>,
+    /// `Content-Type` of the request body
+    pub content_type: Option<String>,
+    /// Request body exactly as sent, needed to [replay](crate::replay) the call
+    ///
+    /// Not redacted, so only store it where credentials and personal data
+    /// may be kept; see [`AuditFile::with_raw_bodies`].
+    pub raw_body: Option<Vec<u8>>,
+    /// Status of the last response, if one was received
+    pub status: Option<StatusCode>,
+    /// Error the call failed with, redacted like the body
+    pub error: Option<String>,
+    /// Time from the start of the first attempt to the final outcome
+    pub duration: Duration,
+}
+
+impl AuditRecord {
+    /// Record the request side of a call about to be sent
+    pub(crate) fn request(
+        timestamp: SystemTime,
+        endpoint: Endpoint,
+        method: &Method,
+        request: Option<&reqwest::Request>,
+        base_url: &str,
+        request_id: &str,
+    ) -> Self {
+        let body = request
+            .and_then(|request| request.body())
+            .and_then(|body| body.as_bytes());
+        let url = request.map_or_else(|| String::from("<streaming request>"), |request| request.url().to_string());
+        Self {
+            timestamp,
+            endpoint,
+            method: method.clone(),
+            path: url.strip_prefix(base_url).unwrap_or(&url).to_string(),
+            url,
+            request_id: request_id.to_string(),
+            server_request_id: None,
+            body_sha256: body.map(|body| hex(&Sha256::digest(body))),
+            body: body.and_then(|body| {
+                let mut value = serde_json::from_slice(body).ok()?;
+                redact::redact_json(&mut value);
+                Some(value.to_string())
+            }),
+            content_type: request
+                .and_then(|request| request.headers().get(CONTENT_TYPE))
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            raw_body: body.map(<[u8]>::to_vec),
+            status: None,
+            error: None,
+            duration: Duration::ZERO,
+        }
+    }
+    
+    /// Fill in the outcome of the call
+    pub(crate) fn complete<T>(mut self, result: &Result<ApiResponse<T>>, duration: Duration) -> Self {
+        match result {
+            Ok(response) => {
+                self.server_request_id = response.server_request_id.clone();
+                self.status = response.status;
+            }
+            Err(e) => {
+                self.server_request_id = e.server_request_id().map(str::to_string);
+                self.status = e.status_code().and_then(|code| StatusCode::from_u16(code).ok());
+                self.error = Some(redact::redact_text(&e.message()));
+            }
+        }
+        self.duration = duration;
+        self
+    }
+    
+    /// The record as a JSON object, without the raw body
+    ///
+    /// The timestamp is given in milliseconds since the Unix epoch.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.stored(false).to_json()
+    }
+    
+    /// Read a record written by [`AuditRecord::to_json`] or [`AuditFile`]
+    pub fn from_json(value: serde_json::Value) -> Result<Self> {
+        let stored: StoredRecord = serde_json::from_value(value)?;
+        let endpoint = Endpoint::ALL
+            .iter()
+            .copied()
+            .find(|endpoint| endpoint.as_str() == stored.endpoint)
+            .ok_or_else(|| Error::Config(format!("Unknown endpoint in audit record: {}", stored.endpoint)))?;
+        let method = Method::from_bytes(stored.method.as_bytes())
+            .map_err(|_| Error::Config(format!("Invalid method in audit record: {}", stored.method)))?;
+        
+        Ok(Self {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(stored.timestamp_ms),
+            endpoint,
+            method,
+            url: stored.url,
+            path: stored.path,
+            request_id: stored.request_id,
+            server_request_id: stored.server_request_id,
+            body_sha256: stored.body_sha256,
+            body: stored.body,
+            content_type: stored.content_type,
+            raw_body: stored.raw_body.map(String::into_bytes),
+            status: stored.status.and_then(|code| StatusCode::from_u16(code).ok()),
+            error: stored.error,
+            duration: Duration::from_millis(stored.duration_ms),
+        })
+    }
+    
+    /// The serialized form, optionally with the raw body
+    fn stored(&self, raw_body: bool) -> StoredRecord {
+        let timestamp = self
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        StoredRecord {
+            timestamp_ms: timestamp.as_millis() as u64,
+            endpoint: self.endpoint.as_str().to_string(),
+            method: self.method.to_string(),
+            url: self.url.clone(),
+            path: self.path.clone(),
+            request_id: self.request_id.clone(),
+            server_request_id: self.server_request_id.clone(),
+            body_sha256: self.body_sha256.clone(),
+            body: self.body.clone(),
+            content_type: self.content_type.clone(),
+            raw_body: self
+                .raw_body
+                .as_ref()
+                .filter(|_| raw_body)
+                .map(|body| String::from_utf8_lossy(body).into_owned()),
+            status: self.status.map(|status| status.as_u16()),
+            error: self.error.clone(),
+            duration_ms: self.duration.as_millis() as u64,
+        }
+    }
+}
+
+/// JSON layout of an [`AuditRecord`]
+#[derive(Serialize, Deserialize)]
+struct StoredRecord {
+    timestamp_ms: u64,
+    endpoint: String,
+    method: String,
+    url: String,
+    path: String,
+    request_id: String,
+    server_request_id: Option<String>,
+    body_sha256: Option<String>,
+    body: Option<String>,
+    content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw_body: Option<String>,
+    status: Option<u16>,
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+impl StoredRecord {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// Receives an [`AuditRecord`] for every call the client makes
+///
+/// The client waits for the sink before returning the result of a call, so
+/// the evidence is stored before the caller acts on it. Storage failures are
+/// up to the sink to handle; the call's result is returned either way.
+///
+/// ```
+/// use ecommerce_api_client::async_trait;
+/// use ecommerce_api_client::audit::{AuditRecord, AuditSink};
+///
+/// struct Stdout;
+///
+/// #[async_trait]
+/// impl AuditSink for Stdout {
+///     async fn record(&self, record: AuditRecord) {
+///         println!("{}", record.to_json());
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait AuditSink: Send + Sync + 'static {
+    /// Store the record of a finished call
+    async fn record(&self, record: AuditRecord);
+}
+
+/// Appends records as JSON lines to a file
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct AuditFile {
+    file: std::sync::Mutex<std::fs::File>,
+    raw_bodies: bool,
+    failures: std::sync::atomic::AtomicU64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AuditFile {
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: std::sync::Mutex::new(file),
+            raw_bodies: false,
+            failures: Default::default(),
+        })
+    }
+    
+    /// Also write unredacted request bodies, so the calls can be replayed
+    pub fn with_raw_bodies(mut self) -> Self {
+        self.raw_bodies = true;
+        self
+    }
+    
+    /// Number of records that could not be written
+    pub fn failures(&self) -> u64 {
+        self.failures.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl AuditSink for AuditFile {
+    async fn record(&self, record: AuditRecord) {
+        use std::io::Write;
+        
+        let line = format!("{}\n", record.stored(self.raw_bodies).to_json());
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if file.write_all(line.as_bytes()).and_then(|_| file.flush()).is_err() {
+            self.failures.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+/// Lowercase hex encoding of a digest
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    
+    fn record() -> AuditRecord {
+        let request = reqwest::Client::new()
+            .post("https://api.example.com/api_customer/orders")
+            .body(r#"{"addressbook":{"country":"US","email":"jane@example.com"}}"#)
+            .build()
+            .unwrap();
+        AuditRecord::request(
+            SystemTime::UNIX_EPOCH,
+            Endpoint::CreateOrder,
+            &Method::POST,
+            Some(&request),
+            "https://api.example.com",
+            "req-1",
+        )
+    }
+    
+    #[test]
+    fn test_record_request() {
+        let record = record();
+        assert_eq!(record.body.as_deref(), Some(r#"{"addressbook":{"country":"US","email":"[REDACTED]"}}"#));
+        assert_eq!(record.body_sha256.as_ref().unwrap().len(), 64);
+        assert_eq!(record.url, "https://api.example.com/api_customer/orders");
+        assert_eq!(record.path, "/api_customer/orders");
+        assert!(record.to_json().get("raw_body").is_none());
+        
+        let failed = record.complete::<()>(
+            &Err(Error::NotFound("missing".to_string())
+                .with_context(|context| context.server_request_id = Some("srv-42".to_string()))),
+            Duration::from_millis(40),
+        );
+        let json = failed.to_json();
+        assert_eq!(json["status"], 404);
+        assert_eq!(json["server_request_id"], "srv-42");
+        assert_eq!(json["endpoint"], "create_order");
+        assert_eq!(json["duration_ms"], 40);
+    }
+    
+    #[tokio::test]
+    async fn test_audit_file() {
+        let path = std::env::temp_dir().join(format!("ecommerce-api-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = AuditFile::open(&path).unwrap();
+        
+        let mut response = ApiResponse::new(());
+        response.status = Some(StatusCode::CREATED);
+        sink.record(record().complete(&Ok(response), Duration::from_millis(5))).await;
+        sink.record(record()).await;
+        
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["status"], 201);
+        assert_eq!(lines[0]["request_id"], "req-1");
+        assert_eq!(sink.failures(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_round_trip() {
+        let record = record();
+        let stored = record.stored(true).to_json();
+        assert_eq!(stored["raw_body"], r#"{"addressbook":{"country":"US","email":"jane@example.com"}}"#);
+        
+        let read = AuditRecord::from_json(stored).unwrap();
+        assert_eq!(read.endpoint, Endpoint::CreateOrder);
+        assert_eq!(read.method, Method::POST);
+        assert_eq!(read.raw_body, record.raw_body);
+        assert_eq!(read.timestamp, record.timestamp);
+        assert!(AuditRecord::from_json(serde_json::json!({"endpoint": "create_order"})).is_err());
+    }
+}
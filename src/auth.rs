@@ -0,0 +1,498 @@
+//! Authentication schemes for the API client
+
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use crate::runtime::Instant;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use zeroize::Zeroizing;
+
+/// Tokens are refreshed this long before they actually expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// How requests are authenticated against the API
+///
+/// Secrets are redacted from the `Debug` output.
+#[derive(Clone)]
+pub enum AuthScheme {
+    /// HTTP Basic authentication with account email and API token
+    Basic { email: String, token: SecretString },
+    /// Static bearer token
+    Bearer(SecretString),
+    /// API key sent in a custom header such as `X-Api-Key`
+    ApiKey { header: String, key: SecretString },
+    /// OAuth2 client-credentials flow with a cached bearer token
+    OAuth2(OAuth2),
+    /// Session cookie obtained by logging in with email and password
+    Session(Session),
+    /// Credentials fetched from a provider before each request
+    Provider(Arc<dyn CredentialProvider>),
+    /// Locally signed JWT sent as a bearer token
+    #[cfg(feature = "jwt")]
+    Jwt(crate::jwt::JwtAuth),
+}
+
+/// Credentials supplied by a [`CredentialProvider`]
+///
+/// Secrets are redacted from the `Debug` output.
+#[derive(Clone)]
+pub enum Credentials {
+    /// HTTP Basic authentication with account email and API token
+    Basic { email: String, token: SecretString },
+    /// Bearer token
+    Bearer(SecretString),
+    /// API key sent in a custom header such as `X-Api-Key`
+    ApiKey { header: String, key: SecretString },
+}
+
+impl Credentials {
+    /// Compute the authentication header for these credentials
+    fn header(&self) -> Result<(HeaderName, HeaderValue)> {
+        match self {
+            Credentials::Basic { email, token } => {
+                let auth_string = Zeroizing::new(format!("{}:{}", email, token.expose_secret()));
+                let encoded = Zeroizing::new(STANDARD.encode(auth_string.as_bytes()));
+                sensitive_header(AUTHORIZATION, &format!("Basic {}", encoded.as_str()))
+            }
+            Credentials::Bearer(token) => {
+                sensitive_header(AUTHORIZATION, &format!("Bearer {}", token.expose_secret()))
+            }
+            Credentials::ApiKey { header, key } => {
+                let name = HeaderName::from_bytes(header.as_bytes())
+                    .map_err(|e| Error::InvalidCredentials(format!("Invalid API key header name: {}", e)))?;
+                sensitive_header(name, key.expose_secret())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Basic { email, .. } => f
+                .debug_struct("Basic")
+                .field("email", email)
+                .field("token", &"<redacted>")
+                .finish(),
+            Credentials::Bearer(_) => f.debug_tuple("Bearer").field(&"<redacted>").finish(),
+            Credentials::ApiKey { header, .. } => f
+                .debug_struct("ApiKey")
+                .field("header", header)
+                .field("key", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// Source of credentials consulted before each request
+///
+/// Implement this to plug in secret managers or token rotation instead of
+/// baking a static token into the client at construction time.
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    /// Get the credentials to use for the next request
+    async fn credentials(&self) -> Result<Credentials>;
+}
+
+/// Build a header value that is marked sensitive
+///
+/// The formatted value is zeroized once the header has been built.
+fn sensitive_header(name: HeaderName, value: &str) -> Result<(HeaderName, HeaderValue)> {
+    let value = Zeroizing::new(value.to_string());
+    let mut value = HeaderValue::from_str(&value)
+        .map_err(|e| Error::InvalidCredentials(format!("Invalid auth header: {}", e)))?;
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+impl AuthScheme {
+    /// Compute the authentication header for a request
+    ///
+    /// Session authentication is carried by the cookie jar and adds no header.
+    pub(crate) async fn header(&self, http_client: &reqwest::Client) -> Result<Option<(HeaderName, HeaderValue)>> {
+        let credentials = match self {
+            AuthScheme::Basic { email, token } => Credentials::Basic {
+                email: email.clone(),
+                token: token.clone(),
+            },
+            AuthScheme::Bearer(token) => Credentials::Bearer(token.clone()),
+            AuthScheme::ApiKey { header, key } => Credentials::ApiKey {
+                header: header.clone(),
+                key: key.clone(),
+            },
+            AuthScheme::OAuth2(oauth) => Credentials::Bearer(oauth.token(http_client).await?),
+            AuthScheme::Session(_) => return Ok(None),
+            AuthScheme::Provider(provider) => provider.credentials().await?,
+            #[cfg(feature = "jwt")]
+            AuthScheme::Jwt(jwt) => Credentials::Bearer(jwt.token()?.into()),
+        };
+        
+        credentials.header().map(Some)
+    }
+}
+
+impl fmt::Debug for AuthScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthScheme::Basic { email, .. } => f
+                .debug_struct("Basic")
+                .field("email", email)
+                .field("token", &"<redacted>")
+                .finish(),
+            AuthScheme::Bearer(_) => f.debug_tuple("Bearer").field(&"<redacted>").finish(),
+            AuthScheme::ApiKey { header, .. } => f
+                .debug_struct("ApiKey")
+                .field("header", header)
+                .field("key", &"<redacted>")
+                .finish(),
+            AuthScheme::OAuth2(oauth) => f.debug_tuple("OAuth2").field(oauth).finish(),
+            AuthScheme::Session(session) => f.debug_tuple("Session").field(session).finish(),
+            AuthScheme::Provider(_) => f.debug_tuple("Provider").field(&"<provider>").finish(),
+            #[cfg(feature = "jwt")]
+            AuthScheme::Jwt(jwt) => f.debug_tuple("Jwt").field(jwt).finish(),
+        }
+    }
+}
+
+/// OAuth2 client-credentials authentication
+///
+/// The access token is fetched lazily on first use, cached until shortly
+/// before it expires and shared between clones of the client.
+#[derive(Clone)]
+pub struct OAuth2 {
+    token_url: String,
+    client_id: String,
+    client_secret: SecretString,
+    scopes: Vec<String>,
+    cache: Arc<Mutex<Option<CachedToken>>>,
+}
+
+/// Access token cached by the OAuth2 flow
+#[derive(Debug, Clone)]
+struct CachedToken {
+    access_token: SecretString,
+    expires_at: Option<Instant>,
+}
+
+impl CachedToken {
+    fn is_fresh(&self) -> bool {
+        self.expires_at
+            .map(|expires_at| Instant::now() + EXPIRY_MARGIN < expires_at)
+            .unwrap_or(true)
+    }
+}
+
+/// Token endpoint response
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+impl OAuth2 {
+    /// Create a client-credentials flow against the given token endpoint
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: SecretString::new(client_secret.into()),
+            scopes: Vec::new(),
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+    
+    /// Request the given scopes with each token
+    pub fn with_scopes<S: Into<String>>(mut self, scopes: impl IntoIterator<Item = S>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    /// Get a valid access token, fetching a new one if needed
+    pub(crate) async fn token(&self, http_client: &reqwest::Client) -> Result<SecretString> {
+        // Hold the lock while fetching so concurrent requests share one refresh
+        let mut cache = self.cache.lock().await;
+        if let Some(token) = cache.as_ref().filter(|token| token.is_fresh()) {
+            return Ok(token.access_token.clone());
+        }
+        
+        let token = self.fetch(http_client).await?;
+        let access_token = token.access_token.clone();
+        *cache = Some(token);
+        Ok(access_token)
+    }
+    
+    /// Drop the cached token so the next request fetches a new one
+    pub(crate) async fn invalidate(&self) {
+        *self.cache.lock().await = None;
+    }
+    
+    /// Request a new token from the token endpoint
+    async fn fetch(&self, http_client: &reqwest::Client) -> Result<CachedToken> {
+        let mut form = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            form.push(("scope", self.scopes.join(" ")));
+        }
+        
+        let response = http_client
+            .post(&self.token_url)
+            .basic_auth(&self.client_id, Some(self.client_secret.expose_secret()))
+            .form(&form)
+            .send()
+            .await
+            .map_err(Error::from_send)?;
+        
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::Unauthorized(format!(
+                "OAuth2 token request failed with status {}: {}",
+                status.as_u16(),
+                error_text
+            )));
+        }
+        
+        let token = response
+            .json::<TokenResponse>()
+            .await
+            .map_err(Error::Http)?;
+        
+        Ok(CachedToken {
+            access_token: SecretString::new(token.access_token),
+            expires_at: token
+                .expires_in
+                .map(|seconds| Instant::now() + Duration::from_secs(seconds)),
+        })
+    }
+}
+
+impl fmt::Debug for OAuth2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+/// Session login with account email and password
+///
+/// The client logs in before the first authenticated request and again
+/// whenever the session expires. Login state is shared between clones of
+/// the client, which also share the cookie jar.
+#[derive(Clone)]
+pub struct Session {
+    email: String,
+    password: SecretString,
+    logged_in: Arc<Mutex<bool>>,
+}
+
+/// Payload of the login endpoint
+#[derive(Serialize)]
+pub(crate) struct LoginRequest<'a> {
+    email: &'a str,
+    password: &'a str,
+}
+
+impl Session {
+    /// Create session credentials
+    pub fn new(email: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            email: email.into(),
+            password: SecretString::new(password.into()),
+            logged_in: Arc::new(Mutex::new(false)),
+        }
+    }
+    
+    /// Payload sent to the login endpoint
+    pub(crate) fn login_request(&self) -> LoginRequest<'_> {
+        LoginRequest {
+            email: &self.email,
+            password: self.password.expose_secret(),
+        }
+    }
+    
+    /// Lock the login state; held while logging in so concurrent requests
+    /// share a single login
+    pub(crate) async fn state(&self) -> tokio::sync::MutexGuard<'_, bool> {
+        self.logged_in.lock().await
+    }
+    
+    /// Mark the session as expired so the next request logs in again
+    pub(crate) async fn expire(&self) {
+        *self.logged_in.lock().await = false;
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("email", &self.email)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_headers() {
+        let http_client = reqwest::Client::new();
+        
+        let basic = AuthScheme::Basic {
+            email: "user@example.com".to_string(),
+            token: "token".to_string().into(),
+        };
+        let (name, value) = basic.header(&http_client).await.unwrap().unwrap();
+        assert_eq!(name, AUTHORIZATION);
+        assert_eq!(value, "Basic dXNlckBleGFtcGxlLmNvbTp0b2tlbg==");
+        assert!(value.is_sensitive());
+        
+        let bearer = AuthScheme::Bearer("abc123".to_string().into());
+        let (name, value) = bearer.header(&http_client).await.unwrap().unwrap();
+        assert_eq!(name, AUTHORIZATION);
+        assert_eq!(value, "Bearer abc123");
+        
+        let api_key = AuthScheme::ApiKey {
+            header: "X-Api-Key".to_string(),
+            key: "k-123".to_string().into(),
+        };
+        let (name, value) = api_key.header(&http_client).await.unwrap().unwrap();
+        assert_eq!(name, "x-api-key");
+        assert_eq!(value, "k-123");
+        
+        let invalid = AuthScheme::ApiKey {
+            header: "X Api Key".to_string(),
+            key: "k-123".to_string().into(),
+        };
+        assert!(matches!(invalid.header(&http_client).await.unwrap_err(), Error::InvalidCredentials(_)));
+        
+        let session = AuthScheme::Session(Session::new("user@example.com", "password"));
+        assert!(session.header(&http_client).await.unwrap().is_none());
+    }
+    
+    struct RotatingProvider {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+    
+    #[async_trait]
+    impl CredentialProvider for RotatingProvider {
+        async fn credentials(&self) -> Result<Credentials> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(Credentials::Bearer(format!("token-{}", call).into()))
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_credential_provider_called_per_request() {
+        let http_client = reqwest::Client::new();
+        let scheme = AuthScheme::Provider(Arc::new(RotatingProvider {
+            calls: Default::default(),
+        }));
+        
+        let (_, first) = scheme.header(&http_client).await.unwrap().unwrap();
+        let (_, second) = scheme.header(&http_client).await.unwrap().unwrap();
+        assert_eq!(first, "Bearer token-0");
+        assert_eq!(second, "Bearer token-1");
+        assert!(!format!("{:?}", scheme).contains("token"));
+    }
+    
+    #[test]
+    fn test_auth_scheme_debug_redacts_secrets() {
+        let schemes = [
+            AuthScheme::Basic {
+                email: "user@example.com".to_string(),
+                token: "s3cr3t".to_string().into(),
+            },
+            AuthScheme::Bearer("s3cr3t".to_string().into()),
+            AuthScheme::ApiKey {
+                header: "X-Api-Key".to_string(),
+                key: "s3cr3t".to_string().into(),
+            },
+            AuthScheme::Session(Session::new("user@example.com", "s3cr3t")),
+        ];
+        
+        for scheme in schemes {
+            let debug = format!("{:?}", scheme);
+            assert!(!debug.contains("s3cr3t"), "{}", debug);
+            assert!(debug.contains("<redacted>"));
+        }
+    }
+    
+    #[test]
+    fn test_cached_token_freshness() {
+        let fresh = CachedToken {
+            access_token: "abc".to_string().into(),
+            expires_at: Some(Instant::now() + Duration::from_secs(3600)),
+        };
+        let expiring = CachedToken {
+            access_token: "abc".to_string().into(),
+            expires_at: Some(Instant::now() + Duration::from_secs(5)),
+        };
+        let unbounded = CachedToken {
+            access_token: "abc".to_string().into(),
+            expires_at: None,
+        };
+        
+        assert!(fresh.is_fresh());
+        assert!(!expiring.is_fresh());
+        assert!(unbounded.is_fresh());
+    }
+    
+    #[test]
+    fn test_oauth2_debug_redacts_secret() {
+        let oauth = OAuth2::new("https://auth.example.com/token", "client", "s3cr3t")
+            .with_scopes(["orders:write"]);
+        let debug = format!("{:?}", oauth);
+        
+        assert!(debug.contains("client"));
+        assert!(debug.contains("orders:write"));
+        assert!(!debug.contains("s3cr3t"));
+    }
+    
+    #[tokio::test]
+    async fn test_session_state() {
+        let session = Session::new("user@example.com", "password");
+        assert!(!*session.state().await);
+        
+        *session.state().await = true;
+        assert!(*session.clone().state().await);
+        
+        session.expire().await;
+        assert!(!*session.state().await);
+        
+        let payload = serde_json::to_value(session.login_request()).unwrap();
+        assert_eq!(payload["email"], "user@example.com");
+        assert_eq!(payload["password"], "password");
+    }
+    
+    #[tokio::test]
+    async fn test_oauth2_uses_cached_token() {
+        let oauth = OAuth2::new("http://127.0.0.1:9/token", "client", "secret");
+        *oauth.cache.lock().await = Some(CachedToken {
+            access_token: "cached".to_string().into(),
+            expires_at: None,
+        });
+        
+        let token = oauth.token(&reqwest::Client::new()).await.unwrap();
+        assert_eq!(token.expose_secret(), "cached");
+        
+        oauth.invalidate().await;
+        assert!(oauth.cache.lock().await.is_none());
+    }
+}
@@ -0,0 +1,217 @@
+//! Synchronous client for programs without an async runtime
+//!
+//! Requires the `blocking` feature. Each [`Client`] drives the async
+//! [`crate::Client`] on its own single-threaded tokio runtime, so retries,
+//! authentication and every other setting behave exactly as they do in
+//! async code.
+//!
+//! ```no_run
+//! use ecommerce_api_client::blocking::Client;
+//! use ecommerce_api_client::types::CreateOrderRequest;
+//!
+//! # fn main() -> ecommerce_api_client::Result<()> {
+//! let client = Client::from_env()?;
+//! let order = client.create_order(CreateOrderRequest::default())?;
+//! println!("Created order {:?}", order);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::builder::ClientBuilder;
+use crate::bulk::BulkReport;
+use crate::error::{Error, Result};
+use crate::filter::OrderFilter;
+use crate::options::RequestOptions;
+use crate::pagination::{Page, PageRequest};
+use crate::response::ApiResponse;
+use crate::shutdown::ShutdownReport;
+use crate::types::{CreateOrderRequest, CreateOrderResponse, Link, Order, OrderId};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Blocking counterpart of [`crate::Client`]
+///
+/// Clones share the runtime and the underlying client. Calls block the
+/// current thread and must not be made from within an async runtime, where
+/// they panic.
+#[derive(Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Create a new client with the specified base URL
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::from_async(crate::Client::new(base_url)?)
+    }
+    
+    /// Create a client configured from `ECOMMERCE_API_*` environment variables
+    ///
+    /// See [`ClientBuilder::from_env`].
+    pub fn from_env() -> Result<Self> {
+        Self::from_async(crate::Client::from_env()?)
+    }
+    
+    /// Create a client from a TOML or YAML configuration file
+    ///
+    /// See [`crate::Client::from_config_file`].
+    pub fn from_config_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::from_async(crate::Client::from_config_file(path)?)
+    }
+    
+    /// Create a builder; finish it with [`ClientBuilder::build_blocking`]
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(base_url)
+    }
+    
+    /// Wrap a configured async client
+    pub fn from_async(inner: crate::Client) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Config(format!("Cannot start runtime: {}", e)))?;
+        
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+    
+    /// The wrapped async client, e.g. to read its circuit or rate limit state
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+    
+    /// Create a new order
+    pub fn create_order(&self, request: CreateOrderRequest) -> Result<CreateOrderResponse> {
+        self.block_on(self.inner.create_order(request))
+    }
+    
+    /// Create a new order, returning the response metadata alongside the order
+    pub fn create_order_with_meta(&self, request: CreateOrderRequest) -> Result<ApiResponse<CreateOrderResponse>> {
+        self.block_on(self.inner.create_order_with_meta(request))
+    }
+    
+    /// Create a new order with per-request options such as an idempotency key
+    pub fn create_order_with(
+        &self,
+        request: CreateOrderRequest,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse<CreateOrderResponse>> {
+        self.block_on(self.inner.create_order_with(request, options))
+    }
+    
+    /// Fetch the order with `order_id`
+    pub fn get_order(&self, order_id: &OrderId) -> Result<Order> {
+        self.block_on(self.inner.get_order(order_id))
+    }
+    
+    /// Fetch one page of the orders matching `filter`
+    pub fn list_orders(&self, filter: &OrderFilter, page: &PageRequest) -> Result<Page<Order>> {
+        self.block_on(self.inner.list_orders(filter, page))
+    }
+    
+    /// Cancel an order, returning it in its cancelled state
+    pub fn cancel_order(&self, order_id: &OrderId) -> Result<Order> {
+        self.block_on(self.inner.cancel_order(order_id))
+    }
+    
+    /// Cancel many orders, several at a time
+    ///
+    /// See [`crate::Client::cancel_orders`].
+    pub fn cancel_orders(&self, order_ids: &[OrderId]) -> BulkReport<Order> {
+        self.block_on(self.inner.cancel_orders(order_ids))
+    }
+    
+    /// Log in and store the session cookie for subsequent requests
+    pub fn login(&self) -> Result<()> {
+        self.block_on(self.inner.login())
+    }
+    
+    /// Follow a hypermedia link returned by the API
+    pub fn follow<T: DeserializeOwned>(&self, link: &Link) -> Result<T> {
+        self.block_on(self.inner.follow(link))
+    }
+    
+    /// Stop accepting calls and wait up to `deadline` for in-flight ones
+    ///
+    /// See [`crate::Client::shutdown`].
+    pub fn shutdown(&self, deadline: Duration) -> ShutdownReport {
+        self.block_on(self.inner.shutdown(deadline))
+    }
+    
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    
+    #[test]
+    fn test_create_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = Client::builder(format!("http://{}", listener.local_addr().unwrap()))
+            .build_blocking()
+            .unwrap();
+        
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let read = socket.read(&mut request).unwrap();
+            socket
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8_lossy(&request[..read]).into_owned()
+        });
+        
+        let result = client.create_order(CreateOrderRequest::default());
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /api_customer/orders"));
+        assert!(matches!(result.unwrap_err().inner(), Error::NotFound(_)));
+    }
+    
+    #[test]
+    fn test_get_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = Client::new(format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let read = socket.read(&mut request).unwrap();
+            socket
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8_lossy(&request[..read]).into_owned()
+        });
+        
+        let result = client.get_order(&OrderId("7".to_string()));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /api_customer/orders/7 "));
+        assert!(matches!(result.unwrap_err().inner(), Error::NotFound(_)));
+    }
+    
+    #[test]
+    fn test_login_requires_session() {
+        let client = Client::new("https://api.example.com").unwrap();
+        assert!(matches!(client.login().unwrap_err(), Error::InvalidCredentials(_)));
+        assert!(format!("{:?}", client.clone()).contains("api.example.com"));
+    }
+}
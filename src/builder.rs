@@ -0,0 +1,1064 @@
+//! Builder for configuring a [`Client`]
+
+use crate::audit::AuditSink;
+use crate::auth::AuthScheme;
+use secrecy::SecretString;
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::client::{Client, DeprecationCallback, ErrorCallback};
+use crate::deprecation::DeprecationNotice;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::connector::{Connector, ConnectorTransport};
+#[cfg(unix)]
+use crate::connector::UnixSocket;
+use crate::envelope::Envelope;
+use crate::error::{Error, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::failover::{self, Routing};
+use crate::format::Format;
+use crate::hedge::HedgePolicy;
+use crate::idempotency::IdempotencyStore;
+use crate::metrics::MetricsObserver;
+use crate::options::{Endpoint, IdempotencyKeys};
+#[cfg(feature = "opentelemetry")]
+use crate::propagation::Propagation;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::proxy::ProxyConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::redirect::RedirectPolicy;
+use crate::retry::{RetryBudget, RetryPolicy};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::tls::{Certificate, CertificatePin, Identity, TlsBackend};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::{HttpTransport, Transport};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::header::USER_AGENT;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Environment variable holding the base URL
+pub const ENV_URL: &str = "ECOMMERCE_API_URL";
+/// Environment variable holding the account email for Basic auth
+pub const ENV_EMAIL: &str = "ECOMMERCE_API_EMAIL";
+/// Environment variable holding the API token
+pub const ENV_TOKEN: &str = "ECOMMERCE_API_TOKEN";
+/// Environment variable overriding the request timeout, in seconds
+pub const ENV_TIMEOUT: &str = "ECOMMERCE_API_TIMEOUT";
+/// Environment variable overriding the connect timeout, in seconds
+pub const ENV_CONNECT_TIMEOUT: &str = "ECOMMERCE_API_CONNECT_TIMEOUT";
+/// Environment variable holding a proxy URL for all requests
+pub const ENV_PROXY: &str = "ECOMMERCE_API_PROXY";
+/// Environment variable listing comma-separated hosts that bypass the proxy
+pub const ENV_NO_PROXY: &str = "ECOMMERCE_API_NO_PROXY";
+
+/// Default `User-Agent` header
+const DEFAULT_USER_AGENT: &str = concat!("ecommerce-api-client/", env!("CARGO_PKG_VERSION"));
+
+/// Builder for a [`Client`] with custom transport, resilience and auth settings
+///
+/// ```no_run
+/// # use ecommerce_api_client::{Client, retry};
+/// # use std::time::Duration;
+/// # fn main() -> ecommerce_api_client::Result<()> {
+/// let client = Client::builder("https://api.example.com")
+///     .timeout(Duration::from_secs(60))
+///     .user_agent("order-importer/2.1")
+///     .retry(retry::Fixed { max_attempts: 2, delay: Duration::from_secs(1) })
+///     .rate_limit(5.0, 10)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ClientBuilder {
+    base_url: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    fallback_urls: Vec<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    failover_cool_down: Duration,
+    #[cfg(not(target_arch = "wasm32"))]
+    routing: Routing,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeout: Duration,
+    #[cfg(not(target_arch = "wasm32"))]
+    connect_timeout: Duration,
+    user_agent: String,
+    headers: HeaderMap,
+    #[cfg(not(target_arch = "wasm32"))]
+    http2_prior_knowledge: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    http2_adaptive_window: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    tls_backend: TlsBackend,
+    #[cfg(not(target_arch = "wasm32"))]
+    identity: Option<Identity>,
+    #[cfg(not(target_arch = "wasm32"))]
+    root_certificates: Vec<Certificate>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pins: Vec<CertificatePin>,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxies: Vec<ProxyConfig>,
+    auth: Option<AuthScheme>,
+    envelope: Envelope,
+    format: Format,
+    retry: Option<Arc<dyn RetryPolicy>>,
+    retry_budget: Option<RetryBudget>,
+    rate_limit: Option<(f64, u32)>,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    hedging: Option<HedgePolicy>,
+    metrics: Option<Arc<dyn MetricsObserver>>,
+    audit: Option<Arc<dyn AuditSink>>,
+    on_error: Option<ErrorCallback>,
+    on_deprecation: Option<DeprecationCallback>,
+    deadline: Option<Duration>,
+    max_response_size: Option<u64>,
+    duplicate_guard: bool,
+    order_cache: Option<Duration>,
+    idempotency_keys: IdempotencyKeys,
+    idempotency_store: Option<Arc<dyn IdempotencyStore>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    redirect: RedirectPolicy,
+    #[cfg(feature = "opentelemetry")]
+    propagation: Propagation,
+    http_client: Option<reqwest::Client>,
+    #[cfg(not(target_arch = "wasm32"))]
+    transport: Option<Arc<dyn HttpTransport>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    endpoint_timeouts: Vec<(Endpoint, Duration)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    debug_capture: Option<usize>,
+}
+
+impl ClientBuilder {
+    /// Create a builder for the given base URL
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            #[cfg(not(target_arch = "wasm32"))]
+            fallback_urls: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            failover_cool_down: failover::DEFAULT_COOL_DOWN,
+            #[cfg(not(target_arch = "wasm32"))]
+            routing: Routing::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            timeout: Duration::from_secs(30),
+            #[cfg(not(target_arch = "wasm32"))]
+            connect_timeout: Duration::from_secs(10),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: HeaderMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            http2_prior_knowledge: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            http2_adaptive_window: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            tls_backend: TlsBackend::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            identity: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            root_certificates: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            pins: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            proxies: Vec::new(),
+            auth: None,
+            envelope: Envelope::default(),
+            format: Format::default(),
+            retry: None,
+            retry_budget: None,
+            rate_limit: None,
+            circuit_breaker: None,
+            hedging: None,
+            metrics: None,
+            audit: None,
+            on_error: None,
+            on_deprecation: None,
+            deadline: None,
+            max_response_size: None,
+            duplicate_guard: false,
+            order_cache: None,
+            idempotency_keys: IdempotencyKeys::default(),
+            idempotency_store: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            redirect: RedirectPolicy::default(),
+            #[cfg(feature = "opentelemetry")]
+            propagation: Propagation::default(),
+            http_client: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            transport: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            endpoint_timeouts: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            debug_capture: None,
+        }
+    }
+    
+    /// Create a builder configured from environment variables
+    ///
+    /// Reads the base URL from `ECOMMERCE_API_URL` and applies the overrides
+    /// described in [`ClientBuilder::env_overrides`].
+    pub fn from_env() -> Result<Self> {
+        let lookup = |name: &str| std::env::var(name).ok();
+        let base_url = lookup(ENV_URL)
+            .ok_or_else(|| Error::Config(format!("{} is not set", ENV_URL)))?;
+        
+        Self::new(base_url).env_overrides_from(lookup)
+    }
+    
+    /// Override settings from environment variables
+    ///
+    /// - `ECOMMERCE_API_URL`: base URL
+    /// - `ECOMMERCE_API_TOKEN`: Basic auth together with `ECOMMERCE_API_EMAIL`
+    ///   or the email already set, otherwise a bearer token
+    /// - `ECOMMERCE_API_TIMEOUT`, `ECOMMERCE_API_CONNECT_TIMEOUT`: seconds
+    /// - `ECOMMERCE_API_PROXY`: proxy URL for all requests, bypassed for the
+    ///   comma-separated hosts in `ECOMMERCE_API_NO_PROXY`
+    pub fn env_overrides(self) -> Result<Self> {
+        self.env_overrides_from(|name| std::env::var(name).ok())
+    }
+    
+    pub(crate) fn env_overrides_from(mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        if let Some(base_url) = lookup(ENV_URL) {
+            self.base_url = base_url;
+        }
+        
+        match (lookup(ENV_EMAIL), lookup(ENV_TOKEN)) {
+            (Some(email), Some(token)) => {
+                self.auth = Some(AuthScheme::Basic {
+                    email,
+                    token: SecretString::new(token),
+                });
+            }
+            (None, Some(token)) => {
+                let token = SecretString::new(token);
+                self.auth = Some(match self.auth.take() {
+                    Some(AuthScheme::Basic { email, .. }) => AuthScheme::Basic { email, token },
+                    _ => AuthScheme::Bearer(token),
+                });
+            }
+            (Some(_), None) => {
+                return Err(Error::Config(format!("{} is set without {}", ENV_EMAIL, ENV_TOKEN)));
+            }
+            (None, None) => {}
+        }
+        
+        // Timeouts and proxies are left to the host environment on wasm
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(timeout) = lookup(ENV_TIMEOUT) {
+                self.timeout = parse_seconds(ENV_TIMEOUT, &timeout)?;
+            }
+            if let Some(timeout) = lookup(ENV_CONNECT_TIMEOUT) {
+                self.connect_timeout = parse_seconds(ENV_CONNECT_TIMEOUT, &timeout)?;
+            }
+            if let Some(proxy) = lookup(ENV_PROXY) {
+                let no_proxy = lookup(ENV_NO_PROXY).unwrap_or_default();
+                let hosts = no_proxy.split(',').map(str::trim).filter(|host| !host.is_empty());
+                self.proxies.push(ProxyConfig::all(proxy).no_proxy(hosts));
+            }
+        }
+        Ok(self)
+    }
+    
+    /// Total time allowed for a request, including reading the body (default 30s)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    /// Time allowed for establishing a connection (default 10s)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+    
+    /// Override the timeout for one endpoint, e.g. large batch submissions
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn endpoint_timeout(mut self, endpoint: Endpoint, timeout: Duration) -> Self {
+        self.endpoint_timeouts.push((endpoint, timeout));
+        self
+    }
+    
+    /// Set the `User-Agent` header sent with every request
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+    
+    /// Send a header with every request
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+    
+    /// Send all of the given headers with every request
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+    
+    /// Speak HTTP/2 from the start instead of negotiating it
+    ///
+    /// Lets many small calls share one multiplexed connection to gateways that
+    /// support HTTP/2, including over plain HTTP. Servers that only speak
+    /// HTTP/1.1 will reject every request.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2_prior_knowledge = true;
+        self
+    }
+    
+    /// Size HTTP/2 flow-control windows from the measured bandwidth-delay product
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2_adaptive_window = enabled;
+        self
+    }
+    
+    /// Select the TLS implementation
+    ///
+    /// Only needed when both the `rustls` and `native-tls` features are enabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tls_backend(mut self, backend: TlsBackend) -> Self {
+        self.tls_backend = backend;
+        self
+    }
+    
+    /// Present a client certificate for mutual TLS
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+    
+    /// Trust an additional root certificate, e.g. a corporate or staging CA
+    ///
+    /// The built-in web PKI roots remain trusted; verification is never disabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+    
+    /// Only accept servers presenting a certificate matching one of the pins
+    ///
+    /// Pinning requires the rustls backend.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pin_certificate(mut self, pin: CertificatePin) -> Self {
+        self.pins.push(pin);
+        self
+    }
+    
+    /// Route requests through a proxy
+    ///
+    /// Can be called more than once, e.g. for separate HTTP and HTTPS proxies.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+    
+    /// Set the authentication scheme
+    pub fn auth(mut self, auth: AuthScheme) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+    
+    /// Set how response payloads are wrapped by this API deployment
+    pub fn envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = envelope;
+        self
+    }
+    
+    /// Set the body format used for requests and preferred for responses
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+    
+    /// Set the policy for automatic retries of transient failures
+    pub fn retry(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry = Some(Arc::new(policy));
+        self
+    }
+    
+    /// Limit retries to a share of recent requests
+    pub fn retry_budget(mut self, budget: RetryBudget) -> Self {
+        self.retry_budget = Some(budget);
+        self
+    }
+    
+    /// Limit outgoing requests to `requests_per_second`, allowing bursts of up to `burst`
+    ///
+    /// [`ClientBuilder::build`] fails with [`Error::Config`] for a rate that
+    /// is not positive or a burst of zero.
+    pub fn rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.rate_limit = Some((requests_per_second, burst));
+        self
+    }
+    
+    /// Fail fast while the upstream is unhealthy
+    pub fn circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+    
+    /// Send a duplicate of GET requests that are slower than usual
+    pub fn hedging(mut self, policy: HedgePolicy) -> Self {
+        self.hedging = Some(policy);
+        self
+    }
+    
+    /// Report every call to a metrics observer
+    ///
+    /// See [`MetricsRecorder`](crate::metrics::MetricsRecorder) for the `metrics` crate.
+    pub fn metrics_observer(mut self, observer: impl MetricsObserver) -> Self {
+        self.metrics = Some(Arc::new(observer));
+        self
+    }
+    
+    /// Write a record of every call to an audit sink
+    ///
+    /// See [`AuditFile`](crate::audit::AuditFile) for JSON lines in a file.
+    pub fn audit_sink(mut self, sink: impl AuditSink) -> Self {
+        self.audit = Some(Arc::new(sink));
+        self
+    }
+    
+    /// Call `callback` with every error a call finally fails with, after retries
+    ///
+    /// See [`Client::with_on_error`].
+    pub fn on_error(mut self, callback: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.on_error = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Call `callback` whenever a response announces a deprecation or sunset
+    ///
+    /// See [`Client::with_on_deprecation`].
+    pub fn on_deprecation(
+        mut self,
+        callback: impl Fn(Endpoint, &DeprecationNotice) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_deprecation = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Bound every call, including retries and backoff, to `deadline`
+    ///
+    /// See [`Client::with_deadline`].
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    
+    /// Limit the size of response bodies read into memory
+    ///
+    /// See [`Client::with_max_response_size`].
+    pub fn max_response_size(mut self, bytes: u64) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+    
+    /// Answer repeated order reads from a local cache for `ttl`
+    ///
+    /// See [`Client::with_order_cache`].
+    pub fn order_cache(mut self, ttl: Duration) -> Self {
+        self.order_cache = Some(ttl);
+        self
+    }
+    
+    /// Look for an existing order before creating one with a customer order reference
+    ///
+    /// See [`Client::with_duplicate_guard`].
+    pub fn duplicate_guard(mut self) -> Self {
+        self.duplicate_guard = true;
+        self
+    }
+    
+    /// Choose how order creation picks the `Idempotency-Key` it sends
+    ///
+    /// See [`Client::with_idempotency_keys`].
+    pub fn idempotency_keys(mut self, keys: IdempotencyKeys) -> Self {
+        self.idempotency_keys = keys;
+        self
+    }
+    
+    /// Remember the idempotency key of each order in `store`
+    ///
+    /// See [`Client::with_idempotency_store`].
+    pub fn idempotency_store(mut self, store: impl IdempotencyStore) -> Self {
+        self.idempotency_store = Some(Arc::new(store));
+        self
+    }
+    
+    /// Keep the raw request and response of the last `capacity` exchanges
+    ///
+    /// See [`Client::with_debug_capture`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn debug_capture(mut self, capacity: usize) -> Self {
+        self.debug_capture = Some(capacity);
+        self
+    }
+    
+    /// Add a backup base URL, tried in order when the preceding ones fail
+    ///
+    /// See [`Client::with_failover`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn fallback_url(mut self, base_url: impl Into<String>) -> Self {
+        self.fallback_urls.push(base_url.into());
+        self
+    }
+    
+    /// How long a failed base URL is avoided before it is tried again (default 30s)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn failover_cool_down(mut self, cool_down: Duration) -> Self {
+        self.failover_cool_down = cool_down;
+        self
+    }
+    
+    /// Choose how requests are distributed over the base URLs (default ordered failover)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+    
+    /// Set how redirects are followed and where credentials are forwarded
+    ///
+    /// Defaults to following up to 10 redirects, sending credentials only to
+    /// the origin of the original request.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect = policy;
+        self
+    }
+    
+    /// Choose which trace context headers are sent (default W3C `traceparent`)
+    #[cfg(feature = "opentelemetry")]
+    pub fn propagation(mut self, propagation: Propagation) -> Self {
+        self.propagation = propagation;
+        self
+    }
+    
+    /// Reuse an existing `reqwest::Client` and its connection pool
+    ///
+    /// Timeouts, the user agent, default headers and proxies are then taken
+    /// from the given client. Client certificates, extra roots and pins
+    /// cannot be applied to it, so setting them as well is an error.
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+    
+    /// Send requests through a custom transport instead of `reqwest`
+    ///
+    /// The user agent, default headers and timeout are applied to requests
+    /// handed to the transport. TLS and proxy settings belong to the
+    /// transport, so setting them as well is an error. See
+    /// [`Client::with_transport`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn transport(mut self, transport: impl HttpTransport) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+    
+    /// Open connections through a custom connector instead of TCP
+    ///
+    /// Requests are sent as HTTP/1.1 over the connections it returns, with
+    /// the other settings applied as for [`ClientBuilder::transport`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connector(self, connector: impl Connector) -> Self {
+        self.transport(ConnectorTransport::new(Arc::new(connector)))
+    }
+    
+    /// Send all requests over the Unix domain socket at `path`
+    ///
+    /// The base URL still determines the `Host` header and request paths,
+    /// e.g. `http://localhost` for a local gateway.
+    #[cfg(unix)]
+    pub fn unix_socket(self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.connector(UnixSocket::new(path))
+    }
+    
+    /// Build the client
+    pub fn build(mut self) -> Result<Client> {
+        // Validate URL format
+        url::Url::parse(&self.base_url)
+            .map_err(|e| Error::InvalidUrl(format!("Invalid base URL: {}", e)))?;
+        #[cfg(not(target_arch = "wasm32"))]
+        for base_url in &self.fallback_urls {
+            url::Url::parse(base_url)
+                .map_err(|e| Error::InvalidUrl(format!("Invalid base URL: {}", e)))?;
+        }
+        
+        #[cfg(not(target_arch = "wasm32"))]
+        let transport = match self.transport.take() {
+            Some(transport) => Some(self.wrap_transport(transport)?),
+            None => None,
+        };
+        let http_client = match self.http_client.take() {
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(_) if self.identity.is_some() || !self.root_certificates.is_empty() || !self.pins.is_empty() => {
+                return Err(Error::Config(
+                    "TLS options cannot be applied to an injected HTTP client".to_string(),
+                ));
+            }
+            Some(http_client) => http_client,
+            None => self.build_http_client()?,
+        };
+        
+        let mut client = Client::from_parts(self.base_url, http_client)
+            .with_envelope(self.envelope)
+            .with_format(self.format);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(transport) = transport {
+            client = client.with_shared_transport(transport);
+        }
+        if let Some(auth) = self.auth {
+            client = client.with_auth(auth);
+        }
+        if let Some(retry) = self.retry {
+            client = client.with_shared_retry(retry);
+        }
+        if let Some(budget) = self.retry_budget {
+            client = client.with_retry_budget(budget);
+        }
+        if let Some((requests_per_second, burst)) = self.rate_limit {
+            client = client.with_rate_limit(requests_per_second, burst)?;
+        }
+        if let Some(config) = self.circuit_breaker {
+            client = client.with_circuit_breaker(config);
+        }
+        if let Some(policy) = self.hedging {
+            client = client.with_hedging(policy);
+        }
+        if let Some(observer) = self.metrics {
+            client = client.with_shared_metrics_observer(observer);
+        }
+        if let Some(sink) = self.audit {
+            client = client.with_shared_audit_sink(sink);
+        }
+        if let Some(callback) = self.on_error {
+            client = client.with_shared_on_error(callback);
+        }
+        if let Some(callback) = self.on_deprecation {
+            client = client.with_shared_on_deprecation(callback);
+        }
+        if let Some(deadline) = self.deadline {
+            client = client.with_deadline(deadline);
+        }
+        if let Some(bytes) = self.max_response_size {
+            client = client.with_max_response_size(bytes);
+        }
+        if let Some(ttl) = self.order_cache {
+            client = client.with_order_cache(ttl);
+        }
+        if self.duplicate_guard {
+            client = client.with_duplicate_guard();
+        }
+        client = client.with_idempotency_keys(self.idempotency_keys);
+        if let Some(store) = self.idempotency_store {
+            client = client.with_shared_idempotency_store(store);
+        }
+        #[cfg(feature = "opentelemetry")]
+        {
+            client = client.with_propagation(self.propagation);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if !self.fallback_urls.is_empty() {
+                client = client
+                    .with_failover(self.fallback_urls, self.failover_cool_down)
+                    .with_routing(self.routing);
+            }
+            client = client.with_redirect_policy(self.redirect);
+            for (endpoint, timeout) in self.endpoint_timeouts {
+                client = client.with_endpoint_timeout(endpoint, timeout);
+            }
+            if let Some(capacity) = self.debug_capture {
+                client = client.with_debug_capture(capacity);
+            }
+        }
+        Ok(client)
+    }
+    
+    /// Build a [`blocking::Client`](crate::blocking::Client) for synchronous code
+    #[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+    pub fn build_blocking(self) -> Result<crate::blocking::Client> {
+        crate::blocking::Client::from_async(self.build()?)
+    }
+    
+    /// Build an HTTP client with the configured transport settings
+    #[cfg(not(target_arch = "wasm32"))]
+    fn build_http_client(&mut self) -> Result<reqwest::Client> {
+        let headers = self.request_headers();
+        
+        let mut http_builder = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .user_agent(self.user_agent.as_str())
+            .cookie_store(true)
+            // Redirects are followed by the client to control credential forwarding
+            .redirect(reqwest::redirect::Policy::none())
+            .http2_adaptive_window(self.http2_adaptive_window)
+            .default_headers(headers);
+        if self.http2_prior_knowledge {
+            http_builder = http_builder.http2_prior_knowledge();
+        }
+        http_builder = match self.tls_backend {
+            #[cfg(feature = "rustls")]
+            TlsBackend::Rustls => http_builder.use_rustls_tls(),
+            #[cfg(feature = "native-tls")]
+            TlsBackend::NativeTls => http_builder.use_native_tls(),
+        };
+        
+        for proxy in &self.proxies {
+            http_builder = http_builder.proxy(proxy.to_reqwest()?);
+        }
+        
+        if self.pins.is_empty() {
+            for certificate in &self.root_certificates {
+                http_builder = http_builder.add_root_certificate(certificate.to_reqwest()?);
+            }
+            if let Some(identity) = &self.identity {
+                http_builder = http_builder.identity(identity.to_reqwest(self.tls_backend)?);
+            }
+        } else {
+            #[cfg(feature = "native-tls")]
+            if self.tls_backend == TlsBackend::NativeTls {
+                return Err(Error::Tls("Certificate pinning requires the rustls backend".to_string()));
+            }
+            // Pinning needs a custom verifier, so the TLS configuration is built here
+            #[cfg(feature = "rustls")]
+            {
+                let pins = std::mem::take(&mut self.pins);
+                let config = crate::tls::pinned_config(&self.root_certificates, pins, self.identity.as_ref())?;
+                http_builder = http_builder.use_preconfigured_tls(config);
+            }
+        }
+        
+        http_builder.build().map_err(Error::Http)
+    }
+    
+    /// Build an HTTP client backed by the `fetch` API
+    #[cfg(target_arch = "wasm32")]
+    fn build_http_client(&mut self) -> Result<reqwest::Client> {
+        reqwest::Client::builder()
+            .user_agent(self.user_agent.as_str())
+            .default_headers(self.request_headers())
+            .build()
+            .map_err(Error::Http)
+    }
+    
+    /// Headers sent with every request
+    fn request_headers(&self) -> HeaderMap {
+        let mut headers = self.headers.clone();
+        headers
+            .entry(CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static("application/json"));
+        headers
+    }
+    
+    /// Wrap a custom transport with the configured headers and timeout
+    #[cfg(not(target_arch = "wasm32"))]
+    fn wrap_transport(&self, transport: Arc<dyn HttpTransport>) -> Result<Transport> {
+        let tls_configured = self.identity.is_some() || !self.root_certificates.is_empty() || !self.pins.is_empty();
+        if tls_configured || !self.proxies.is_empty() || self.http_client.is_some() {
+            return Err(Error::Config(
+                "TLS, proxy and HTTP client options cannot be combined with a custom transport".to_string(),
+            ));
+        }
+        
+        let mut headers = self.request_headers();
+        let user_agent = HeaderValue::from_str(&self.user_agent)
+            .map_err(|e| Error::Config(format!("Invalid user agent: {}", e)))?;
+        headers.entry(USER_AGENT).or_insert(user_agent);
+        Ok(Transport::new(transport, headers, Some(self.timeout)))
+    }
+}
+
+/// Parse a duration given in (possibly fractional) seconds
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_seconds(name: &str, value: &str) -> Result<Duration> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| Error::Config(format!("{} must be a number of seconds, got {:?}", name, value)))
+}
+
+impl fmt::Debug for ClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ClientBuilder");
+        debug.field("base_url", &self.base_url);
+        #[cfg(not(target_arch = "wasm32"))]
+        debug
+            .field("fallback_urls", &self.fallback_urls)
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout);
+        debug.field("user_agent", &self.user_agent);
+        #[cfg(not(target_arch = "wasm32"))]
+        debug
+            .field("http2_prior_knowledge", &self.http2_prior_knowledge)
+            .field("tls_backend", &self.tls_backend)
+            .field("proxies", &self.proxies);
+        debug
+            .field("auth", &self.auth)
+            .field("envelope", &self.envelope)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_build_with_identity() {
+        let identity = Identity::from_pem(
+            include_bytes!("../tests/fixtures/client.crt"),
+            include_bytes!("../tests/fixtures/client.key"),
+        )
+        .unwrap();
+        
+        let client = ClientBuilder::new("https://api.example.com")
+            .identity(identity)
+            .build();
+        assert!(client.is_ok());
+    }
+    
+    #[test]
+    fn test_build_with_roots_and_pins() {
+        let root = Certificate::from_pem(include_bytes!("../tests/fixtures/ca.crt")).unwrap();
+        
+        let client = ClientBuilder::new("https://api.example.com")
+            .add_root_certificate(root.clone())
+            .build();
+        assert!(client.is_ok());
+        
+        #[cfg(feature = "rustls")]
+        {
+            let server = Certificate::from_pem(include_bytes!("../tests/fixtures/server.crt")).unwrap();
+            let client = ClientBuilder::new("https://api.example.com")
+                .add_root_certificate(root.clone())
+                .pin_certificate(CertificatePin::from_certificate(&server))
+                .build();
+            assert!(client.is_ok());
+        }
+    }
+    
+    #[cfg(feature = "native-tls")]
+    #[test]
+    fn test_native_tls_backend() {
+        let identity = Identity::from_pem(
+            include_bytes!("../tests/fixtures/client.crt"),
+            include_bytes!("../tests/fixtures/client.key"),
+        )
+        .unwrap();
+        let root = Certificate::from_pem(include_bytes!("../tests/fixtures/ca.crt")).unwrap();
+        
+        let client = ClientBuilder::new("https://api.example.com")
+            .tls_backend(TlsBackend::NativeTls)
+            .identity(identity)
+            .add_root_certificate(root.clone())
+            .build();
+        assert!(client.is_ok());
+        
+        let result = ClientBuilder::new("https://api.example.com")
+            .tls_backend(TlsBackend::NativeTls)
+            .pin_certificate(CertificatePin::from_certificate(&root))
+            .build();
+        assert!(matches!(result.unwrap_err(), Error::Tls(_)));
+    }
+    
+    #[test]
+    fn test_build_with_transport_options() {
+        let client = ClientBuilder::new("https://api.example.com")
+            .timeout(Duration::from_secs(5))
+            .connect_timeout(Duration::from_secs(1))
+            .user_agent("order-importer/2.1")
+            .default_header(HeaderName::from_static("x-tenant"), HeaderValue::from_static("acme"))
+            .build();
+        assert!(client.is_ok());
+        
+        let result = ClientBuilder::new("https://api.example.com")
+            .user_agent("bad\nagent")
+            .build();
+        assert!(matches!(result.unwrap_err(), Error::Http(_)));
+    }
+    
+    #[tokio::test]
+    async fn test_http2_prior_knowledge() {
+        use tokio::io::AsyncReadExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = ClientBuilder::new(format!("http://{}", listener.local_addr().unwrap()))
+            .http2_prior_knowledge()
+            .http2_adaptive_window(true)
+            .build()
+            .unwrap();
+        let call = tokio::spawn(async move { client.create_order(Default::default()).await });
+        
+        // The connection opens with the HTTP/2 preface rather than an HTTP/1.1 request line
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut preface = [0u8; 14];
+        socket.read_exact(&mut preface).await.unwrap();
+        assert_eq!(&preface, b"PRI * HTTP/2.0");
+        call.abort();
+    }
+    
+    #[test]
+    fn test_build_with_client_options() {
+        let client = ClientBuilder::new("https://api.example.com")
+            .auth(AuthScheme::Bearer("token".to_string().into()))
+            .retry(crate::retry::Never)
+            .rate_limit(10.0, 5)
+            .circuit_breaker(CircuitBreakerConfig::default())
+            .build()
+            .unwrap();
+        
+        assert!(format!("{:?}", client).contains("Bearer"));
+        assert!(client.circuit_state().is_some());
+        
+        let result = ClientBuilder::new("https://api.example.com")
+            .proxy(ProxyConfig::all("not a url"))
+            .build();
+        assert!(matches!(result.unwrap_err(), Error::Config(_)));
+        
+        for (requests_per_second, burst) in [(0.0, 5), (5.0, 0)] {
+            let result = ClientBuilder::new("https://api.example.com")
+                .rate_limit(requests_per_second, burst)
+                .build();
+            assert!(matches!(result.unwrap_err(), Error::Config(_)));
+        }
+    }
+    
+    #[test]
+    fn test_build_with_fallback_urls() {
+        let client = ClientBuilder::new("https://api.example.com")
+            .fallback_url("https://backup.example.com")
+            .failover_cool_down(Duration::from_secs(5))
+            .routing(Routing::Healthiest)
+            .build()
+            .unwrap();
+        assert_eq!(client.active_base_url(), "https://api.example.com");
+        assert_eq!(client.routing_state().len(), 2);
+        
+        let result = ClientBuilder::new("https://api.example.com")
+            .fallback_url("backup")
+            .build();
+        assert!(matches!(result.unwrap_err(), Error::InvalidUrl(_)));
+    }
+    
+    #[test]
+    fn test_build_with_transport() {
+        let client = ClientBuilder::new("https://api.example.com")
+            .transport(reqwest::Client::new())
+            .build();
+        assert!(client.is_ok());
+        
+        let result = ClientBuilder::new("https://api.example.com")
+            .transport(reqwest::Client::new())
+            .proxy(ProxyConfig::all("http://proxy.internal:3128"))
+            .build();
+        assert!(matches!(result.unwrap_err(), Error::Config(_)));
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_build_with_unix_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let path = std::env::temp_dir().join(format!("ecommerce-api-client-builder-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let client = ClientBuilder::new("http://localhost")
+            .unix_socket(&path)
+            .user_agent("order-importer/2.1")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let read = socket.read(&mut request).await.unwrap();
+            socket.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8_lossy(&request[..read]).into_owned()
+        });
+        
+        let result = client.create_order(Default::default()).await;
+        assert!(matches!(result.unwrap_err().inner(), Error::NotFound(_)));
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /api_customer/orders HTTP/1.1"));
+        assert!(request.contains("user-agent: order-importer/2.1"));
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+    }
+    
+    #[test]
+    fn test_env_overrides() {
+        let builder = ClientBuilder::new("https://api.example.com")
+            .env_overrides_from(env(&[
+                (ENV_URL, "https://staging.example.com"),
+                (ENV_EMAIL, "ci@example.com"),
+                (ENV_TOKEN, "secret-token"),
+                (ENV_TIMEOUT, "2.5"),
+                (ENV_PROXY, "http://proxy.internal:3128"),
+                (ENV_NO_PROXY, "localhost, .internal.example.com"),
+            ]))
+            .unwrap();
+        
+        assert_eq!(builder.base_url, "https://staging.example.com");
+        assert_eq!(builder.timeout, Duration::from_millis(2500));
+        assert!(matches!(builder.auth, Some(AuthScheme::Basic { ref email, .. }) if email == "ci@example.com"));
+        assert!(!format!("{:?}", builder).contains("secret-token"));
+        assert!(builder.build().is_ok());
+        
+        let builder = ClientBuilder::new("https://api.example.com")
+            .env_overrides_from(env(&[(ENV_TOKEN, "secret-token")]))
+            .unwrap();
+        assert!(matches!(builder.auth, Some(AuthScheme::Bearer(_))));
+    }
+    
+    #[test]
+    fn test_env_overrides_invalid() {
+        let builder = ClientBuilder::new("https://api.example.com");
+        let result = builder.env_overrides_from(env(&[(ENV_TIMEOUT, "soon")]));
+        assert!(matches!(result.unwrap_err(), Error::Config(_)));
+        
+        let builder = ClientBuilder::new("https://api.example.com");
+        let result = builder.env_overrides_from(env(&[(ENV_EMAIL, "ci@example.com")]));
+        assert!(matches!(result.unwrap_err(), Error::Config(_)));
+    }
+    
+    #[test]
+    fn test_build_with_http_client() {
+        let client = ClientBuilder::new("https://api.example.com")
+            .with_http_client(reqwest::Client::new())
+            .build();
+        assert!(client.is_ok());
+        
+        let root = Certificate::from_pem(include_bytes!("../tests/fixtures/ca.crt")).unwrap();
+        let result = ClientBuilder::new("https://api.example.com")
+            .with_http_client(reqwest::Client::new())
+            .add_root_certificate(root)
+            .build();
+        assert!(matches!(result.unwrap_err(), Error::Config(_)));
+    }
+    
+    #[test]
+    fn test_build_invalid_url() {
+        let result = ClientBuilder::new("not-a-url").build();
+        assert!(matches!(result.unwrap_err(), Error::InvalidUrl(_)));
+    }
+}
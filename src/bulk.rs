@@ -0,0 +1,276 @@
+//! Submitting or cancelling many orders at once
+
+use crate::checkpoint::{CheckpointStore, DEFAULT_CHECKPOINT_EVERY};
+use crate::error::{Error, ErrorKind, Result};
+use crate::progress::ProgressObserver;
+use crate::retry::RetryPolicy;
+use crate::types::CreateOrderResponse;
+use std::fmt;
+use std::sync::Arc;
+
+/// Orders submitted at the same time unless configured otherwise
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// How [`Client::create_orders_bulk`](crate::Client::create_orders_bulk) and
+/// [`Client::cancel_orders_with`](crate::Client::cancel_orders_with) send requests
+///
+/// ```
+/// # use ecommerce_api_client::bulk::BulkOptions;
+/// # use ecommerce_api_client::retry::Exponential;
+/// let options = BulkOptions::new()
+///     .concurrency(8)
+///     .retry(Exponential::default())
+///     .idempotency_prefix("import-2024-06-01");
+/// ```
+#[derive(Clone)]
+pub struct BulkOptions {
+    concurrency: usize,
+    retry: Option<Arc<dyn RetryPolicy>>,
+    idempotency_prefix: Option<String>,
+    progress: Option<Arc<dyn ProgressObserver>>,
+    checkpoint: Option<(Arc<dyn CheckpointStore>, String)>,
+    checkpoint_every: usize,
+}
+
+impl BulkOptions {
+    /// Submit [`DEFAULT_CONCURRENCY`] orders at a time with the client's retry policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Submit up to `limit` orders at the same time
+    pub fn concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+    
+    /// Retry each order with `policy` instead of the client's policy
+    pub fn retry(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry = Some(Arc::new(policy));
+        self
+    }
+    
+    /// Derive each order's `Idempotency-Key` from `prefix` and its index
+    ///
+    /// Resubmitting the same batch with the same prefix then cannot create
+    /// duplicates. Without a prefix the checkpoint key is used if one is
+    /// set, and otherwise a random prefix per call.
+    pub fn idempotency_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.idempotency_prefix = Some(prefix.into());
+        self
+    }
+    
+    /// Report each finished order to `observer`
+    pub fn progress(mut self, observer: impl ProgressObserver) -> Self {
+        self.progress = Some(Arc::new(observer));
+        self
+    }
+    
+    /// Record the orders that succeeded in `store` under `key`
+    ///
+    /// Submitting the same batch again with the same key skips those orders
+    /// and lists them in [`BulkReport::resumed`]. The checkpoint is cleared
+    /// once every order succeeded.
+    pub fn checkpoint(mut self, store: impl CheckpointStore, key: impl Into<String>) -> Self {
+        self.checkpoint = Some((Arc::new(store), key.into()));
+        self
+    }
+    
+    /// Save the checkpoint after every `items` finished orders, and at the end
+    pub fn checkpoint_every(mut self, items: usize) -> Self {
+        self.checkpoint_every = items.max(1);
+        self
+    }
+    
+    /// The same options without a checkpoint
+    pub(crate) fn without_checkpoint(mut self) -> Self {
+        self.checkpoint = None;
+        self
+    }
+    
+    /// The same concurrency and retry policy, for cancelling orders that were created
+    pub(crate) fn compensation(&self) -> Self {
+        Self {
+            concurrency: self.concurrency,
+            retry: self.retry.clone(),
+            ..Default::default()
+        }
+    }
+    
+    /// Maximum number of orders in flight
+    pub(crate) fn concurrency_limit(&self) -> usize {
+        self.concurrency
+    }
+    
+    /// Retry policy overriding the client's, if any
+    pub(crate) fn retry_policy(&self) -> Option<&Arc<dyn RetryPolicy>> {
+        self.retry.as_ref()
+    }
+    
+    /// Observer of the submission's progress, if any
+    pub(crate) fn progress_observer(&self) -> Option<&Arc<dyn ProgressObserver>> {
+        self.progress.as_ref()
+    }
+    
+    /// Checkpoint store and key, if any
+    pub(crate) fn checkpointing(&self) -> Option<(&Arc<dyn CheckpointStore>, &str)> {
+        self.checkpoint.as_ref().map(|(store, key)| (store, key.as_str()))
+    }
+    
+    /// Orders finished between checkpoints
+    pub(crate) fn checkpoint_interval(&self) -> usize {
+        self.checkpoint_every
+    }
+    
+    /// Idempotency key prefix, from the checkpoint key or generated unless one was set
+    pub(crate) fn key_prefix(&self) -> String {
+        self.idempotency_prefix
+            .clone()
+            .or_else(|| self.checkpoint.as_ref().map(|(_, key)| key.clone()))
+            .unwrap_or_else(crate::request_id::generate)
+    }
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            retry: None,
+            idempotency_prefix: None,
+            progress: None,
+            checkpoint: None,
+            checkpoint_every: DEFAULT_CHECKPOINT_EVERY,
+        }
+    }
+}
+
+impl fmt::Debug for BulkOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BulkOptions")
+            .field("concurrency", &self.concurrency)
+            .field("idempotency_prefix", &self.idempotency_prefix)
+            .field("checkpoint", &self.checkpoint.as_ref().map(|(_, key)| key))
+            .finish_non_exhaustive()
+    }
+}
+
+/// An order that was created or cancelled
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct BulkSuccess<T = CreateOrderResponse> {
+    /// Position of the order in the submitted batch
+    pub index: usize,
+    /// The API's response for the order
+    pub response: T,
+}
+
+/// An order that could not be created or cancelled
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct BulkFailure {
+    /// Position of the order in the submitted batch
+    pub index: usize,
+    /// Why the order failed
+    pub error: Error,
+}
+
+/// Outcome of a batch submission, split by what to do with each order
+///
+/// Each list is ordered by index.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct BulkReport<T = CreateOrderResponse> {
+    /// Orders that were created or cancelled
+    pub succeeded: Vec<BulkSuccess<T>>,
+    /// Orders that failed transiently, e.g. on timeouts or 5xx responses,
+    /// and are worth resubmitting later with the same idempotency prefix
+    pub retryable: Vec<BulkFailure>,
+    /// Orders the API rejected, which fail again unless they are changed
+    pub permanent: Vec<BulkFailure>,
+    /// Orders skipped because the checkpoint of an earlier run records them as done
+    pub resumed: Vec<usize>,
+}
+
+impl<T> BulkReport<T> {
+    /// Sort results, in index order, into a report
+    pub(crate) fn from_results(results: impl IntoIterator<Item = (usize, Result<T>)>) -> Self {
+        let mut report = Self::default();
+        for (index, result) in results {
+            match result {
+                Ok(response) => report.succeeded.push(BulkSuccess { index, response }),
+                Err(error) if is_transient(&error) => report.retryable.push(BulkFailure { index, error }),
+                Err(error) => report.permanent.push(BulkFailure { index, error }),
+            }
+        }
+        report
+    }
+    
+    /// Whether every order was created
+    pub fn is_complete(&self) -> bool {
+        self.retryable.is_empty() && self.permanent.is_empty()
+    }
+    
+    /// Indices of the orders worth resubmitting
+    pub fn retryable_indices(&self) -> Vec<usize> {
+        self.retryable.iter().map(|failure| failure.index).collect()
+    }
+}
+
+impl<T> Default for BulkReport<T> {
+    fn default() -> Self {
+        Self {
+            succeeded: Vec::new(),
+            retryable: Vec::new(),
+            permanent: Vec::new(),
+            resumed: Vec::new(),
+        }
+    }
+}
+
+/// Whether a failure may go away when the order is submitted again later
+fn is_transient(error: &Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::Network
+            | ErrorKind::Timeout
+            | ErrorKind::RateLimited
+            | ErrorKind::ServerTransient
+            | ErrorKind::Cancelled
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_report_classification() {
+        let results = vec![
+            Err(Error::ServerError(503, String::new(), None)),
+            Err(Error::UnprocessableEntity("invalid".into())),
+            Err(Error::RateLimit("slow down".to_string(), None)),
+        ];
+        let report = BulkReport::<()>::from_results(results.into_iter().enumerate());
+        assert!(!report.is_complete());
+        assert!(report.succeeded.is_empty());
+        assert_eq!(report.retryable_indices(), [0, 2]);
+        assert_eq!(report.permanent[0].index, 1);
+        assert!(BulkReport::<()>::from_results(Vec::new()).is_complete());
+    }
+    
+    #[test]
+    fn test_bulk_options() {
+        let options = BulkOptions::new().concurrency(0).idempotency_prefix("batch");
+        assert_eq!(options.concurrency_limit(), 1);
+        assert_eq!(options.key_prefix(), "batch");
+        assert!(options.retry_policy().is_none());
+        
+        let resumable = BulkOptions::new().checkpoint(crate::checkpoint::MemoryCheckpointStore::new(), "import-7");
+        assert_eq!(resumable.key_prefix(), "import-7");
+        assert_eq!(resumable.checkpoint_interval(), DEFAULT_CHECKPOINT_EVERY);
+        
+        let generated = BulkOptions::new();
+        assert_eq!(generated.concurrency_limit(), DEFAULT_CONCURRENCY);
+        assert_ne!(generated.key_prefix(), generated.key_prefix());
+    }
+}
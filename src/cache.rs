@@ -0,0 +1,157 @@
+//! Short-lived cache of orders read from the API
+//!
+//! Dashboards tend to poll the same orders every few seconds. With
+//! [`Client::with_order_cache`](crate::Client::with_order_cache) set, repeated
+//! reads within the time to live are answered locally instead of spending
+//! the rate limit, and the client drops entries its own mutations make stale.
+
+use crate::pagination::{Page, PageRequest};
+use crate::runtime::Instant;
+use crate::types::{Order, OrderId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Query parameters and page of a cached order listing
+type PageKey = (Vec<(String, String)>, PageRequest);
+
+/// Orders and order pages read within the time to live, shared between clones
+#[derive(Debug)]
+pub(crate) struct OrderCache {
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    orders: HashMap<OrderId, (Instant, Order)>,
+    pages: HashMap<PageKey, (Instant, Page<Order>)>,
+}
+
+impl OrderCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// The order with `order_id`, if read within the time to live
+    pub(crate) fn order(&self, order_id: &OrderId) -> Option<Order> {
+        let entries = self.lock();
+        let (read_at, order) = entries.orders.get(order_id)?;
+        (read_at.elapsed() < self.ttl).then(|| order.clone())
+    }
+    
+    /// The page of orders for `query` and `page`, if read within the time to live
+    pub(crate) fn page(&self, query: &[(String, String)], page: &PageRequest) -> Option<Page<Order>> {
+        let entries = self.lock();
+        let (read_at, cached) = entries.pages.get(&(query.to_vec(), page.clone()))?;
+        (read_at.elapsed() < self.ttl).then(|| cached.clone())
+    }
+    
+    /// Remember an order that was just read
+    pub(crate) fn insert_order(&self, order: &Order) {
+        let mut entries = self.lock();
+        self.evict_expired(&mut entries);
+        entries
+            .orders
+            .insert(OrderId(order.id.to_string()), (Instant::now(), order.clone()));
+    }
+    
+    /// Remember a page of orders that was just read, and each order on it
+    pub(crate) fn insert_page(&self, query: &[(String, String)], page: &PageRequest, orders: &Page<Order>) {
+        let mut entries = self.lock();
+        self.evict_expired(&mut entries);
+        let now = Instant::now();
+        for order in &orders.items {
+            entries.orders.insert(OrderId(order.id.to_string()), (now, order.clone()));
+        }
+        entries.pages.insert((query.to_vec(), page.clone()), (now, orders.clone()));
+    }
+    
+    /// Forget the order with `order_id` and every page, any of which may list it
+    pub(crate) fn invalidate(&self, order_id: &OrderId) {
+        let mut entries = self.lock();
+        entries.orders.remove(order_id);
+        entries.pages.clear();
+    }
+    
+    /// Forget every page, e.g. after an order was created
+    pub(crate) fn invalidate_pages(&self) {
+        self.lock().pages.clear();
+    }
+    
+    /// Forget everything
+    pub(crate) fn clear(&self) {
+        let mut entries = self.lock();
+        entries.orders.clear();
+        entries.pages.clear();
+    }
+    
+    /// Drop entries past the time to live, so polling many orders does not grow the cache forever
+    fn evict_expired(&self, entries: &mut Entries) {
+        let ttl = self.ttl;
+        entries.orders.retain(|_, (read_at, _)| read_at.elapsed() < ttl);
+        entries.pages.retain(|_, (read_at, _)| read_at.elapsed() < ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn order(id: u64) -> Order {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "status_order_id": 1,
+            "customer_id": 7,
+            "customer_order_reference": format!("ORDER-{}", id),
+            "gross_total": "10.00",
+            "addressbook_id": 3,
+        }))
+        .unwrap()
+    }
+    
+    fn page(orders: Vec<Order>) -> Page<Order> {
+        Page {
+            items: orders,
+            total: None,
+            page: Some(1),
+            next_cursor: None,
+            next: None,
+        }
+    }
+    
+    #[test]
+    fn test_invalidation() {
+        let cache = OrderCache::new(Duration::from_secs(60));
+        let query = vec![("status".to_string(), "1".to_string())];
+        let first = PageRequest::first_page(10);
+        cache.insert_page(&query, &first, &page(vec![order(1), order(2)]));
+        assert_eq!(cache.order(&OrderId("2".to_string())), Some(order(2)));
+        assert_eq!(cache.page(&query, &first).unwrap().items.len(), 2);
+        assert!(cache.page(&[], &first).is_none());
+        
+        cache.invalidate(&OrderId("1".to_string()));
+        assert!(cache.order(&OrderId("1".to_string())).is_none());
+        assert!(cache.order(&OrderId("2".to_string())).is_some());
+        assert!(cache.page(&query, &first).is_none());
+        
+        cache.clear();
+        assert!(cache.order(&OrderId("2".to_string())).is_none());
+    }
+    
+    #[test]
+    fn test_expiry() {
+        let cache = OrderCache::new(Duration::ZERO);
+        cache.insert_order(&order(1));
+        assert!(cache.order(&OrderId("1".to_string())).is_none());
+        cache.insert_order(&order(2));
+        assert_eq!(cache.lock().orders.len(), 1);
+    }
+}
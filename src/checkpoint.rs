@@ -0,0 +1,216 @@
+//! Checkpoints that let interrupted batch jobs resume where they stopped
+//!
+//! Bulk submissions record which items are done and listings record the
+//! next page to fetch. Running the job again with the same store and key
+//! skips the work already done; once the job completes its checkpoint is
+//! cleared.
+
+use crate::pagination::PageRequest;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Items completed between checkpoints of a bulk submission unless configured otherwise
+pub const DEFAULT_CHECKPOINT_EVERY: usize = 25;
+
+/// Progress of a batch job
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Checkpoint {
+    /// Indices of the batch items that succeeded
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub completed: BTreeSet<usize>,
+    /// Page a listing continues with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page: Option<PageRequest>,
+    /// Items listed before `next_page`
+    #[serde(default)]
+    pub items: u64,
+}
+
+/// Persists [`Checkpoint`]s under a key naming the job
+///
+/// A checkpoint that cannot be loaded fails the job before any work is
+/// done, since the work already done is unknown. A job whose checkpoint
+/// cannot be saved carries on and at worst repeats work when resumed; bulk
+/// submissions report such failures to the client's
+/// [`with_on_error`](crate::Client::with_on_error) callback.
+///
+/// ```
+/// use ecommerce_api_client::async_trait;
+/// use ecommerce_api_client::checkpoint::{Checkpoint, CheckpointStore};
+///
+/// struct Discard;
+///
+/// #[async_trait]
+/// impl CheckpointStore for Discard {
+///     async fn load(&self, _key: &str) -> std::io::Result<Option<Checkpoint>> {
+///         Ok(None)
+///     }
+///     async fn save(&self, _key: &str, _checkpoint: &Checkpoint) -> std::io::Result<()> {
+///         Ok(())
+///     }
+///     async fn clear(&self, _key: &str) -> std::io::Result<()> {
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait CheckpointStore: Send + Sync + 'static {
+    /// The last checkpoint saved under `key`, if any
+    async fn load(&self, key: &str) -> io::Result<Option<Checkpoint>>;
+    
+    /// Replace the checkpoint saved under `key`
+    async fn save(&self, key: &str, checkpoint: &Checkpoint) -> io::Result<()>;
+    
+    /// Forget the checkpoint saved under `key`
+    async fn clear(&self, key: &str) -> io::Result<()>;
+}
+
+/// Keeps checkpoints in memory, e.g. for tests or jobs retried within one process
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointStore {
+    checkpoints: Mutex<HashMap<String, Checkpoint>>,
+}
+
+impl MemoryCheckpointStore {
+    /// An empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// The checkpoint saved under `key`, if any
+    pub fn get(&self, key: &str) -> Option<Checkpoint> {
+        self.lock().get(key).cloned()
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Checkpoint>> {
+        self.checkpoints.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for MemoryCheckpointStore {
+    async fn load(&self, key: &str) -> io::Result<Option<Checkpoint>> {
+        Ok(self.get(key))
+    }
+    
+    async fn save(&self, key: &str, checkpoint: &Checkpoint) -> io::Result<()> {
+        self.lock().insert(key.to_string(), checkpoint.clone());
+        Ok(())
+    }
+    
+    async fn clear(&self, key: &str) -> io::Result<()> {
+        self.lock().remove(key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S: CheckpointStore> CheckpointStore for Arc<S> {
+    async fn load(&self, key: &str) -> io::Result<Option<Checkpoint>> {
+        (**self).load(key).await
+    }
+    
+    async fn save(&self, key: &str, checkpoint: &Checkpoint) -> io::Result<()> {
+        (**self).save(key, checkpoint).await
+    }
+    
+    async fn clear(&self, key: &str) -> io::Result<()> {
+        (**self).clear(key).await
+    }
+}
+
+/// Keeps each checkpoint as a JSON file in a directory
+///
+/// A crash while saving leaves the previous checkpoint in place.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct FileCheckpointStore {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileCheckpointStore {
+    /// Store checkpoints in `dir`, creating it if needed
+    pub fn open(dir: impl Into<std::path::PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+    
+    /// File of the checkpoint saved under `key`
+    fn path(&self, key: &str) -> std::path::PathBuf {
+        let name: String = key
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl CheckpointStore for FileCheckpointStore {
+    async fn load(&self, key: &str) -> io::Result<Option<Checkpoint>> {
+        match std::fs::read(self.path(key)) {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    async fn save(&self, key: &str, checkpoint: &Checkpoint) -> io::Result<()> {
+        crate::fs::write_atomic(&self.path(key), &serde_json::to_vec(checkpoint)?)
+    }
+    
+    async fn clear(&self, key: &str) -> io::Result<()> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_checkpoint_serde() {
+        let checkpoint = Checkpoint {
+            completed: [0, 2].into_iter().collect(),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        assert_eq!(json, r#"{"completed":[0,2],"items":0}"#);
+        assert_eq!(serde_json::from_str::<Checkpoint>(&json).unwrap(), checkpoint);
+        assert_eq!(serde_json::from_str::<Checkpoint>("{}").unwrap(), Checkpoint::default());
+    }
+    
+    #[tokio::test]
+    async fn test_file_store() {
+        let dir = std::env::temp_dir().join(format!("ecommerce-api-checkpoints-{}", std::process::id()));
+        let store = FileCheckpointStore::open(&dir).unwrap();
+        let checkpoint = Checkpoint {
+            next_page: Some(PageRequest::Offset { page: 4, per_page: 50 }),
+            items: 150,
+            ..Default::default()
+        };
+        
+        assert_eq!(store.load("sync/products").await.unwrap(), None);
+        store.save("sync/products", &checkpoint).await.unwrap();
+        assert_eq!(store.load("sync/products").await.unwrap(), Some(checkpoint));
+        assert!(dir.join("sync_products.json").exists());
+        store.clear("sync/products").await.unwrap();
+        store.clear("sync/products").await.unwrap();
+        assert_eq!(store.load("sync/products").await.unwrap(), None);
+        
+        // A damaged checkpoint is an error rather than a fresh start
+        std::fs::write(dir.join("sync_products.json"), b"{").unwrap();
+        assert!(store.load("sync/products").await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,278 @@
+//! Circuit breaker for failing upstreams
+
+use crate::error::{Error, Result};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use crate::runtime::Instant;
+use std::time::Duration;
+
+/// Circuit breaker thresholds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Fraction of failed requests (0.0 - 1.0) in the window that opens the circuit
+    pub failure_rate_threshold: f64,
+    /// Minimum number of requests in the window before the rate is evaluated
+    pub minimum_requests: u32,
+    /// Sliding window over which the failure rate is computed
+    pub window: Duration,
+    /// How long the circuit stays open before allowing trial requests
+    pub cool_down: Duration,
+    /// Number of trial requests allowed while half-open
+    pub half_open_max_calls: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_rate_threshold: 0.5,
+            minimum_requests: 10,
+            window: Duration::from_secs(60),
+            cool_down: Duration::from_secs(30),
+            half_open_max_calls: 1,
+        }
+    }
+}
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests fail fast with [`Error::CircuitOpen`]
+    Open,
+    /// A limited number of trial requests probe whether the upstream recovered
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Inner {
+    Closed { outcomes: VecDeque<(Instant, bool)> },
+    Open { until: Instant },
+    /// `since` tells trials of this half-open period from older ones
+    HalfOpen { in_flight: u32, since: Instant },
+}
+
+/// Circuit breaker shared by all clones of a client
+///
+/// Only upstream failures (network errors and 5xx responses) count towards
+/// the failure rate; client errors such as 400 or 404 do not.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Create a closed circuit breaker
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner::Closed {
+                outcomes: VecDeque::new(),
+            }),
+        }
+    }
+    
+    /// Current state of the circuit
+    pub fn state(&self) -> CircuitState {
+        match &*self.lock() {
+            Inner::Closed { .. } => CircuitState::Closed,
+            Inner::Open { until } if Instant::now() < *until => CircuitState::Open,
+            Inner::Open { .. } | Inner::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+    
+    /// Admit a request, or fail fast while the circuit is open
+    ///
+    /// The outcome of the request is given to [`CircuitPermit::record`]. A
+    /// permit dropped without an outcome, e.g. because the request was
+    /// cancelled, frees its trial slot without counting either way.
+    pub(crate) fn try_acquire(&self) -> Result<CircuitPermit<'_>> {
+        let mut inner = self.lock();
+        let now = Instant::now();
+        
+        let trial = match &mut *inner {
+            Inner::Closed { .. } => None,
+            Inner::Open { until } if now < *until => return Err(Error::CircuitOpen(*until - now)),
+            Inner::Open { .. } => {
+                *inner = Inner::HalfOpen { in_flight: 1, since: now };
+                Some(now)
+            }
+            Inner::HalfOpen { in_flight, since } if *in_flight < self.config.half_open_max_calls => {
+                *in_flight += 1;
+                Some(*since)
+            }
+            Inner::HalfOpen { .. } => return Err(Error::CircuitOpen(Duration::ZERO)),
+        };
+        Ok(CircuitPermit { breaker: self, trial })
+    }
+    
+    /// Record the outcome of an admitted request
+    ///
+    /// `trial` is the half-open period the request was admitted in, if any.
+    fn record<T>(&self, trial: Option<Instant>, result: &Result<T>) {
+        let failed = matches!(result, Err(e) if is_upstream_failure(e));
+        let mut inner = self.lock();
+        let now = Instant::now();
+        
+        match &mut *inner {
+            Inner::Closed { outcomes } => {
+                outcomes.push_back((now, failed));
+                while outcomes
+                    .front()
+                    .is_some_and(|(at, _)| now.duration_since(*at) > self.config.window)
+                {
+                    outcomes.pop_front();
+                }
+                
+                let total = outcomes.len() as u32;
+                let failures = outcomes.iter().filter(|(_, failed)| *failed).count() as f64;
+                if total >= self.config.minimum_requests
+                    && failures / total as f64 >= self.config.failure_rate_threshold
+                {
+                    *inner = Inner::Open {
+                        until: now + self.config.cool_down,
+                    };
+                }
+            }
+            Inner::HalfOpen { since, .. } if trial != Some(*since) => {}
+            Inner::HalfOpen { .. } if failed => {
+                *inner = Inner::Open {
+                    until: now + self.config.cool_down,
+                };
+            }
+            Inner::HalfOpen { .. } => {
+                *inner = Inner::Closed {
+                    outcomes: VecDeque::new(),
+                };
+            }
+            // A request admitted before the circuit opened
+            Inner::Open { .. } => {}
+        }
+    }
+    
+    /// Free the slot of a trial that ended without an outcome
+    fn release(&self, trial: Instant) {
+        if let Inner::HalfOpen { in_flight, since } = &mut *self.lock() {
+            if *since == trial {
+                *in_flight = in_flight.saturating_sub(1);
+            }
+        }
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Admission of one request by a [`CircuitBreaker`]
+#[must_use = "dropping the permit discards the request's outcome"]
+#[derive(Debug)]
+pub(crate) struct CircuitPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    trial: Option<Instant>,
+}
+
+impl CircuitPermit<'_> {
+    /// Count the outcome of the admitted request
+    pub(crate) fn record<T>(mut self, result: &Result<T>) {
+        self.breaker.record(self.trial.take(), result);
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(trial) = self.trial {
+            self.breaker.release(trial);
+        }
+    }
+}
+
+/// Check whether an error indicates an unhealthy upstream
+fn is_upstream_failure(error: &Error) -> bool {
+    matches!(error.inner(), Error::Http(_) | Error::Connect(_) | Error::Transport { .. } | Error::ServerError(..))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn breaker(cool_down: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_rate_threshold: 0.5,
+            minimum_requests: 4,
+            window: Duration::from_secs(60),
+            cool_down,
+            half_open_max_calls: 1,
+        })
+    }
+    
+    fn failure() -> Result<()> {
+        Err(Error::ServerError(502, "Bad Gateway".to_string(), None))
+    }
+    
+    #[test]
+    fn test_opens_on_failure_rate() {
+        let breaker = breaker(Duration::from_secs(30));
+        
+        for result in [Ok(()), failure(), Ok(())] {
+            breaker.try_acquire().unwrap().record(&result);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        
+        breaker.try_acquire().unwrap().record(&failure());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(matches!(breaker.try_acquire().unwrap_err(), Error::CircuitOpen(_)));
+    }
+    
+    #[test]
+    fn test_client_errors_do_not_trip() {
+        let breaker = breaker(Duration::from_secs(30));
+        
+        for _ in 0..10 {
+            breaker.try_acquire().unwrap().record::<()>(&Err(Error::BadRequest("invalid".into())));
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+    
+    #[test]
+    fn test_half_open_recovery() {
+        let breaker = breaker(Duration::from_millis(10));
+        for _ in 0..4 {
+            breaker.try_acquire().unwrap().record(&failure());
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+        
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        
+        // Only one trial request is admitted while half-open
+        let trial = breaker.try_acquire().unwrap();
+        assert!(breaker.try_acquire().is_err());
+        
+        // A failed trial re-opens the circuit
+        trial.record(&failure());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        
+        std::thread::sleep(Duration::from_millis(20));
+        breaker.try_acquire().unwrap().record(&Ok(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+    
+    #[test]
+    fn test_dropped_trial_frees_slot() {
+        let breaker = breaker(Duration::from_millis(10));
+        for _ in 0..4 {
+            breaker.try_acquire().unwrap().record(&failure());
+        }
+        std::thread::sleep(Duration::from_millis(20));
+        
+        // A cancelled trial neither closes nor re-opens the circuit
+        drop(breaker.try_acquire().unwrap());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        let trial = breaker.try_acquire().unwrap();
+        assert!(breaker.try_acquire().is_err());
+        trial.record(&Ok(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
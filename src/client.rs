@@ -1,132 +1,4636 @@
 //! HTTP client for the e-commerce API
 
-use crate::error::{Error, Result};
-use crate::types::{CreateOrderRequest, CreateOrderResponse};
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
-use std::time::Duration;
+use crate::api_error::ApiErrorBody;
+use crate::audit::{AuditRecord, AuditSink};
+use crate::auth::{AuthScheme, CredentialProvider, OAuth2, Session};
+use crate::builder::ClientBuilder;
+use crate::bulk::{BulkFailure, BulkOptions, BulkReport};
+use crate::cache::OrderCache;
+use crate::checkpoint::Checkpoint;
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::Config;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::debug_log::{DebugEntry, DebugLog};
+use crate::deprecation::DeprecationNotice;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::disk_cache::{CachedResponse, DiskCache};
+use crate::envelope::Envelope;
+use crate::error::{Error, ErrorKind, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::failover::{Failover, RegionHealth, Routing};
+use crate::export::{ExportJob, ExportJobId, ExportSpec, ExportStatus, ExportWait};
+use crate::filter::OrderFilter;
+use crate::format::Format;
+use crate::hedge::{HedgePolicy, Hedger};
+use crate::idempotency::{IdempotencyRecord, IdempotencyStore, Outcome};
+use crate::json_stream::JsonArrayDecoder;
+use crate::metrics::{MetricsObserver, RequestCompleted, RequestStarted};
+use crate::options::{Endpoint, IdempotencyKeys, RequestOptions};
+use crate::pagination::{ListLimits, Page, PageRequest, Paginator};
+use crate::progress::{Phase, ProgressObserver, ProgressTracker};
+#[cfg(feature = "opentelemetry")]
+use crate::propagation::Propagation;
+use crate::rate_limit::{RateLimitStatus, RateLimiter};
+use crate::reconcile::ReconcileReport;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::redirect::RedirectPolicy;
+use crate::request_id::{self, REQUEST_ID};
+use crate::response::{self, ApiResponse, Conditional};
+use crate::saga::{Compensation, SagaReport};
+use crate::retry::{self, Exponential, RetryBudget, RetryPolicy};
+use crate::shutdown::{Lifecycle, ShutdownReport};
+use crate::single_flight::SingleFlight;
+use crate::store::{OrderQuery, OrderStore, StoredOrder};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::{HttpTransport, Transport};
+use crate::watch::{OrderChange, Polling};
+#[cfg(all(any(feature = "sse", feature = "websocket"), not(target_arch = "wasm32")))]
+use crate::events::ApiEvent;
+#[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+use crate::sse;
+#[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+use std::pin::Pin;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+use crate::websocket::{self, WebSocketOptions};
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+use futures_util::SinkExt;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+use tokio_tungstenite::tungstenite;
+use crate::types::{
+    CreateOrderRequest, CreateOrderResponse, CustomerOrderReference, FindOrCreate, Link, Order, OrderId, Product,
+};
+#[cfg(feature = "log")]
+use crate::logging;
+#[cfg(feature = "tracing")]
+use crate::trace;
+use bytes::Bytes;
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use secrecy::SecretString;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use crate::runtime::{self, Instant};
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
+/// Path of the orders collection
+const ORDERS_PATH: &str = "/api_customer/orders";
+
+/// Path of the product catalogue
+const PRODUCTS_PATH: &str = "/api_customer/products";
+
+/// Path of the export jobs collection
+const EXPORTS_PATH: &str = "/api_customer/exports";
+
+/// Path of the event stream, served as Server-Sent Events or over a WebSocket
+#[cfg(all(any(feature = "sse", feature = "websocket"), not(target_arch = "wasm32")))]
+const EVENTS_PATH: &str = "/api_customer/events";
+
+/// Pause before reconnecting to the event stream, unless the server asks for another
+#[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+const EVENT_STREAM_RETRY: Duration = Duration::from_secs(3);
+
+/// Lifetime of one event stream connection; the stream then reconnects where it left off
+#[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+const EVENT_STREAM_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Orders fetched when looking one up by reference, enough to spot duplicates
+const REFERENCE_MATCHES: u64 = 10;
+
+/// Time a long-poll response may take beyond the wait the API was asked for
+const LONG_POLL_GRACE: Duration = Duration::from_secs(10);
+
+/// Pause before asking a long-poll endpoint again after a failed request
+const LONG_POLL_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+/// Callback receiving the final error of failed calls
+pub(crate) type ErrorCallback = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// Callback receiving deprecation notices of successful calls
+pub(crate) type DeprecationCallback = Arc<dyn Fn(Endpoint, &DeprecationNotice) + Send + Sync>;
 
 /// HTTP client for interacting with the e-commerce API
-#[derive(Debug, Clone)]
+///
+/// Credentials are redacted from the `Debug` output and zeroized on drop.
+#[derive(Clone)]
 pub struct Client {
     /// Base URL for the API
     base_url: String,
     /// HTTP client instance with optimized settings
     http_client: reqwest::Client,
-    /// Authentication credentials
-    credentials: Option<(String, String)>, // (email, token)
+    /// Custom transport used instead of sending through `http_client`
+    #[cfg(not(target_arch = "wasm32"))]
+    transport: Option<Transport>,
+    /// Authentication scheme
+    auth: Option<AuthScheme>,
+    /// How response payloads are wrapped
+    envelope: Envelope,
+    /// Body format used for requests and preferred for responses
+    format: Format,
+    /// Policy for automatic retries
+    retry: Arc<dyn RetryPolicy>,
+    /// Optional cap on retries shared between clones
+    retry_budget: Option<Arc<RetryBudget>>,
+    /// Optional circuit breaker shared between clones
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    /// Optional hedging of slow GET requests
+    hedger: Option<Arc<Hedger>>,
+    /// Optional client-side rate limiter shared between clones
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Optional backup base URLs, shared between clones
+    #[cfg(not(target_arch = "wasm32"))]
+    failover: Option<Arc<Failover>>,
+    /// How requests are distributed over the base URLs
+    #[cfg(not(target_arch = "wasm32"))]
+    routing: Routing,
+    /// How 3xx responses are followed
+    #[cfg(not(target_arch = "wasm32"))]
+    redirect: RedirectPolicy,
+    /// Timeouts overriding the client default for individual endpoints
+    #[cfg(not(target_arch = "wasm32"))]
+    endpoint_timeouts: HashMap<Endpoint, Duration>,
+    /// Trace context headers sent with each attempt
+    #[cfg(feature = "opentelemetry")]
+    propagation: Propagation,
+    /// Optional observer notified about every call
+    metrics: Option<Arc<dyn MetricsObserver>>,
+    /// Optional sink receiving a record of every call
+    audit: Option<Arc<dyn AuditSink>>,
+    /// Optional callback receiving every final error
+    on_error: Option<ErrorCallback>,
+    /// Optional callback receiving deprecation notices
+    on_deprecation: Option<DeprecationCallback>,
+    /// Overall deadline for each call, including retries
+    deadline: Option<Duration>,
+    /// Largest response body read into memory, in bytes
+    max_response_size: Option<u64>,
+    /// Whether order creation first looks for the customer order reference
+    duplicate_guard: bool,
+    /// How order creation picks its `Idempotency-Key`
+    idempotency_keys: IdempotencyKeys,
+    /// Optional store of the keys used per customer order reference
+    idempotency_store: Option<Arc<dyn IdempotencyStore>>,
+    /// Optional local record of the orders created through the client
+    order_store: Option<Arc<dyn OrderStore>>,
+    /// Optional cache of orders read recently, shared between clones
+    order_cache: Option<Arc<OrderCache>>,
+    /// Optional on-disk cache of catalogue pages, shared between clones
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<Arc<DiskCache>>,
+    /// Optional registry of reads in flight, shared between clones
+    single_flight: Option<Arc<SingleFlight>>,
+    /// Raw exchanges captured in debug mode, shared between clones
+    #[cfg(not(target_arch = "wasm32"))]
+    debug_log: Option<Arc<DebugLog>>,
+    /// Most recent quota reported by the server
+    last_rate_limit: Arc<Mutex<Option<RateLimitStatus>>>,
+    /// Order statuses after which an order no longer changes
+    terminal_statuses: Vec<u64>,
+    /// In-flight calls, shared between clones for graceful shutdown
+    lifecycle: Arc<Lifecycle>,
 }
 
 impl Client {
     /// Create a new client with the specified base URL
     pub fn new(base_url: impl Into<String>) -> Result<Self> {
-        let base_url = base_url.into();
-        
-        // Validate URL format
-        url::Url::parse(&base_url)
-            .map_err(|e| Error::InvalidUrl(format!("Invalid base URL: {}", e)))?;
-        
-        // Build HTTP client with proper configurations
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            USER_AGENT,
-            HeaderValue::from_static("ecommerce-api-client/0.1.0"),
-        );
-        headers.insert(
-            CONTENT_TYPE,
-            HeaderValue::from_static("application/json"),
-        );
-        
-        let http_client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10))
-            .use_rustls_tls()
-            .default_headers(headers)
-            .build()
-            .map_err(Error::Http)?;
-        
-        Ok(Self {
+        Self::builder(base_url).build()
+    }
+    
+    /// Create a client configured from `ECOMMERCE_API_*` environment variables
+    ///
+    /// See [`ClientBuilder::from_env`].
+    pub fn from_env() -> Result<Self> {
+        ClientBuilder::from_env()?.build()
+    }
+    
+    /// Create a client from a TOML or YAML configuration file
+    ///
+    /// `ECOMMERCE_API_*` environment variables override the file. See [`Config`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_config_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Config::from_file(path)?.into_builder()?.build()
+    }
+    
+    /// Create a builder for a client with custom transport settings
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(base_url)
+    }
+    
+    /// Assemble a client from a validated base URL and HTTP client
+    pub(crate) fn from_parts(base_url: String, http_client: reqwest::Client) -> Self {
+        Self {
             base_url,
             http_client,
-            credentials: None,
-        })
+            #[cfg(not(target_arch = "wasm32"))]
+            transport: None,
+            auth: None,
+            envelope: Envelope::default(),
+            format: Format::default(),
+            retry: Arc::new(Exponential::default()),
+            retry_budget: None,
+            circuit_breaker: None,
+            hedger: None,
+            rate_limiter: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            failover: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            routing: Routing::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            redirect: RedirectPolicy::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            endpoint_timeouts: HashMap::new(),
+            #[cfg(feature = "opentelemetry")]
+            propagation: Propagation::default(),
+            metrics: None,
+            audit: None,
+            on_error: None,
+            on_deprecation: None,
+            deadline: None,
+            max_response_size: None,
+            duplicate_guard: false,
+            idempotency_keys: IdempotencyKeys::default(),
+            idempotency_store: None,
+            order_store: None,
+            order_cache: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: None,
+            single_flight: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            debug_log: None,
+            last_rate_limit: Arc::default(),
+            terminal_statuses: Vec::new(),
+            lifecycle: Arc::default(),
+        }
     }
     
     /// Set authentication credentials
     pub fn with_credentials(mut self, email: impl Into<String>, token: impl Into<String>) -> Self {
-        self.credentials = Some((email.into(), token.into()));
+        self.auth = Some(AuthScheme::Basic {
+            email: email.into(),
+            token: SecretString::new(token.into()),
+        });
+        self
+    }
+    
+    /// Authenticate with a static bearer token instead of Basic auth
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.auth = Some(AuthScheme::Bearer(SecretString::new(token.into())));
+        self
+    }
+    
+    /// Authenticate with an API key sent in a custom header such as `X-Api-Key`
+    pub fn with_api_key(mut self, header_name: impl Into<String>, key: impl Into<String>) -> Self {
+        self.auth = Some(AuthScheme::ApiKey {
+            header: header_name.into(),
+            key: SecretString::new(key.into()),
+        });
+        self
+    }
+    
+    /// Authenticate with an OAuth2 client-credentials flow
+    pub fn with_oauth2(mut self, oauth: OAuth2) -> Self {
+        self.auth = Some(AuthScheme::OAuth2(oauth));
+        self
+    }
+    
+    /// Authenticate with a session cookie obtained from the login endpoint
+    ///
+    /// The client logs in before the first request and again whenever the
+    /// session expires; call [`Client::login`] to log in eagerly.
+    pub fn with_session(mut self, email: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some(AuthScheme::Session(Session::new(email, password)));
+        self
+    }
+    
+    /// Fetch credentials from a provider before each request
+    ///
+    /// Use this to integrate secret managers that rotate tokens.
+    pub fn with_credential_provider(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.auth = Some(AuthScheme::Provider(Arc::new(provider)));
+        self
+    }
+    
+    /// Authenticate with locally signed JWTs sent as bearer tokens
+    #[cfg(feature = "jwt")]
+    pub fn with_jwt(mut self, jwt: crate::jwt::JwtAuth) -> Self {
+        self.auth = Some(AuthScheme::Jwt(jwt));
+        self
+    }
+    
+    /// Set the authentication scheme
+    pub fn with_auth(mut self, auth: AuthScheme) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+    
+    /// Set how response payloads are wrapped by this API deployment
+    pub fn with_envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = envelope;
+        self
+    }
+    
+    /// Set the body format used for requests and preferred for responses
+    ///
+    /// Responses are decoded according to their `Content-Type`, so a server
+    /// that answers in a different format than requested is still understood.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+    
+    /// Set the policy for automatic retries of transient failures
+    ///
+    /// Only idempotent requests and requests carrying an `Idempotency-Key`
+    /// header are retried, so orders are never submitted twice by accident.
+    /// Defaults to [`Exponential`] backoff; use [`retry::Never`] to disable.
+    pub fn with_retry(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry = Arc::new(policy);
+        self
+    }
+    
+    /// Set a retry policy that is already shared
+    pub(crate) fn with_shared_retry(mut self, policy: Arc<dyn RetryPolicy>) -> Self {
+        self.retry = policy;
+        self
+    }
+    
+    /// Limit retries to a share of recent requests across all clones of this client
+    ///
+    /// Once the budget is spent, failures are returned instead of retried so
+    /// a degraded upstream does not receive amplified traffic.
+    pub fn with_retry_budget(mut self, budget: RetryBudget) -> Self {
+        self.retry_budget = Some(Arc::new(budget));
+        self
+    }
+    
+    /// Fail fast with [`Error::CircuitOpen`] while the upstream is unhealthy
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(Arc::new(CircuitBreaker::new(config)));
+        self
+    }
+    
+    /// Current circuit breaker state, if one is configured
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.circuit_breaker.as_ref().map(|breaker| breaker.state())
+    }
+    
+    /// Limit outgoing requests to `requests_per_second`, allowing bursts of up to `burst`
+    ///
+    /// Retries count against the limit too. `X-RateLimit-Remaining` and
+    /// `X-RateLimit-Reset` response headers slow the limiter down further as
+    /// the server's quota nears exhaustion. Fails with [`Error::Config`]
+    /// for a rate that is not positive or a burst of zero.
+    pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Result<Self> {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(requests_per_second, burst)?));
+        Ok(self)
+    }
+    
+    /// Send a duplicate of GET requests that are slower than usual
+    ///
+    /// The first response wins and the other request is cancelled, which cuts
+    /// tail latency at the cost of some extra load on the server.
+    pub fn with_hedging(mut self, policy: HedgePolicy) -> Self {
+        self.hedger = Some(Arc::new(Hedger::new(policy)));
+        self
+    }
+    
+    /// Fail over to backup base URLs when the primary is unreachable
+    ///
+    /// After a connection error or 5xx response, requests go to the next
+    /// base URL in order until the failed one has cooled down. Failed
+    /// requests are retried on the backup according to the retry policy.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_failover<I, S>(mut self, backup_urls: I, cool_down: Duration) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let backup_urls = backup_urls.into_iter().map(Into::into).collect();
+        self.failover = Some(Arc::new(Failover::new(self.base_url.clone(), backup_urls, cool_down)));
+        self
+    }
+    
+    /// Choose how requests are distributed over the base URLs
+    ///
+    /// Only relevant together with [`Client::with_failover`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+    
+    /// Base URL requests are currently sent to
+    ///
+    /// This is the configured base URL unless the client has failed over to a backup.
+    pub fn active_base_url(&self) -> &str {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(failover) = &self.failover {
+            return failover.active(self.routing);
+        }
+        &self.base_url
+    }
+    
+    /// Health of every base URL, the primary first
+    ///
+    /// Empty unless backup base URLs are configured.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn routing_state(&self) -> Vec<RegionHealth> {
+        self.failover
+            .as_ref()
+            .map(|failover| failover.state(self.routing))
+            .unwrap_or_default()
+    }
+    
+    /// Send requests through a custom transport instead of `reqwest`
+    ///
+    /// The transport receives each request as assembled by the client,
+    /// including authentication, and is responsible for its own default
+    /// headers and connection timeouts. OAuth2 tokens are still fetched
+    /// through `reqwest`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_transport(self, transport: impl HttpTransport) -> Self {
+        self.with_shared_transport(Transport::new(Arc::new(transport), Default::default(), None))
+    }
+    
+    /// Send requests through an already wrapped custom transport
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn with_shared_transport(mut self, transport: Transport) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+    
+    /// Set how redirects are followed and where credentials are forwarded
+    ///
+    /// Only takes effect when the underlying HTTP client does not follow
+    /// redirects itself, which is the case unless one was injected with
+    /// [`ClientBuilder::with_http_client`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect = policy;
+        self
+    }
+    
+    /// Override the client-wide timeout for one endpoint
+    ///
+    /// A timeout given in [`RequestOptions`] still takes precedence.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_endpoint_timeout(mut self, endpoint: Endpoint, timeout: Duration) -> Self {
+        self.endpoint_timeouts.insert(endpoint, timeout);
+        self
+    }
+    
+    /// Choose which trace context headers are sent (default W3C `traceparent`)
+    #[cfg(feature = "opentelemetry")]
+    pub fn with_propagation(mut self, propagation: Propagation) -> Self {
+        self.propagation = propagation;
+        self
+    }
+    
+    /// Report every call to a metrics observer
+    pub fn with_metrics_observer(mut self, observer: impl MetricsObserver) -> Self {
+        self.metrics = Some(Arc::new(observer));
+        self
+    }
+    
+    /// Set a metrics observer that is already shared
+    pub(crate) fn with_shared_metrics_observer(mut self, observer: Arc<dyn MetricsObserver>) -> Self {
+        self.metrics = Some(observer);
+        self
+    }
+    
+    /// Write a record of every call to an audit sink
+    ///
+    /// See [`AuditSink`] for when records are written.
+    pub fn with_audit_sink(mut self, sink: impl AuditSink) -> Self {
+        self.audit = Some(Arc::new(sink));
+        self
+    }
+    
+    /// Set an audit sink that is already shared
+    pub(crate) fn with_shared_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Some(sink);
+        self
+    }
+    
+    /// Call `callback` with every error a call finally fails with, after retries
+    ///
+    /// The error's [`context`](Error::context) describes the call, so
+    /// failures can be pushed to Sentry or alerting in one place. The callback
+    /// runs inline before the caller gets the error and should not block.
+    ///
+    /// Failures of the [order store](Client::with_order_store), and of the
+    /// [idempotency store](Client::with_idempotency_store) once an order was
+    /// sent, are passed here too, as [`Error::Storage`], although the call
+    /// itself succeeds.
+    pub fn with_on_error(mut self, callback: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.on_error = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Set an error callback that is already shared
+    pub(crate) fn with_shared_on_error(mut self, callback: ErrorCallback) -> Self {
+        self.on_error = Some(callback);
+        self
+    }
+    
+    /// Call `callback` whenever a response announces a deprecation or sunset
+    ///
+    /// The notice is also available as [`ApiResponse::deprecation`].
+    pub fn with_on_deprecation(
+        mut self,
+        callback: impl Fn(Endpoint, &DeprecationNotice) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_deprecation = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Set a deprecation callback that is already shared
+    pub(crate) fn with_shared_on_deprecation(mut self, callback: DeprecationCallback) -> Self {
+        self.on_deprecation = Some(callback);
+        self
+    }
+    
+    /// Bound every call, including retries and backoff, to `deadline`
+    ///
+    /// Unlike the timeout, which applies to each attempt, this caps the
+    /// worst-case latency of a call. Backoff that would end past the deadline
+    /// is not waited out. Calls over the deadline fail with
+    /// [`Error::DeadlineExceeded`]; [`RequestOptions::deadline`] overrides it.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    
+    /// Fail with [`Error::ResponseTooLarge`] instead of reading response
+    /// bodies larger than `bytes`
+    ///
+    /// Guards long-running services against an upstream returning huge
+    /// bodies. Unlimited by default.
+    pub fn with_max_response_size(mut self, bytes: u64) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+    
+    /// Look for an existing order before creating one with a customer order reference
+    ///
+    /// If [`Client::find_order_by_reference`] finds one, creating the order
+    /// fails with [`Error::DuplicateReference`] holding the existing order
+    /// instead of shipping it twice. Costs one extra request per order.
+    pub fn with_duplicate_guard(mut self) -> Self {
+        self.duplicate_guard = true;
+        self
+    }
+    
+    /// Choose how order creation picks the `Idempotency-Key` it sends
+    ///
+    /// Orders get a random key by default, which makes retrying their
+    /// creation safe.
+    pub fn with_idempotency_keys(mut self, keys: IdempotencyKeys) -> Self {
+        self.idempotency_keys = keys;
+        self
+    }
+    
+    /// Remember the idempotency key of each order in `store`
+    ///
+    /// Creating an order with the same customer order reference again then
+    /// reuses its key, even after a restart, unless the API rejected the
+    /// order last time. Orders without a reference are not recorded.
+    pub fn with_idempotency_store(mut self, store: impl IdempotencyStore) -> Self {
+        self.idempotency_store = Some(Arc::new(store));
+        self
+    }
+    
+    /// Set an idempotency store that is already shared
+    pub(crate) fn with_shared_idempotency_store(mut self, store: Arc<dyn IdempotencyStore>) -> Self {
+        self.idempotency_store = Some(store);
+        self
+    }
+    
+    /// Record every order created through the client in `store`
+    ///
+    /// The record keeps the order's line items and its last known remote
+    /// state, which [`Client::get_order`], [`Client::cancel_order`] and
+    /// [`Client::list_orders`] update for orders already recorded. Calls do
+    /// not fail when the store does; the failure is passed to
+    /// [`Client::with_on_error`] instead.
+    pub fn with_order_store(mut self, store: impl OrderStore) -> Self {
+        self.order_store = Some(Arc::new(store));
+        self
+    }
+    
+    /// Treat orders in these statuses as final, e.g. delivered or cancelled
+    ///
+    /// [`Client::watch_order`] stops once the watched order reaches one of them.
+    pub fn with_terminal_statuses(mut self, statuses: impl IntoIterator<Item = u64>) -> Self {
+        self.terminal_statuses = statuses.into_iter().collect();
+        self
+    }
+    
+    /// Answer repeated order reads from a local cache for `ttl`
+    ///
+    /// [`Client::get_order`] and [`Client::list_orders`] then return what
+    /// they read within the last `ttl` instead of asking the API again.
+    /// Creating or cancelling orders through this client or its clones
+    /// invalidates the affected entries; call [`Client::invalidate_order`]
+    /// for changes made elsewhere. Lookups by reference and paginators
+    /// always ask the API.
+    pub fn with_order_cache(mut self, ttl: Duration) -> Self {
+        self.order_cache = Some(Arc::new(OrderCache::new(ttl)));
+        self
+    }
+    
+    /// Drop the order with `order_id`, and every cached page, from the order cache
+    pub fn invalidate_order(&self, order_id: &OrderId) {
+        if let Some(cache) = &self.order_cache {
+            cache.invalidate(order_id);
+        }
+    }
+    
+    /// Drop everything from the order cache
+    pub fn clear_order_cache(&self) {
+        if let Some(cache) = &self.order_cache {
+            cache.clear();
+        }
+    }
+    
+    /// Answer product listings from a cache on disk that outlives the process
+    ///
+    /// Pages read within the cache's time to live are returned without
+    /// asking the API. Older pages are fetched with their `ETag` in
+    /// `If-None-Match`, and kept for another time to live if the server
+    /// answers `304 Not Modified`. Applies to [`Client::list_products`],
+    /// [`Client::product_pages`] and the product streams.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_disk_cache(mut self, cache: DiskCache) -> Self {
+        self.disk_cache = Some(Arc::new(cache));
+        self
+    }
+    
+    /// Share the result of a read with identical reads made while it is in flight
+    ///
+    /// Concurrent calls to [`Client::get_order`], [`Client::list_orders`]
+    /// or [`Client::list_products`] with the same arguments, on this client
+    /// or its clones, then send a single request. Failures are shared as
+    /// well, except network and decoding errors that cannot be copied:
+    /// after those, one waiting call sends the request again for the rest.
+    pub fn with_request_coalescing(mut self) -> Self {
+        self.single_flight = Some(Arc::new(SingleFlight::new()));
+        self
+    }
+    
+    /// Keep the raw request and response of the last `capacity` exchanges
+    ///
+    /// Every attempt, redirect and login is captured; read them with
+    /// [`Client::debug_log`]. Meant for settling disputes about what was
+    /// sent, as bodies are buffered and kept in memory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_debug_capture(mut self, capacity: usize) -> Self {
+        self.debug_log = Some(Arc::new(DebugLog::new(capacity)));
         self
     }
     
+    /// Exchanges captured since debug capture was enabled, oldest first
+    ///
+    /// Empty unless [`Client::with_debug_capture`] is set.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn debug_log(&self) -> Vec<DebugEntry> {
+        self.debug_log
+            .as_ref()
+            .map(|debug_log| debug_log.entries())
+            .unwrap_or_default()
+    }
+    
+    /// Quota reported in the `X-RateLimit-*` headers of the most recent response
+    pub fn last_rate_limit(&self) -> Option<RateLimitStatus> {
+        *self.last_rate_limit.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Stop accepting calls and wait up to `deadline` for in-flight ones
+    ///
+    /// Applies to all clones of this client. Calls started afterwards fail
+    /// with [`Error::Shutdown`]; calls still running (or waiting to retry)
+    /// when the deadline passes are cancelled with the same error and listed
+    /// in the report.
+    pub async fn shutdown(&self, deadline: Duration) -> ShutdownReport {
+        self.lifecycle.shutdown(deadline).await
+    }
+    
     /// Create a new order
     pub async fn create_order(&self, request: CreateOrderRequest) -> Result<CreateOrderResponse> {
-        let url = format!("{}/api_customer/orders", self.base_url);
-        
-        let mut req_builder = self.http_client
-            .post(&url)
-            .json(&request);
-        
-        // Add authentication if configured
-        if let Some((email, token)) = &self.credentials {
-            let auth_string = format!("{}:{}", email, token);
-            let encoded = STANDARD.encode(auth_string.as_bytes());
-            req_builder = req_builder.header(
-                AUTHORIZATION,
-                HeaderValue::from_str(&format!("Basic {}", encoded))
-                    .map_err(|e| Error::InvalidCredentials(format!("Invalid auth header: {}", e)))?
-            );
+        self.create_order_with_meta(request)
+            .await
+            .map(ApiResponse::into_inner)
+    }
+    
+    /// Create a new order, returning the response metadata alongside the order
+    pub async fn create_order_with_meta(
+        &self,
+        request: CreateOrderRequest,
+    ) -> Result<ApiResponse<CreateOrderResponse>> {
+        self.create_order_with(request, &RequestOptions::default()).await
+    }
+    
+    /// Create a new order with per-request options such as an idempotency key
+    pub async fn create_order_with(
+        &self,
+        request: CreateOrderRequest,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse<CreateOrderResponse>> {
+        if let (true, Some(reference)) = (self.duplicate_guard, &request.customer_order_reference) {
+            if let Some(existing) = self.find_order_by_reference(CustomerOrderReference(reference.clone())).await? {
+                return Err(Error::DuplicateReference(Box::new(existing)));
+            }
+        }
+        self.post_order(&request, options).await
+    }
+    
+    /// Return the order with the request's customer order reference, creating it if there is none
+    ///
+    /// If the order appears between the lookup and the creation, e.g. from a
+    /// concurrent retry, the API rejects the second order with a conflict and
+    /// the order is looked up again. Requests without a reference are always
+    /// created.
+    pub async fn find_or_create_order(&self, request: CreateOrderRequest) -> Result<FindOrCreate> {
+        let Some(reference) = request.customer_order_reference.clone().map(CustomerOrderReference) else {
+            return self.create_order(request).await.map(|response| FindOrCreate::Created(Box::new(response)));
+        };
+        if let Some(existing) = self.find_order_by_reference(reference.clone()).await? {
+            return Ok(FindOrCreate::Existing(Box::new(existing)));
+        }
+        match self.post_order(&request, &RequestOptions::default()).await {
+            Ok(response) => Ok(FindOrCreate::Created(Box::new(response.into_inner()))),
+            Err(e) if matches!(e.inner(), Error::Conflict(_)) => match self.find_order_by_reference(reference).await? {
+                Some(existing) => Ok(FindOrCreate::Existing(Box::new(existing))),
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Send an order creation request
+    async fn post_order(
+        &self,
+        request: &CreateOrderRequest,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse<CreateOrderResponse>> {
+        let url = format!("{}{}", self.base_url, ORDERS_PATH);
+        let store = self.idempotency_store.as_ref().zip(request.customer_order_reference.as_deref());
+        let mut options = options.clone();
+        if options.idempotency_key_header().is_none() {
+            let stored = match store {
+                Some((store, reference)) => store
+                    .get(reference)
+                    .await
+                    .map_err(Error::Storage)?
+                    .filter(|record| !matches!(record.outcome, Outcome::Rejected { .. }))
+                    .map(|record| record.key),
+                None => None,
+            };
+            if let Some(key) = stored.or_else(|| self.idempotency_keys.key_for(request)) {
+                options = options.idempotency_key(key)?;
+            }
+        }
+        let idempotency_key = options.idempotency_key_header().map(str::to_string);
+        let store = store.zip(idempotency_key.as_deref());
+        if let Some(((store, reference), key)) = store {
+            // Sending without a recorded key could create a duplicate after a restart
+            store.put(reference, &IdempotencyRecord::pending(key)).await.map_err(Error::Storage)?;
         }
         
-        let response = req_builder
-            .send()
-            .await
-            .map_err(Error::Http)?;
+        let req_builder = self.request(Endpoint::CreateOrder, Method::POST, &url);
+        let req_builder = self.encode_body(req_builder, request)?;
         
-        // Handle different response status codes
-        let status = response.status();
-        if status.is_success() {
-            response
-                .json::<CreateOrderResponse>()
-                .await
-                .map_err(Error::Http)
-        } else {
-            let status_code = status.as_u16();
-            let error_text = response.text().await.unwrap_or_default();
-            
-            match status_code {
-                400 => Err(Error::BadRequest(error_text)),
-                401 => Err(Error::Unauthorized("Invalid credentials".to_string())),
-                404 => Err(Error::NotFound("Endpoint not found".to_string())),
-                429 => Err(Error::RateLimit("Rate limit exceeded".to_string())),
-                500..=599 => Err(Error::ServerError(status_code, error_text)),
-                _ => Err(Error::UnexpectedStatus(status_code, error_text)),
+        let result: Result<ApiResponse<CreateOrderResponse>> =
+            self.execute(Endpoint::CreateOrder, req_builder, true, &options).await;
+        if let Some(cache) = &self.order_cache {
+            cache.invalidate_pages();
+        }
+        if let Some(((store, reference), key)) = store {
+            let outcome = match &result {
+                Ok(response) => Outcome::Created {
+                    order_id: response.data.order.id,
+                },
+                Err(e) if e.kind() == ErrorKind::Validation => Outcome::Rejected { message: e.message() },
+                Err(_) => Outcome::Pending,
+            };
+            let record = IdempotencyRecord {
+                key: key.to_string(),
+                outcome,
+            };
+            // The pending record already holds the key, so a retry stays safe
+            self.report_store_failure(store.put(reference, &record).await);
+        }
+        let mut response = result?;
+        if let Some(store) = &self.order_store {
+            let created = &response.data;
+            let stored = StoredOrder::new(created.order.clone(), created.order_products.clone(), runtime::system_now());
+            self.report_store_failure(store.put(&stored).await);
+        }
+        response.idempotency_key = idempotency_key;
+        Ok(response)
+    }
+    
+    /// Create many orders, several at a time
+    ///
+    /// Every order carries an `Idempotency-Key` derived from the batch prefix
+    /// and its index, so failed submissions are retried with the client's
+    /// retry policy, or the one in `options`, without risking duplicates.
+    /// The report refers to orders by their index in `requests`.
+    ///
+    /// ```no_run
+    /// # use ecommerce_api_client::{BulkOptions, Client};
+    /// # use ecommerce_api_client::types::CreateOrderRequest;
+    /// # async fn example(client: Client, orders: Vec<CreateOrderRequest>) {
+    /// let options = BulkOptions::new().concurrency(8).idempotency_prefix("import-2024-06-01");
+    /// let report = client.create_orders_bulk(orders, options).await;
+    /// for failure in &report.permanent {
+    ///     eprintln!("order {} rejected: {}", failure.index, failure.error);
+    /// }
+    /// println!("resubmit later: {:?}", report.retryable_indices());
+    /// # }
+    /// ```
+    pub async fn create_orders_bulk(
+        &self,
+        requests: Vec<CreateOrderRequest>,
+        options: BulkOptions,
+    ) -> BulkReport {
+        let prefix = options.key_prefix();
+        self.bulk(requests, &options, |client, index, request| {
+            let options = RequestOptions::new().idempotency_key(format!("{}-{}", prefix, index));
+            async move {
+                client
+                    .create_order_with(request, &options?)
+                    .await
+                    .map(ApiResponse::into_inner)
             }
+        })
+        .await
+    }
+    
+    /// Create a group of orders that stand or fall together
+    ///
+    /// If the API rejects any order, the orders that were created are
+    /// cancelled again and the cancellations are listed in
+    /// [`SagaReport::compensations`]. Orders that failed transiently may
+    /// still have been created, so they are first sent again with the same
+    /// idempotency key and cancelled too if they exist; any still unresolved
+    /// stay in the report's `retryable` list and keep the group from counting
+    /// as rolled back.
+    ///
+    /// Without a rejection, transient failures do not trigger a rollback;
+    /// resubmit the group with [`SagaReport::idempotency_prefix`] to finish
+    /// it. A checkpoint in `options` is ignored, since orders created by an
+    /// earlier run could not be undone.
+    pub async fn create_orders_saga(&self, requests: Vec<CreateOrderRequest>, options: BulkOptions) -> SagaReport {
+        let idempotency_prefix = options.key_prefix();
+        let options = options.without_checkpoint().idempotency_prefix(idempotency_prefix.clone());
+        let mut report = self.create_orders_bulk(requests.clone(), options.clone()).await;
+        if report.permanent.is_empty() {
+            return SagaReport {
+                report,
+                compensations: Vec::new(),
+                idempotency_prefix,
+            };
+        }
+        
+        if !report.retryable.is_empty() {
+            let unresolved: Vec<_> = std::mem::take(&mut report.retryable)
+                .into_iter()
+                .map(|failure| (failure.index, requests[failure.index].clone()))
+                .collect();
+            let prefix = &idempotency_prefix;
+            let resent = self
+                .bulk(unresolved, &options, |client, _, (index, request)| {
+                    let options = RequestOptions::new().idempotency_key(format!("{}-{}", prefix, index));
+                    async move {
+                        let result = match options {
+                            Ok(options) => client.create_order_with(request, &options).await,
+                            Err(e) => Err(e),
+                        };
+                        Ok::<_, Error>((index, result.map(ApiResponse::into_inner)))
+                    }
+                })
+                .await;
+            // Each call succeeds with its order's own outcome, keyed by its index in the group
+            let resolved = BulkReport::from_results(resent.succeeded.into_iter().map(|success| success.response));
+            report.succeeded.extend(resolved.succeeded);
+            report.retryable.extend(resolved.retryable);
+            report.permanent.extend(resolved.permanent);
+            report.succeeded.sort_by_key(|success| success.index);
+            report.permanent.sort_by_key(|failure| failure.index);
+        }
+        
+        let created: Vec<_> = report
+            .succeeded
+            .iter()
+            .map(|success| (success.index, OrderId(success.response.order.id.to_string())))
+            .collect();
+        let order_ids: Vec<_> = created.iter().map(|(_, order_id)| order_id.clone()).collect();
+        let cancelled = self.cancel_orders_with(&order_ids, options.compensation()).await;
+        let mut results: Vec<Option<Result<Order>>> = order_ids.iter().map(|_| None).collect();
+        for success in cancelled.succeeded {
+            results[success.index] = Some(Ok(success.response));
+        }
+        for failure in cancelled.retryable.into_iter().chain(cancelled.permanent) {
+            results[failure.index] = Some(Err(failure.error));
+        }
+        let compensations = created
+            .into_iter()
+            .zip(results)
+            .filter_map(|((index, order_id), result)| Some(Compensation { index, order_id, result: result? }))
+            .collect();
+        SagaReport {
+            report,
+            compensations,
+            idempotency_prefix,
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
     
-    #[test]
-    fn test_client_creation() {
-        let client = Client::new("https://api.example.com").unwrap();
-        assert_eq!(client.base_url, "https://api.example.com");
-        assert!(client.credentials.is_none());
+    /// Fetch the order with `order_id`
+    pub async fn get_order(&self, order_id: &OrderId) -> Result<Order> {
+        self.get_order_with(order_id, &RequestOptions::default()).await
     }
     
-    #[test]
-    fn test_client_with_credentials() {
-        let client = Client::new("https://api.example.com")
-            .unwrap()
-            .with_credentials("test@example.com", "token123");
+    /// Fetch the order with `order_id` with per-request options such as a trace id
+    ///
+    /// The order cache is still consulted, but a fetch with options is not
+    /// coalesced with others in flight.
+    pub async fn get_order_with(&self, order_id: &OrderId, options: &RequestOptions) -> Result<Order> {
+        if let Some(order) = self.order_cache.as_ref().and_then(|cache| cache.order(order_id)) {
+            return Ok(order);
+        }
+        let url = format!("{}{}/{}", self.base_url, ORDERS_PATH, order_id.0);
+        let req_builder = self.request(Endpoint::GetOrder, Method::GET, &url);
         
-        assert!(client.credentials.is_some());
-        let (email, token) = client.credentials.unwrap();
-        assert_eq!(email, "test@example.com");
-        assert_eq!(token, "token123");
+        let call = async {
+            self.execute(Endpoint::GetOrder, req_builder, true, options)
+                .await
+                .map(ApiResponse::into_inner)
+        };
+        let order: Order = match options.is_empty() {
+            true => self.coalesce(url, call).await?,
+            false => call.await?,
+        };
+        if let Some(cache) = &self.order_cache {
+            cache.insert_order(&order);
+        }
+        self.refresh_stored(std::slice::from_ref(&order)).await;
+        Ok(order)
+    }
+    
+    /// Fetch the order with `order_id` unless it still has the `ETag` `etag`
+    ///
+    /// `etag` is sent in `If-None-Match`, so an unchanged order costs a
+    /// bodiless `304 Not Modified` instead of the whole order. Pass the
+    /// [`ApiResponse::etag`] of the last [`Conditional::Modified`], and keep
+    /// its order to use while it is unchanged. The order cache is not consulted.
+    pub async fn get_order_if_modified(
+        &self,
+        order_id: &OrderId,
+        etag: Option<&str>,
+    ) -> Result<Conditional<ApiResponse<Order>>> {
+        self.get_order_if_modified_with(order_id, etag, &RequestOptions::default()).await
+    }
+    
+    /// Fetch the order with `order_id` unless it still has the `ETag` `etag`, with per-request options
+    pub async fn get_order_if_modified_with(
+        &self,
+        order_id: &OrderId,
+        etag: Option<&str>,
+        options: &RequestOptions,
+    ) -> Result<Conditional<ApiResponse<Order>>> {
+        let url = format!("{}{}/{}", self.base_url, ORDERS_PATH, order_id.0);
+        let req_builder = self.request(Endpoint::GetOrder, Method::GET, &url);
+        
+        self.fetch_if_modified(Endpoint::GetOrder, req_builder, etag, options).await
+    }
+    
+    /// Poll the order with `order_id` every `interval`, yielding its state whenever it changes
+    ///
+    /// The first item is the order as first read. Each later poll sends the
+    /// `ETag` of the last response, and states equal to the previous one are
+    /// skipped. The stream ends after yielding a status set with
+    /// [`Client::with_terminal_statuses`], or after an error that retrying
+    /// cannot fix, such as [`Error::NotFound`]. Other errors are yielded and
+    /// polling continues.
+    ///
+    /// ```no_run
+    /// # use ecommerce_api_client::{types::OrderId, Client};
+    /// # use std::time::Duration;
+    /// use futures_util::{pin_mut, StreamExt};
+    ///
+    /// # async fn example(client: Client, order_id: OrderId) -> ecommerce_api_client::Result<()> {
+    /// let changes = client.watch_order(&order_id, Duration::from_secs(60));
+    /// pin_mut!(changes);
+    /// while let Some(change) = changes.next().await {
+    ///     let change = change?;
+    ///     if change.status_changed() {
+    ///         println!("order {} is now in status {}", change.order.id, change.order.status_order_id);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch_order(&self, order_id: &OrderId, interval: Duration) -> impl Stream<Item = Result<OrderChange>> {
+        self.watch_order_with(order_id, Polling::Interval(interval))
+    }
+    
+    /// Follow the order with `order_id` through a long-poll endpoint, yielding its state whenever it changes
+    ///
+    /// Each request asks the API to hold the response for up to `wait`
+    /// (`?wait=60s`) until the order differs from the `ETag` sent, and the
+    /// next request is issued as soon as one returns, so changes arrive with
+    /// less delay and fewer requests than with [`Client::watch_order`]. The
+    /// timeout of these requests is extended to `wait` plus a grace period.
+    /// An API that answers at once without a change is asked again only
+    /// after the rest of `wait`, so ignoring the parameter degrades to
+    /// polling every `wait`. The stream ends as [`Client::watch_order`]'s does.
+    pub fn long_poll_order(&self, order_id: &OrderId, wait: Duration) -> impl Stream<Item = Result<OrderChange>> {
+        self.watch_order_with(order_id, Polling::LongPoll(wait))
+    }
+    
+    /// Stream the changes of an order, read the way `polling` says
+    fn watch_order_with(&self, order_id: &OrderId, polling: Polling) -> impl Stream<Item = Result<OrderChange>> {
+        struct Watch {
+            client: Client,
+            url: String,
+            etag: Option<String>,
+            last: Option<Order>,
+            delay: Option<Duration>,
+        }
+        
+        let watch = Watch {
+            client: self.clone(),
+            url: format!("{}{}/{}", self.base_url, ORDERS_PATH, order_id.0),
+            etag: None,
+            last: None,
+            delay: None,
+        };
+        stream::unfold(Some(watch), move |watch| async move {
+            let mut watch = watch?;
+            loop {
+                if let Some(delay) = watch.delay.take() {
+                    runtime::sleep(delay).await;
+                }
+                
+                let client = &watch.client;
+                let mut req_builder = client.request(Endpoint::GetOrder, Method::GET, &watch.url);
+                let mut options = RequestOptions::default();
+                if let Polling::LongPoll(wait) = polling {
+                    req_builder = req_builder.query(&[("wait", format!("{}s", wait.as_secs().max(1)))]);
+                    let timeout = wait + LONG_POLL_GRACE;
+                    options = options.timeout(timeout);
+                    if client.deadline.is_some_and(|deadline| deadline < timeout) {
+                        options = options.deadline(timeout);
+                    }
+                }
+                if let Some(etag) = &watch.etag {
+                    req_builder = req_builder.header(IF_NONE_MATCH, etag);
+                }
+                
+                let started = Instant::now();
+                let response = client
+                    .execute::<Option<Order>>(Endpoint::GetOrder, req_builder, true, &options)
+                    .await;
+                watch.delay = match polling {
+                    Polling::Interval(interval) => Some(interval),
+                    Polling::LongPoll(_) if response.is_err() => Some(LONG_POLL_ERROR_DELAY),
+                    // Unless the order changed, an early answer means the API did not hold the request
+                    Polling::LongPoll(wait) => wait.checked_sub(started.elapsed()),
+                };
+                let mut response = match response {
+                    Ok(response) => response,
+                    Err(e) => {
+                        let retry = e.is_retryable();
+                        return Some((Err(e), retry.then_some(watch)));
+                    }
+                };
+                if response.etag.is_some() {
+                    watch.etag = response.etag.take();
+                }
+                // Not modified, or modified in ways the order does not show
+                let Some(order) = response.data.take() else {
+                    continue;
+                };
+                if watch.last.as_ref().is_some_and(|last| last.diff(&order).is_empty()) {
+                    continue;
+                }
+                
+                if matches!(polling, Polling::LongPoll(_)) {
+                    watch.delay = None;
+                }
+                client.refresh_stored(std::slice::from_ref(&order)).await;
+                let terminal = client.terminal_statuses.contains(&order.status_order_id);
+                let change = OrderChange::new(watch.last.replace(order.clone()), order);
+                return Some((Ok(change), (!terminal).then_some(watch)));
+            }
+        })
+    }
+    
+    /// Cancel an order, returning it in its cancelled state
+    pub async fn cancel_order(&self, order_id: &OrderId) -> Result<Order> {
+        self.cancel_order_with(order_id, &RequestOptions::default()).await
+    }
+    
+    /// Cancel an order with per-request options such as an idempotency key
+    pub async fn cancel_order_with(&self, order_id: &OrderId, options: &RequestOptions) -> Result<Order> {
+        let url = format!("{}{}/{}", self.base_url, ORDERS_PATH, order_id.0);
+        let req_builder = self.request(Endpoint::CancelOrder, Method::DELETE, &url);
+        
+        let result = self
+            .execute(Endpoint::CancelOrder, req_builder, true, options)
+            .await
+            .map(ApiResponse::into_inner);
+        if let Some(cache) = &self.order_cache {
+            cache.invalidate(order_id);
+            if let Ok(order) = &result {
+                cache.insert_order(order);
+            }
+        }
+        if let Ok(order) = &result {
+            self.refresh_stored(std::slice::from_ref(order)).await;
+        }
+        result
+    }
+    
+    /// Update the remote state of the orders already in the order store
+    async fn refresh_stored(&self, orders: &[Order]) {
+        let Some(store) = &self.order_store else {
+            return;
+        };
+        for order in orders {
+            match store.get(order.id).await {
+                Ok(Some(mut stored)) => {
+                    stored.order = order.clone();
+                    stored.refreshed_at = runtime::system_now();
+                    self.report_store_failure(store.put(&stored).await);
+                }
+                Ok(None) => {}
+                Err(e) => self.report_store_failure(Err(e)),
+            }
+        }
+    }
+    
+    /// Pass a store failure that does not fail the call to the error callback as [`Error::Storage`]
+    fn report_store_failure(&self, result: std::io::Result<()>) {
+        if let (Err(e), Some(on_error)) = (result, &self.on_error) {
+            on_error(&Error::Storage(e));
+        }
+    }
+    
+    /// Cancel many orders, several at a time
+    ///
+    /// The report refers to orders by their index in `order_ids`.
+    pub async fn cancel_orders(&self, order_ids: &[OrderId]) -> BulkReport<Order> {
+        self.cancel_orders_with(order_ids, BulkOptions::default()).await
+    }
+    
+    /// Cancel many orders with the concurrency and retry policy in `options`
+    ///
+    /// Cancelling is idempotent, so the idempotency prefix is not used.
+    pub async fn cancel_orders_with(&self, order_ids: &[OrderId], options: BulkOptions) -> BulkReport<Order> {
+        self.bulk(order_ids.iter().collect(), &options, |client, _, order_id| async move {
+            client.cancel_order(order_id).await
+        })
+        .await
+    }
+    
+    /// Run `call` for every item with the concurrency, retry policy and checkpoint in `options`
+    ///
+    /// If the checkpoint cannot be loaded, no item is run and every item is
+    /// reported as retryable with the storage error.
+    async fn bulk<I, T, F, Fut>(&self, items: Vec<I>, options: &BulkOptions, call: F) -> BulkReport<T>
+    where
+        F: Fn(Client, usize, I) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let client = match options.retry_policy() {
+            Some(policy) => self.clone().with_shared_retry(policy.clone()),
+            None => self.clone(),
+        };
+        let checkpointing = options.checkpointing();
+        let mut checkpoint = match checkpointing {
+            Some((store, key)) => match store.load(key).await {
+                Ok(checkpoint) => checkpoint.unwrap_or_default(),
+                Err(e) => {
+                    let failures = (0..items.len()).map(|index| BulkFailure {
+                        index,
+                        error: Error::Storage(std::io::Error::new(e.kind(), e.to_string())),
+                    });
+                    return BulkReport {
+                        retryable: failures.collect(),
+                        ..Default::default()
+                    };
+                }
+            },
+            None => Checkpoint::default(),
+        };
+        let (resumed, items): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .enumerate()
+            .partition(|(index, _)| checkpoint.completed.contains(index));
+        
+        let progress = ProgressTracker::maybe(options.progress_observer(), Phase::Submitting, Some(items.len() as u64));
+        let pending = stream::iter(items)
+            .map(|(index, item)| {
+                let result = call(client.clone(), index, item);
+                async move { (index, result.await) }
+            })
+            .buffer_unordered(options.concurrency_limit());
+        futures_util::pin_mut!(pending);
+        let mut results = Vec::new();
+        let mut unsaved = 0;
+        while let Some((index, result)) = pending.next().await {
+            if let Some(progress) = &progress {
+                progress.report(results.len() as u64 + 1);
+            }
+            if let (Some((store, key)), Ok(_)) = (checkpointing, &result) {
+                checkpoint.completed.insert(index);
+                unsaved += 1;
+                if unsaved >= options.checkpoint_interval() {
+                    self.report_store_failure(store.save(key, &checkpoint).await);
+                    unsaved = 0;
+                }
+            }
+            results.push((index, result));
+        }
+        results.sort_by_key(|(index, _)| *index);
+        
+        let mut report = BulkReport::from_results(results);
+        report.resumed = resumed.into_iter().map(|(index, _)| index).collect();
+        if let Some((store, key)) = checkpointing {
+            if report.is_complete() {
+                self.report_store_failure(store.clear(key).await);
+            } else if unsaved > 0 {
+                self.report_store_failure(store.save(key, &checkpoint).await);
+            }
+        }
+        report
+    }
+    
+    /// Fetch one page of the orders matching `filter`
+    pub async fn list_orders(&self, filter: &OrderFilter, page: &PageRequest) -> Result<Page<Order>> {
+        self.list_orders_with(filter, page, &RequestOptions::default()).await
+    }
+    
+    /// Fetch one page of the orders matching `filter` with per-request options
+    ///
+    /// See [`Client::get_order_with`].
+    pub async fn list_orders_with(
+        &self,
+        filter: &OrderFilter,
+        page: &PageRequest,
+        options: &RequestOptions,
+    ) -> Result<Page<Order>> {
+        let query = filter.query();
+        if let Some(cached) = self.order_cache.as_ref().and_then(|cache| cache.page(&query, page)) {
+            return Ok(cached);
+        }
+        let orders = self
+            .coalesced_page(Endpoint::ListOrders, ORDERS_PATH, &query, page, options)
+            .await?;
+        if let Some(cache) = &self.order_cache {
+            cache.insert_page(&query, page, &orders);
+        }
+        self.refresh_stored(&orders.items).await;
+        Ok(orders)
+    }
+    
+    /// Fetch one page of the orders matching `filter` unless it still has the `ETag` `etag`
+    ///
+    /// See [`Client::get_order_if_modified`].
+    pub async fn list_orders_if_modified(
+        &self,
+        filter: &OrderFilter,
+        page: &PageRequest,
+        etag: Option<&str>,
+    ) -> Result<Conditional<ApiResponse<Page<Order>>>> {
+        self.list_orders_if_modified_with(filter, page, etag, &RequestOptions::default()).await
+    }
+    
+    /// Fetch one page of the orders matching `filter` unless it still has the `ETag` `etag`, with per-request options
+    pub async fn list_orders_if_modified_with(
+        &self,
+        filter: &OrderFilter,
+        page: &PageRequest,
+        etag: Option<&str>,
+        options: &RequestOptions,
+    ) -> Result<Conditional<ApiResponse<Page<Order>>>> {
+        let url = format!("{}{}", self.base_url, ORDERS_PATH);
+        let req_builder = self
+            .request(Endpoint::ListOrders, Method::GET, &url)
+            .query(&filter.query())
+            .query(&page.query());
+        
+        let response = self.fetch_if_modified(Endpoint::ListOrders, req_builder, etag, options).await?;
+        Ok(response.modified().map_or(Conditional::NotModified, |mut response| {
+            let items = std::mem::take(&mut response.data);
+            let fetched = Page::from_response(response.clone().map(|_| items), page);
+            Conditional::Modified(response.map(|_| fetched))
+        }))
+    }
+    
+    /// Page through the orders matching `filter`, starting at `first`
+    pub fn order_pages(&self, filter: &OrderFilter, first: PageRequest) -> Paginator<Order> {
+        Paginator::new(self.clone(), Endpoint::ListOrders, ORDERS_PATH, filter.query(), first)
+    }
+    
+    /// Fetch the orders matching `filter` into memory, failing if `limits` are exceeded
+    pub async fn list_all_orders(&self, filter: &OrderFilter, limits: ListLimits) -> Result<Vec<Order>> {
+        self.order_pages(filter, PageRequest::first_page(limits.per_page))
+            .collect_all(limits)
+            .await
+    }
+    
+    /// Stream the orders matching `filter`, fetching pages of `per_page` as they are consumed
+    ///
+    /// ```no_run
+    /// # use ecommerce_api_client::{Client, OrderFilter};
+    /// use futures_util::{pin_mut, StreamExt};
+    ///
+    /// # async fn example(client: Client) -> ecommerce_api_client::Result<()> {
+    /// let orders = client.orders_stream(&OrderFilter::new().status(3), 100);
+    /// pin_mut!(orders);
+    /// while let Some(order) = orders.next().await {
+    ///     println!("{}", order?.customer_order_reference);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn orders_stream(&self, filter: &OrderFilter, per_page: u64) -> impl Stream<Item = Result<Order>> {
+        self.orders_stream_from(filter, PageRequest::first_page(per_page))
+    }
+    
+    /// Stream the orders matching `filter`, resuming at a saved [`PageRequest`]
+    pub fn orders_stream_from(&self, filter: &OrderFilter, resume: PageRequest) -> impl Stream<Item = Result<Order>> {
+        self.order_pages(filter, resume).into_stream()
+    }
+    
+    /// Find the order with our own customer order reference
+    ///
+    /// Returns `None` if no order has the reference, and
+    /// [`Error::AmbiguousReference`] if several do. Orders the search
+    /// returns with a different reference, e.g. prefix matches, are ignored.
+    pub async fn find_order_by_reference(&self, reference: CustomerOrderReference) -> Result<Option<Order>> {
+        let query = OrderFilter::new().customer_order_reference(reference.0.as_str()).query();
+        let first = PageRequest::first_page(REFERENCE_MATCHES);
+        let page = match self.fetch_page::<Order>(Endpoint::ListOrders, ORDERS_PATH, &query, &first).await {
+            Ok(page) => page,
+            Err(e) if matches!(e.inner(), Error::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut matches: Vec<Order> = page
+            .items
+            .into_iter()
+            .filter(|order| order.customer_order_reference == reference.0)
+            .collect();
+        match matches.len() {
+            0 | 1 => Ok(matches.pop()),
+            count => Err(Error::AmbiguousReference {
+                reference: reference.0,
+                matches: count,
+            }),
+        }
+    }
+    
+    /// Compare the orders `store` recorded as created in `from..until` with the API's
+    ///
+    /// Lists the orders the API reports as created in the range within
+    /// `limits`. Recorded orders the listing lacks are looked up one by one,
+    /// since the local and remote creation times can straddle the range's
+    /// ends, and so are listed orders that were recorded outside the range.
+    /// Neither the store nor the order cache is updated.
+    pub async fn reconcile_orders(
+        &self,
+        store: &dyn OrderStore,
+        from: SystemTime,
+        until: SystemTime,
+        limits: ListLimits,
+    ) -> Result<ReconcileReport> {
+        let query = OrderQuery::new().recorded_between(from, until);
+        let mut local: BTreeMap<u64, StoredOrder> = store
+            .query(&query)
+            .await
+            .map_err(Error::Storage)?
+            .into_iter()
+            .map(|stored| (stored.order.id, stored))
+            .collect();
+        let filter = OrderFilter::new().created_from(from).created_to(until);
+        let remote = self.list_all_orders(&filter, limits).await?;
+        
+        let mut report = ReconcileReport::default();
+        for order in remote {
+            let recorded = match local.remove(&order.id) {
+                Some(stored) => Some(stored),
+                None => store.get(order.id).await.map_err(Error::Storage)?,
+            };
+            match recorded {
+                Some(stored) => report.compare(stored, order),
+                None => report.missing_locally.push(order),
+            }
+        }
+        for stored in local.into_values() {
+            let url = format!("{}{}/{}", self.base_url, ORDERS_PATH, stored.order.id);
+            let req_builder = self.request(Endpoint::GetOrder, Method::GET, &url);
+            let result = self
+                .execute(Endpoint::GetOrder, req_builder, true, &RequestOptions::default())
+                .await
+                .map(ApiResponse::into_inner);
+            match result {
+                Ok(order) => report.compare(stored, order),
+                Err(e) if matches!(e.inner(), Error::NotFound(_)) => report.missing_remotely.push(stored),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(report)
+    }
+    
+    /// Fetch one page of products
+    pub async fn list_products(&self, page: &PageRequest) -> Result<Page<Product>> {
+        self.list_products_with(page, &RequestOptions::default()).await
+    }
+    
+    /// Fetch one page of products with per-request options
+    ///
+    /// See [`Client::get_order_with`].
+    pub async fn list_products_with(&self, page: &PageRequest, options: &RequestOptions) -> Result<Page<Product>> {
+        self.coalesced_page(Endpoint::ListProducts, PRODUCTS_PATH, &[], page, options).await
+    }
+    
+    /// Stream every product from one unpaginated response
+    ///
+    /// Products are decoded as the body arrives rather than after buffering
+    /// it, so catalogues of hundreds of megabytes can be synced with bounded
+    /// memory. The maximum response size, if set, limits each product.
+    /// Only JSON responses are supported, and the request is not retried.
+    pub async fn products_unpaged(&self) -> Result<impl Stream<Item = Result<Product>>> {
+        self.stream_array(PRODUCTS_PATH, Vec::new()).await
+    }
+    
+    /// Stream the orders matching `filter` from one unpaginated response
+    ///
+    /// See [`Client::products_unpaged`].
+    pub async fn orders_unpaged(&self, filter: &OrderFilter) -> Result<impl Stream<Item = Result<Order>>> {
+        self.stream_array(ORDERS_PATH, filter.query()).await
+    }
+    
+    /// Page through all products, starting at `first`
+    pub fn product_pages(&self, first: PageRequest) -> Paginator<Product> {
+        Paginator::new(self.clone(), Endpoint::ListProducts, PRODUCTS_PATH, Vec::new(), first)
+    }
+    
+    /// Stream every product, fetching pages of `per_page` as they are consumed
+    pub fn products_stream(&self, per_page: u64) -> impl Stream<Item = Result<Product>> {
+        self.products_stream_from(PageRequest::first_page(per_page))
+    }
+    
+    /// Stream every product, resuming at a saved [`PageRequest`]
+    pub fn products_stream_from(&self, resume: PageRequest) -> impl Stream<Item = Result<Product>> {
+        self.product_pages(resume).into_stream()
+    }
+    
+    /// Start an export job, returning its id
+    pub async fn request_export(&self, spec: &ExportSpec) -> Result<ExportJobId> {
+        self.request_export_with(spec, &RequestOptions::default()).await
+    }
+    
+    /// Start an export job with per-request options such as an idempotency key
+    pub async fn request_export_with(&self, spec: &ExportSpec, options: &RequestOptions) -> Result<ExportJobId> {
+        let url = format!("{}{}", self.base_url, EXPORTS_PATH);
+        let req_builder = self.request(Endpoint::Export, Method::POST, &url);
+        let req_builder = self.encode_body(req_builder, spec)?;
+        
+        let response: ApiResponse<ExportJob> = self.execute(Endpoint::Export, req_builder, true, options).await?;
+        Ok(response.data.id)
+    }
+    
+    /// Fetch the current state of an export job
+    pub async fn poll_export(&self, job_id: &ExportJobId) -> Result<ExportJob> {
+        self.poll_export_with(job_id, &RequestOptions::default()).await
+    }
+    
+    /// Fetch the current state of an export job with per-request options
+    pub async fn poll_export_with(&self, job_id: &ExportJobId, options: &RequestOptions) -> Result<ExportJob> {
+        let url = format!("{}{}/{}", self.base_url, EXPORTS_PATH, job_id);
+        let req_builder = self.request(Endpoint::Export, Method::GET, &url);
+        
+        self.execute(Endpoint::Export, req_builder, true, options)
+            .await
+            .map(ApiResponse::into_inner)
+    }
+    
+    /// Stream the file of a completed export job
+    ///
+    /// The file is neither buffered nor limited by the maximum response size,
+    /// and the download is not retried.
+    pub async fn download_export(&self, job_id: &ExportJobId) -> Result<impl Stream<Item = Result<Bytes>>> {
+        self.download(job_id, None).await
+    }
+    
+    /// Stream an export file, reporting the bytes received to `progress`
+    async fn download(
+        &self,
+        job_id: &ExportJobId,
+        progress: Option<&Arc<dyn ProgressObserver>>,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let url = format!("{}{}/{}/download", self.base_url, EXPORTS_PATH, job_id);
+        let req_builder = self.http_client.get(&url).header(ACCEPT, "*/*");
+        
+        let response = self.send(req_builder, true).await?;
+        let response = self.check_status(response).await?;
+        let progress = ProgressTracker::maybe(progress, Phase::Downloading, response.content_length());
+        let mut received = 0;
+        Ok(response::body_stream(response).inspect(move |chunk| {
+            if let (Ok(chunk), Some(progress)) = (chunk, &progress) {
+                received += chunk.len() as u64;
+                progress.report(received);
+            }
+        }))
+    }
+    
+    /// Request an export, poll it with growing intervals and stream its file
+    ///
+    /// Fails with [`Error::ExportFailed`] if the job fails, and with
+    /// [`Error::DeadlineExceeded`] if it is still running after `wait.timeout`.
+    ///
+    /// ```no_run
+    /// # use ecommerce_api_client::{Client, OrderFilter};
+    /// # use ecommerce_api_client::export::{ExportSpec, ExportWait};
+    /// use futures_util::{pin_mut, StreamExt};
+    ///
+    /// # async fn example(client: Client) -> ecommerce_api_client::Result<()> {
+    /// let file = client
+    ///     .export_and_wait(&ExportSpec::orders(&OrderFilter::new()), ExportWait::default())
+    ///     .await?;
+    /// pin_mut!(file);
+    /// while let Some(chunk) = file.next().await {
+    ///     println!("{} bytes", chunk?.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_and_wait(
+        &self,
+        spec: &ExportSpec,
+        wait: ExportWait,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let started = Instant::now();
+        let job_id = self.request_export(spec).await?;
+        let waiting = ProgressTracker::maybe(wait.progress.as_ref(), Phase::Waiting, Some(100));
+        let mut interval = wait.initial_interval;
+        let mut polls = 0;
+        loop {
+            if started.elapsed() + interval > wait.timeout {
+                return Err(Error::DeadlineExceeded {
+                    deadline: wait.timeout,
+                    attempts: polls,
+                    elapsed: started.elapsed(),
+                    last_error: None,
+                });
+            }
+            runtime::sleep(interval).await;
+            let job = self.poll_export(&job_id).await?;
+            polls += 1;
+            if let (Some(waiting), Some(percent)) = (&waiting, job.progress) {
+                waiting.report(percent.clamp(0.0, 100.0) as u64);
+            }
+            match job.status {
+                ExportStatus::Completed => return self.download(&job_id, wait.progress.as_ref()).await,
+                ExportStatus::Failed => {
+                    return Err(Error::ExportFailed {
+                        job_id: job_id.0,
+                        message: job.error.unwrap_or_default(),
+                    })
+                }
+                _ => interval = wait.next_interval(interval),
+            }
+        }
+    }
+    
+    /// Subscribe to the API's Server-Sent Events stream
+    ///
+    /// Yields each event as it is pushed. When the connection drops, it is
+    /// reopened after the delay the server last asked for (3 seconds by
+    /// default) with the `Last-Event-ID` of the last event received, so the
+    /// server can replay what was missed. Failures to connect are yielded
+    /// and retried, except those that retrying cannot fix, such as
+    /// [`Error::Unauthorized`], which end the stream. So do messages that
+    /// are not valid events, without ending it.
+    ///
+    /// ```no_run
+    /// # use ecommerce_api_client::Client;
+    /// use futures_util::{pin_mut, StreamExt};
+    ///
+    /// # async fn example(client: Client) -> ecommerce_api_client::Result<()> {
+    /// let events = client.subscribe_events();
+    /// pin_mut!(events);
+    /// while let Some(event) = events.next().await {
+    ///     let event = event?;
+    ///     println!("{:?}: {}", event.id, event.payload.event_type());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+    pub fn subscribe_events(&self) -> impl Stream<Item = Result<ApiEvent>> {
+        self.subscribe_events_from(None)
+    }
+    
+    /// Subscribe to the event stream, resuming after the event with `last_event_id`
+    #[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+    pub fn subscribe_events_from(&self, last_event_id: Option<String>) -> impl Stream<Item = Result<ApiEvent>> {
+        struct Subscription {
+            client: Client,
+            decoder: sse::Decoder,
+            body: Option<Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>>,
+            pending: std::collections::VecDeque<Result<ApiEvent>>,
+            connected: bool,
+        }
+        
+        let subscription = Subscription {
+            client: self.clone(),
+            decoder: sse::Decoder::new(last_event_id),
+            body: None,
+            pending: Default::default(),
+            connected: false,
+        };
+        stream::unfold(Some(subscription), |subscription| async move {
+            let mut subscription = subscription?;
+            loop {
+                if let Some(event) = subscription.pending.pop_front() {
+                    return Some((event, Some(subscription)));
+                }
+                let Some(body) = &mut subscription.body else {
+                    if subscription.connected {
+                        runtime::sleep(subscription.decoder.retry.unwrap_or(EVENT_STREAM_RETRY)).await;
+                    }
+                    subscription.connected = true;
+                    match subscription.client.connect_events(subscription.decoder.last_event_id.as_deref()).await {
+                        Ok(response) => subscription.body = Some(Box::pin(response::body_stream(response))),
+                        Err(e) => {
+                            let retry = e.is_retryable();
+                            return Some((Err(e), retry.then_some(subscription)));
+                        }
+                    }
+                    continue;
+                };
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        let messages = subscription.decoder.push(&chunk);
+                        subscription.pending.extend(messages.into_iter().map(sse::Message::into_event));
+                    }
+                    // Disconnected: reconnect from the last event received
+                    Some(Err(_)) | None => {
+                        subscription.body = None;
+                        subscription.decoder.reset();
+                    }
+                }
+            }
+        })
+    }
+    
+    /// Open a connection to the event stream
+    #[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+    async fn connect_events(&self, last_event_id: Option<&str>) -> Result<reqwest::Response> {
+        let url = format!("{}{}", self.base_url, EVENTS_PATH);
+        // A long timeout of its own, as the client's would cut the stream off
+        let mut req_builder = self
+            .http_client
+            .get(&url)
+            .header(ACCEPT, "text/event-stream")
+            .header(reqwest::header::CACHE_CONTROL, "no-cache")
+            .timeout(EVENT_STREAM_TIMEOUT);
+        if let Some(id) = last_event_id {
+            req_builder = req_builder.header("Last-Event-ID", id);
+        }
+        
+        let response = self.send(req_builder, true).await?;
+        self.check_status(response).await
+    }
+    
+    /// Follow a WebSocket feed of events, yielding each one as it is pushed
+    ///
+    /// The upgrade request carries the client's credentials; session
+    /// cookies are not sent, and TLS uses the default roots rather than the
+    /// client's TLS settings. While the connection is quiet it is pinged,
+    /// and one that stops answering is dropped. Dropped connections are
+    /// reopened after the backoff in `options`, which grows while
+    /// connecting fails. Failures to connect are yielded; those that
+    /// retrying cannot fix, such as [`Error::Unauthorized`], end the stream.
+    /// Frames that are not valid events are yielded as errors too.
+    ///
+    /// ```no_run
+    /// # use ecommerce_api_client::Client;
+    /// # use ecommerce_api_client::websocket::WebSocketOptions;
+    /// use futures_util::{pin_mut, StreamExt};
+    ///
+    /// # async fn example(client: Client) -> ecommerce_api_client::Result<()> {
+    /// let events = client.subscribe_websocket(WebSocketOptions::new().url("wss://feed.example.com/events"));
+    /// pin_mut!(events);
+    /// while let Some(event) = events.next().await {
+    ///     println!("{}", event?.payload.event_type());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+    pub fn subscribe_websocket(&self, options: WebSocketOptions) -> impl Stream<Item = Result<ApiEvent>> {
+        type Socket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+        
+        struct Feed {
+            client: Client,
+            options: WebSocketOptions,
+            socket: Option<Socket>,
+            pending: std::collections::VecDeque<Result<ApiEvent>>,
+            attempted: bool,
+            failures: u32,
+            awaiting_pong: bool,
+        }
+        
+        let feed = Feed {
+            client: self.clone(),
+            options,
+            socket: None,
+            pending: Default::default(),
+            attempted: false,
+            failures: 0,
+            awaiting_pong: false,
+        };
+        stream::unfold(Some(feed), |feed| async move {
+            let mut feed = feed?;
+            loop {
+                if let Some(event) = feed.pending.pop_front() {
+                    return Some((event, Some(feed)));
+                }
+                let Some(socket) = &mut feed.socket else {
+                    if feed.attempted {
+                        runtime::sleep(feed.options.reconnect_delay(feed.failures)).await;
+                    }
+                    feed.attempted = true;
+                    match feed.client.connect_websocket(&feed.options).await {
+                        Ok(socket) => {
+                            feed.socket = Some(socket);
+                            feed.failures = 0;
+                            feed.awaiting_pong = false;
+                        }
+                        Err(e) => {
+                            feed.failures = feed.failures.saturating_add(1);
+                            let retry = e.is_retryable();
+                            return Some((Err(e), retry.then_some(feed)));
+                        }
+                    }
+                    continue;
+                };
+                
+                let quiet = match feed.awaiting_pong {
+                    true => feed.options.pong_timeout,
+                    false => feed.options.ping_interval,
+                };
+                let lost = match runtime::timeout(quiet, socket.next()).await {
+                    // Quiet for too long: ping, or give up on a connection that did not answer
+                    None if feed.awaiting_pong => true,
+                    None => {
+                        feed.awaiting_pong = true;
+                        socket.send(tungstenite::Message::Ping(Vec::new())).await.is_err()
+                    }
+                    Some(Some(Ok(message))) => {
+                        feed.awaiting_pong = false;
+                        match message {
+                            tungstenite::Message::Text(text) => feed.pending.push_back(websocket::parse_frame(text.as_bytes())),
+                            tungstenite::Message::Binary(data) => feed.pending.push_back(websocket::parse_frame(&data)),
+                            _ => {}
+                        }
+                        false
+                    }
+                    // Closed or broken; a close frame was already answered
+                    Some(Some(Err(_))) | Some(None) => true,
+                };
+                if lost {
+                    feed.socket = None;
+                }
+            }
+        })
+    }
+    
+    /// Open an authenticated WebSocket connection to the event feed
+    #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+    async fn connect_websocket(
+        &self,
+        options: &WebSocketOptions,
+    ) -> Result<tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>> {
+        use tungstenite::client::IntoClientRequest;
+        
+        let url = match &options.url {
+            Some(url) => url.clone(),
+            // `http` becomes `ws` and `https` becomes `wss`
+            None => format!("{}{}", self.base_url, EVENTS_PATH).replacen("http", "ws", 1),
+        };
+        let mut request = url.as_str().into_client_request().map_err(Error::transport_unsent)?;
+        let authorized = self.authorize(self.http_client.get(&self.base_url)).await?;
+        let authorized = authorized.build().map_err(Error::Http)?;
+        for (name, value) in authorized.headers() {
+            request.headers_mut().insert(name, value.clone());
+        }
+        
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((socket, _)) => Ok(socket),
+            Err(tungstenite::Error::Http(response)) => {
+                let status = response.status().as_u16();
+                let (parts, body) = response.into_parts();
+                let response = reqwest::Response::from(http::Response::from_parts(parts, body.unwrap_or_default()));
+                Err(self.check_status(response).await.err().unwrap_or(Error::UnexpectedStatus(status, String::new())))
+            }
+            Err(e) => Err(Error::transport(e)),
+        }
+    }
+    
+    /// Request a JSON array and decode its items as they arrive
+    async fn stream_array<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: Vec<(String, String)>,
+    ) -> Result<impl Stream<Item = Result<T>>> {
+        let url = format!("{}{}", self.base_url, path);
+        // Without the endpoint timeout, which would cut off long downloads
+        let req_builder = self
+            .http_client
+            .get(&url)
+            .header(ACCEPT, Format::Json.accept())
+            .query(&query);
+        
+        let response = self.send(req_builder, true).await?;
+        let response = self.check_status(response).await?;
+        
+        let mut decoder = match self.envelope {
+            Envelope::None => JsonArrayDecoder::new(),
+            Envelope::Wrapped | Envelope::Auto => JsonArrayDecoder::field("data"),
+        };
+        if let Some(limit) = self.max_response_size {
+            decoder = decoder.max_item_size(limit);
+        }
+        let chunks = response::body_stream(response);
+        let items = chunks
+            .map(Some)
+            .chain(stream::once(async { None }))
+            .scan(Some(decoder), |decoder, chunk| {
+                let items: Vec<Result<T>> = match (decoder.as_mut(), chunk) {
+                    (None, _) => return future::ready(None),
+                    (Some(decoder), Some(Ok(chunk))) => match decoder.push(&chunk) {
+                        Ok(items) => items.into_iter().map(Ok).collect(),
+                        Err(e) => vec![Err(e)],
+                    },
+                    (Some(_), Some(Err(e))) => vec![Err(e)],
+                    (Some(_), None) => match decoder.take().map(JsonArrayDecoder::finish) {
+                        Some(Err(e)) => vec![Err(e)],
+                        _ => Vec::new(),
+                    },
+                };
+                if items.iter().any(Result::is_err) {
+                    *decoder = None;
+                }
+                future::ready(Some(stream::iter(items)))
+            })
+            .flatten();
+        Ok(items)
+    }
+    
+    /// Fetch one page of a list endpoint
+    pub(crate) async fn fetch_page<T: DeserializeOwned>(
+        &self,
+        endpoint: Endpoint,
+        path: &str,
+        query: &[(String, String)],
+        page: &PageRequest,
+    ) -> Result<Page<T>> {
+        self.fetch_page_with(endpoint, path, query, page, &RequestOptions::default()).await
+    }
+    
+    /// Fetch one page of a list endpoint with per-request options
+    async fn fetch_page_with<T: DeserializeOwned>(
+        &self,
+        endpoint: Endpoint,
+        path: &str,
+        query: &[(String, String)],
+        page: &PageRequest,
+        options: &RequestOptions,
+    ) -> Result<Page<T>> {
+        let url = format!("{}{}", self.base_url, path);
+        let req_builder = self
+            .request(endpoint, Method::GET, &url)
+            .query(query)
+            .query(&page.query());
+        
+        #[cfg(not(target_arch = "wasm32"))]
+        if let (Endpoint::ListProducts, Some(cache)) = (endpoint, &self.disk_cache) {
+            let cached = self.fetch_page_cached(cache, endpoint, req_builder, page, options).await?;
+            return Ok(Page {
+                items: cached
+                    .items
+                    .into_iter()
+                    .map(serde_json::from_value)
+                    .collect::<std::result::Result<_, _>>()?,
+                total: cached.total,
+                page: cached.page,
+                next_cursor: cached.next_cursor,
+                next: cached.next,
+            });
+        }
+        
+        let response = self.execute(endpoint, req_builder, true, options).await?;
+        Ok(Page::from_response(response, page))
+    }
+    
+    /// Fetch one page of a list endpoint, sharing a fetch of the same page already in flight
+    ///
+    /// Fetches with options are not shared.
+    async fn coalesced_page<T: DeserializeOwned + Clone + Send + Sync + 'static>(
+        &self,
+        endpoint: Endpoint,
+        path: &str,
+        query: &[(String, String)],
+        page: &PageRequest,
+        options: &RequestOptions,
+    ) -> Result<Page<T>> {
+        let fetch = self.fetch_page_with(endpoint, path, query, page, options);
+        if !options.is_empty() {
+            return fetch.await;
+        }
+        let key = format!("{}{} {:?} {:?}", self.base_url, path, query, page);
+        self.coalesce(key, fetch).await
+    }
+    
+    /// Run `call` through the registry of reads in flight, if coalescing is enabled
+    async fn coalesce<R: Clone + Send + Sync + 'static>(
+        &self,
+        key: String,
+        call: impl std::future::Future<Output = Result<R>>,
+    ) -> Result<R> {
+        // Boxed, as the future of a call is large and would otherwise be held here too
+        let call = Box::pin(call);
+        match &self.single_flight {
+            Some(single_flight) => single_flight.run(key, call).await,
+            None => call.await,
+        }
+    }
+    
+    /// Fetch one page through the disk cache, revalidating stale pages with their `ETag`
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn fetch_page_cached(
+        &self,
+        cache: &DiskCache,
+        endpoint: Endpoint,
+        req_builder: reqwest::RequestBuilder,
+        page: &PageRequest,
+        options: &RequestOptions,
+    ) -> Result<Page<serde_json::Value>> {
+        let url = request_url(&req_builder);
+        let cached = cache.get(&url);
+        let req_builder = match &cached {
+            Some(cached) if cache.is_fresh(cached) => return Ok(serde_json::from_value(cached.body.clone())?),
+            Some(CachedResponse { etag: Some(etag), .. }) => req_builder.header(IF_NONE_MATCH, etag),
+            _ => req_builder,
+        };
+        
+        let mut response: ApiResponse<Option<Vec<serde_json::Value>>> =
+            self.execute(endpoint, req_builder, true, options).await?;
+        let etag = response.etag.clone();
+        let fetched = match (response.data.take(), cached) {
+            (Some(items), _) => Page::from_response(response.map(|_| items), page),
+            (None, Some(cached)) => serde_json::from_value(cached.body)?,
+            (None, None) => return Err(Error::UnexpectedStatus(304, String::new())),
+        };
+        cache.put(&url, etag, serde_json::to_value(&fetched)?);
+        Ok(fetched)
+    }
+    
+    /// Send a GET with `etag` in `If-None-Match`, if given
+    async fn fetch_if_modified<T: DeserializeOwned>(
+        &self,
+        endpoint: Endpoint,
+        req_builder: reqwest::RequestBuilder,
+        etag: Option<&str>,
+        options: &RequestOptions,
+    ) -> Result<Conditional<ApiResponse<T>>> {
+        let req_builder = match etag {
+            Some(etag) => req_builder.header(IF_NONE_MATCH, etag),
+            None => req_builder,
+        };
+        
+        let mut response: ApiResponse<Option<T>> = self.execute(endpoint, req_builder, true, options).await?;
+        Ok(match response.data.take() {
+            Some(data) => Conditional::Modified(response.map(|_| data)),
+            None => Conditional::NotModified,
+        })
+    }
+    
+    /// Log in and store the session cookie for subsequent requests
+    pub async fn login(&self) -> Result<()> {
+        let Some(AuthScheme::Session(session)) = &self.auth else {
+            return Err(Error::InvalidCredentials(
+                "Login requires session credentials".to_string(),
+            ));
+        };
+        
+        let mut logged_in = session.state().await;
+        self.perform_login(session).await?;
+        *logged_in = true;
+        Ok(())
+    }
+    
+    /// Follow a hypermedia link returned by the API
+    ///
+    /// Relative links are resolved against the base URL. Credentials are only
+    /// attached when the link points at the same origin as the base URL.
+    pub async fn follow<T: DeserializeOwned>(&self, link: &Link) -> Result<T> {
+        self.follow_with(link, &RequestOptions::default()).await
+    }
+    
+    /// Follow a hypermedia link with per-request options
+    pub async fn follow_with<T: DeserializeOwned>(&self, link: &Link, options: &RequestOptions) -> Result<T> {
+        let url = self.resolve_link(link)?;
+        
+        let authenticate = self.is_same_origin(&url);
+        let req_builder = self.request(Endpoint::FollowLink, Method::GET, url);
+        
+        self.execute(Endpoint::FollowLink, req_builder, authenticate, options)
+            .await
+            .map(ApiResponse::into_inner)
+    }
+    
+    /// Re-send an audited call against this client's base URL
+    pub(crate) async fn replay(&self, record: &AuditRecord) -> Result<ApiResponse<serde_json::Value>> {
+        let url = self.replay_url(record)?;
+        let mut req_builder = self.request(record.endpoint, record.method.clone(), url);
+        match (&record.raw_body, &record.body_sha256) {
+            (Some(body), _) => {
+                if let Some(content_type) = &record.content_type {
+                    req_builder = req_builder.header(CONTENT_TYPE, content_type);
+                }
+                req_builder = req_builder.body(body.clone());
+            }
+            (None, Some(_)) => {
+                return Err(Error::Config(format!(
+                    "Audit record {} has no raw body to replay",
+                    record.request_id
+                )));
+            }
+            (None, None) => {}
+        }
+        
+        self.execute(record.endpoint, req_builder, true, &RequestOptions::default()).await
+    }
+    
+    /// URL an audited call is replayed to
+    pub(crate) fn replay_url(&self, record: &AuditRecord) -> Result<String> {
+        if !record.path.starts_with('/') {
+            return Err(Error::InvalidUrl(format!(
+                "Cannot replay a call to another origin: {}",
+                record.url
+            )));
+        }
+        Ok(format!("{}{}", self.base_url, record.path))
+    }
+    
+    /// Start a request negotiating the configured format
+    fn request(
+        &self,
+        endpoint: Endpoint,
+        method: Method,
+        url: impl reqwest::IntoUrl,
+    ) -> reqwest::RequestBuilder {
+        let req_builder = self.http_client
+            .request(method, url)
+            .header(ACCEPT, self.format.accept());
+        
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.endpoint_timeouts.get(&endpoint) {
+            return req_builder.timeout(*timeout);
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = endpoint;
+        req_builder
+    }
+    
+    /// Serialize a request body in the configured format
+    fn encode_body<B: Serialize>(
+        &self,
+        req_builder: reqwest::RequestBuilder,
+        body: &B,
+    ) -> Result<reqwest::RequestBuilder> {
+        Ok(req_builder
+            .header(CONTENT_TYPE, self.format.content_type())
+            .body(self.format.encode(body)?))
+    }
+    
+    /// Resolve a link against the base URL
+    fn resolve_link(&self, link: &Link) -> Result<url::Url> {
+        url::Url::parse(&self.base_url)
+            .and_then(|base| base.join(&link.href))
+            .map_err(|e| Error::InvalidUrl(format!("Invalid link {}: {}", link.href, e)))
+    }
+    
+    /// Check whether a URL shares the base URL's origin
+    fn is_same_origin(&self, url: &url::Url) -> bool {
+        let is_origin = |base_url: &String| {
+            url::Url::parse(base_url)
+                .map(|base| base.origin() == url.origin())
+                .unwrap_or(false)
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(failover) = &self.failover {
+            return failover.base_urls().iter().any(is_origin);
+        }
+        is_origin(&self.base_url)
+    }
+    
+    /// Send a request with per-request options, stopping early if it is cancelled
+    async fn execute<T: DeserializeOwned>(
+        &self,
+        endpoint: Endpoint,
+        req_builder: reqwest::RequestBuilder,
+        authenticate: bool,
+        options: &RequestOptions,
+    ) -> Result<ApiResponse<T>> {
+        let mut req_builder = options.apply(req_builder);
+        let request_id = match options.request_id_header() {
+            Some(id) => id.to_string(),
+            None => {
+                let id = request_id::generate();
+                req_builder = req_builder.header(REQUEST_ID, &id);
+                id
+            }
+        };
+        // A clone of the request, unavailable for streaming bodies
+        let probe = req_builder
+            .try_clone()
+            .and_then(|builder| builder.build().ok());
+        
+        let (method, url, path) = match &probe {
+            Some(request) => (
+                request.method().clone(),
+                request.url().to_string(),
+                Some(request.url().path().to_string()),
+            ),
+            None => (Method::POST, String::from("<streaming request>"), None),
+        };
+        #[cfg(feature = "tracing")]
+        let span = trace::request_span(endpoint, &method, &url, &request_id);
+        #[cfg(feature = "log")]
+        let call_log = logging::CallLog::start(&method, probe.as_ref(), &request_id);
+        let audit = self.audit.as_ref().map(|sink| {
+            let record = AuditRecord::request(
+                runtime::system_now(),
+                endpoint,
+                &method,
+                probe.as_ref(),
+                &self.base_url,
+                &request_id,
+            );
+            (sink, record)
+        });
+        let _in_flight = self.lifecycle.enter(method.clone(), url.clone())?;
+        
+        if let Some(observer) = &self.metrics {
+            observer.request_started(&RequestStarted { endpoint, method: method.clone() });
+        }
+        let started = Instant::now();
+        let attempts = AtomicU32::new(0);
+        let deadline = options.deadline_budget().or(self.deadline);
+        let call = async {
+            tokio::select! {
+                biased;
+                _ = cancelled(options.cancellation_token()) => Err(Error::Cancelled),
+                _ = self.lifecycle.abandoned() => Err(Error::Shutdown),
+                _ = expired(deadline) => Err(Error::DeadlineExceeded {
+                    deadline: deadline.unwrap_or_default(),
+                    attempts: attempts.load(Ordering::Relaxed),
+                    elapsed: started.elapsed(),
+                    last_error: None,
+                }),
+                result = self.execute_with_retries(
+                    req_builder,
+                    probe,
+                    authenticate,
+                    &attempts,
+                    deadline.map(|deadline| (deadline, started)),
+                ) => result,
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let call = call.instrument(span.clone());
+        
+        let result = call.await;
+        let attempts = attempts.into_inner();
+        let result = match result {
+            Ok(mut response) => {
+                response.request_id = Some(request_id);
+                Ok(response)
+            }
+            Err(e) => Err(e.with_context(|context| {
+                context.request_id = Some(request_id);
+                context.endpoint = Some(endpoint);
+                context.method = Some(method.clone());
+                context.url = Some(url);
+                context.path = path;
+                context.attempts = attempts;
+                context.elapsed = Some(started.elapsed());
+            })),
+        };
+        #[cfg(feature = "tracing")]
+        {
+            span.record("attempts", attempts);
+            trace::record(&span, &result, Some(started.elapsed()));
+        }
+        #[cfg(feature = "log")]
+        call_log.finish(&result, started.elapsed(), attempts);
+        if let Some(observer) = &self.metrics {
+            observer.request_completed(&RequestCompleted {
+                endpoint,
+                method,
+                status: match &result {
+                    Ok(response) => response.status,
+                    Err(e) => e.status_code().and_then(|code| StatusCode::from_u16(code).ok()),
+                },
+                success: result.is_ok(),
+                duration: started.elapsed(),
+                retries: attempts.saturating_sub(1),
+            });
+        }
+        if let Some((sink, record)) = audit {
+            sink.record(record.complete(&result, started.elapsed())).await;
+        }
+        match (&result, &self.on_error, &self.on_deprecation) {
+            (Err(e), Some(on_error), _) => on_error(e),
+            (Ok(response), _, Some(on_deprecation)) => {
+                if let Some(notice) = &response.deprecation {
+                    on_deprecation(endpoint, notice);
+                }
+            }
+            _ => {}
+        }
+        result
+    }
+    
+    /// Send a request and decode the response, retrying transient failures
+    async fn execute_with_retries<T: DeserializeOwned>(
+        &self,
+        req_builder: reqwest::RequestBuilder,
+        probe: Option<reqwest::Request>,
+        authenticate: bool,
+        attempts: &AtomicU32,
+        deadline: Option<(Duration, Instant)>,
+    ) -> Result<ApiResponse<T>> {
+        let idempotent = probe.as_ref().is_some_and(retry::is_idempotent);
+        let hedger = self.hedger.as_deref().filter(|_| {
+            probe
+                .as_ref()
+                .is_some_and(|request| matches!(*request.method(), Method::GET | Method::HEAD))
+        });
+        
+        if let Some(budget) = &self.retry_budget {
+            budget.deposit();
+        }
+        
+        let mut req_builder = req_builder;
+        let mut attempt = 1;
+        loop {
+            // Streaming bodies cannot be cloned and are sent only once
+            let next_builder = req_builder.try_clone();
+            attempts.store(attempt, Ordering::Relaxed);
+            
+            let attempt_result = async {
+                match hedger {
+                    Some(hedger) => self.hedged_attempt(hedger, req_builder, authenticate).await,
+                    None => self.attempt(req_builder, authenticate).await,
+                }
+            };
+            #[cfg(feature = "tracing")]
+            let result = {
+                let span = trace::attempt_span(attempt);
+                let result = attempt_result.instrument(span.clone()).await;
+                trace::record(&span, &result, None);
+                result
+            };
+            #[cfg(not(feature = "tracing"))]
+            let result = attempt_result.await;
+            
+            // Non-idempotent requests are only retried if they never reached the server
+            let (error, next_builder) = match (result, next_builder) {
+                (Err(error), Some(next_builder)) if idempotent || error.is_unsent() => {
+                    (error, next_builder)
+                }
+                (result, _) => return result,
+            };
+            
+            let within_budget = || self.retry_budget.as_ref().is_none_or(|budget| budget.withdraw());
+            let delay = self.retry.should_retry(error.inner(), attempt);
+            // Give up now rather than sleep past the deadline
+            if let (Some(delay), Some((deadline, started))) = (delay, deadline) {
+                if started.elapsed() + delay >= deadline {
+                    return Err(Error::DeadlineExceeded {
+                        deadline,
+                        attempts: attempt,
+                        elapsed: started.elapsed(),
+                        last_error: Some(Box::new(error)),
+                    });
+                }
+            }
+            match delay {
+                Some(delay) if within_budget() => {
+                    runtime::sleep(delay).await;
+                    req_builder = next_builder;
+                    attempt += 1;
+                }
+                _ => return Err(error),
+            }
+        }
+    }
+    
+    /// Make an attempt, sending a duplicate if the first request is slow
+    ///
+    /// The first successful response wins; dropping the other future cancels
+    /// its request.
+    async fn hedged_attempt<T: DeserializeOwned>(
+        &self,
+        hedger: &Hedger,
+        req_builder: reqwest::RequestBuilder,
+        authenticate: bool,
+    ) -> Result<ApiResponse<T>> {
+        let Some(hedge_builder) = req_builder.try_clone() else {
+            return self.attempt(req_builder, authenticate).await;
+        };
+        
+        let started = Instant::now();
+        let mut primary = std::pin::pin!(self.attempt(req_builder, authenticate));
+        let result = tokio::select! {
+            result = &mut primary => result,
+            _ = runtime::sleep(hedger.delay()) => {
+                let mut hedge = std::pin::pin!(self.attempt(hedge_builder, authenticate));
+                tokio::select! {
+                    result = &mut primary => match result {
+                        Ok(response) => Ok(response),
+                        Err(_) => hedge.await,
+                    },
+                    result = &mut hedge => match result {
+                        Ok(response) => Ok(response),
+                        Err(_) => primary.await,
+                    },
+                }
+            }
+        };
+        
+        if result.is_ok() {
+            hedger.record(started.elapsed());
+        }
+        result
+    }
+    
+    /// Make a single attempt against the active base URL, guarded by the circuit breaker
+    async fn attempt<T: DeserializeOwned>(
+        &self,
+        req_builder: reqwest::RequestBuilder,
+        authenticate: bool,
+    ) -> Result<ApiResponse<T>> {
+        // Permits free their slot if this future is dropped before the outcome is known
+        let permit = match &self.circuit_breaker {
+            Some(breaker) => Some(breaker.try_acquire()?),
+            None => None,
+        };
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let (req_builder, in_flight) = match &self.failover {
+            Some(failover) => {
+                let (req_builder, index) = failover.route(self.routing, req_builder)?;
+                (req_builder, Some(failover.start(index)))
+            }
+            None => (req_builder, None),
+        };
+        // Each attempt is a child span with its own id
+        #[cfg(feature = "opentelemetry")]
+        let req_builder = self.propagation.inject(req_builder);
+        
+        let result = match self.send(req_builder, authenticate).await {
+            Ok(response) => self.handle_response(response).await,
+            Err(e) => Err(e),
+        };
+        
+        if let Some(permit) = permit {
+            permit.record(&result);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(in_flight) = in_flight {
+            in_flight.finish(&result);
+        }
+        result
+    }
+    
+    /// Send a request, following redirects according to the redirect policy
+    ///
+    /// Credentials are only applied to hops the policy trusts.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn send(
+        &self,
+        req_builder: reqwest::RequestBuilder,
+        authenticate: bool,
+    ) -> Result<reqwest::Response> {
+        let (http_client, request) = req_builder.build_split();
+        let mut request = request.map_err(Error::Http)?;
+        let origin = request.url().clone();
+        let mut redirects = 0;
+        
+        loop {
+            let replay = request.try_clone();
+            let authenticate = authenticate && self.redirect.forwards_credentials(&origin, request.url());
+            let req_builder = reqwest::RequestBuilder::from_parts(http_client.clone(), request);
+            let response = self.send_authenticated(req_builder, authenticate).await?;
+            
+            match self.redirect.next_request(&response, replay, redirects)? {
+                Some(next) => {
+                    request = next;
+                    redirects += 1;
+                }
+                None => return Ok(response),
+            }
+        }
+    }
+    
+    /// Send a request; `fetch` follows redirects on its own
+    #[cfg(target_arch = "wasm32")]
+    async fn send(
+        &self,
+        req_builder: reqwest::RequestBuilder,
+        authenticate: bool,
+    ) -> Result<reqwest::Response> {
+        self.send_authenticated(req_builder, authenticate).await
+    }
+    
+    /// Send a single request, authenticating it if requested
+    ///
+    /// OAuth2 tokens, sessions and provided credentials rejected with a 401
+    /// are renewed and the request is sent once more.
+    async fn send_authenticated(
+        &self,
+        req_builder: reqwest::RequestBuilder,
+        authenticate: bool,
+    ) -> Result<reqwest::Response> {
+        if !authenticate {
+            return self.transmit(req_builder).await;
+        }
+        
+        let retry_builder = req_builder.try_clone();
+        let response = self.transmit(self.authorize(req_builder).await?).await?;
+        
+        let Some(retry_builder) = retry_builder else {
+            return Ok(response);
+        };
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        
+        match &self.auth {
+            Some(AuthScheme::OAuth2(oauth)) => oauth.invalidate().await,
+            Some(AuthScheme::Session(session)) => session.expire().await,
+            // The provider is consulted again for the retry
+            Some(AuthScheme::Provider(_)) => {}
+            #[cfg(feature = "jwt")]
+            Some(AuthScheme::Jwt(jwt)) => jwt.invalidate(),
+            _ => return Ok(response),
+        }
+        
+        self.transmit(self.authorize(retry_builder).await?).await
+    }
+    
+    /// Put a request on the wire, capturing the exchange in debug mode
+    async fn transmit(&self, req_builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(debug_log) = &self.debug_log {
+            return debug_log
+                .capture(req_builder, self.max_response_size, |req_builder| self.send_request(req_builder))
+                .await;
+        }
+        self.send_request(req_builder).await
+    }
+    
+    /// Put a request on the wire, through the custom transport if there is one
+    async fn send_request(&self, req_builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(transport) = &self.transport {
+            return transport
+                .send(req_builder.build().map_err(Error::Http)?, self.max_response_size)
+                .await;
+        }
+        req_builder.send().await.map_err(Error::from_send)
+    }
+    
+    /// Add authentication if configured
+    async fn authorize(&self, req_builder: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder> {
+        let Some(auth) = &self.auth else {
+            return Ok(req_builder);
+        };
+        
+        if let AuthScheme::Session(session) = auth {
+            let mut logged_in = session.state().await;
+            if !*logged_in {
+                self.perform_login(session).await?;
+                *logged_in = true;
+            }
+        }
+        
+        match auth.header(&self.http_client).await? {
+            Some((name, value)) => Ok(req_builder.header(name, value)),
+            None => Ok(req_builder),
+        }
+    }
+    
+    /// Exchange session credentials for a session cookie
+    async fn perform_login(&self, session: &Session) -> Result<()> {
+        let url = format!("{}/api_customer/login", self.base_url);
+        
+        let req_builder = self.request(Endpoint::Login, Method::POST, &url);
+        let req_builder = self.encode_body(req_builder, &session.login_request())?;
+        
+        let response = self.transmit(req_builder).await?;
+        self.check_status(response).await?;
+        Ok(())
+    }
+    
+    /// Map the response status to an error or decode the payload
+    ///
+    /// Either carries the id the server reported for the request.
+    async fn handle_response<T: DeserializeOwned>(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<ApiResponse<T>> {
+        let Some(server_request_id) = request_id::from_headers(response.headers()) else {
+            return self.decode_response(response).await;
+        };
+        match self.decode_response(response).await {
+            Ok(mut response) => {
+                response.server_request_id = Some(server_request_id);
+                Ok(response)
+            }
+            Err(e) => Err(e.with_context(|context| context.server_request_id = Some(server_request_id))),
+        }
+    }
+    
+    /// Map the response status to an error or decode the payload
+    async fn decode_response<T: DeserializeOwned>(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<ApiResponse<T>> {
+        let response = self.check_status(response).await?;
+        let rate_limit = RateLimitStatus::from_headers(response.headers());
+        let deprecation = DeprecationNotice::from_headers(response.headers());
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let status = response.status();
+        #[cfg(not(target_arch = "wasm32"))]
+        let version = Some(response.version());
+        // `fetch` does not expose the negotiated version
+        #[cfg(target_arch = "wasm32")]
+        let version = None;
+        
+        let format = Format::from_content_type(
+            response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok()),
+        );
+        let mut decoded = if status == StatusCode::NOT_MODIFIED {
+            // Only the `Option` payloads of conditional requests accept a missing body
+            let data = serde_json::from_value(serde_json::Value::Null)
+                .map_err(|_| Error::UnexpectedStatus(status.as_u16(), String::new()))?;
+            ApiResponse::new(data)
+        } else {
+            let body = response::read_body(response, self.max_response_size).await?;
+            format.decode(self.envelope, &body).map_err(|e| Error::Decode {
+                source: Box::new(e),
+                body: String::from_utf8_lossy(&body).into_owned(),
+                status: status.as_u16(),
+            })?
+        };
+        decoded.rate_limit = rate_limit;
+        decoded.etag = etag;
+        decoded.deprecation = deprecation;
+        decoded.status = Some(status);
+        decoded.version = version;
+        Ok(decoded)
+    }
+    
+    /// Map unsuccessful response status codes to errors
+    async fn check_status(&self, response: reqwest::Response) -> Result<reqwest::Response> {
+        if let Some(quota) = RateLimitStatus::from_headers(response.headers()) {
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.observe(&quota);
+            }
+            *self.last_rate_limit.lock().unwrap_or_else(|e| e.into_inner()) = Some(quota);
+        }
+        
+        // Handle different response status codes
+        let status = response.status();
+        if status.is_success() || status == StatusCode::NOT_MODIFIED {
+            Ok(response)
+        } else {
+            let status_code = status.as_u16();
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(retry::parse_retry_after);
+            let error_text = match response::read_body(response, self.max_response_size).await {
+                Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+                Err(e @ Error::ResponseTooLarge(_)) => return Err(e),
+                Err(_) => String::new(),
+            };
+            
+            match status_code {
+                400 => Err(Error::BadRequest(ApiErrorBody::parse(error_text))),
+                401 => Err(Error::Unauthorized("Invalid credentials".to_string())),
+                403 => Err(Error::Forbidden(ApiErrorBody::parse(error_text))),
+                404 => Err(Error::NotFound("Endpoint not found".to_string())),
+                409 => Err(Error::Conflict(ApiErrorBody::parse(error_text))),
+                422 => Err(Error::UnprocessableEntity(ApiErrorBody::parse(error_text))),
+                429 => Err(Error::RateLimit("Rate limit exceeded".to_string(), retry_after)),
+                503 => Err(Error::ServerError(status_code, error_text, retry_after)),
+                500..=599 => Err(Error::ServerError(status_code, error_text, None)),
+                _ => Err(Error::UnexpectedStatus(status_code, error_text)),
+            }
+        }
+    }
+}
+
+/// Resolve once `deadline` has passed, or never without a deadline
+async fn expired(deadline: Option<Duration>) {
+    match deadline {
+        Some(deadline) => runtime::sleep(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Full URL a request will be sent to, including its query, for keying caches
+fn request_url(req_builder: &reqwest::RequestBuilder) -> String {
+    req_builder
+        .try_clone()
+        .and_then(|builder| builder.build().ok())
+        .map(|request| request.url().to_string())
+        .unwrap_or_default()
+}
+
+/// Resolve once the token is cancelled, or never without a token
+async fn cancelled(token: Option<&CancellationToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("base_url", &self.base_url)
+            .field("auth", &self.auth)
+            .field("envelope", &self.envelope)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_error::ApiErrorCode;
+    use crate::error::ErrorKind;
+    use secrecy::ExposeSecret;
+    
+    #[test]
+    fn test_client_creation() {
+        let client = Client::new("https://api.example.com").unwrap();
+        assert_eq!(client.base_url, "https://api.example.com");
+        assert!(client.auth.is_none());
+        assert_eq!(client.envelope, Envelope::Auto);
+    }
+    
+    #[test]
+    fn test_client_with_credentials() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_credentials("test@example.com", "token123");
+        
+        assert!(client.auth.is_some());
+        let Some(AuthScheme::Basic { email, token }) = client.auth else {
+            panic!("expected basic auth");
+        };
+        assert_eq!(email, "test@example.com");
+        assert_eq!(token.expose_secret(), "token123");
+    }
+    
+    #[test]
+    fn test_client_debug_redacts_credentials() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_credentials("test@example.com", "token123");
+        
+        let debug = format!("{:?}", client);
+        assert!(debug.contains("https://api.example.com"));
+        assert!(debug.contains("test@example.com"));
+        assert!(!debug.contains("token123"));
+    }
+    
+    #[test]
+    fn test_client_with_bearer_token() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_bearer_token("abc123");
+        
+        assert!(matches!(client.auth, Some(AuthScheme::Bearer(ref token)) if token.expose_secret() == "abc123"));
+    }
+    
+    #[test]
+    fn test_client_with_api_key() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_api_key("X-Api-Key", "key-123");
+        
+        assert!(matches!(
+            client.auth,
+            Some(AuthScheme::ApiKey { ref header, ref key }) if header == "X-Api-Key" && key.expose_secret() == "key-123"
+        ));
+        assert!(!format!("{:?}", client).contains("key-123"));
+    }
+    
+    #[test]
+    fn test_client_with_session() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_session("user@example.com", "password");
+        
+        assert!(matches!(client.auth, Some(AuthScheme::Session(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_login_requires_session() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_bearer_token("abc123");
+        
+        assert!(matches!(client.login().await.unwrap_err(), Error::InvalidCredentials(_)));
+    }
+    
+    #[test]
+    fn test_client_with_oauth2() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_oauth2(OAuth2::new("https://auth.example.com/token", "client", "secret"));
+        
+        assert!(matches!(client.auth, Some(AuthScheme::OAuth2(_))));
+    }
+    
+    #[test]
+    fn test_client_with_envelope() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_envelope(Envelope::Wrapped);
+        
+        assert_eq!(client.envelope, Envelope::Wrapped);
+    }
+    
+    #[test]
+    fn test_resolve_link() {
+        let client = Client::new("https://api.example.com").unwrap();
+        
+        let relative = client.resolve_link(&Link::new("/api_customer/orders/70/invoice")).unwrap();
+        assert_eq!(relative.as_str(), "https://api.example.com/api_customer/orders/70/invoice");
+        assert!(client.is_same_origin(&relative));
+        
+        let foreign = client.resolve_link(&Link::new("https://cdn.example.net/invoice.pdf")).unwrap();
+        assert_eq!(foreign.as_str(), "https://cdn.example.net/invoice.pdf");
+        assert!(!client.is_same_origin(&foreign));
+    }
+    
+    #[test]
+    fn test_client_with_retry() {
+        let error = Error::ServerError(503, "Service Unavailable".to_string(), None);
+        
+        let client = Client::new("https://api.example.com").unwrap();
+        assert!(client.retry.should_retry(&error, 1).is_some());
+        
+        let client = client.with_retry(retry::Never);
+        assert!(client.retry.should_retry(&error, 1).is_none());
+    }
+    
+    #[test]
+    fn test_client_with_circuit_breaker() {
+        let client = Client::new("https://api.example.com").unwrap();
+        assert!(client.circuit_state().is_none());
+        
+        let client = client.with_circuit_breaker(CircuitBreakerConfig::default());
+        assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+    }
+    
+    #[test]
+    fn test_client_with_rate_limit() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_rate_limit(5.0, 10)
+            .unwrap();
+        
+        assert!(client.rate_limiter.is_some());
+        
+        let result = Client::new("https://api.example.com").unwrap().with_rate_limit(0.0, 10);
+        assert!(matches!(result.unwrap_err(), Error::Config(_)));
+    }
+    
+    #[test]
+    fn test_last_rate_limit_shared_between_clones() {
+        let client = Client::new("https://api.example.com").unwrap();
+        let clone = client.clone();
+        assert!(client.last_rate_limit().is_none());
+        
+        let quota = RateLimitStatus {
+            limit: Some(100),
+            remaining: 99,
+            reset: None,
+        };
+        *clone.last_rate_limit.lock().unwrap() = Some(quota);
+        assert_eq!(client.last_rate_limit(), Some(quota));
+    }
+    
+    #[test]
+    fn test_client_with_retry_budget() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_retry_budget(RetryBudget::default());
+        let clone = client.clone();
+        
+        assert!(Arc::ptr_eq(
+            client.retry_budget.as_ref().unwrap(),
+            clone.retry_budget.as_ref().unwrap()
+        ));
+    }
+    
+    #[test]
+    fn test_client_with_hedging() {
+        let client = Client::new("https://api.example.com").unwrap();
+        assert!(client.hedger.is_none());
+        
+        let client = client.with_hedging(HedgePolicy::default());
+        assert_eq!(client.hedger.unwrap().delay(), Duration::from_millis(500));
+    }
+    
+    #[test]
+    fn test_endpoint_timeout() {
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_endpoint_timeout(Endpoint::CreateOrder, Duration::from_secs(120));
+        let url = "https://api.example.com/api_customer/orders";
+        
+        let request = client.request(Endpoint::CreateOrder, Method::POST, url).build().unwrap();
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(120)));
+        
+        let request = client.request(Endpoint::Login, Method::POST, url).build().unwrap();
+        assert_eq!(request.timeout(), None);
+        
+        // Per-call options take precedence over the endpoint timeout
+        let options = RequestOptions::new().timeout(Duration::from_secs(3));
+        let request = options
+            .apply(client.request(Endpoint::CreateOrder, Method::POST, url))
+            .build()
+            .unwrap();
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(3)));
+    }
+    
+    #[tokio::test]
+    async fn test_cancelled_request() {
+        let client = Client::new("http://127.0.0.1:1").unwrap();
+        let token = CancellationToken::new();
+        token.cancel();
+        
+        let options = RequestOptions::new().cancellation(token);
+        let result = client.create_order_with(CreateOrderRequest::default(), &options).await;
+        assert!(matches!(result.unwrap_err().inner(), Error::Cancelled));
+    }
+    
+    #[tokio::test]
+    async fn test_shutdown_abandons_in_flight() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                connections.push(socket);
+            }
+        });
+        
+        let client = Client::new(base_url).unwrap();
+        let call = tokio::spawn({
+            let client = client.clone();
+            async move { client.create_order(CreateOrderRequest::default()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        
+        let report = client.shutdown(Duration::from_millis(50)).await;
+        assert_eq!(report.abandoned.len(), 1);
+        assert!(report.abandoned[0].url.ends_with("/api_customer/orders"));
+        assert!(matches!(call.await.unwrap().unwrap_err().inner(), Error::Shutdown));
+        
+        let result = client.create_order(CreateOrderRequest::default()).await;
+        assert!(matches!(result.unwrap_err(), Error::Shutdown));
+    }
+    
+    /// Answer one request per connection with the given responses, passing on
+    /// each request as received
+    fn serve(
+        listener: tokio::net::TcpListener,
+        responses: Vec<String>,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<String> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    let Some(end) = text.find("\r\n\r\n") else { continue };
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                        .unwrap_or(0);
+                    if read == 0 || request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+                sender.send(String::from_utf8_lossy(&request).into_owned()).unwrap();
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        receiver
+    }
+    
+    fn redirect_to(status: &str, location: &str) -> String {
+        format!("HTTP/1.1 {}\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status, location)
+    }
+    
+    const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    
+    #[tokio::test]
+    async fn test_redirect_keeps_credentials_on_same_origin() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let mut requests = serve(listener, vec![
+            redirect_to("307 Temporary Redirect", "/eu/api_customer/orders"),
+            NOT_FOUND.to_string(),
+        ]);
+        
+        let client = Client::new(base_url).unwrap().with_bearer_token("abc123");
+        let _ = client.create_order(CreateOrderRequest::default()).await;
+        
+        let first = requests.recv().await.unwrap();
+        let second = requests.recv().await.unwrap();
+        assert!(first.starts_with("POST /api_customer/orders "));
+        assert!(second.starts_with("POST /eu/api_customer/orders "));
+        assert!(second.to_ascii_lowercase().contains("authorization: bearer abc123"));
+        assert!(second.contains("order_products"));
+    }
+    
+    #[tokio::test]
+    async fn test_redirect_strips_credentials_across_origins() {
+        let origin = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let other = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let other_url = format!("http://{}/orders", other.local_addr().unwrap());
+        let base_url = format!("http://{}", origin.local_addr().unwrap());
+        let _origin_requests = serve(origin, vec![redirect_to("302 Found", &other_url)]);
+        let mut other_requests = serve(other, vec![NOT_FOUND.to_string()]);
+        
+        let client = Client::new(base_url).unwrap().with_bearer_token("abc123");
+        let _ = client.create_order(CreateOrderRequest::default()).await;
+        
+        // 302 turns the POST into a GET without a body or credentials
+        let redirected = other_requests.recv().await.unwrap();
+        assert!(redirected.starts_with("GET /orders "));
+        assert!(!redirected.to_ascii_lowercase().contains("authorization"));
+        
+    }
+    
+    #[tokio::test]
+    async fn test_redirect_policy_none() {
+        let origin = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = Client::new(format!("http://{}", origin.local_addr().unwrap()))
+            .unwrap()
+            .with_redirect_policy(RedirectPolicy::none());
+        let _requests = serve(origin, vec![redirect_to("307 Temporary Redirect", "/eu")]);
+        let result = client.create_order(CreateOrderRequest::default()).await;
+        assert!(matches!(result.unwrap_err().inner(), Error::UnexpectedStatus(307, _)));
+    }
+    
+    #[tokio::test]
+    async fn test_failover_to_backup() {
+        // Nothing listens on the primary once its listener is dropped
+        let primary = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let primary_url = format!("http://{}", primary.local_addr().unwrap());
+        drop(primary);
+        let backup = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backup_url = format!("http://{}", backup.local_addr().unwrap());
+        let mut requests = serve(backup, vec![NOT_FOUND.to_string()]);
+        
+        let client = Client::new(primary_url)
+            .unwrap()
+            .with_retry(retry::Fixed { max_attempts: 2, delay: Duration::ZERO })
+            .with_failover([backup_url.clone()], Duration::from_secs(30));
+        let result = client.create_order(CreateOrderRequest::default()).await;
+        
+        // The connection error is retried against the backup
+        assert!(matches!(result.unwrap_err().inner(), Error::NotFound(_)));
+        assert!(requests.recv().await.unwrap().starts_with("POST /api_customer/orders "));
+        assert_eq!(client.active_base_url(), backup_url);
+    }
+    
+    #[tokio::test]
+    async fn test_cancelled_trial_frees_circuit() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // Fails the first request, never answers the second, and rejects the rest
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            for response in ["HTTP/1.1 503 Service Unavailable", "", "HTTP/1.1 404 Not Found"] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let _ = socket.read(&mut [0u8; 4096]).await;
+                if !response.is_empty() {
+                    let response = format!("{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", response);
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                }
+                connections.push(socket);
+            }
+        });
+        
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_retry(retry::Never)
+            .with_circuit_breaker(CircuitBreakerConfig {
+                minimum_requests: 1,
+                cool_down: Duration::from_millis(50),
+                ..CircuitBreakerConfig::default()
+            });
+        let _ = client.create_order(CreateOrderRequest::default()).await;
+        assert_eq!(client.circuit_state(), Some(CircuitState::Open));
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        
+        // The trial request is dropped when its deadline passes
+        let options = RequestOptions::new().deadline(Duration::from_millis(100));
+        let result = client.create_order_with(CreateOrderRequest::default(), &options).await;
+        assert!(matches!(result.unwrap_err().inner(), Error::DeadlineExceeded { .. }));
+        assert_eq!(client.circuit_state(), Some(CircuitState::HalfOpen));
+        
+        let result = client.create_order(CreateOrderRequest::default()).await;
+        assert!(matches!(result.unwrap_err().inner(), Error::NotFound(_)));
+        assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+    }
+    
+    #[tokio::test]
+    async fn test_custom_transport() {
+        use crate::transport::{HttpRequest, HttpResponse};
+        
+        /// Rejects every request, echoing its authorization header
+        struct Echo;
+        
+        #[async_trait::async_trait]
+        impl HttpTransport for Echo {
+            async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+                let authorization = request.headers()[reqwest::header::AUTHORIZATION].as_bytes().to_vec();
+                Ok(http::Response::builder().status(400).body(authorization).unwrap())
+            }
+        }
+        
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_bearer_token("abc123")
+            .with_transport(Echo);
+        let result = client.create_order(CreateOrderRequest::default()).await;
+        assert!(matches!(result.unwrap_err().inner(), Error::BadRequest(body) if body.raw == "Bearer abc123"));
+    }
+    
+    #[tokio::test]
+    async fn test_metrics_observer() {
+        use crate::metrics::{MetricsObserver, RequestCompleted};
+        
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<RequestCompleted>>);
+        
+        impl MetricsObserver for Arc<Recorder> {
+            fn request_completed(&self, event: &RequestCompleted) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let _requests = serve(listener, vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            NOT_FOUND.to_string(),
+        ]);
+        
+        let recorder = Arc::new(Recorder::default());
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_retry(retry::Fixed { max_attempts: 2, delay: Duration::from_millis(1) })
+            .with_metrics_observer(recorder.clone());
+        let options = RequestOptions::new().idempotency_key("import-0042").unwrap();
+        let _ = client.create_order_with(CreateOrderRequest::default(), &options).await;
+        
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].endpoint, Endpoint::CreateOrder);
+        assert_eq!(events[0].status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(events[0].retries, 1);
+        assert!(!events[0].success);
+    }
+    
+    #[tokio::test]
+    async fn test_on_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let _requests = serve(listener, vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            NOT_FOUND.to_string(),
+        ]);
+        
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let reported = errors.clone();
+        let client = Client::builder(base_url)
+            .retry(retry::Fixed { max_attempts: 2, delay: Duration::from_millis(1) })
+            .on_error(move |error| reported.lock().unwrap().push((error.status_code(), error.context().cloned())))
+            .build()
+            .unwrap();
+        let options = RequestOptions::new().idempotency_key("import-0042").unwrap();
+        let error = client.create_order_with(CreateOrderRequest::default(), &options).await.unwrap_err();
+        
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        let (status, context) = &errors[0];
+        let context = context.as_ref().unwrap();
+        assert_eq!(*status, Some(404));
+        assert_eq!(context.endpoint, Some(Endpoint::CreateOrder));
+        assert_eq!(context.method, Some(Method::POST));
+        assert!(context.url.as_ref().unwrap().ends_with("/api_customer/orders"));
+        assert_eq!(context.attempts, 2);
+        assert_eq!(context.request_id.as_deref(), error.request_id());
+    }
+    
+    #[tokio::test]
+    async fn test_on_deprecation() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let deprecated = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nDeprecation: true\r\nSunset: Sun, 30 Jun 2024 23:59:59 GMT\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+        let _requests = serve(listener, vec![deprecated.to_string()]);
+        
+        let notices = Arc::new(Mutex::new(Vec::new()));
+        let reported = notices.clone();
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_on_deprecation(move |endpoint, notice| reported.lock().unwrap().push((endpoint, notice.clone())));
+        let _: serde_json::Value = client.follow(&Link::new("/api_customer/orders/1")).await.unwrap();
+        
+        let notices = notices.lock().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].0, Endpoint::FollowLink);
+        assert!(notices[0].1.deprecated);
+        assert!(notices[0].1.sunset.is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_call_error_source_and_backtrace() {
+        let client = Client::new("http://127.0.0.1:1").unwrap().with_retry(retry::Never);
+        
+        let error = client.get_order(&OrderId("1".to_string())).await.unwrap_err();
+        assert!(matches!(error.inner(), Error::Connect(_)));
+        let mut chain = std::iter::successors(Some(&error as &dyn std::error::Error), |e| e.source());
+        assert!(chain.any(|e| e.is::<std::io::Error>()));
+        // Captured on a default client, without any opt-in besides the feature
+        #[cfg(feature = "backtrace")]
+        assert!(error.backtrace().is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_request_ids() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let not_found = "HTTP/1.1 404 Not Found\r\nX-Request-Id: srv-42\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let mut requests = serve(listener, vec![not_found.to_string(), NOT_FOUND.to_string()]);
+        let client = Client::new(base_url).unwrap();
+        
+        let error = client.create_order(CreateOrderRequest::default()).await.unwrap_err();
+        assert!(matches!(error.inner(), Error::NotFound(_)));
+        let request_id = error.request_id().unwrap();
+        assert_eq!(request_id.len(), 36);
+        assert!(requests.recv().await.unwrap().contains(&format!("x-request-id: {}", request_id)));
+        assert_eq!(error.server_request_id(), Some("srv-42"));
+        let message = error.to_string();
+        assert!(message.starts_with("Request failed (POST /api_customer/orders, attempt 1, after "));
+        assert!(message.ends_with(&format!(", request id {}, server request id srv-42)", request_id)));
+        
+        let options = RequestOptions::new().request_id("ticket-1234").unwrap();
+        let error = client
+            .create_order_with(CreateOrderRequest::default(), &options)
+            .await
+            .unwrap_err();
+        assert_eq!(error.request_id(), Some("ticket-1234"));
+        assert_eq!(error.server_request_id(), None);
+        assert_eq!(requests.recv().await.unwrap().matches("x-request-id").count(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_decode_error_keeps_body() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let body = r#"["queued"]"#;
+        let accepted = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let _requests = serve(listener, vec![accepted]);
+        
+        let client = Client::new(base_url).unwrap();
+        let error = client.create_order(CreateOrderRequest::default()).await.unwrap_err();
+        let Error::Decode { source, body: received, status } = error.inner() else {
+            panic!("expected a decode error, got {:?}", error);
+        };
+        assert!(matches!(**source, Error::Json(_)));
+        assert_eq!(received, body);
+        assert_eq!(*status, 200);
+        assert_eq!(error.status_code(), Some(200));
+    }
+    
+    /// A `200 OK` response with a JSON body
+    fn json_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+    
+    /// An order as listed by the API
+    fn order_json(id: u64) -> String {
+        format!(
+            r#"{{"id":{0},"status_order_id":1,"customer_id":7,"customer_order_reference":"ORDER-{0}","gross_total":"10.00","addressbook_id":3}}"#,
+            id
+        )
+    }
+    
+    #[tokio::test]
+    async fn test_order_pages() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let first = format!(
+            r#"{{"data":[{},{}],"meta":{{"page":1,"per_page":2,"total":3}}}}"#,
+            order_json(1),
+            order_json(2)
+        );
+        let second = format!(r#"{{"data":[{}],"meta":{{"page":2,"per_page":2,"total":3}}}}"#, order_json(3));
+        let mut requests = serve(listener, vec![json_response(&first), json_response(&second)]);
+        
+        let client = Client::new(base_url).unwrap();
+        let mut pages = client.order_pages(&OrderFilter::new(), PageRequest::first_page(2));
+        let mut references = Vec::new();
+        while let Some(page) = pages.next_page().await.unwrap() {
+            assert_eq!(page.total, Some(3));
+            references.extend(page.into_iter().map(|order| order.customer_order_reference));
+        }
+        assert_eq!(references, ["ORDER-1", "ORDER-2", "ORDER-3"]);
+        assert_eq!(pages.pages_fetched(), 2);
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?page=1&per_page=2 "));
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?page=2&per_page=2 "));
+    }
+    
+    #[tokio::test]
+    async fn test_list_orders_filter() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let mut requests = serve(listener, vec![json_response(&format!("[{}]", order_json(1)))]);
+        
+        let client = Client::new(base_url).unwrap();
+        let filter = OrderFilter::new()
+            .customer_order_reference("ORDER 1")
+            .invoice_state(crate::filter::InvoiceState::Invoiced);
+        let page = client.list_orders(&filter, &PageRequest::first_page(10)).await.unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert!(requests
+            .recv()
+            .await
+            .unwrap()
+            .starts_with("GET /api_customer/orders?customer_order_reference=ORDER+1&invoiced=true&page=1&per_page=10 "));
+    }
+    
+    #[tokio::test]
+    async fn test_find_order_by_reference() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let prefix_match = order_json(1).replace("ORDER-1", "ORDER-10");
+        let responses = vec![
+            json_response(&format!("[{},{}]", prefix_match, order_json(1))),
+            NOT_FOUND.to_string(),
+            json_response(&format!("[{},{}]", order_json(1), order_json(1))),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap();
+        let reference = || CustomerOrderReference("ORDER-1".to_string());
+        
+        let order = client.find_order_by_reference(reference()).await.unwrap().unwrap();
+        assert_eq!(order.id, 1);
+        assert!(requests
+            .recv()
+            .await
+            .unwrap()
+            .starts_with("GET /api_customer/orders?customer_order_reference=ORDER-1&page=1&per_page=10 "));
+        assert!(client.find_order_by_reference(reference()).await.unwrap().is_none());
+        let error = client.find_order_by_reference(reference()).await.unwrap_err();
+        assert!(matches!(error, Error::AmbiguousReference { matches: 2, .. }), "{:?}", error);
+    }
+    
+    #[tokio::test]
+    async fn test_duplicate_guard() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(2)));
+        let responses = vec![
+            json_response(&format!("[{}]", order_json(1))),
+            json_response("[]"),
+            created,
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_duplicate_guard();
+        let request = |reference: &str| CreateOrderRequest {
+            customer_order_reference: Some(reference.to_string()),
+            ..Default::default()
+        };
+        
+        let error = client.create_order(request("ORDER-1")).await.unwrap_err();
+        assert!(matches!(&error, Error::DuplicateReference(existing) if existing.id == 1), "{:?}", error);
+        assert_eq!(error.kind(), ErrorKind::Validation);
+        assert_eq!(client.create_order(request("ORDER-2")).await.unwrap().order.id, 2);
+        
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?customer_order_reference=ORDER-1&"));
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?customer_order_reference=ORDER-2&"));
+        assert!(requests.recv().await.unwrap().starts_with("POST /api_customer/orders "));
+    }
+    
+    #[tokio::test]
+    async fn test_automatic_idempotency_keys() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(1)));
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![unavailable.to_string(), created.clone(), created.clone(), created];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Fixed {
+            max_attempts: 2,
+            delay: Duration::from_millis(1),
+        });
+        let request = CreateOrderRequest {
+            customer_order_reference: Some("ORDER-1".to_string()),
+            ..Default::default()
+        };
+        let key_sent = |request: String| {
+            let request = request.to_ascii_lowercase();
+            let start = request.find("idempotency-key: ").unwrap() + "idempotency-key: ".len();
+            request[start..].lines().next().unwrap().to_string()
+        };
+        
+        // The random key makes the POST retryable and is the same for both attempts
+        let response = client.create_order_with_meta(request.clone()).await.unwrap();
+        let key = response.idempotency_key.unwrap();
+        assert_eq!(key.len(), 36);
+        assert_eq!(key_sent(requests.recv().await.unwrap()), key);
+        assert_eq!(key_sent(requests.recv().await.unwrap()), key);
+        
+        let derived = client.clone().with_idempotency_keys(IdempotencyKeys::FromReference);
+        let response = derived.create_order_with_meta(request.clone()).await.unwrap();
+        let key = IdempotencyKeys::FromReference.key_for(&request).unwrap();
+        assert_eq!(response.idempotency_key, Some(key.clone()));
+        assert_eq!(key_sent(requests.recv().await.unwrap()), key);
+        
+        let options = RequestOptions::new().idempotency_key("mine").unwrap();
+        let response = derived.create_order_with(request, &options).await.unwrap();
+        assert_eq!(response.idempotency_key.as_deref(), Some("mine"));
+    }
+    
+    #[tokio::test]
+    async fn test_idempotency_store() {
+        use crate::idempotency::MemoryIdempotencyStore;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(1)));
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let invalid = "HTTP/1.1 422 Unprocessable Entity\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![unavailable.to_string(), created.clone(), invalid.to_string(), created];
+        let _requests = serve(listener, responses);
+        let store = Arc::new(MemoryIdempotencyStore::new());
+        let request = |reference: &str| CreateOrderRequest {
+            customer_order_reference: Some(reference.to_string()),
+            ..Default::default()
+        };
+        
+        let crashed = Client::new(base_url.clone())
+            .unwrap()
+            .with_retry(retry::Never)
+            .with_idempotency_store(store.clone());
+        assert!(crashed.create_order(request("ORDER-1")).await.is_err());
+        let pending = store.get("ORDER-1").await.unwrap().unwrap();
+        assert_eq!(pending.outcome, Outcome::Pending);
+        
+        // A new client reuses the key of the interrupted attempt
+        let restarted = Client::new(base_url).unwrap().with_idempotency_store(store.clone());
+        let response = restarted.create_order_with_meta(request("ORDER-1")).await.unwrap();
+        assert_eq!(response.idempotency_key.as_ref(), Some(&pending.key));
+        assert_eq!(store.get("ORDER-1").await.unwrap().unwrap().outcome, Outcome::Created { order_id: 1 });
+        
+        assert!(restarted.create_order(request("ORDER-2")).await.is_err());
+        let rejected = store.get("ORDER-2").await.unwrap().unwrap();
+        assert!(matches!(rejected.outcome, Outcome::Rejected { .. }));
+        let response = restarted.create_order_with_meta(request("ORDER-2")).await.unwrap();
+        assert_ne!(response.idempotency_key, Some(rejected.key));
+    }
+    
+    #[tokio::test]
+    async fn test_idempotency_store_failure() {
+        use crate::idempotency::IdempotencyStore;
+        
+        struct ReadOnlyStore;
+        
+        #[async_trait::async_trait]
+        impl IdempotencyStore for ReadOnlyStore {
+            async fn get(&self, _reference: &str) -> std::io::Result<Option<IdempotencyRecord>> {
+                Ok(None)
+            }
+            
+            async fn put(&self, _reference: &str, _record: &IdempotencyRecord) -> std::io::Result<()> {
+                Err(std::io::Error::other("read-only file system"))
+            }
+        }
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let mut requests = serve(listener, vec![NOT_FOUND.to_string()]);
+        let client = Client::new(base_url).unwrap().with_idempotency_store(ReadOnlyStore);
+        
+        // The order is not sent without its key recorded
+        let request = CreateOrderRequest {
+            customer_order_reference: Some("ORDER-1".to_string()),
+            ..Default::default()
+        };
+        let result = client.create_order(request).await;
+        assert!(matches!(result.unwrap_err(), Error::Storage(_)));
+        assert!(requests.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_order_cache() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = vec![
+            json_response(&format!("[{},{}]", order_json(1), order_json(2))),
+            json_response(&order_json(1)),
+            json_response(&format!("[{}]", order_json(2))),
+            json_response(&order_json(3)),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_order_cache(Duration::from_secs(60));
+        let filter = OrderFilter::new().status(1);
+        let first = PageRequest::first_page(10);
+        let order_id = |id: &str| OrderId(id.to_string());
+        
+        assert_eq!(client.list_orders(&filter, &first).await.unwrap().items.len(), 2);
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?"));
+        assert_eq!(client.list_orders(&filter, &first).await.unwrap().items.len(), 2);
+        assert_eq!(client.get_order(&order_id("2")).await.unwrap().id, 2);
+        
+        // Cancelling invalidates the cached pages and caches the cancelled order
+        client.cancel_order(&order_id("1")).await.unwrap();
+        assert!(requests.recv().await.unwrap().starts_with("DELETE /api_customer/orders/1 "));
+        assert_eq!(client.get_order(&order_id("1")).await.unwrap().id, 1);
+        assert_eq!(client.clone().list_orders(&filter, &first).await.unwrap().items.len(), 1);
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?"));
+        
+        assert_eq!(client.get_order(&order_id("3")).await.unwrap().id, 3);
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders/3 "));
+        assert_eq!(client.get_order(&order_id("3")).await.unwrap().id, 3);
+        assert!(requests.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_conditional_requests() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let tagged = |body: String, etag: &str| {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                etag,
+                body.len(),
+                body
+            )
+        };
+        let not_modified = "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n";
+        let responses = vec![
+            tagged(order_json(1), "\"v1\""),
+            not_modified.to_string(),
+            tagged(format!("[{}]", order_json(1)), "\"p1\""),
+            json_response(&order_json(1)),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap();
+        let order_id = OrderId("1".to_string());
+        let if_none_match = |request: String| {
+            let request = request.to_ascii_lowercase();
+            let start = request.find("if-none-match: ")? + "if-none-match: ".len();
+            request[start..].lines().next().map(str::to_string)
+        };
+        
+        let first = client.get_order_if_modified(&order_id, None).await.unwrap().modified().unwrap();
+        assert_eq!(first.data.id, 1);
+        assert_eq!(first.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(if_none_match(requests.recv().await.unwrap()), None);
+        
+        let second = client.get_order_if_modified(&order_id, first.etag.as_deref()).await.unwrap();
+        assert_eq!(second, Conditional::NotModified);
+        assert_eq!(if_none_match(requests.recv().await.unwrap()).as_deref(), Some("\"v1\""));
+        
+        // Nothing is remembered between calls
+        let page = client
+            .list_orders_if_modified(&OrderFilter::new(), &PageRequest::first_page(10), None)
+            .await
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(page.data.items.len(), 1);
+        assert_eq!(page.etag.as_deref(), Some("\"p1\""));
+        assert_eq!(if_none_match(requests.recv().await.unwrap()), None);
+        
+        // Plain fetches neither send nor need an ETag
+        assert_eq!(client.get_order(&order_id).await.unwrap().id, 1);
+        assert_eq!(if_none_match(requests.recv().await.unwrap()), None);
+    }
+    
+    #[tokio::test]
+    async fn test_disk_cache() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let body = r#"[{"id":1,"product_code":"P-1","name":"Product 1","price":"1.00","currency":"EUR"}]"#;
+        let responses = vec![
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"c1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+        ];
+        let mut requests = serve(listener, responses);
+        let dir = std::env::temp_dir().join(format!("ecommerce-api-client-disk-cache-{}", std::process::id()));
+        let first = PageRequest::first_page(10);
+        
+        let client = Client::new(base_url.clone())
+            .unwrap()
+            .with_disk_cache(DiskCache::open(&dir, Duration::from_secs(60)).unwrap());
+        assert_eq!(client.list_products(&first).await.unwrap().items[0].id, 1);
+        assert!(!requests.recv().await.unwrap().to_ascii_lowercase().contains("if-none-match"));
+        assert_eq!(client.list_products(&first).await.unwrap().items[0].id, 1);
+        
+        // A later run revalidates the stale page and keeps it on 304
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_disk_cache(DiskCache::open(&dir, Duration::ZERO).unwrap());
+        let page = client.list_products(&first).await.unwrap();
+        assert_eq!(page.items[0].product_code, crate::types::ProductCode("P-1".to_string()));
+        assert!(requests.recv().await.unwrap().contains("\"c1\""));
+        assert!(requests.recv().await.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[tokio::test]
+    async fn test_request_coalescing() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let mut requests = serve(listener, vec![json_response(&order_json(1))]);
+        let client = Client::new(base_url).unwrap().with_request_coalescing();
+        let clone = client.clone();
+        let order_id = OrderId("1".to_string());
+        
+        let (first, second) = tokio::join!(Box::pin(client.get_order(&order_id)), Box::pin(clone.get_order(&order_id)));
+        assert_eq!(first.unwrap().id, 1);
+        assert_eq!(second.unwrap().id, 1);
+        requests.recv().await.unwrap();
+        assert!(requests.recv().await.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_read_options() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = vec![
+            json_response(&order_json(1)),
+            json_response(&order_json(1)),
+            json_response(&format!("[{}]", order_json(1))),
+            json_response(&order_json(1)),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_request_coalescing();
+        let options = RequestOptions::new().trace_id("batch-7").unwrap();
+        let order_id = OrderId("1".to_string());
+        
+        // Fetches with options are not shared
+        let (first, second) = tokio::join!(
+            Box::pin(client.get_order_with(&order_id, &options)),
+            Box::pin(client.get_order(&order_id))
+        );
+        assert_eq!(first.unwrap().id, 1);
+        assert_eq!(second.unwrap().id, 1);
+        let sent = [requests.recv().await.unwrap(), requests.recv().await.unwrap()];
+        assert_eq!(sent.iter().filter(|request| request.contains("x-trace-id: batch-7")).count(), 1);
+        
+        let page = client
+            .list_orders_with(&OrderFilter::new(), &PageRequest::first_page(10), &options)
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert!(requests.recv().await.unwrap().contains("x-trace-id: batch-7"));
+        client.cancel_order_with(&order_id, &options).await.unwrap();
+        assert!(requests.recv().await.unwrap().contains("x-trace-id: batch-7"));
+    }
+    
+    #[tokio::test]
+    async fn test_order_store() {
+        use crate::store::{MemoryOrderStore, OrderQuery, OrderStore};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let cancelled = order_json(1).replace(r#""status_order_id":1"#, r#""status_order_id":4"#);
+        let responses = vec![
+            json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(1))),
+            json_response(&cancelled),
+            json_response(&order_json(2)),
+        ];
+        let _requests = serve(listener, responses);
+        let store = Arc::new(MemoryOrderStore::new());
+        let client = Client::new(base_url).unwrap().with_order_store(store.clone());
+        
+        client.create_order(CreateOrderRequest::default()).await.unwrap();
+        assert_eq!(store.get(1).await.unwrap().unwrap().order.status_order_id, 1);
+        client.cancel_order(&OrderId("1".to_string())).await.unwrap();
+        assert_eq!(store.query(&OrderQuery::new().status(4)).await.unwrap().len(), 1);
+        
+        // Orders created elsewhere are not recorded
+        client.get_order(&OrderId("2".to_string())).await.unwrap();
+        assert_eq!(store.get(2).await.unwrap(), None);
+    }
+    
+    #[tokio::test]
+    async fn test_order_store_failures_reported() {
+        use crate::store::{OrderStore, StoredOrder};
+        
+        struct FailingStore;
+        
+        #[async_trait::async_trait]
+        impl OrderStore for FailingStore {
+            async fn put(&self, _stored: &StoredOrder) -> std::io::Result<()> {
+                Err(std::io::Error::other("disk full"))
+            }
+            
+            async fn get(&self, _order_id: u64) -> std::io::Result<Option<StoredOrder>> {
+                Err(std::io::Error::other("disk full"))
+            }
+            
+            async fn orders(&self) -> std::io::Result<Vec<StoredOrder>> {
+                Ok(Vec::new())
+            }
+        }
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = vec![
+            json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(1))),
+            json_response(&order_json(1)),
+        ];
+        let _requests = serve(listener, responses);
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_order_store(FailingStore)
+            .with_on_error({
+                let reported = reported.clone();
+                move |error| reported.lock().unwrap().push(error.kind())
+            });
+        
+        // The calls succeed, with each failure passed to the callback
+        client.create_order(CreateOrderRequest::default()).await.unwrap();
+        client.get_order(&OrderId("1".to_string())).await.unwrap();
+        assert_eq!(*reported.lock().unwrap(), [ErrorKind::Storage, ErrorKind::Storage]);
+    }
+    
+    #[tokio::test]
+    async fn test_reconcile_orders() {
+        use crate::store::{MemoryOrderStore, OrderStore, StoredOrder};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let with_status = |id: u64, status: u64| {
+            order_json(id).replace(r#""status_order_id":1"#, &format!(r#""status_order_id":{}"#, status))
+        };
+        let listing = format!("[{},{},{}]", order_json(1), with_status(2, 4), order_json(5));
+        let mut requests = serve(listener, vec![json_response(&listing), NOT_FOUND.to_string()]);
+        let client = Client::new(base_url).unwrap();
+        let store = MemoryOrderStore::new();
+        let recorded_at = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        for id in [1, 2, 3] {
+            let order = serde_json::from_str(&order_json(id)).unwrap();
+            store.put(&StoredOrder::new(order, Vec::new(), recorded_at)).await.unwrap();
+        }
+        
+        let from = SystemTime::UNIX_EPOCH;
+        let until = SystemTime::UNIX_EPOCH + Duration::from_secs(200);
+        let report = client.reconcile_orders(&store, from, until, ListLimits::default()).await.unwrap();
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.status_mismatches.len(), 1);
+        assert_eq!(report.status_mismatches[0].remote.id, 2);
+        assert_eq!(report.missing_locally.iter().map(|order| order.id).collect::<Vec<_>>(), [5]);
+        assert_eq!(report.missing_remotely.iter().map(|stored| stored.order.id).collect::<Vec<_>>(), [3]);
+        assert!(requests.recv().await.unwrap().contains("created_from=1970-01-01T00%3A00%3A00Z"));
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders/3 "));
+        
+        // Not updated by reconciling
+        assert_eq!(store.get(2).await.unwrap().unwrap().order.status_order_id, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_watch_order() {
+        use futures_util::StreamExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let tagged = |body: String, etag: &str| {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                etag,
+                body.len(),
+                body
+            )
+        };
+        let shipped = order_json(1).replace(r#""status_order_id":1"#, r#""status_order_id":3"#);
+        let delivered = order_json(1).replace(r#""status_order_id":1"#, r#""status_order_id":5"#);
+        let responses = vec![
+            tagged(order_json(1), "\"v1\""),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".to_string(),
+            // A new ETag for the same order is not a change
+            tagged(order_json(1), "\"v2\""),
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            tagged(shipped, "\"v3\""),
+            tagged(delivered, "\"v4\""),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_retry(retry::Never)
+            .with_terminal_statuses([5, 6]);
+        
+        let changes: Vec<_> = client
+            .watch_order(&OrderId("1".to_string()), Duration::from_millis(1))
+            .collect()
+            .await;
+        assert_eq!(changes.len(), 4);
+        let first = changes[0].as_ref().unwrap();
+        assert_eq!((first.previous.as_ref(), first.changes.len()), (None, 0));
+        assert!(matches!(changes[1].as_ref().unwrap_err().inner(), Error::ServerError(503, ..)));
+        let shipped = changes[2].as_ref().unwrap();
+        assert!(shipped.status_changed());
+        assert_eq!(shipped.changes[0].field, "status_order_id");
+        assert_eq!(changes[3].as_ref().unwrap().order.status_order_id, 5);
+        
+        let mut etags = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            let request = request.to_ascii_lowercase();
+            etags.push(request.split("if-none-match: ").nth(1).and_then(|rest| rest.lines().next()).map(str::to_string));
+        }
+        let etag = |value: &str| Some(value.to_string());
+        assert_eq!(etags, [None, etag("\"v1\""), etag("\"v1\""), etag("\"v2\""), etag("\"v2\""), etag("\"v3\"")]);
+    }
+    
+    #[tokio::test]
+    async fn test_long_poll_order() {
+        use futures_util::StreamExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let cancelled = order_json(1).replace(r#""status_order_id":1"#, r#""status_order_id":4"#);
+        let mut requests = serve(listener, vec![json_response(&order_json(1)), json_response(&cancelled)]);
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_deadline(Duration::from_secs(5))
+            .with_terminal_statuses([4]);
+        
+        let started = Instant::now();
+        let changes: Vec<_> = client
+            .long_poll_order(&OrderId("1".to_string()), Duration::from_secs(60))
+            .collect()
+            .await;
+        // Each change is followed by the next request at once
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[1].as_ref().unwrap().order.status_order_id, 4);
+        for _ in 0..2 {
+            let request = requests.recv().await.unwrap();
+            assert!(request.starts_with("GET /api_customer/orders/1?wait=60s "));
+        }
+    }
+    
+    #[cfg(feature = "sse")]
+    #[tokio::test]
+    async fn test_subscribe_events() {
+        use crate::events::EventSource;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let stream = |body: &str| {
+            format!("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n{}", body)
+        };
+        let responses = vec![
+            stream("retry: 10\nid: 1\nevent: order.status_changed\ndata: {\"order_id\":42,\"status_order_id\":3}\n\n"),
+            // Cut off in the middle of a message, which is sent again after reconnecting
+            stream(": keep-alive\n\nid: 2\nevent: shipment.created\ndata: {\"shipment_id\""),
+            stream("id: 2\nevent: shipment.created\ndata: {\"shipment_id\":\"shp_1\",\"order_id\":42}\n\n"),
+            "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        
+        let events: Vec<_> = client.subscribe_events().collect().await;
+        assert_eq!(events.len(), 3);
+        let status = events[0].as_ref().unwrap();
+        assert_eq!((status.id.as_deref(), status.source), (Some("1"), EventSource::ServerSent));
+        assert_eq!(events[1].as_ref().unwrap().order_id(), Some(42));
+        assert!(matches!(events[2].as_ref().unwrap_err().inner(), Error::Unauthorized(_)));
+        
+        let mut last_event_ids = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            assert!(request.starts_with("GET /api_customer/events "));
+            let request = request.to_ascii_lowercase();
+            last_event_ids.push(request.split("last-event-id: ").nth(1).and_then(|rest| rest.lines().next()).map(str::to_string));
+        }
+        let id = |value: &str| Some(value.to_string());
+        assert_eq!(last_event_ids, [None, id("1"), id("1"), id("2")]);
+    }
+    
+    #[cfg(feature = "websocket")]
+    #[tokio::test]
+    async fn test_subscribe_websocket() {
+        use crate::events::EventSource;
+        use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+        use tokio_tungstenite::tungstenite::Message;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let event = |id: &str| format!(r#"{{"id":"{}","type":"order.status_changed","data":{{"order_id":42,"status_order_id":3}}}}"#, id);
+        let (headers, mut received) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for connection in 0..3 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let headers = headers.clone();
+                // The callback's error type is set by tungstenite
+                #[allow(clippy::result_large_err)]
+                let accept = move |request: &Request, response: Response| {
+                    headers.send((request.uri().path().to_string(), request.headers().get("authorization").cloned())).unwrap();
+                    match connection {
+                        2 => {
+                            let mut rejected = ErrorResponse::new(None);
+                            *rejected.status_mut() = http::StatusCode::UNAUTHORIZED;
+                            Err(rejected)
+                        }
+                        _ => Ok(response),
+                    }
+                };
+                let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, accept).await else {
+                    continue;
+                };
+                if connection == 1 {
+                    // Silent until pinged
+                    assert!(matches!(socket.next().await, Some(Ok(Message::Ping(_)))));
+                }
+                socket.send(Message::Text(event(&format!("evt_{}", connection)))).await.unwrap();
+                socket.close(None).await.unwrap();
+            }
+        });
+        
+        let options = WebSocketOptions::new()
+            .ping_interval(Duration::from_millis(50))
+            .backoff(Duration::from_millis(10), Duration::from_millis(100));
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never).with_bearer_token("secret");
+        let events: Vec<_> = client.subscribe_websocket(options).collect().await;
+        assert_eq!(events.len(), 3);
+        for (index, event) in events[..2].iter().enumerate() {
+            let event = event.as_ref().unwrap();
+            assert_eq!((event.id.clone(), event.source), (Some(format!("evt_{}", index)), EventSource::WebSocket));
+        }
+        assert!(matches!(events[2].as_ref().unwrap_err().inner(), Error::Unauthorized(_)));
+        
+        for _ in 0..3 {
+            let (path, authorization) = received.recv().await.unwrap();
+            assert_eq!(path, "/api_customer/events");
+            assert_eq!(authorization.unwrap(), "Bearer secret");
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_outbox_drain() {
+        use crate::outbox::{MemoryOutboxStore, Outbox, OutboxStore};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(1)));
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let invalid = "HTTP/1.1 422 Unprocessable Entity\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![unavailable.to_string(), created, invalid.to_string()];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        let store = Arc::new(MemoryOutboxStore::new());
+        let outbox = Outbox::new(client, store.clone());
+        let first = outbox.enqueue(CreateOrderRequest::default()).await.unwrap();
+        let second = outbox.enqueue(CreateOrderRequest::default()).await.unwrap();
+        
+        // Offline: nothing is sent after the first failure
+        let report = outbox.drain().await.unwrap();
+        assert!(report.created.is_empty());
+        assert_eq!(report.pending, 2);
+        let queued = outbox.pending().await.unwrap();
+        assert_eq!((queued[0].attempts, queued[1].attempts), (1, 0));
+        assert!(requests.recv().await.unwrap().to_ascii_lowercase().contains(&format!("idempotency-key: {}", first)));
+        
+        let report = outbox.drain().await.unwrap();
+        assert_eq!(report.created.len(), 1);
+        assert_eq!(report.rejected.iter().map(|entry| entry.id.as_str()).collect::<Vec<_>>(), [second.as_str()]);
+        assert_eq!(report.pending, 0);
+        assert!(requests.recv().await.unwrap().to_ascii_lowercase().contains(&format!("idempotency-key: {}", first)));
+        assert!(outbox.pending().await.unwrap().is_empty());
+        
+        outbox.discard(&second).await.unwrap();
+        assert!(store.entries().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_outbox_skips_poison_entry() {
+        use crate::outbox::Outbox;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(1)));
+        let mut requests = serve(listener, vec![NOT_FOUND.to_string(), created, NOT_FOUND.to_string()]);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        let outbox = Outbox::new(client, crate::outbox::MemoryOutboxStore::new()).max_attempts(2);
+        let poison = outbox.enqueue(CreateOrderRequest::default()).await.unwrap();
+        let second = outbox.enqueue(CreateOrderRequest::default()).await.unwrap();
+        
+        // The failing head of the queue does not hold up the order behind it
+        let report = outbox.drain().await.unwrap();
+        assert_eq!(report.created.len(), 1);
+        assert_eq!(report.failed.iter().map(|entry| entry.id.as_str()).collect::<Vec<_>>(), [poison.as_str()]);
+        assert_eq!(report.pending, 0);
+        assert!(requests.recv().await.unwrap().to_ascii_lowercase().contains(&format!("idempotency-key: {}", poison)));
+        assert!(requests.recv().await.unwrap().to_ascii_lowercase().contains(&format!("idempotency-key: {}", second)));
+        
+        // ...and is set aside once it has failed `max_attempts` times
+        let report = outbox.drain().await.unwrap();
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].attempts, 2);
+        assert!(report.failed.is_empty());
+        assert!(outbox.pending().await.unwrap().is_empty());
+        assert_eq!(outbox.rejected().await.unwrap()[0].id, poison);
+    }
+    
+    #[tokio::test]
+    async fn test_find_or_create_order() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let conflict = "HTTP/1.1 409 Conflict\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![
+            json_response(&format!("[{}]", order_json(1))),
+            json_response("[]"),
+            json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(2))),
+            json_response("[]"),
+            conflict.to_string(),
+            json_response(&format!("[{}]", order_json(3))),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_duplicate_guard();
+        let request = |reference: &str| CreateOrderRequest {
+            customer_order_reference: Some(reference.to_string()),
+            ..Default::default()
+        };
+        
+        let existing = client.find_or_create_order(request("ORDER-1")).await.unwrap();
+        assert!(matches!(&existing, FindOrCreate::Existing(order) if order.id == 1));
+        let created = client.find_or_create_order(request("ORDER-2")).await.unwrap();
+        assert!(created.is_created());
+        assert_eq!(created.order().id, 2);
+        
+        // ORDER-3 is created by someone else between the lookup and the POST
+        let raced = client.find_or_create_order(request("ORDER-3")).await.unwrap();
+        assert!(!raced.is_created());
+        assert_eq!(raced.order().id, 3);
+        
+        let methods: Vec<_> = (0..6)
+            .map(|_| requests.try_recv().unwrap().split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(methods, ["GET", "GET", "POST", "GET", "POST", "GET"]);
+    }
+    
+    #[tokio::test]
+    async fn test_orders_stream() {
+        use futures_util::StreamExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let first = format!("[{},{}]", order_json(1), order_json(2));
+        let mut requests = serve(listener, vec![json_response(&first), NOT_FOUND.to_string()]);
+        
+        let client = Client::new(base_url).unwrap();
+        let orders = client.orders_stream(&OrderFilter::new(), 2);
+        futures_util::pin_mut!(orders);
+        assert_eq!(orders.next().await.unwrap().unwrap().id, 1);
+        // The second page is only requested once the first is used up
+        assert!(requests.try_recv().is_ok());
+        assert!(requests.try_recv().is_err());
+        assert_eq!(orders.next().await.unwrap().unwrap().id, 2);
+        assert!(matches!(orders.next().await.unwrap().unwrap_err().inner(), Error::NotFound(_)));
+        assert!(orders.next().await.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_resume_page_stream() {
+        use futures_util::StreamExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let first = format!(r#"{{"data":[{}],"meta":{{"next_cursor":"c2"}}}}"#, order_json(1));
+        let second = format!(r#"{{"data":[{}]}}"#, order_json(2));
+        let mut requests = serve(listener, vec![json_response(&first), json_response(&second)]);
+        let client = Client::new(base_url).unwrap();
+        
+        // A sync handles one page, saves its resume point and stops
+        let pages = client
+            .order_pages(&OrderFilter::new(), PageRequest::first_cursor(1))
+            .into_page_stream();
+        futures_util::pin_mut!(pages);
+        let page = pages.next().await.unwrap().unwrap();
+        let saved = serde_json::to_string(&page.next).unwrap();
+        
+        let resume: Option<PageRequest> = serde_json::from_str(&saved).unwrap();
+        let orders: Vec<_> = client
+            .orders_stream_from(&OrderFilter::new(), resume.unwrap())
+            .collect()
+            .await;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].as_ref().unwrap().id, 2);
+        requests.recv().await.unwrap();
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?limit=1&cursor=c2 "));
+    }
+    
+    #[tokio::test]
+    async fn test_page_checkpoint() {
+        use crate::checkpoint::MemoryCheckpointStore;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let first = format!(r#"{{"data":[{}],"meta":{{"next_cursor":"c2"}}}}"#, order_json(1));
+        let second = format!(r#"{{"data":[{}]}}"#, order_json(2));
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![json_response(&first), unavailable.to_string(), json_response(&second)];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        let store = Arc::new(MemoryCheckpointStore::new());
+        
+        // The sync handles the first page and fails fetching the second
+        let mut pages = client
+            .order_pages(&OrderFilter::new(), PageRequest::first_cursor(1))
+            .checkpoint(store.clone(), "orders");
+        assert_eq!(pages.next_page().await.unwrap().unwrap().items[0].id, 1);
+        assert!(pages.next_page().await.is_err());
+        let saved = store.get("orders").unwrap();
+        assert_eq!(saved.next_page, Some(PageRequest::Cursor { cursor: Some("c2".to_string()), limit: 1 }));
+        assert_eq!(saved.items, 1);
+        
+        let mut pages = client
+            .order_pages(&OrderFilter::new(), PageRequest::first_cursor(1))
+            .checkpoint(store.clone(), "orders");
+        assert_eq!(pages.next_page().await.unwrap().unwrap().items[0].id, 2);
+        assert!(pages.next_page().await.unwrap().is_none());
+        assert!(store.get("orders").is_none());
+        
+        requests.recv().await.unwrap();
+        for _ in 0..2 {
+            assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/orders?limit=1&cursor=c2 "));
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_prefetch_pages() {
+        use futures_util::StreamExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most_in_flight = Arc::new(AtomicUsize::new(0));
+        let (counters, most) = (in_flight.clone(), most_in_flight.clone());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let (in_flight, most) = (counters.clone(), most.clone());
+                tokio::spawn(async move {
+                    let mut buffer = [0u8; 4096];
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    let request = String::from_utf8_lossy(&buffer[..read]).into_owned();
+                    let page: u64 = request.split("page=").nth(1).unwrap().split('&').next().unwrap().parse().unwrap();
+                    most.fetch_max(in_flight.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let body = format!(r#"{{"data":[{}],"meta":{{"page":{},"total_pages":5}}}}"#, order_json(page), page);
+                    socket.write_all(json_response(&body).as_bytes()).await.unwrap();
+                });
+            }
+        });
+        
+        let client = Client::new(base_url).unwrap();
+        let orders: Vec<_> = client
+            .order_pages(&OrderFilter::new(), PageRequest::first_page(1))
+            .prefetch(3)
+            .into_stream()
+            .map(|order| order.unwrap().id)
+            .collect()
+            .await;
+        assert_eq!(orders, [1, 2, 3, 4, 5]);
+        let most = most_in_flight.load(Ordering::SeqCst);
+        assert!(most > 1 && most <= 3, "{} requests in flight", most);
+    }
+    
+    #[tokio::test]
+    async fn test_list_all_orders() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let full = json_response(&format!("[{},{}]", order_json(1), order_json(2)));
+        let short = json_response(&format!("[{}]", order_json(3)));
+        let _requests = serve(listener, vec![full.clone(), short, full.clone(), full]);
+        let client = Client::new(base_url).unwrap();
+        
+        let limits = ListLimits {
+            per_page: 2,
+            ..Default::default()
+        };
+        let orders = client.list_all_orders(&OrderFilter::new(), limits).await.unwrap();
+        assert_eq!(orders.len(), 3);
+        
+        let error = client
+            .list_all_orders(&OrderFilter::new(), ListLimits { max_pages: 2, ..limits })
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::ListingTooLarge { pages: 2, items: 4, .. }), "{:?}", error);
+    }
+    
+    #[tokio::test]
+    async fn test_export_and_wait() {
+        use crate::export::ExportFormat;
+        use futures_util::StreamExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let csv = "id,customer_order_reference\n1,ORDER-1\n";
+        let responses = vec![
+            json_response(r#"{"id":"exp-1","status":"pending"}"#),
+            json_response(r#"{"id":"exp-1","status":"running","progress":50}"#),
+            json_response(r#"{"id":"exp-1","status":"completed"}"#),
+            format!("HTTP/1.1 200 OK\r\nContent-Type: text/csv\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", csv.len(), csv),
+            json_response(r#"{"id":"exp-2","status":"pending"}"#),
+            json_response(r#"{"id":"exp-2","status":"failed","error":"disk full"}"#),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap();
+        let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = updates.clone();
+        let wait = ExportWait {
+            initial_interval: Duration::from_millis(5),
+            progress: Some(Arc::new(move |progress: &crate::Progress| {
+                recorded.lock().unwrap().push((progress.phase, progress.done, progress.total));
+            })),
+            ..Default::default()
+        };
+        
+        let spec = ExportSpec::orders(&OrderFilter::new()).format(ExportFormat::Csv);
+        let file = client.export_and_wait(&spec, wait.clone()).await.unwrap();
+        let chunks: Vec<_> = file.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks.concat(), csv.as_bytes());
+        let total = csv.len() as u64;
+        assert_eq!(
+            updates.lock().unwrap().as_slice(),
+            [(Phase::Waiting, 50, Some(100)), (Phase::Downloading, total, Some(total))]
+        );
+        assert!(requests.recv().await.unwrap().starts_with("POST /api_customer/exports "));
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/exports/exp-1 "));
+        requests.recv().await.unwrap();
+        assert!(requests.recv().await.unwrap().starts_with("GET /api_customer/exports/exp-1/download "));
+        
+        let error = client.export_and_wait(&ExportSpec::products(), wait).await.err().unwrap();
+        assert!(matches!(&error, Error::ExportFailed { job_id, message } if job_id == "exp-2" && message == "disk full"), "{:?}", error);
+    }
+    
+    #[tokio::test]
+    async fn test_products_unpaged() {
+        use futures_util::StreamExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let product = |id: u64| {
+            format!(
+                r#"{{"id":{0},"product_code":"P-{0}","name":"Product {0}","price":"1.00","currency":"EUR"}}"#,
+                id
+            )
+        };
+        let body = format!(r#"{{"data":[{},{}],"meta":{{"total":2}}}}"#, product(1), product(2));
+        let truncated = format!("[{},{}", product(1), product(2));
+        let _requests = serve(listener, vec![json_response(&body), json_response(&truncated)]);
+        let client = Client::new(base_url).unwrap();
+        
+        let products: Vec<_> = client.products_unpaged().await.unwrap().collect().await;
+        let codes: Vec<_> = products.into_iter().map(|product| product.unwrap().product_code.0).collect();
+        assert_eq!(codes, ["P-1", "P-2"]);
+        
+        let products: Vec<_> = client.products_unpaged().await.unwrap().collect().await;
+        assert_eq!(products.len(), 2);
+        assert!(products[0].is_ok());
+        assert!(matches!(products[1], Err(Error::Json(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_create_orders_bulk() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = |id: u64| json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(id)));
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let invalid = "HTTP/1.1 422 Unprocessable Entity\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![created(1), unavailable.to_string(), created(2), invalid.to_string()];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        
+        let options = BulkOptions::new()
+            .concurrency(1)
+            .retry(retry::Fixed {
+                max_attempts: 2,
+                delay: Duration::from_millis(1),
+            })
+            .idempotency_prefix("batch");
+        let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = updates.clone();
+        let options = options.progress(move |progress: &crate::Progress| {
+            recorded.lock().unwrap().push((progress.phase, progress.done, progress.total));
+        });
+        let report = client
+            .create_orders_bulk(vec![CreateOrderRequest::default(); 3], options)
+            .await;
+        let submitting: Vec<_> = (1..=3).map(|done| (Phase::Submitting, done, Some(3))).collect();
+        assert_eq!(*updates.lock().unwrap(), submitting);
+        let created: Vec<_> = report.succeeded.iter().map(|success| (success.index, success.response.order.id)).collect();
+        assert_eq!(created, [(0, 1), (1, 2)]);
+        assert!(report.retryable.is_empty());
+        assert_eq!(report.permanent[0].index, 2);
+        assert!(matches!(report.permanent[0].error.inner(), Error::UnprocessableEntity(_)));
+        
+        for key in ["batch-0", "batch-1", "batch-1", "batch-2"] {
+            let request = requests.recv().await.unwrap().to_ascii_lowercase();
+            assert!(request.contains(&format!("idempotency-key: {}\r\n", key)), "{}", request);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_bulk_checkpoint() {
+        use crate::checkpoint::MemoryCheckpointStore;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = |id: u64| json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(id)));
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![created(1), unavailable.to_string(), created(3), created(2)];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        let store = Arc::new(MemoryCheckpointStore::new());
+        let options = BulkOptions::new().concurrency(1).checkpoint(store.clone(), "import-7");
+        
+        let report = client
+            .create_orders_bulk(vec![CreateOrderRequest::default(); 3], options.clone())
+            .await;
+        assert_eq!(report.retryable_indices(), [1]);
+        assert_eq!(store.get("import-7").unwrap().completed.into_iter().collect::<Vec<_>>(), [0, 2]);
+        
+        let report = client
+            .create_orders_bulk(vec![CreateOrderRequest::default(); 3], options)
+            .await;
+        assert!(report.is_complete());
+        assert_eq!(report.resumed, [0, 2]);
+        assert_eq!(report.succeeded[0].index, 1);
+        assert!(store.get("import-7").is_none());
+        
+        for key in ["import-7-0", "import-7-1", "import-7-2", "import-7-1"] {
+            let request = requests.recv().await.unwrap().to_ascii_lowercase();
+            assert!(request.contains(&format!("idempotency-key: {}\r\n", key)), "{}", request);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_bulk_checkpoint_failures() {
+        use crate::checkpoint::{Checkpoint, CheckpointStore};
+        use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+        
+        /// Fails to load until `readable` is set, and always fails to save
+        #[derive(Default)]
+        struct BrokenStore {
+            readable: AtomicBool,
+        }
+        
+        #[async_trait::async_trait]
+        impl CheckpointStore for BrokenStore {
+            async fn load(&self, _key: &str) -> std::io::Result<Option<Checkpoint>> {
+                match self.readable.load(Ordering::SeqCst) {
+                    true => Ok(None),
+                    false => Err(std::io::Error::other("permission denied")),
+                }
+            }
+            
+            async fn save(&self, _key: &str, _checkpoint: &Checkpoint) -> std::io::Result<()> {
+                Err(std::io::Error::other("disk full"))
+            }
+            
+            async fn clear(&self, _key: &str) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = |id: u64| json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(id)));
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let mut requests = serve(listener, vec![created(1), unavailable.to_string()]);
+        let errors = Arc::new(AtomicU32::new(0));
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never).with_on_error({
+            let errors = errors.clone();
+            move |error| {
+                if matches!(error, Error::Storage(_)) {
+                    errors.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+        let store = Arc::new(BrokenStore::default());
+        let options = BulkOptions::new().concurrency(1).checkpoint(store.clone(), "import-8");
+        
+        // Nothing is sent while the work already done is unknown
+        let report = client
+            .create_orders_bulk(vec![CreateOrderRequest::default(); 2], options.clone())
+            .await;
+        assert_eq!(report.retryable_indices(), [0, 1]);
+        assert!(matches!(report.retryable[0].error, Error::Storage(_)));
+        assert!(requests.try_recv().is_err());
+        
+        // Failed saves are reported without failing the orders
+        store.readable.store(true, Ordering::SeqCst);
+        let report = client
+            .create_orders_bulk(vec![CreateOrderRequest::default(); 2], options.checkpoint_every(1))
+            .await;
+        assert_eq!(report.succeeded.len(), 1);
+        assert_eq!(report.retryable_indices(), [1]);
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_create_orders_saga() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = |id: u64| json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(id)));
+        let invalid = "HTTP/1.1 422 Unprocessable Entity\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![
+            created(1),
+            invalid.to_string(),
+            created(3),
+            json_response(&order_json(1)),
+            NOT_FOUND.to_string(),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        
+        let saga = client
+            .create_orders_saga(vec![CreateOrderRequest::default(); 3], BulkOptions::new().concurrency(1))
+            .await;
+        assert!(!saga.is_committed());
+        assert!(!saga.is_rolled_back());
+        let transcript: Vec<_> = saga
+            .compensations
+            .iter()
+            .map(|compensation| (compensation.index, compensation.order_id.0.as_str(), compensation.is_cancelled()))
+            .collect();
+        assert_eq!(transcript, [(0, "1", true), (2, "3", false)]);
+        
+        for _ in 0..3 {
+            assert!(requests.recv().await.unwrap().starts_with("POST /api_customer/orders "));
+        }
+        assert!(requests.recv().await.unwrap().starts_with("DELETE /api_customer/orders/1 "));
+        assert!(requests.recv().await.unwrap().starts_with("DELETE /api_customer/orders/3 "));
+    }
+    
+    #[tokio::test]
+    async fn test_saga_resolves_transient_failures() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let created = |id: u64| json_response(&format!(r#"{{"order":{},"order_products":[]}}"#, order_json(id)));
+        let invalid = "HTTP/1.1 422 Unprocessable Entity\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let responses = vec![
+            invalid.to_string(),
+            unavailable.to_string(),
+            created(2),
+            json_response(&order_json(2)),
+        ];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap().with_retry(retry::Never);
+        
+        let saga = client
+            .create_orders_saga(vec![CreateOrderRequest::default(); 2], BulkOptions::new().concurrency(1))
+            .await;
+        assert!(saga.is_rolled_back());
+        assert!(saga.report.retryable.is_empty());
+        assert_eq!(saga.compensations.len(), 1);
+        assert_eq!((saga.compensations[0].index, saga.compensations[0].order_id.0.as_str()), (1, "2"));
+        
+        // The order that failed transiently was sent again with the same key
+        let key = format!("idempotency-key: {}-1\r\n", saga.idempotency_prefix);
+        requests.recv().await.unwrap();
+        assert!(requests.recv().await.unwrap().to_ascii_lowercase().contains(&key));
+        assert!(requests.recv().await.unwrap().to_ascii_lowercase().contains(&key));
+        assert!(requests.recv().await.unwrap().starts_with("DELETE /api_customer/orders/2 "));
+    }
+    
+    #[tokio::test]
+    async fn test_cancel_orders() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = vec![json_response(&order_json(1)), NOT_FOUND.to_string(), json_response(&order_json(3))];
+        let mut requests = serve(listener, responses);
+        let client = Client::new(base_url).unwrap();
+        
+        let ids: Vec<_> = ["1", "2", "3"].iter().map(|id| OrderId(id.to_string())).collect();
+        let report = client.cancel_orders_with(&ids, BulkOptions::new().concurrency(1)).await;
+        let cancelled: Vec<_> = report.succeeded.iter().map(|success| success.response.id).collect();
+        assert_eq!(cancelled, [1, 3]);
+        assert_eq!(report.permanent[0].index, 1);
+        assert!(requests.recv().await.unwrap().starts_with("DELETE /api_customer/orders/1 "));
+    }
+    
+    #[tokio::test]
+    async fn test_deadline() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let _requests = serve(listener, vec![unavailable.to_string(); 3]);
+        
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_retry(retry::Fixed {
+                max_attempts: 10,
+                delay: Duration::from_millis(200),
+            })
+            .with_deadline(Duration::from_millis(300));
+        let options = RequestOptions::new().idempotency_key("order-1").unwrap();
+        let error = client
+            .create_order_with(CreateOrderRequest::default(), &options)
+            .await
+            .unwrap_err();
+        // The second backoff would end past the deadline
+        let Error::DeadlineExceeded { attempts, last_error, .. } = error.inner() else {
+            panic!("expected the deadline to pass, got {:?}", error);
+        };
+        assert_eq!(*attempts, 2);
+        assert!(matches!(last_error.as_deref(), Some(Error::ServerError(503, ..))));
+        
+        // A server that never answers is cut off by the deadline itself
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", silent.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _connection = silent.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        let client = Client::new(base_url).unwrap();
+        let options = RequestOptions::new().deadline(Duration::from_millis(100));
+        let error = client
+            .create_order_with(CreateOrderRequest::default(), &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(error.inner(), Error::DeadlineExceeded { attempts: 1, last_error: None, .. }));
+        assert_eq!(error.kind(), ErrorKind::Timeout);
+    }
+    
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let html = "<html>".repeat(100);
+        let declared = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            html.len(),
+            html
+        );
+        // Without a length the limit is only hit while reading
+        let undeclared = format!("HTTP/1.1 502 Bad Gateway\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n{}", html);
+        let _requests = serve(listener, vec![declared, undeclared]);
+        
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_retry(retry::Never)
+            .with_max_response_size(256);
+        for _ in 0..2 {
+            let error = client.create_order(CreateOrderRequest::default()).await.unwrap_err();
+            assert!(matches!(error.inner(), Error::ResponseTooLarge(256)), "{:?}", error);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_validation_errors() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let body = r#"{"message":"Validation failed","errors":{"order_products[0].quantity":["must be positive"]}}"#;
+        let rejected = format!(
+            "HTTP/1.1 422 Unprocessable Entity\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let duplicate = r#"{"error":{"code":"DUPLICATE_ORDER_REFERENCE","message":"Order reference already used"}}"#;
+        let conflict = format!(
+            "HTTP/1.1 409 Conflict\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            duplicate.len(),
+            duplicate
+        );
+        let _requests = serve(listener, vec![rejected, conflict]);
+        
+        let client = Client::new(base_url).unwrap();
+        let error = client.create_order(CreateOrderRequest::default()).await.unwrap_err();
+        assert!(matches!(error.inner(), Error::UnprocessableEntity(_)));
+        assert_eq!(error.status_code(), Some(422));
+        let details = error.api_error().unwrap();
+        assert_eq!(details.message.as_deref(), Some("Validation failed"));
+        assert_eq!(details.field_errors("order_products[0].quantity"), ["must be positive"]);
+        
+        let error = client.create_order(CreateOrderRequest::default()).await.unwrap_err();
+        assert!(matches!(error.inner(), Error::Conflict(_)));
+        assert_eq!(error.api_error_code(), Some(ApiErrorCode::DuplicateOrderReference));
+        assert_eq!(error.status_code(), Some(409));
+    }
+    
+    #[tokio::test]
+    async fn test_debug_capture() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let rejected = "HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\nContent-Length: 13\r\nConnection: close\r\n\r\nmissing field";
+        let _requests = serve(listener, vec![NOT_FOUND.to_string(), rejected.to_string()]);
+        
+        let client = Client::new(base_url)
+            .unwrap()
+            .with_bearer_token("abc123")
+            .with_debug_capture(1);
+        let _ = client.create_order(CreateOrderRequest::default()).await;
+        let error = client.create_order(CreateOrderRequest::default()).await.unwrap_err();
+        // The response body is still there for the client to decode
+        assert!(matches!(error.inner(), Error::BadRequest(body) if body.raw == "missing field"));
+        
+        let log = client.debug_log();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].status, Some(StatusCode::BAD_REQUEST));
+        assert_eq!(log[0].response_text(), "missing field");
+        assert_eq!(log[0].request_headers[reqwest::header::AUTHORIZATION], crate::redact::REDACTED);
+        assert_eq!(log[0].request_text().unwrap(), serde_json::to_string(&CreateOrderRequest::default()).unwrap());
+        assert!(Client::new("https://api.example.com").unwrap().debug_log().is_empty());
     }
     
     #[test]
@@ -0,0 +1,324 @@
+//! Declarative client configuration loaded from TOML or YAML files
+
+use crate::auth::AuthScheme;
+use crate::builder::ClientBuilder;
+use crate::error::{Error, Result};
+use crate::proxy::ProxyConfig;
+use crate::retry::{self, Exponential};
+use secrecy::SecretString;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Client settings for one environment
+///
+/// Secrets are never stored in the file itself; [`CredentialsConfig`] names
+/// the environment variable or file holding the token instead.
+///
+/// ```toml
+/// base_url = "https://api.example.com"
+/// sandbox_url = "https://sandbox.example.com"
+/// sandbox = true
+/// timeout_secs = 60
+///
+/// [credentials]
+/// email = "orders@example.com"
+/// token_env = "SUPPLIER_API_TOKEN"
+///
+/// [retry]
+/// max_attempts = 5
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Production base URL
+    pub base_url: Option<String>,
+    /// Base URL used instead of `base_url` when `sandbox` is set
+    pub sandbox_url: Option<String>,
+    /// Backup hosts for `base_url`, tried in order when it fails
+    pub fallback_urls: Vec<String>,
+    /// Talk to the sandbox environment
+    pub sandbox: bool,
+    /// Where to find the credentials
+    pub credentials: Option<CredentialsConfig>,
+    /// Total request timeout in seconds
+    pub timeout_secs: Option<f64>,
+    /// Connect timeout in seconds
+    pub connect_timeout_secs: Option<f64>,
+    /// `User-Agent` header
+    pub user_agent: Option<String>,
+    /// Proxy URL for all requests
+    pub proxy: Option<String>,
+    /// Hosts that bypass the proxy
+    pub no_proxy: Vec<String>,
+    /// Retry settings
+    pub retry: Option<RetryConfig>,
+}
+
+/// Reference to the API credentials
+///
+/// With an `email` the token is used for Basic auth, otherwise as a bearer token.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CredentialsConfig {
+    /// Account email for Basic auth
+    pub email: Option<String>,
+    /// Environment variable holding the token
+    pub token_env: Option<String>,
+    /// File holding the token, e.g. a mounted secret
+    pub token_file: Option<PathBuf>,
+}
+
+/// Exponential backoff settings
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Maximum number of attempts, including the first one; 1 disables retries
+    pub max_attempts: u32,
+    /// Backoff before the first retry, in milliseconds
+    pub base_delay_ms: u64,
+    /// Upper bound for the backoff, in milliseconds
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        let defaults = Exponential::default();
+        Self {
+            max_attempts: defaults.max_attempts,
+            base_delay_ms: defaults.base_delay.as_millis() as u64,
+            max_delay_ms: defaults.max_delay.as_millis() as u64,
+        }
+    }
+}
+
+impl Config {
+    /// Load a configuration file, choosing the format by extension
+    ///
+    /// `.toml` needs the `toml` feature; `.yaml` and `.yml` need the `yaml` feature.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let parse: Result<fn(&str) -> Result<Self>> = match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Ok(Self::from_toml_str),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Ok(Self::from_yaml_str),
+            _ => Err(Error::Config(format!(
+                "Unsupported config file {}, expected .toml (`toml` feature) or .yaml (`yaml` feature)",
+                path.display()
+            ))),
+        };
+        let parse = parse?;
+        
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("Cannot read {}: {}", path.display(), e)))?;
+        parse(&contents).map_err(|e| match e {
+            Error::Config(message) => Error::Config(format!("Invalid config {}: {}", path.display(), message)),
+            e => e,
+        })
+    }
+    
+    /// Parse a TOML configuration
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| Error::Config(e.to_string()))
+    }
+    
+    /// Parse a YAML configuration
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(contents: &str) -> Result<Self> {
+        serde_yaml::from_str(contents).map_err(|e| Error::Config(e.to_string()))
+    }
+    
+    /// Create a client builder from these settings
+    ///
+    /// `ECOMMERCE_API_*` environment variables override the file, see
+    /// [`ClientBuilder::env_overrides`].
+    pub fn into_builder(self) -> Result<ClientBuilder> {
+        self.into_builder_with(|name| std::env::var(name).ok())?
+            .env_overrides()
+    }
+    
+    fn into_builder_with(self, lookup: impl Fn(&str) -> Option<String>) -> Result<ClientBuilder> {
+        let base_url = match self.sandbox {
+            true => self.sandbox_url.ok_or_else(|| {
+                Error::Config("sandbox is set but sandbox_url is missing".to_string())
+            })?,
+            false => self.base_url.ok_or_else(|| Error::Config("base_url is missing".to_string()))?,
+        };
+        let mut builder = ClientBuilder::new(base_url);
+        
+        if !self.sandbox {
+            for fallback_url in self.fallback_urls {
+                builder = builder.fallback_url(fallback_url);
+            }
+        }
+        if let Some(credentials) = self.credentials {
+            builder = builder.auth(credentials.resolve(lookup)?);
+        }
+        if let Some(seconds) = self.timeout_secs {
+            builder = builder.timeout(seconds_to_duration("timeout_secs", seconds)?);
+        }
+        if let Some(seconds) = self.connect_timeout_secs {
+            builder = builder.connect_timeout(seconds_to_duration("connect_timeout_secs", seconds)?);
+        }
+        if let Some(user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(ProxyConfig::all(proxy).no_proxy(self.no_proxy));
+        }
+        if let Some(retry) = self.retry {
+            builder = match retry.max_attempts {
+                0 | 1 => builder.retry(retry::Never),
+                max_attempts => builder.retry(Exponential {
+                    max_attempts,
+                    base_delay: Duration::from_millis(retry.base_delay_ms),
+                    max_delay: Duration::from_millis(retry.max_delay_ms),
+                }),
+            };
+        }
+        Ok(builder)
+    }
+}
+
+impl CredentialsConfig {
+    /// Read the referenced token and build the auth scheme
+    fn resolve(self, lookup: impl Fn(&str) -> Option<String>) -> Result<AuthScheme> {
+        let token = match (&self.token_env, &self.token_file) {
+            (Some(name), _) => lookup(name)
+                .ok_or_else(|| Error::Config(format!("Token variable {} is not set", name)))?,
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .map(|token| token.trim().to_string())
+                .map_err(|e| Error::Config(format!("Cannot read token file {}: {}", path.display(), e)))?,
+            (None, None) => {
+                return Err(Error::Config("credentials need token_env or token_file".to_string()));
+            }
+        };
+        
+        let token = SecretString::new(token);
+        Ok(match self.email {
+            Some(email) => AuthScheme::Basic { email, token },
+            None => AuthScheme::Bearer(token),
+        })
+    }
+}
+
+fn seconds_to_duration(name: &str, seconds: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| Error::Config(format!("{} must be a non-negative number, got {}", name, seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn lookup(name: &str) -> Option<String> {
+        (name == "SUPPLIER_API_TOKEN").then(|| "secret-token".to_string())
+    }
+    
+    #[test]
+    fn test_into_builder() {
+        let config = Config {
+            base_url: Some("https://api.example.com".to_string()),
+            sandbox_url: Some("https://sandbox.example.com".to_string()),
+            sandbox: true,
+            credentials: Some(CredentialsConfig {
+                email: Some("orders@example.com".to_string()),
+                token_env: Some("SUPPLIER_API_TOKEN".to_string()),
+                token_file: None,
+            }),
+            timeout_secs: Some(60.0),
+            retry: Some(RetryConfig {
+                max_attempts: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        
+        let builder = config.into_builder_with(lookup).unwrap();
+        let debug = format!("{:?}", builder);
+        assert!(debug.contains("https://sandbox.example.com"));
+        assert!(debug.contains("orders@example.com"));
+        assert!(!debug.contains("secret-token"));
+        assert!(builder.build().is_ok());
+    }
+    
+    #[test]
+    fn test_missing_settings() {
+        let result = Config::default().into_builder_with(lookup);
+        assert!(matches!(result.unwrap_err(), Error::Config(_)));
+        
+        let config = Config {
+            base_url: Some("https://api.example.com".to_string()),
+            credentials: Some(CredentialsConfig {
+                token_env: Some("UNSET_TOKEN".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(config.into_builder_with(lookup).unwrap_err(), Error::Config(_)));
+    }
+    
+    #[test]
+    fn test_from_file_unsupported() {
+        let result = Config::from_file("README.md");
+        assert!(matches!(result.unwrap_err(), Error::Config(message) if message.starts_with("Unsupported config file README.md")));
+    }
+    
+    #[test]
+    fn test_env_token_keeps_file_email() {
+        let config = Config {
+            base_url: Some("https://api.example.com".to_string()),
+            credentials: Some(CredentialsConfig {
+                email: Some("orders@example.com".to_string()),
+                token_env: Some("SUPPLIER_API_TOKEN".to_string()),
+                token_file: None,
+            }),
+            ..Default::default()
+        };
+        
+        let builder = config
+            .into_builder_with(lookup)
+            .unwrap()
+            .env_overrides_from(|name| (name == "ECOMMERCE_API_TOKEN").then(|| "rotated-token".to_string()))
+            .unwrap();
+        let debug = format!("{:?}", builder);
+        assert!(debug.contains("Basic"));
+        assert!(debug.contains("orders@example.com"));
+    }
+    
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml_str(
+            r#"
+            base_url = "https://api.example.com"
+            timeout_secs = 12.5
+            
+            [credentials]
+            token_env = "SUPPLIER_API_TOKEN"
+            
+            [retry]
+            max_attempts = 5
+            "#,
+        )
+        .unwrap();
+        
+        assert_eq!(config.timeout_secs, Some(12.5));
+        assert_eq!(config.retry.unwrap().max_attempts, 5);
+        assert!(Config::from_toml_str("unknown = 1").is_err());
+    }
+    
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_from_yaml() {
+        let config = Config::from_yaml_str(
+            "base_url: https://api.example.com\nsandbox: true\nsandbox_url: https://sandbox.example.com\n",
+        )
+        .unwrap();
+        
+        assert!(config.sandbox);
+        assert_eq!(config.sandbox_url.as_deref(), Some("https://sandbox.example.com"));
+    }
+}
@@ -0,0 +1,268 @@
+//! Custom connectors, e.g. Unix domain sockets to a local gateway
+
+use crate::error::{Error, Result};
+use crate::transport::{HttpRequest, HttpResponse, HttpTransport, MaxResponseSize};
+use async_trait::async_trait;
+use hyper::body::HttpBody;
+use hyper::client::connect::{Connected, Connection as HyperConnection};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Byte stream to the API server opened by a [`Connector`]
+pub trait Connection: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin + 'static> Connection for T {}
+
+/// Opens connections on behalf of the client
+///
+/// Requests are written as plain HTTP/1.1 to the returned stream, and
+/// idle connections are pooled per host. Use it for sidecar proxies and
+/// local gateways that are not reachable over TCP; anything encrypting
+/// the stream is up to the connector.
+///
+/// ```
+/// use ecommerce_api_client::async_trait;
+/// use ecommerce_api_client::connector::{Connection, Connector};
+/// use ecommerce_api_client::http::Uri;
+///
+/// /// Always talks to the sidecar, whatever the base URL says
+/// struct Sidecar;
+///
+/// #[async_trait]
+/// impl Connector for Sidecar {
+///     async fn connect(&self, _uri: &Uri) -> std::io::Result<Box<dyn Connection>> {
+///         let stream = tokio::net::TcpStream::connect("127.0.0.1:15001").await?;
+///         Ok(Box::new(stream))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Connector: Send + Sync + 'static {
+    /// Open a connection for requests to `uri`
+    async fn connect(&self, uri: &http::Uri) -> io::Result<Box<dyn Connection>>;
+}
+
+/// Connects to a Unix domain socket, whatever host the base URL names
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct UnixSocket {
+    path: std::path::PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixSocket {
+    /// Connect to the socket at `path`
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl Connector for UnixSocket {
+    async fn connect(&self, _uri: &http::Uri) -> io::Result<Box<dyn Connection>> {
+        let stream = tokio::net::UnixStream::connect(&self.path).await?;
+        Ok(Box::new(stream))
+    }
+}
+
+/// HTTP/1.1 over connections opened by a [`Connector`]
+pub(crate) struct ConnectorTransport {
+    client: hyper::Client<Service, hyper::Body>,
+}
+
+impl ConnectorTransport {
+    pub(crate) fn new(connector: Arc<dyn Connector>) -> Self {
+        Self {
+            client: hyper::Client::builder().build(Service(connector)),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ConnectorTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let limit = MaxResponseSize::of(&request);
+        let response = self
+            .client
+            .request(request.map(hyper::Body::from))
+            .await
+            .map_err(|e| match e.is_connect() {
+                true => Error::transport_unsent(e),
+                false => Error::transport(e),
+            })?;
+        
+        let (parts, body) = response.into_parts();
+        let body = read_body(body, limit).await?;
+        Ok(HttpResponse::from_parts(parts, body))
+    }
+}
+
+/// Read a body, failing once it grows past `limit` bytes
+async fn read_body(mut body: hyper::Body, limit: Option<u64>) -> Result<Vec<u8>> {
+    let too_large = |size: u64| limit.filter(|limit| size > *limit).map(Error::ResponseTooLarge);
+    if let Some(error) = body.size_hint().exact().and_then(too_large) {
+        return Err(error);
+    }
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(Error::transport)?;
+        if let Some(error) = too_large((buffer.len() + chunk.len()) as u64) {
+            return Err(error);
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(buffer)
+}
+
+impl fmt::Debug for ConnectorTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectorTransport").finish_non_exhaustive()
+    }
+}
+
+/// Adapts a [`Connector`] to hyper's connector interface
+#[derive(Clone)]
+struct Service(Arc<dyn Connector>);
+
+impl hyper::service::Service<http::Uri> for Service {
+    type Response = Stream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Stream>> + Send>>;
+    
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    
+    fn call(&mut self, uri: http::Uri) -> Self::Future {
+        let connector = self.0.clone();
+        Box::pin(async move { connector.connect(&uri).await.map(Stream) })
+    }
+}
+
+/// A boxed [`Connection`] usable by hyper
+struct Stream(Box<dyn Connection>);
+
+impl HyperConnection for Stream {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_write(cx, buf)
+    }
+    
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0).poll_flush(cx)
+    }
+    
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tokio::sync::Mutex;
+    
+    /// Hands out one end of an in-memory pipe
+    struct Pipe(Mutex<Option<DuplexStream>>);
+    
+    #[async_trait]
+    impl Connector for Pipe {
+        async fn connect(&self, _uri: &http::Uri) -> io::Result<Box<dyn Connection>> {
+            let stream = self.0.lock().await.take().ok_or(io::ErrorKind::ConnectionRefused)?;
+            Ok(Box::new(stream))
+        }
+    }
+    
+    /// Answer one request on `stream` with 204 and return the request head
+    async fn answer(mut stream: impl Connection) -> String {
+        let mut request = vec![0u8; 4096];
+        let read = stream.read(&mut request).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8_lossy(&request[..read]).into_owned()
+    }
+    
+    fn request() -> HttpRequest {
+        http::Request::get("http://gateway.local/api_customer/orders")
+            .body(Vec::new())
+            .unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_custom_connector() {
+        let (client_end, server_end) = tokio::io::duplex(4096);
+        let transport = ConnectorTransport::new(Arc::new(Pipe(Mutex::new(Some(client_end)))));
+        let server = tokio::spawn(answer(server_end));
+        
+        let response = transport.send(request()).await.unwrap();
+        assert_eq!(response.status(), 204);
+        assert!(server.await.unwrap().starts_with("GET /api_customer/orders HTTP/1.1"));
+    }
+    
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let (client_end, mut server_end) = tokio::io::duplex(4096);
+        let transport = ConnectorTransport::new(Arc::new(Pipe(Mutex::new(Some(client_end)))));
+        // A chunked body of unknown length, stopped once it passes the limit
+        tokio::spawn(async move {
+            let mut request = vec![0u8; 4096];
+            let _ = server_end.read(&mut request).await;
+            let _ = server_end.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").await;
+            loop {
+                if server_end.write_all(b"400\r\n").await.is_err()
+                    || server_end.write_all(&[b'x'; 1024]).await.is_err()
+                    || server_end.write_all(b"\r\n").await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+        
+        let mut request = request();
+        request.extensions_mut().insert(MaxResponseSize(4096));
+        let error = transport.send(request).await.unwrap_err();
+        assert!(matches!(error, Error::ResponseTooLarge(4096)));
+    }
+    
+    #[tokio::test]
+    async fn test_connect_failure_is_unsent() {
+        let transport = ConnectorTransport::new(Arc::new(Pipe(Mutex::new(None))));
+        let error = transport.send(request()).await.unwrap_err();
+        assert!(matches!(error, Error::Transport { unsent: true, .. }));
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket() {
+        let path = std::env::temp_dir().join(format!("ecommerce-api-client-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move { answer(listener.accept().await.unwrap().0).await });
+        
+        let transport = ConnectorTransport::new(Arc::new(UnixSocket::new(&path)));
+        let response = transport.send(request()).await.unwrap();
+        assert_eq!(response.status(), 204);
+        assert!(server.await.unwrap().contains("host: gateway.local"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+//! Capture of raw exchanges, for settling what was actually sent and received
+
+use crate::error::{Error, Result};
+use crate::redact;
+use crate::response;
+use crate::runtime::{self, Instant};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Method, StatusCode};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// One HTTP exchange as it went over the wire
+///
+/// Bodies are kept byte for byte, except that credentials in JSON bodies,
+/// such as a login password, are replaced with [`REDACTED`](redact::REDACTED).
+/// Credential headers like `Authorization` and `Cookie` are redacted too.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DebugEntry {
+    /// When the request was sent
+    pub timestamp: SystemTime,
+    /// HTTP method
+    pub method: Method,
+    /// Request URL
+    pub url: String,
+    /// Request headers set by the client
+    pub request_headers: HeaderMap,
+    /// Request body as sent
+    pub request_body: Option<Vec<u8>>,
+    /// Response status, unset if no response was received
+    pub status: Option<StatusCode>,
+    /// Response headers
+    pub response_headers: HeaderMap,
+    /// Response body as received
+    pub response_body: Vec<u8>,
+    /// Error the exchange failed with
+    pub error: Option<String>,
+    /// Time until the response body was read
+    pub duration: Duration,
+}
+
+impl DebugEntry {
+    /// Request body as text, replacing invalid UTF-8
+    pub fn request_text(&self) -> Option<Cow<'_, str>> {
+        self.request_body.as_deref().map(String::from_utf8_lossy)
+    }
+    
+    /// Response body as text, replacing invalid UTF-8
+    pub fn response_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.response_body)
+    }
+}
+
+/// The most recent exchanges of a client and its clones
+#[derive(Debug)]
+pub(crate) struct DebugLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<DebugEntry>>,
+}
+
+impl DebugLog {
+    /// Keep up to `capacity` exchanges
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+    
+    /// The captured exchanges, oldest first
+    pub(crate) fn entries(&self) -> Vec<DebugEntry> {
+        self.lock().iter().cloned().collect()
+    }
+    
+    /// Perform an exchange with `send`, capturing it
+    ///
+    /// The response body is read here, up to `max_body` bytes, and handed on
+    /// in a rebuilt response.
+    pub(crate) async fn capture<F, Fut>(
+        &self,
+        req_builder: reqwest::RequestBuilder,
+        max_body: Option<u64>,
+        send: F,
+    ) -> Result<reqwest::Response>
+    where
+        F: FnOnce(reqwest::RequestBuilder) -> Fut,
+        Fut: Future<Output = Result<reqwest::Response>>,
+    {
+        let snapshot = req_builder.try_clone().and_then(|builder| builder.build().ok());
+        let mut entry = DebugEntry {
+            timestamp: runtime::system_now(),
+            method: snapshot.as_ref().map_or(Method::POST, |request| request.method().clone()),
+            url: snapshot
+                .as_ref()
+                .map_or_else(|| String::from("<streaming request>"), |request| request.url().to_string()),
+            request_headers: snapshot
+                .as_ref()
+                .map(|request| redact_headers(request.headers()))
+                .unwrap_or_default(),
+            request_body: snapshot
+                .as_ref()
+                .and_then(|request| request.body())
+                .and_then(|body| body.as_bytes())
+                .map(redact_body),
+            status: None,
+            response_headers: HeaderMap::new(),
+            response_body: Vec::new(),
+            error: None,
+            duration: Duration::ZERO,
+        };
+        
+        let started = Instant::now();
+        let result = match send(req_builder).await {
+            Ok(response) => {
+                let status = response.status();
+                let version = response.version();
+                let headers = response.headers().clone();
+                match response::read_body(response, max_body).await {
+                    Ok(body) => {
+                        entry.status = Some(status);
+                        entry.response_headers = redact_headers(&headers);
+                        entry.response_body = body.clone();
+                        let mut builder = http::Response::builder().status(status).version(version);
+                        if let Some(response_headers) = builder.headers_mut() {
+                            *response_headers = headers;
+                        }
+                        builder
+                            .body(body)
+                            .map(reqwest::Response::from)
+                            .map_err(Error::transport)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        entry.duration = started.elapsed();
+        if let Err(e) = &result {
+            entry.error = Some(e.message());
+        }
+        self.push(entry);
+        result
+    }
+    
+    fn push(&self, entry: DebugEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.lock();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<DebugEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Copy of `headers` with credential values replaced
+fn redact_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut redacted = headers.clone();
+    for (name, value) in redacted.iter_mut() {
+        if redact::is_credential(name.as_str()) {
+            *value = HeaderValue::from_static(redact::REDACTED);
+        }
+    }
+    redacted
+}
+
+/// The body as sent, unless it is JSON carrying credentials
+fn redact_body(body: &[u8]) -> Vec<u8> {
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return body.to_vec();
+    };
+    if redact::redact_credentials(&mut value) {
+        value.to_string().into_bytes()
+    } else {
+        body.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, SET_COOKIE};
+    
+    #[test]
+    fn test_redact_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc123"));
+        headers.insert(SET_COOKIE, HeaderValue::from_static("session=xyz"));
+        headers.insert("x-api-key", HeaderValue::from_static("k-1"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted[AUTHORIZATION], redact::REDACTED);
+        assert_eq!(redacted[SET_COOKIE], redact::REDACTED);
+        assert_eq!(redacted["x-api-key"], redact::REDACTED);
+        assert_eq!(redacted[CONTENT_TYPE], "application/json");
+    }
+    
+    #[test]
+    fn test_redact_body() {
+        // Bodies without credentials keep their exact formatting
+        let order = b"{\"customer_order_reference\": \"ORDER-001\",  \"phone\": \"555-0100\"}";
+        assert_eq!(redact_body(order), order.to_vec());
+        
+        let login = br#"{"email":"jane@example.com","password":"hunter2"}"#;
+        assert_eq!(redact_body(login), br#"{"email":"jane@example.com","password":"[REDACTED]"}"#.to_vec());
+    }
+}
@@ -0,0 +1,121 @@
+//! `Deprecation`, `Sunset` and `Warning` response headers
+
+use reqwest::header::{HeaderMap, HeaderName, LINK, WARNING};
+use std::time::{Duration, SystemTime};
+
+/// `Deprecation` header (RFC 9745)
+pub const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+/// `Sunset` header (RFC 8594)
+pub const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// Retirement notice sent by the server alongside a response
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeprecationNotice {
+    /// Whether the server marked the endpoint as deprecated
+    pub deprecated: bool,
+    /// When the endpoint was or will be deprecated, if the server gave a date
+    pub deprecated_at: Option<SystemTime>,
+    /// When the endpoint will stop working
+    pub sunset: Option<SystemTime>,
+    /// Documentation linked with `rel="deprecation"` or `rel="sunset"`
+    pub link: Option<String>,
+    /// Texts of `Warning` headers
+    pub warnings: Vec<String>,
+}
+
+impl DeprecationNotice {
+    /// Parse the notice from response headers, if the server sent one
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let text = |name: &HeaderName| headers.get(name).and_then(|value| value.to_str().ok());
+        let deprecation = text(&DEPRECATION).map(str::trim);
+        let notice = Self {
+            deprecated: deprecation.is_some_and(|value| !value.eq_ignore_ascii_case("false")),
+            deprecated_at: deprecation.and_then(parse_date),
+            sunset: text(&SUNSET).and_then(parse_date),
+            link: headers
+                .get_all(LINK)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .find_map(deprecation_link),
+            warnings: headers
+                .get_all(WARNING)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .map(warning_text)
+                .collect(),
+        };
+        (notice != Self::default()).then_some(notice)
+    }
+}
+
+/// A structured date (`@1688169599`) or an HTTP-date
+fn parse_date(value: &str) -> Option<SystemTime> {
+    match value.strip_prefix('@') {
+        Some(seconds) => seconds
+            .parse()
+            .ok()
+            .map(|seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)),
+        None => httpdate::parse_http_date(value).ok(),
+    }
+}
+
+/// Target of a `rel="deprecation"` or `rel="sunset"` link in a `Link` header
+fn deprecation_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        let is_deprecation = params.split(';').any(|param| {
+            let param = param.trim().to_ascii_lowercase();
+            matches!(param.as_str(), "rel=\"deprecation\"" | "rel=deprecation" | "rel=\"sunset\"" | "rel=sunset")
+        });
+        let target = target.trim().strip_prefix('<')?.strip_suffix('>')?;
+        is_deprecation.then(|| target.to_string())
+    })
+}
+
+/// Quoted text of a `Warning` header such as `299 - "Deprecated API"`
+fn warning_text(value: &str) -> String {
+    match (value.find('"'), value.rfind('"')) {
+        (Some(start), Some(end)) if end > start => value[start + 1..end].to_string(),
+        _ => value.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(DeprecationNotice::from_headers(&headers), None);
+        
+        headers.insert(DEPRECATION, HeaderValue::from_static("@1688169599"));
+        headers.insert(SUNSET, HeaderValue::from_static("Sun, 30 Jun 2024 23:59:59 GMT"));
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(r#"<https://api.example.com/v2>; rel="successor-version", <https://docs.example.com/retire>; rel="deprecation""#),
+        );
+        headers.append(WARNING, HeaderValue::from_static(r#"299 - "Use /v2/orders""#));
+        
+        let notice = DeprecationNotice::from_headers(&headers).unwrap();
+        assert!(notice.deprecated);
+        assert_eq!(notice.deprecated_at, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1688169599)));
+        assert_eq!(notice.sunset, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1719791999)));
+        assert_eq!(notice.link.as_deref(), Some("https://docs.example.com/retire"));
+        assert_eq!(notice.warnings, vec!["Use /v2/orders".to_string()]);
+    }
+    
+    #[test]
+    fn test_legacy_deprecation_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(DEPRECATION, HeaderValue::from_static("true"));
+        let notice = DeprecationNotice::from_headers(&headers).unwrap();
+        assert!(notice.deprecated);
+        assert_eq!(notice.deprecated_at, None);
+        
+        headers.insert(DEPRECATION, HeaderValue::from_static("false"));
+        assert_eq!(DeprecationNotice::from_headers(&headers), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Structured differences between two snapshots of an order
+//!
+//! [`Order::diff`] and [`OrderProduct::diff`] list the fields that changed
+//! between two versions of the same order or line item, and
+//! [`diff_line_items`] matches line items by id to find the ones added,
+//! removed or changed. Reconciliation and order watching use them to report
+//! exactly what changed.
+
+use crate::types::{Order, OrderProduct};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A field whose value differs between two snapshots
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FieldChange {
+    /// Name of the field as the API spells it
+    pub field: String,
+    /// Value in the older snapshot, `null` if it was unset
+    pub old: Value,
+    /// Value in the newer snapshot, `null` if it is unset
+    pub new: Value,
+}
+
+/// How a line item differs between two snapshots of an order
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum LineItemChange {
+    /// Only in the newer snapshot
+    Added(OrderProduct),
+    /// Only in the older snapshot
+    Removed(OrderProduct),
+    /// In both snapshots with different fields
+    Changed {
+        /// Id of the line item
+        id: u64,
+        /// Fields that differ
+        changes: Vec<FieldChange>,
+    },
+}
+
+impl Order {
+    /// Fields that differ between this order and `new`, by field name
+    ///
+    /// Hypermedia links are not compared.
+    pub fn diff(&self, new: &Order) -> Vec<FieldChange> {
+        diff_fields(self, new, &["links"])
+    }
+}
+
+impl OrderProduct {
+    /// Fields that differ between this line item and `new`, by field name
+    pub fn diff(&self, new: &OrderProduct) -> Vec<FieldChange> {
+        diff_fields(self, new, &[])
+    }
+}
+
+/// Line items added, removed or changed between `old` and `new`, matched by id
+///
+/// Changes are listed by line item id.
+pub fn diff_line_items(old: &[OrderProduct], new: &[OrderProduct]) -> Vec<LineItemChange> {
+    let mut old: BTreeMap<u64, &OrderProduct> = old.iter().map(|item| (item.id, item)).collect();
+    let mut changes = BTreeMap::new();
+    for item in new {
+        let change = match old.remove(&item.id) {
+            None => LineItemChange::Added(item.clone()),
+            Some(previous) => match previous.diff(item) {
+                fields if fields.is_empty() => continue,
+                fields => LineItemChange::Changed {
+                    id: item.id,
+                    changes: fields,
+                },
+            },
+        };
+        changes.insert(item.id, change);
+    }
+    for (id, item) in old {
+        changes.insert(id, LineItemChange::Removed(item.clone()));
+    }
+    changes.into_values().collect()
+}
+
+/// Compare the serialized fields of `old` and `new`, skipping `ignored`
+fn diff_fields<T: Serialize>(old: &T, new: &T, ignored: &[&str]) -> Vec<FieldChange> {
+    let fields = |value: &T| match serde_json::to_value(value) {
+        Ok(Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let (old, mut new) = (fields(old), fields(new));
+    let mut changes = Vec::new();
+    for (field, old) in old {
+        let new = new.remove(&field).unwrap_or(Value::Null);
+        if old != new && !ignored.contains(&field.as_str()) {
+            changes.push(FieldChange { field, old, new });
+        }
+    }
+    for (field, new) in new {
+        if !new.is_null() && !ignored.contains(&field.as_str()) {
+            changes.push(FieldChange {
+                field,
+                old: Value::Null,
+                new,
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.field.cmp(&b.field));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn order(fields: Value) -> Order {
+        let mut order = json!({
+            "id": 1,
+            "status_order_id": 1,
+            "customer_id": 7,
+            "customer_order_reference": "ORDER-1",
+            "gross_total": "10.00",
+            "addressbook_id": 3,
+        });
+        order.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(order).unwrap()
+    }
+    
+    fn item(id: u64, quantity: &str) -> OrderProduct {
+        serde_json::from_value(json!({
+            "id": id,
+            "order_id": 1,
+            "product_id": 9,
+            "quantity": quantity,
+            "price": "1.00",
+            "final_price": "1.00",
+        }))
+        .unwrap()
+    }
+    
+    #[test]
+    fn test_order_diff() {
+        let old = order(json!({"comments_customer": "leave at door"}));
+        let new = order(json!({"status_order_id": 4, "invoice_no": "INV-1", "links": {"self": "https://x"}}));
+        let changes = old.diff(&new);
+        let fields: Vec<_> = changes.iter().map(|change| change.field.as_str()).collect();
+        assert_eq!(fields, ["comments_customer", "invoice_no", "status_order_id"]);
+        assert_eq!(changes[0].new, Value::Null);
+        assert_eq!(changes[2].old, json!(1));
+        assert_eq!(changes[2].new, json!(4));
+        assert!(old.diff(&old).is_empty());
+    }
+    
+    #[test]
+    fn test_line_item_diff() {
+        let old = [item(1, "1"), item(2, "1"), item(3, "5")];
+        let new = [item(4, "1"), item(2, "3"), item(3, "5")];
+        let changes = diff_line_items(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0], LineItemChange::Removed(item(1, "1")));
+        assert!(matches!(&changes[1], LineItemChange::Changed { id: 2, changes } if changes[0].field == "quantity"));
+        assert_eq!(changes[2], LineItemChange::Added(item(4, "1")));
+    }
+}
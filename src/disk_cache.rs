@@ -0,0 +1,140 @@
+//! Catalogue responses cached on disk between runs
+//!
+//! Short-lived command line tools fetch the same catalogue pages on every
+//! run. With a [`DiskCache`] set through
+//! [`Client::with_disk_cache`](crate::Client::with_disk_cache), product
+//! pages read within the time to live are answered from disk. Older pages
+//! are revalidated with their `ETag`, so an unchanged catalogue costs a
+//! bodiless `304 Not Modified` instead of the whole page.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Response stored for one URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CachedResponse {
+    /// URL the response was read from, to tell apart URLs whose files collide
+    pub(crate) url: String,
+    /// When the response was stored or last revalidated, in milliseconds since the Unix epoch
+    pub(crate) stored_at: u64,
+    /// `ETag` the server reported, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) etag: Option<String>,
+    /// The decoded response
+    pub(crate) body: Value,
+}
+
+/// Keeps each cached response as a JSON file in a directory
+///
+/// Unreadable files count as misses.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+    failures: AtomicU64,
+}
+
+impl DiskCache {
+    /// Cache responses in `dir` for `ttl`, creating the directory if needed
+    pub fn open(dir: impl Into<PathBuf>, ttl: Duration) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            ttl,
+            failures: AtomicU64::default(),
+        })
+    }
+    
+    /// Number of responses that could not be stored
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+    
+    /// Delete every cached response
+    pub fn clear(&self) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// The response stored for `url`, fresh or not
+    pub(crate) fn get(&self, url: &str) -> Option<CachedResponse> {
+        let contents = std::fs::read(self.path(url)).ok()?;
+        let cached: CachedResponse = serde_json::from_slice(&contents).ok()?;
+        (cached.url == url).then_some(cached)
+    }
+    
+    /// Whether `cached` is within the time to live
+    pub(crate) fn is_fresh(&self, cached: &CachedResponse) -> bool {
+        let age = now_millis().saturating_sub(cached.stored_at);
+        u128::from(age) < self.ttl.as_millis()
+    }
+    
+    /// Store `body` as the response for `url`, stamped with the current time
+    pub(crate) fn put(&self, url: &str, etag: Option<String>, body: Value) {
+        let cached = CachedResponse {
+            url: url.to_string(),
+            stored_at: now_millis(),
+            etag,
+            body,
+        };
+        let written = serde_json::to_vec(&cached)
+            .map_err(std::io::Error::from)
+            .and_then(|json| crate::fs::write_atomic(&self.path(url), &json));
+        if written.is_err() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// File of the response for `url`
+    fn path(&self, url: &str) -> PathBuf {
+        let digest = Sha256::digest(url.as_bytes());
+        let name: String = digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_store_and_expiry() {
+        let dir = std::env::temp_dir().join(format!("ecommerce-api-disk-cache-{}", std::process::id()));
+        let url = "https://api.example.com/api_customer/products?page=1";
+        
+        let cache = DiskCache::open(&dir, Duration::from_secs(60)).unwrap();
+        assert_eq!(cache.get(url), None);
+        cache.put(url, Some("\"v1\"".to_string()), serde_json::json!({"items": []}));
+        assert_eq!(cache.failures(), 0);
+        
+        // A new process sees the same response
+        let reopened = DiskCache::open(&dir, Duration::from_secs(60)).unwrap();
+        let cached = reopened.get(url).unwrap();
+        assert_eq!(cached.etag.as_deref(), Some("\"v1\""));
+        assert!(reopened.is_fresh(&cached));
+        assert_eq!(reopened.get("https://api.example.com/api_customer/products?page=2"), None);
+        
+        let expired = DiskCache::open(&dir, Duration::ZERO).unwrap();
+        assert!(!expired.is_fresh(&expired.get(url).unwrap()));
+        
+        cache.clear().unwrap();
+        assert_eq!(cache.get(url), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,150 @@
+//! Envelope-aware response decoding
+//!
+//! Some deployments of the API wrap every payload in
+//! `{ "data": ..., "meta": {...} }`. The helpers here unwrap such responses so
+//! the same typed models can be used regardless of the deployment.
+
+use crate::error::Result;
+use crate::response::ApiResponse;
+use crate::types::Links;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// How response payloads are wrapped by the API deployment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Envelope {
+    /// Payloads are returned unwrapped
+    None,
+    /// Payloads are always wrapped in a `data`/`meta` envelope
+    Wrapped,
+    /// Detect the envelope per response (default)
+    #[default]
+    Auto,
+}
+
+/// Metadata returned alongside enveloped payloads
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Meta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<u64>,
+    /// Any other metadata fields sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Keys allowed next to `data` for a body to be treated as an envelope
+const ENVELOPE_KEYS: &[&str] = &["data", "meta", "links"];
+
+/// Check whether a JSON value looks like a `data`/`meta` envelope
+fn is_envelope(value: &Value) -> bool {
+    match value.as_object() {
+        Some(object) => {
+            object.contains_key("data")
+                && object.keys().all(|key| ENVELOPE_KEYS.contains(&key.as_str()))
+        }
+        None => false,
+    }
+}
+
+/// Split an envelope into its payload, metadata and links
+fn unwrap(value: Value) -> Result<(Value, Option<Meta>, Option<Links>)> {
+    let mut object = match value {
+        Value::Object(object) => object,
+        _ => return Err(serde_json::Error::custom("expected an enveloped JSON object").into()),
+    };
+
+    let data = object
+        .remove("data")
+        .ok_or_else(|| serde_json::Error::custom("missing `data` field in response envelope"))?;
+    let meta = match object.remove("meta") {
+        Some(Value::Null) | None => None,
+        Some(meta) => Some(serde_json::from_value(meta)?),
+    };
+    let links = match object.remove("links") {
+        Some(Value::Null) | None => None,
+        Some(links) => Some(serde_json::from_value(links)?),
+    };
+
+    Ok((data, meta, links))
+}
+
+/// Decode a JSON response body according to the envelope mode
+pub(crate) fn decode<T: DeserializeOwned>(envelope: Envelope, body: &[u8]) -> Result<ApiResponse<T>> {
+    let value: Value = serde_json::from_slice(body)?;
+
+    let (data, meta, links) = match envelope {
+        Envelope::None => (value, None, None),
+        Envelope::Wrapped => unwrap(value)?,
+        Envelope::Auto if is_envelope(&value) => unwrap(value)?,
+        Envelope::Auto => (value, None, None),
+    };
+
+    let mut response = ApiResponse::new(serde_json::from_value(data)?);
+    response.meta = meta;
+    response.links = links;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[derive(Debug, Deserialize)]
+    struct Item {
+        id: u64,
+    }
+
+    #[test]
+    fn test_decode_unwrapped_auto() {
+        let response: ApiResponse<Item> = decode(Envelope::Auto, br#"{"id": 7}"#).unwrap();
+        assert_eq!(response.data.id, 7);
+        assert!(response.meta.is_none());
+    }
+
+    #[test]
+    fn test_decode_wrapped_auto() {
+        let body = br#"{
+            "data": [{"id": 1}, {"id": 2}],
+            "meta": {"page": 2, "per_page": 50, "total": 51, "request_time": 0.02},
+            "links": {"next": "/api_customer/orders?page=3"}
+        }"#;
+        let response: ApiResponse<Vec<Item>> = decode(Envelope::Auto, body).unwrap();
+        assert_eq!(response.data.len(), 2);
+        assert_eq!(response.links.unwrap().get("next").unwrap().href, "/api_customer/orders?page=3");
+
+        let meta = response.meta.unwrap();
+        assert_eq!(meta.page, Some(2));
+        assert_eq!(meta.per_page, Some(50));
+        assert_eq!(meta.total, Some(51));
+        assert!(meta.extra.contains_key("request_time"));
+    }
+
+    #[test]
+    fn test_decode_auto_ignores_payload_with_data_field() {
+        #[derive(Debug, Deserialize)]
+        struct Record {
+            data: String,
+            name: String,
+        }
+
+        let response: ApiResponse<Record> =
+            decode(Envelope::Auto, br#"{"data": "raw", "name": "x"}"#).unwrap();
+        assert_eq!(response.data.data, "raw");
+        assert_eq!(response.data.name, "x");
+        assert!(response.meta.is_none());
+    }
+
+    #[test]
+    fn test_decode_wrapped_requires_data() {
+        let result: Result<ApiResponse<Item>> = decode(Envelope::Wrapped, br#"{"id": 7}"#);
+        assert!(matches!(result.unwrap_err(), Error::Json(_)));
+    }
+}
@@ -1,76 +1,600 @@
 //! Error types for the e-commerce API client
 
+use crate::api_error::{ApiErrorBody, ApiErrorCode};
+use crate::options::Endpoint;
+use crate::types::Order;
+use reqwest::Method;
+use std::fmt;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type alias for convenience
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Error types for the API client
+///
+/// New variants may be added in minor releases; use [`Error::kind`] for
+/// handling that does not depend on the exact variant.
+///
+/// Errors returned by API calls come wrapped in [`Error::Context`], which
+/// names the request they came from. Match on [`Error::inner`] or
+/// [`Error::into_inner`] rather than on the error itself.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// HTTP client errors
+    ///
+    /// The request may have reached the server, so non-idempotent requests
+    /// are not retried after these.
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
     
+    /// Connection could not be established; the request was never sent
+    #[error("Connection error")]
+    Connect(#[source] reqwest::Error),
+    
+    /// Failure reported by a custom [`HttpTransport`](crate::transport::HttpTransport)
+    ///
+    /// `unsent` is set when the request is known not to have left the client.
+    #[error("Transport error")]
+    Transport {
+        source: Box<dyn std::error::Error + Send + Sync>,
+        unsent: bool,
+    },
+    
     /// JSON serialization/deserialization errors
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
     
+    /// A successful response whose body could not be decoded
+    ///
+    /// `body` holds the payload as received, so it can be inspected.
+    #[error("Failed to decode response with status {status}")]
+    Decode {
+        source: Box<Error>,
+        body: String,
+        status: u16,
+    },
+    
+    /// XML deserialization errors
+    #[cfg(feature = "xml")]
+    #[error("XML error: {0}")]
+    Xml(#[from] quick_xml::DeError),
+    
+    /// XML serialization errors
+    #[cfg(feature = "xml")]
+    #[error("XML serialization error: {0}")]
+    XmlSerialize(#[from] quick_xml::SeError),
+    
+    /// JWT signing or key loading errors
+    #[cfg(feature = "jwt")]
+    #[error("JWT error: {0}")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    
+    /// CSV reading or writing errors
+    #[cfg(feature = "csv")]
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    
     /// Invalid URL provided
     #[error("Invalid URL: {0}")]
     InvalidUrl(String),
     
+    /// Missing or invalid client configuration
+    #[error("Configuration error: {0}")]
+    Config(String),
+    
+    /// Invalid TLS configuration (certificates, identities)
+    #[error("TLS error: {0}")]
+    Tls(String),
+    
     /// Invalid authentication credentials
     #[error("Invalid credentials: {0}")]
     InvalidCredentials(String),
     
-    /// Bad request (400)
+    /// Bad request (400), with the error details the server sent
     #[error("Bad request: {0}")]
-    BadRequest(String),
+    BadRequest(ApiErrorBody),
     
     /// Unauthorized (401)
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
     
+    /// Forbidden (403): authenticated, but not allowed to do this
+    #[error("Forbidden: {0}")]
+    Forbidden(ApiErrorBody),
+    
     /// Not found (404)
     #[error("Not found: {0}")]
     NotFound(String),
     
-    /// Rate limit exceeded (429)
+    /// Conflict with the current state (409), such as a duplicate
+    /// `customer_order_reference`
+    #[error("Conflict: {0}")]
+    Conflict(ApiErrorBody),
+    
+    /// Request understood but failed validation (422)
+    #[error("Unprocessable entity: {0}")]
+    UnprocessableEntity(ApiErrorBody),
+    
+    /// Rate limit exceeded (429), with the server's `Retry-After` delay if sent
     #[error("Rate limit exceeded: {0}")]
-    RateLimit(String),
+    RateLimit(String, Option<Duration>),
     
-    /// Server error (5xx)
+    /// Server error (5xx), with the `Retry-After` delay of a 503 if sent
     #[error("Server error {0}: {1}")]
-    ServerError(u16, String),
+    ServerError(u16, String, Option<Duration>),
+    
+    /// Request rejected without being sent because the circuit breaker is open
+    #[error("Circuit open: upstream unavailable, retry in {0:?}")]
+    CircuitOpen(Duration),
+    
+    /// The call's overall deadline passed, counting all attempts and backoff
+    ///
+    /// `last_error` is the failure of the last completed attempt, if retries
+    /// were cut short by the deadline.
+    #[error("Deadline of {deadline:?} exceeded after {attempts} attempts in {elapsed:?}")]
+    DeadlineExceeded {
+        deadline: Duration,
+        attempts: u32,
+        elapsed: Duration,
+        #[source]
+        last_error: Option<Box<Error>>,
+    },
+    
+    /// Request aborted through its cancellation token
+    #[error("Request cancelled")]
+    Cancelled,
+    
+    /// Client is shutting down and no longer accepts calls
+    #[error("Client is shut down")]
+    Shutdown,
+    
+    /// Redirect limit of the client's redirect policy exceeded
+    #[error("Too many redirects: stopped after {0}")]
+    TooManyRedirects(usize),
+    
+    /// Response body larger than the client's configured maximum, in bytes
+    #[error("Response body exceeds the limit of {0} bytes")]
+    ResponseTooLarge(u64),
+    
+    /// Collecting a listing hit one of its [`ListLimits`](crate::pagination::ListLimits)
+    #[error("Listing exceeded its limits after {pages} pages, {items} items and {elapsed:?}")]
+    ListingTooLarge {
+        pages: u64,
+        items: usize,
+        elapsed: Duration,
+    },
+    
+    /// An export job finished without producing a file
+    #[error("Export {job_id} failed: {message}")]
+    ExportFailed {
+        job_id: String,
+        message: String,
+    },
+    
+    /// Several orders share the customer order reference looked up
+    #[error("{matches} orders have the customer order reference {reference}")]
+    AmbiguousReference {
+        reference: String,
+        matches: usize,
+    },
+    
+    /// An order with the customer order reference of a new order already
+    /// exists; see [`Client::with_duplicate_guard`](crate::Client::with_duplicate_guard)
+    #[error("Order {} already has the customer order reference {}", .0.id, .0.customer_order_reference)]
+    DuplicateReference(Box<Order>),
+    
+    /// A webhook callback failed signature verification; see
+    /// [`webhooks::verify_signature`](crate::webhooks::verify_signature)
+    #[error("Invalid webhook signature: {0}")]
+    InvalidSignature(String),
+    
+    /// A local store, such as an [`Outbox`](crate::outbox::Outbox), could not be read or written
+    #[error("Storage error: {0}")]
+    Storage(#[source] std::io::Error),
     
     /// Unexpected HTTP status code
     #[error("Unexpected status {0}: {1}")]
     UnexpectedStatus(u16, String),
+    
+    /// An error from a call, with the ids identifying the request
+    ///
+    /// Errors returned by API calls are wrapped in this variant; match on
+    /// [`Error::inner`] to inspect the underlying error. The message only
+    /// names the call; the underlying error is its [`source`](std::error::Error::source).
+    #[error("Request failed ({context})")]
+    Context {
+        #[source]
+        error: Box<Error>,
+        context: Box<ErrorContext>,
+    },
+}
+
+/// Details of the request an error occurred on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorContext {
+    /// Id sent in the `X-Request-Id` header
+    pub request_id: Option<String>,
+    /// Id the server reported for the request
+    pub server_request_id: Option<String>,
+    /// The endpoint called
+    pub endpoint: Option<Endpoint>,
+    /// HTTP method
+    pub method: Option<Method>,
+    /// Request URL
+    pub url: Option<String>,
+    /// Path of the request URL
+    pub path: Option<String>,
+    /// Number of attempts made, including the first
+    pub attempts: u32,
+    /// Time from the start of the call until it failed
+    pub elapsed: Option<Duration>,
+    /// Stack captured when the error was first given a context
+    #[cfg(feature = "backtrace")]
+    backtrace: Option<CapturedBacktrace>,
+}
+
+/// A shared backtrace; copies of a context compare equal if they share it
+#[cfg(feature = "backtrace")]
+#[derive(Debug, Clone)]
+struct CapturedBacktrace(std::sync::Arc<std::backtrace::Backtrace>);
+
+#[cfg(feature = "backtrace")]
+impl PartialEq for CapturedBacktrace {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(feature = "backtrace")]
+impl Eq for CapturedBacktrace {}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        match (&self.method, &self.path) {
+            (Some(method), Some(path)) => parts.push(format!("{} {}", method, path)),
+            (None, Some(path)) => parts.push(path.clone()),
+            _ => {}
+        }
+        if self.attempts > 0 {
+            parts.push(format!("attempt {}", self.attempts));
+        }
+        if let Some(elapsed) = self.elapsed {
+            parts.push(format!("after {:?}", elapsed));
+        }
+        if let Some(id) = &self.request_id {
+            parts.push(format!("request id {}", id));
+        }
+        if let Some(id) = &self.server_request_id {
+            parts.push(format!("server request id {}", id));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// Broad category of an [`Error`], for handling errors without matching
+/// every variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The connection failed or broke off
+    Network,
+    /// The request or the server took too long
+    Timeout,
+    /// Credentials are missing, invalid or lack permission
+    Auth,
+    /// The server rejected the request's content
+    Validation,
+    /// The server asked to slow down
+    RateLimited,
+    /// The server failed or is unavailable; trying again later may succeed
+    ServerTransient,
+    /// The client was misconfigured or sent a request the API does not support
+    ClientBug,
+    /// The response could not be decoded
+    Decode,
+    /// The call was cancelled or the client shut down
+    Cancelled,
+    /// Local storage failed
+    Storage,
 }
 
 impl Error {
     /// Check if the error is retryable
     pub fn is_retryable(&self) -> bool {
-        matches!(self, 
+        matches!(self.inner(), 
             Error::Http(_) |
-            Error::ServerError(_, _) |
-            Error::RateLimit(_)
+            Error::Connect(_) |
+            Error::Transport { .. } |
+            Error::ServerError(..) |
+            Error::RateLimit(..)
         )
     }
     
+    /// Check if the request is known not to have reached the server
+    ///
+    /// Such requests are safe to retry even when they are not idempotent.
+    pub fn is_unsent(&self) -> bool {
+        matches!(
+            self.inner(),
+            Error::Connect(_) | Error::Transport { unsent: true, .. } | Error::CircuitOpen(_)
+        )
+    }
+    
+    /// Classify the error
+    pub fn kind(&self) -> ErrorKind {
+        match self.inner() {
+            Error::Http(e) if e.is_timeout() => ErrorKind::Timeout,
+            Error::Http(e) if e.is_decode() => ErrorKind::Decode,
+            Error::Http(e) if e.is_builder() => ErrorKind::ClientBug,
+            Error::Http(_) | Error::Connect(_) | Error::Transport { .. } => ErrorKind::Network,
+            Error::Json(_) | Error::Decode { .. } | Error::ResponseTooLarge(_) => ErrorKind::Decode,
+            #[cfg(feature = "xml")]
+            Error::Xml(_) => ErrorKind::Decode,
+            #[cfg(feature = "xml")]
+            Error::XmlSerialize(_) => ErrorKind::ClientBug,
+            #[cfg(feature = "jwt")]
+            Error::Jwt(_) => ErrorKind::Auth,
+            #[cfg(feature = "csv")]
+            Error::Csv(_) => ErrorKind::Decode,
+            Error::InvalidCredentials(_)
+            | Error::InvalidSignature(_)
+            | Error::Unauthorized(_)
+            | Error::Forbidden(_) => ErrorKind::Auth,
+            Error::BadRequest(_)
+            | Error::Conflict(_)
+            | Error::UnprocessableEntity(_)
+            | Error::AmbiguousReference { .. }
+            | Error::DuplicateReference(_) => ErrorKind::Validation,
+            Error::RateLimit(..) => ErrorKind::RateLimited,
+            Error::ServerError(..) | Error::CircuitOpen(_) | Error::ExportFailed { .. } => {
+                ErrorKind::ServerTransient
+            }
+            Error::UnexpectedStatus(408, _) | Error::DeadlineExceeded { .. } => ErrorKind::Timeout,
+            Error::Cancelled | Error::Shutdown => ErrorKind::Cancelled,
+            Error::Storage(_) => ErrorKind::Storage,
+            Error::InvalidUrl(_)
+            | Error::Config(_)
+            | Error::Tls(_)
+            | Error::NotFound(_)
+            | Error::TooManyRedirects(_)
+            | Error::ListingTooLarge { .. }
+            | Error::UnexpectedStatus(..) => ErrorKind::ClientBug,
+            Error::Context { .. } => unreachable!("inner() unwraps the context"),
+        }
+    }
+    
+    /// Wrap a failure of a custom transport; the request may have been sent
+    pub fn transport(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Error::Transport {
+            source: source.into(),
+            unsent: false,
+        }
+    }
+    
+    /// Wrap a failure of a custom transport that happened before the request was sent
+    pub fn transport_unsent(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Error::Transport {
+            source: source.into(),
+            unsent: true,
+        }
+    }
+    
+    /// Classify an error returned while sending a request
+    pub(crate) fn from_send(error: reqwest::Error) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        if error.is_connect() {
+            return Error::Connect(error);
+        }
+        Error::Http(error)
+    }
+    
     /// Get HTTP status code if available
     pub fn status_code(&self) -> Option<u16> {
-        match self {
+        match self.inner() {
             Error::BadRequest(_) => Some(400),
             Error::Unauthorized(_) => Some(401),
+            Error::Forbidden(_) => Some(403),
             Error::NotFound(_) => Some(404),
-            Error::RateLimit(_) => Some(429),
-            Error::ServerError(code, _) => Some(*code),
+            Error::Conflict(_) => Some(409),
+            Error::UnprocessableEntity(_) => Some(422),
+            Error::RateLimit(..) => Some(429),
+            Error::ServerError(code, ..) => Some(*code),
             Error::UnexpectedStatus(code, _) => Some(*code),
+            Error::Decode { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+    
+    /// The error without the request context
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Context { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+    
+    /// Consume the error, dropping the request context
+    pub fn into_inner(self) -> Error {
+        match self {
+            Error::Context { error, .. } => error.into_inner(),
+            error => error,
+        }
+    }
+    
+    /// Message of the error without the request context, followed by its
+    /// source for variants that leave it out of their own message
+    ///
+    /// Used where only a string is kept, such as logs and stored outcomes.
+    pub(crate) fn message(&self) -> String {
+        match self.inner() {
+            error @ Error::Decode { source, .. } => format!("{}: {}", error, source),
+            error @ Error::Transport { source, .. } => format!("{}: {}", error, source),
+            error @ Error::Connect(source) => format!("{}: {}", error, source),
+            error => error.to_string(),
+        }
+    }
+    
+    /// A copy of the error, if it holds no error from another crate
+    ///
+    /// Storage errors are copied with their kind and message only.
+    pub(crate) fn try_clone(&self) -> Option<Error> {
+        Some(match self {
+            Error::Decode { source, body, status } => Error::Decode {
+                source: Box::new(source.try_clone()?),
+                body: body.clone(),
+                status: *status,
+            },
+            Error::InvalidUrl(message) => Error::InvalidUrl(message.clone()),
+            Error::Config(message) => Error::Config(message.clone()),
+            Error::Tls(message) => Error::Tls(message.clone()),
+            Error::InvalidCredentials(message) => Error::InvalidCredentials(message.clone()),
+            Error::BadRequest(body) => Error::BadRequest(body.clone()),
+            Error::Unauthorized(message) => Error::Unauthorized(message.clone()),
+            Error::Forbidden(body) => Error::Forbidden(body.clone()),
+            Error::NotFound(message) => Error::NotFound(message.clone()),
+            Error::Conflict(body) => Error::Conflict(body.clone()),
+            Error::UnprocessableEntity(body) => Error::UnprocessableEntity(body.clone()),
+            Error::RateLimit(message, retry_after) => Error::RateLimit(message.clone(), *retry_after),
+            Error::ServerError(code, message, retry_after) => Error::ServerError(*code, message.clone(), *retry_after),
+            Error::CircuitOpen(retry_in) => Error::CircuitOpen(*retry_in),
+            Error::DeadlineExceeded {
+                deadline,
+                attempts,
+                elapsed,
+                last_error,
+            } => Error::DeadlineExceeded {
+                deadline: *deadline,
+                attempts: *attempts,
+                elapsed: *elapsed,
+                last_error: match last_error {
+                    Some(error) => Some(Box::new(error.try_clone()?)),
+                    None => None,
+                },
+            },
+            Error::Cancelled => Error::Cancelled,
+            Error::Shutdown => Error::Shutdown,
+            Error::TooManyRedirects(count) => Error::TooManyRedirects(*count),
+            Error::ResponseTooLarge(limit) => Error::ResponseTooLarge(*limit),
+            Error::ListingTooLarge { pages, items, elapsed } => Error::ListingTooLarge {
+                pages: *pages,
+                items: *items,
+                elapsed: *elapsed,
+            },
+            Error::ExportFailed { job_id, message } => Error::ExportFailed {
+                job_id: job_id.clone(),
+                message: message.clone(),
+            },
+            Error::AmbiguousReference { reference, matches } => Error::AmbiguousReference {
+                reference: reference.clone(),
+                matches: *matches,
+            },
+            Error::DuplicateReference(order) => Error::DuplicateReference(order.clone()),
+            Error::InvalidSignature(message) => Error::InvalidSignature(message.clone()),
+            Error::Storage(e) => Error::Storage(std::io::Error::new(e.kind(), e.to_string())),
+            Error::UnexpectedStatus(code, message) => Error::UnexpectedStatus(*code, message.clone()),
+            Error::Context { error, context } => Error::Context {
+                error: Box::new(error.try_clone()?),
+                context: context.clone(),
+            },
+            _ => return None,
+        })
+    }
+    
+    /// Details of the request the error occurred on, if known
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
             _ => None,
         }
     }
+    
+    /// Machine error code from the response body
+    pub fn api_error_code(&self) -> Option<ApiErrorCode> {
+        self.api_error()?.error_code()
+    }
+    
+    /// Delay the server asked for before trying again, from the
+    /// `Retry-After` header of a 429 or 503 response
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.inner() {
+            Error::RateLimit(_, retry_after) | Error::ServerError(503, _, retry_after) => *retry_after,
+            _ => None,
+        }
+    }
+    
+    /// Error details from a 400, 403, 409 or 422 response body
+    pub fn api_error(&self) -> Option<&ApiErrorBody> {
+        match self.inner() {
+            Error::BadRequest(body)
+            | Error::Forbidden(body)
+            | Error::Conflict(body)
+            | Error::UnprocessableEntity(body) => Some(body),
+            _ => None,
+        }
+    }
+    
+    /// Id sent in the `X-Request-Id` header of the failed request
+    pub fn request_id(&self) -> Option<&str> {
+        self.context()?.request_id.as_deref()
+    }
+    
+    /// Id the server reported for the failed request
+    pub fn server_request_id(&self) -> Option<&str> {
+        self.context()?.server_request_id.as_deref()
+    }
+    
+    /// The endpoint the failed call was made to
+    pub fn endpoint(&self) -> Option<Endpoint> {
+        self.context()?.endpoint
+    }
+    
+    /// HTTP method of the failed request
+    pub fn method(&self) -> Option<&Method> {
+        self.context()?.method.as_ref()
+    }
+    
+    /// URL path of the failed request
+    pub fn path(&self) -> Option<&str> {
+        self.context()?.path.as_deref()
+    }
+    
+    /// Number of attempts made before the call failed, including the first
+    pub fn attempts(&self) -> Option<u32> {
+        self.context().map(|context| context.attempts)
+    }
+    
+    /// Time from the start of the call until it failed
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.context()?.elapsed
+    }
+    
+    /// Stack trace captured where the client first attached a context to
+    /// the error
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> Option<&std::backtrace::Backtrace> {
+        self.context()?.backtrace.as_ref().map(|captured| &*captured.0)
+    }
+    
+    /// Add to the request context, wrapping the error if it has none yet
+    ///
+    /// With the `backtrace` feature, wrapping captures a backtrace.
+    pub(crate) fn with_context(self, update: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (error, mut context) = match self {
+            Error::Context { error, context } => (error, context),
+            error => (Box::new(error), Box::default()),
+        };
+        #[cfg(feature = "backtrace")]
+        if context.backtrace.is_none() {
+            let backtrace = std::backtrace::Backtrace::force_capture();
+            context.backtrace = Some(CapturedBacktrace(std::sync::Arc::new(backtrace)));
+        }
+        update(&mut context);
+        Error::Context { error, context }
+    }
 }
 
 #[cfg(test)]
@@ -79,19 +603,141 @@ mod tests {
     
     #[test]
     fn test_error_retryable() {
-        assert!(Error::ServerError(500, "Internal Server Error".to_string()).is_retryable());
-        assert!(Error::RateLimit("Too many requests".to_string()).is_retryable());
-        assert!(!Error::BadRequest("Invalid request".to_string()).is_retryable());
+        assert!(Error::ServerError(500, "Internal Server Error".to_string(), None).is_retryable());
+        assert!(Error::RateLimit("Too many requests".to_string(), None).is_retryable());
+        assert!(!Error::BadRequest("Invalid request".into()).is_retryable());
         assert!(!Error::Unauthorized("Invalid token".to_string()).is_retryable());
     }
     
+    #[tokio::test]
+    async fn test_connect_errors_are_unsent() {
+        // Nothing listens on port 1, so the connection is refused
+        let error = reqwest::Client::new()
+            .post("http://127.0.0.1:1/api_customer/orders")
+            .send()
+            .await
+            .map_err(Error::from_send)
+            .unwrap_err();
+        
+        assert!(matches!(error, Error::Connect(_)));
+        assert!(error.is_unsent());
+        assert!(error.is_retryable());
+        assert!(!Error::ServerError(502, "Bad Gateway".to_string(), None).is_unsent());
+        assert!(Error::transport_unsent("connection refused").is_unsent());
+        assert!(!Error::transport("connection reset").is_unsent());
+    }
+    
+    #[tokio::test]
+    async fn test_source_chain() {
+        let error = reqwest::Client::new()
+            .get("http://127.0.0.1:1/")
+            .send()
+            .await
+            .map_err(Error::from_send)
+            .unwrap_err()
+            .with_context(|context| context.request_id = Some("req-1".to_string()));
+        
+        // Context -> Connect -> reqwest -> hyper -> io
+        let mut chain = std::iter::successors(Some(&error as &dyn std::error::Error), |e| e.source());
+        assert!(chain.any(|e| e.is::<std::io::Error>()));
+        #[cfg(feature = "backtrace")]
+        assert!(error.backtrace().is_some());
+    }
+    
     #[test]
     fn test_error_status_code() {
-        assert_eq!(Error::BadRequest("test".to_string()).status_code(), Some(400));
+        assert_eq!(Error::BadRequest("test".into()).status_code(), Some(400));
+        assert_eq!(Error::Forbidden("test".into()).status_code(), Some(403));
+        assert_eq!(Error::Conflict("test".into()).status_code(), Some(409));
+        assert_eq!(Error::UnprocessableEntity("test".into()).status_code(), Some(422));
         assert_eq!(Error::Unauthorized("test".to_string()).status_code(), Some(401));
         assert_eq!(Error::NotFound("test".to_string()).status_code(), Some(404));
-        assert_eq!(Error::RateLimit("test".to_string()).status_code(), Some(429));
-        assert_eq!(Error::ServerError(503, "test".to_string()).status_code(), Some(503));
+        assert_eq!(Error::RateLimit("test".to_string(), None).status_code(), Some(429));
+        assert_eq!(Error::ServerError(503, "test".to_string(), None).status_code(), Some(503));
         assert_eq!(Error::InvalidUrl("test".to_string()).status_code(), None);
+        
+        let decode = Error::Decode {
+            source: Box::new(serde_json::from_str::<u32>("\"x\"").unwrap_err().into()),
+            body: "\"x\"".to_string(),
+            status: 200,
+        };
+        assert_eq!(decode.status_code(), Some(200));
+        assert!(!decode.is_retryable());
+        assert_eq!(decode.to_string(), "Failed to decode response with status 200");
+        assert!(decode.message().starts_with("Failed to decode response with status 200: JSON error: "));
+    }
+    
+    #[test]
+    fn test_retry_after() {
+        let delay = Some(Duration::from_secs(30));
+        assert_eq!(Error::RateLimit("test".to_string(), delay).retry_after(), delay);
+        assert_eq!(
+            Error::ServerError(503, "Service Unavailable".to_string(), delay)
+                .with_context(|context| context.request_id = Some("req-1".to_string()))
+                .retry_after(),
+            delay
+        );
+        assert_eq!(Error::ServerError(500, "test".to_string(), delay).retry_after(), None);
+        assert_eq!(Error::Cancelled.retry_after(), None);
+    }
+    
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(Error::ServerError(503, "Service Unavailable".to_string(), None).kind(), ErrorKind::ServerTransient);
+        assert_eq!(Error::RateLimit("test".to_string(), None).kind(), ErrorKind::RateLimited);
+        assert_eq!(Error::Conflict("duplicate".into()).kind(), ErrorKind::Validation);
+        assert_eq!(Error::Forbidden("test".into()).kind(), ErrorKind::Auth);
+        assert_eq!(Error::UnexpectedStatus(408, String::new()).kind(), ErrorKind::Timeout);
+        assert_eq!(Error::Config("test".to_string()).kind(), ErrorKind::ClientBug);
+        assert_eq!(Error::transport("connection reset").kind(), ErrorKind::Network);
+        assert_eq!(
+            Error::Cancelled.with_context(|context| context.request_id = Some("req-1".to_string())).kind(),
+            ErrorKind::Cancelled
+        );
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_error_context() {
+        let error = Error::ServerError(503, "Service Unavailable".to_string(), None)
+            .with_context(|context| context.server_request_id = Some("srv-42".to_string()))
+            .with_context(|context| context.request_id = Some("req-1".to_string()));
+        
+        assert!(matches!(error.inner(), Error::ServerError(503, ..)));
+        assert_eq!(error.request_id(), Some("req-1"));
+        assert_eq!(error.server_request_id(), Some("srv-42"));
+        assert_eq!(error.status_code(), Some(503));
+        assert!(error.is_retryable());
+        assert_eq!(
+            error.to_string(),
+            "Request failed (request id req-1, server request id srv-42)"
+        );
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), "Server error 503: Service Unavailable");
+        assert_eq!(error.message(), "Server error 503: Service Unavailable");
+        assert!(matches!(error.into_inner(), Error::ServerError(503, ..)));
+        assert_eq!(Error::Cancelled.request_id(), None);
+    }
+    
+    #[test]
+    fn test_call_context() {
+        let error = Error::BadRequest("missing field".into()).with_context(|context| {
+            context.request_id = Some("req-1".to_string());
+            context.endpoint = Some(Endpoint::CreateOrder);
+            context.method = Some(Method::POST);
+            context.path = Some("/api_customer/orders".to_string());
+            context.attempts = 2;
+            context.elapsed = Some(Duration::from_millis(1500));
+        });
+        
+        assert_eq!(error.endpoint(), Some(Endpoint::CreateOrder));
+        assert_eq!(error.method(), Some(&Method::POST));
+        assert_eq!(error.path(), Some("/api_customer/orders"));
+        assert_eq!(error.attempts(), Some(2));
+        assert_eq!(error.elapsed(), Some(Duration::from_millis(1500)));
+        assert_eq!(
+            error.to_string(),
+            "Request failed (POST /api_customer/orders, attempt 2, after 1.5s, request id req-1)"
+        );
+        assert_eq!(Error::Cancelled.attempts(), None);
+    }
+}
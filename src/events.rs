@@ -0,0 +1,293 @@
+//! One stream of API events, whichever way they are delivered
+//!
+//! [`ApiEvents`] is a stream of [`ApiEvent`]s that can be fed by polling
+//! orders, by webhook callbacks, or by any other transport through
+//! [`ApiEvents::from_stream`]. Events carry the same [`EventPayload`]s
+//! whatever their source, so handling code is written once and the delivery
+//! mechanism can change underneath it.
+//!
+//! ```no_run
+//! # use ecommerce_api_client::{events::ApiEvents, types::OrderId, Client};
+//! # use ecommerce_api_client::webhooks::EventPayload;
+//! # use std::time::Duration;
+//! use futures_util::StreamExt;
+//!
+//! # async fn example(client: Client, order_ids: Vec<OrderId>) -> ecommerce_api_client::Result<()> {
+//! let (sender, pushed) = ApiEvents::channel(64);
+//! // Hand `sender` to a webhook receiver; poll the orders created before it was set up
+//! let mut events = ApiEvents::polling(&client, order_ids, Duration::from_secs(60)).merge(pushed);
+//! while let Some(event) = events.next().await {
+//!     if let EventPayload::OrderStatusChanged(change) = &event?.payload {
+//!         println!("order {} is now in status {}", change.order_id, change.status_order_id);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::types::OrderId;
+use crate::watch::OrderChange;
+use crate::webhooks::{self, EventPayload, InvoiceIssued, OrderStatusChanged};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How an event reached the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EventSource {
+    /// Noticed by polling the API
+    Polling,
+    /// Delivered by a webhook callback
+    Webhook,
+    /// Received on the API's Server-Sent Events stream
+    ServerSent,
+    /// Received on a WebSocket feed
+    WebSocket,
+    /// Pushed by another transport
+    Push,
+}
+
+/// Something that happened on the API side
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ApiEvent {
+    /// Id the API gave the event, `None` for events noticed by polling
+    pub id: Option<String>,
+    /// When the event happened as RFC 3339, if known
+    pub occurred_at: Option<String>,
+    /// How the event reached the client
+    pub source: EventSource,
+    /// What happened
+    pub payload: EventPayload,
+}
+
+impl ApiEvent {
+    /// An event without id or time, e.g. for a custom transport
+    pub fn new(source: EventSource, payload: EventPayload) -> Self {
+        Self {
+            id: None,
+            occurred_at: None,
+            source,
+            payload,
+        }
+    }
+    
+    /// Id of the order the event is about, if any
+    pub fn order_id(&self) -> Option<u64> {
+        match &self.payload {
+            EventPayload::OrderStatusChanged(change) => Some(change.order_id),
+            EventPayload::ShipmentCreated(shipment) => Some(shipment.order_id),
+            EventPayload::InvoiceIssued(invoice) => Some(invoice.order_id),
+            EventPayload::TrackingUpdated(update) => Some(update.order_id),
+            EventPayload::Unknown { data, .. } => data.get("order_id").and_then(|id| id.as_u64()),
+        }
+    }
+    
+    /// The events a polled change amounts to: a status change and a newly issued invoice
+    fn from_change(change: OrderChange) -> Vec<Self> {
+        let Some(previous) = &change.previous else {
+            return Vec::new();
+        };
+        let order = &change.order;
+        let mut events = Vec::new();
+        if previous.status_order_id != order.status_order_id {
+            events.push(Self::new(
+                EventSource::Polling,
+                EventPayload::OrderStatusChanged(OrderStatusChanged {
+                    order_id: order.id,
+                    customer_order_reference: Some(order.customer_order_reference.clone()),
+                    previous_status_order_id: Some(previous.status_order_id),
+                    status_order_id: order.status_order_id,
+                }),
+            ));
+        }
+        if let (None, Some(invoice_no)) = (&previous.invoice_no, &order.invoice_no) {
+            events.push(Self::new(
+                EventSource::Polling,
+                EventPayload::InvoiceIssued(InvoiceIssued {
+                    invoice_no: invoice_no.clone(),
+                    order_id: order.id,
+                    gross_total: Some(order.gross_total.clone()),
+                    currency: None,
+                }),
+            ));
+        }
+        events
+    }
+}
+
+impl From<webhooks::Event> for ApiEvent {
+    fn from(event: webhooks::Event) -> Self {
+        Self {
+            id: Some(event.id),
+            occurred_at: event.created_at,
+            source: EventSource::Webhook,
+            payload: event.payload,
+        }
+    }
+}
+
+/// A stream of [`ApiEvent`]s from one or more transports
+///
+/// Errors of a transport are yielded in between its events; whether the
+/// transport goes on after an error is up to it.
+pub struct ApiEvents {
+    stream: Pin<Box<dyn Stream<Item = Result<ApiEvent>> + Send>>,
+}
+
+impl fmt::Debug for ApiEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiEvents").finish_non_exhaustive()
+    }
+}
+
+impl ApiEvents {
+    /// Events from a custom transport
+    pub fn from_stream(stream: impl Stream<Item = Result<ApiEvent>> + Send + 'static) -> Self {
+        Self {
+            stream: Box::pin(stream),
+        }
+    }
+    
+    /// Events noticed by polling `order_ids` every `interval`
+    ///
+    /// Each order is followed with [`Client::watch_order`] until it reaches
+    /// a terminal status. Its first state is not an event; later status
+    /// changes and newly issued invoices are.
+    pub fn polling(client: &Client, order_ids: impl IntoIterator<Item = OrderId>, interval: Duration) -> Self {
+        let watches = order_ids
+            .into_iter()
+            .map(|order_id| client.watch_order(&order_id, interval).boxed());
+        let events = stream::select_all(watches).flat_map(|change| {
+            let events = match change {
+                Ok(change) => ApiEvent::from_change(change).into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(events)
+        });
+        Self::from_stream(events)
+    }
+    
+    /// Events sent through the returned [`EventSender`], buffering up to `buffer`
+    ///
+    /// The stream ends once every sender is dropped.
+    pub fn channel(buffer: usize) -> (EventSender, Self) {
+        let (sender, mut receiver) = mpsc::channel(buffer);
+        let events = stream::poll_fn(move |cx| receiver.poll_recv(cx));
+        (EventSender { sender }, Self::from_stream(events))
+    }
+    
+    /// Events of both streams, as they arrive; ends when both have ended
+    pub fn merge(self, other: ApiEvents) -> Self {
+        Self::from_stream(stream::select(self, other))
+    }
+}
+
+impl Stream for ApiEvents {
+    type Item = Result<ApiEvent>;
+    
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.as_mut().poll_next(cx)
+    }
+}
+
+/// Feeds events into the stream from [`ApiEvents::channel`]
+///
+/// With the `webhook-server` feature it is an `EventHandler`, so a
+/// `WebhookReceiver` can deliver callbacks into the stream directly.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: mpsc::Sender<Result<ApiEvent>>,
+}
+
+impl EventSender {
+    /// Send `event`, waiting while the buffer is full
+    ///
+    /// Gives the event back if the stream was dropped.
+    pub async fn send(&self, event: impl Into<ApiEvent>) -> std::result::Result<(), ApiEvent> {
+        match self.sender.reserve().await {
+            Ok(permit) => {
+                permit.send(Ok(event.into()));
+                Ok(())
+            }
+            Err(_) => Err(event.into()),
+        }
+    }
+    
+    /// Report a failure of the transport to the stream's consumer
+    pub async fn send_error(&self, error: crate::error::Error) {
+        let _ = self.sender.send(Err(error)).await;
+    }
+    
+    /// Whether the stream was dropped
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+#[cfg(all(feature = "webhook-server", not(target_arch = "wasm32")))]
+#[async_trait::async_trait]
+impl crate::webhook_server::EventHandler for EventSender {
+    async fn handle(&self, event: webhooks::Event) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.send(event).await.map_err(|_| "event stream closed".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Order;
+    
+    fn order(status: u64, invoice_no: Option<&str>) -> Order {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "status_order_id": status,
+            "customer_id": 7,
+            "customer_order_reference": "ORDER-1",
+            "gross_total": "10.00",
+            "addressbook_id": 3,
+            "invoice_no": invoice_no,
+        }))
+        .unwrap()
+    }
+    
+    #[test]
+    fn test_polled_events() {
+        assert!(ApiEvent::from_change(OrderChange::new(None, order(1, None))).is_empty());
+        let events = ApiEvent::from_change(OrderChange::new(Some(order(1, None)), order(3, Some("INV-1"))));
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|event| event.source == EventSource::Polling && event.order_id() == Some(1)));
+        assert!(matches!(
+            &events[0].payload,
+            EventPayload::OrderStatusChanged(change) if change.previous_status_order_id == Some(1) && change.status_order_id == 3
+        ));
+        assert!(matches!(&events[1].payload, EventPayload::InvoiceIssued(invoice) if invoice.invoice_no == "INV-1"));
+        assert!(ApiEvent::from_change(OrderChange::new(Some(order(3, Some("INV-1"))), order(3, Some("INV-1")))).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_channel_and_merge() {
+        let (sender, pushed) = ApiEvents::channel(4);
+        let body = br#"{"id":"evt_1","type":"invoice.issued","data":{"invoice_no":"INV-1","order_id":1}}"#;
+        sender.send(webhooks::parse_event(body).unwrap()).await.unwrap();
+        let custom = ApiEvent::new(EventSource::Push, EventPayload::Unknown {
+            event_type: "order.note_added".to_string(),
+            data: serde_json::json!({"order_id": 2}),
+        });
+        let other = ApiEvents::from_stream(stream::iter([Ok(custom)]));
+        drop(sender);
+        
+        let events: Vec<_> = pushed.merge(other).map(Result::unwrap).collect().await;
+        assert_eq!(events.len(), 2);
+        let webhook = events.iter().find(|event| event.source == EventSource::Webhook).unwrap();
+        assert_eq!(webhook.id.as_deref(), Some("evt_1"));
+        let pushed = events.iter().find(|event| event.source == EventSource::Push).unwrap();
+        assert_eq!(pushed.order_id(), Some(2));
+    }
+}
@@ -0,0 +1,352 @@
+//! Bulk exports
+//!
+//! The API builds large exports in the background: a job is requested,
+//! polled until it completes and its file is then downloaded.
+//!
+//! Result sets already fetched, such as orders from a sync job, can be
+//! written locally as JSON Lines or, with the `csv` feature, as CSV.
+
+use crate::error::Result;
+use crate::filter::OrderFilter;
+use crate::progress::ProgressObserver;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "csv")]
+use crate::types::{CreateOrderResponse, Order, OrderProduct};
+#[cfg(feature = "csv")]
+use serde_json::{Map, Value};
+
+/// Id of an export job
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExportJobId(pub String);
+
+impl fmt::Display for ExportJobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Data set to export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ExportResource {
+    /// Orders, optionally narrowed by an [`OrderFilter`]
+    Orders,
+    /// The product catalogue
+    Products,
+}
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    #[default]
+    Csv,
+    /// JSON lines, one item per line
+    Jsonl,
+}
+
+/// What an export job should contain
+///
+/// ```
+/// # use ecommerce_api_client::export::{ExportFormat, ExportSpec};
+/// # use ecommerce_api_client::OrderFilter;
+/// let spec = ExportSpec::orders(&OrderFilter::new().status(3)).format(ExportFormat::Jsonl);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename = "export")]
+pub struct ExportSpec {
+    resource: ExportResource,
+    format: ExportFormat,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    filters: BTreeMap<String, String>,
+}
+
+impl ExportSpec {
+    /// Export the orders matching `filter`
+    pub fn orders(filter: &OrderFilter) -> Self {
+        Self {
+            resource: ExportResource::Orders,
+            format: ExportFormat::default(),
+            filters: filter.query().into_iter().collect(),
+        }
+    }
+    
+    /// Export the whole product catalogue
+    pub fn products() -> Self {
+        Self {
+            resource: ExportResource::Products,
+            format: ExportFormat::default(),
+            filters: BTreeMap::new(),
+        }
+    }
+    
+    /// File format to export to, CSV unless set
+    pub fn format(mut self, format: ExportFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+/// Progress of an export job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ExportStatus {
+    /// Waiting to be processed
+    Pending,
+    /// Being built
+    Running,
+    /// Ready to download
+    Completed,
+    /// Failed; see [`ExportJob::error`]
+    Failed,
+    /// A status this client does not know
+    #[serde(other)]
+    Unknown,
+}
+
+/// State of an export job as reported by the API
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct ExportJob {
+    /// Job id
+    pub id: ExportJobId,
+    /// Current status
+    pub status: ExportStatus,
+    /// Percentage done, if the server reports it
+    #[serde(default)]
+    pub progress: Option<f64>,
+    /// Why the job failed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ExportJob {
+    /// Whether the job completed or failed
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status, ExportStatus::Completed | ExportStatus::Failed)
+    }
+}
+
+/// How [`Client::export_and_wait`](crate::Client::export_and_wait) polls a job
+#[derive(Clone)]
+pub struct ExportWait {
+    /// Delay before the first poll
+    pub initial_interval: Duration,
+    /// Longest delay between polls; the delay doubles up to this
+    pub max_interval: Duration,
+    /// Give up once the job has not finished after this long
+    pub timeout: Duration,
+    /// Receives the job's progress while waiting, then the bytes downloaded
+    pub progress: Option<Arc<dyn ProgressObserver>>,
+}
+
+impl Default for ExportWait {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(30 * 60),
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for ExportWait {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExportWait")
+            .field("initial_interval", &self.initial_interval)
+            .field("max_interval", &self.max_interval)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExportWait {
+    /// Delay after `interval`, doubled and capped at `max_interval`
+    pub(crate) fn next_interval(&self, interval: Duration) -> Duration {
+        interval.saturating_mul(2).min(self.max_interval)
+    }
+}
+
+/// Write `items` as JSON Lines, one JSON object per line
+pub fn write_jsonl<'a, T: Serialize + 'a>(
+    mut writer: impl Write,
+    items: impl IntoIterator<Item = &'a T>,
+) -> Result<()> {
+    for item in items {
+        serde_json::to_writer(&mut writer, item)?;
+        writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+    }
+    writer.flush().map_err(serde_json::Error::io)?;
+    Ok(())
+}
+
+/// Write `items` as CSV with a header row
+///
+/// Nested objects such as addresses are flattened into dotted columns like
+/// `addressbook.city`; arrays are written as JSON. The columns are those of
+/// all items, sorted by name, so every item is serialized before anything
+/// is written.
+#[cfg(feature = "csv")]
+pub fn write_csv<T: Serialize>(writer: impl Write, items: &[T]) -> Result<()> {
+    let mut columns = std::collections::BTreeSet::new();
+    let mut rows = Vec::with_capacity(items.len());
+    for item in items {
+        let mut row = Map::new();
+        flatten("", serde_json::to_value(item)?, &mut row);
+        columns.extend(row.keys().cloned());
+        rows.push(row);
+    }
+    
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(&columns)?;
+    for row in &rows {
+        writer.write_record(columns.iter().map(|column| row.get(column).map(cell).unwrap_or_default()))?;
+    }
+    writer.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+/// Write orders as CSV, one row per order
+#[cfg(feature = "csv")]
+pub fn write_orders_csv(writer: impl Write, orders: &[Order]) -> Result<()> {
+    write_csv(writer, orders)
+}
+
+/// Write order lines as CSV, one row per line
+#[cfg(feature = "csv")]
+pub fn write_order_products_csv(writer: impl Write, products: &[OrderProduct]) -> Result<()> {
+    write_csv(writer, products)
+}
+
+/// Write created orders as CSV, one row per order line with its order's
+/// columns prefixed by `order.` and the line's by `line.`
+#[cfg(feature = "csv")]
+pub fn write_order_lines_csv(writer: impl Write, responses: &[CreateOrderResponse]) -> Result<()> {
+    #[derive(Serialize)]
+    struct OrderLine<'a> {
+        order: &'a Order,
+        line: &'a OrderProduct,
+    }
+    
+    let lines: Vec<_> = responses
+        .iter()
+        .flat_map(|response| {
+            response
+                .order_products
+                .iter()
+                .map(move |line| OrderLine { order: &response.order, line })
+        })
+        .collect();
+    write_csv(writer, &lines)
+}
+
+/// Flatten nested objects into `prefix.key` entries
+#[cfg(feature = "csv")]
+fn flatten(prefix: &str, value: Value, row: &mut Map<String, Value>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten(&key, value, row);
+            }
+        }
+        value => {
+            row.insert(prefix.to_string(), value);
+        }
+    }
+}
+
+/// Text of a CSV cell
+#[cfg(feature = "csv")]
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_spec_body() {
+        let spec = ExportSpec::orders(&OrderFilter::new().status(3)).format(ExportFormat::Jsonl);
+        assert_eq!(
+            serde_json::to_value(&spec).unwrap(),
+            serde_json::json!({"resource": "orders", "format": "jsonl", "filters": {"status_order_id": "3"}})
+        );
+        assert_eq!(
+            serde_json::to_value(ExportSpec::products()).unwrap(),
+            serde_json::json!({"resource": "products", "format": "csv"})
+        );
+    }
+    
+    #[test]
+    fn test_job_status() {
+        let job: ExportJob = serde_json::from_str(r#"{"id":"exp-1","status":"running","progress":40.0}"#).unwrap();
+        assert_eq!(job.status, ExportStatus::Running);
+        assert!(!job.is_finished());
+        
+        let job: ExportJob = serde_json::from_str(r#"{"id":"exp-1","status":"archived"}"#).unwrap();
+        assert_eq!(job.status, ExportStatus::Unknown);
+        
+        let wait = ExportWait::default();
+        assert_eq!(wait.next_interval(Duration::from_secs(20)), Duration::from_secs(30));
+    }
+    
+    #[test]
+    fn test_write_jsonl() {
+        let mut output = Vec::new();
+        write_jsonl(&mut output, &[serde_json::json!({"id": 1}), serde_json::json!({"id": 2})]).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"id\":1}\n{\"id\":2}\n");
+    }
+    
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_write_csv_flattens() {
+        use crate::types::{Addressbook, CreateOrderRequest};
+        
+        let address = Addressbook {
+            country: "DE".to_string(),
+            name: None,
+            address: None,
+            address2: None,
+            city: Some("Berlin, Mitte".to_string()),
+            province: None,
+            postal_code: None,
+            phone: None,
+            email: None,
+            comments: None,
+        };
+        let requests = [
+            CreateOrderRequest {
+                customer_order_reference: Some("ORDER-1".to_string()),
+                addressbook: Some(address),
+                ..Default::default()
+            },
+            CreateOrderRequest {
+                comments_customer: Some("no address".to_string()),
+                ..Default::default()
+            },
+        ];
+        let mut output = Vec::new();
+        write_csv(&mut output, &requests).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "addressbook.city,addressbook.country,comments_customer,customer_order_reference,order_products\n\
+             \"Berlin, Mitte\",DE,,ORDER-1,[]\n\
+             ,,no address,,[]\n"
+        );
+    }
+}
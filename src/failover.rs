@@ -0,0 +1,330 @@
+//! Failover and health-aware routing between API hosts
+
+use crate::error::{Error, Result};
+use std::sync::{Mutex, MutexGuard};
+use crate::runtime::Instant;
+use std::time::Duration;
+
+/// Default time a failed base URL is avoided before it is tried again
+pub const DEFAULT_COOL_DOWN: Duration = Duration::from_secs(30);
+
+/// Weight of the newest sample in the latency and error rate averages
+const EWMA_WEIGHT: f64 = 0.2;
+
+/// How strongly errors count against a region compared to latency
+const ERROR_PENALTY: f64 = 10.0;
+
+/// How requests are distributed over the base URLs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Routing {
+    /// Use the first base URL in order that is not cooling down
+    #[default]
+    Ordered,
+    /// Use the available base URL with the lowest latency, weighted by its error rate
+    ///
+    /// Base URLs without any samples yet are tried first.
+    Healthiest,
+}
+
+/// Health of one base URL as seen by the client
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionHealth {
+    /// The base URL
+    pub base_url: String,
+    /// Moving average of successful response times, once there are samples
+    pub latency: Option<Duration>,
+    /// Moving average share of requests failing with connection errors or 5xx
+    pub error_rate: f64,
+    /// Whether the base URL is cooling down after a failure
+    pub cooling_down: bool,
+    /// Whether requests are currently routed here
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Health {
+    failed_at: Option<Instant>,
+    latency: Option<Duration>,
+    error_rate: f64,
+}
+
+impl Health {
+    fn cooling_down(&self, now: Instant, cool_down: Duration) -> bool {
+        self.failed_at.is_some_and(|at| now.duration_since(at) < cool_down)
+    }
+    
+    /// Lower is better
+    fn score(&self) -> f64 {
+        let latency = self.latency.map_or(0.0, |latency| latency.as_secs_f64());
+        latency * (1.0 + ERROR_PENALTY * self.error_rate)
+    }
+}
+
+/// Ordered base URLs, the primary first, with the health of each
+///
+/// Requests avoid base URLs cooling down after a connection error or 5xx
+/// response; once the cool-down has elapsed they are tried again. Among
+/// the rest, the [`Routing`] strategy picks one.
+pub(crate) struct Failover {
+    base_urls: Vec<String>,
+    cool_down: Duration,
+    health: Mutex<Vec<Health>>,
+}
+
+impl Failover {
+    /// Create a failover set from the primary and backup base URLs
+    pub(crate) fn new(primary: String, backups: Vec<String>, cool_down: Duration) -> Self {
+        let mut base_urls = vec![primary];
+        base_urls.extend(backups);
+        let health = Mutex::new(vec![Health::default(); base_urls.len()]);
+        
+        Self {
+            base_urls,
+            cool_down,
+            health,
+        }
+    }
+    
+    /// All base URLs, the primary first
+    pub(crate) fn base_urls(&self) -> &[String] {
+        &self.base_urls
+    }
+    
+    /// Base URL requests are currently sent to
+    pub(crate) fn active(&self, routing: Routing) -> &str {
+        &self.base_urls[self.select_at(routing, Instant::now())]
+    }
+    
+    /// Snapshot of the health of every base URL
+    pub(crate) fn state(&self, routing: Routing) -> Vec<RegionHealth> {
+        let now = Instant::now();
+        let active = self.select_at(routing, now);
+        let health = self.lock();
+        
+        self.base_urls
+            .iter()
+            .zip(health.iter())
+            .enumerate()
+            .map(|(index, (base_url, health))| RegionHealth {
+                base_url: base_url.clone(),
+                latency: health.latency,
+                error_rate: health.error_rate,
+                cooling_down: health.cooling_down(now, self.cool_down),
+                active: index == active,
+            })
+            .collect()
+    }
+    
+    /// Point a request at the base URL chosen by the routing strategy
+    ///
+    /// Returns the index of the chosen base URL for [`Failover::record`].
+    pub(crate) fn route(
+        &self,
+        routing: Routing,
+        req_builder: reqwest::RequestBuilder,
+    ) -> Result<(reqwest::RequestBuilder, usize)> {
+        let index = self.select_at(routing, Instant::now());
+        if index == 0 {
+            return Ok((req_builder, index));
+        }
+        
+        let (http_client, request) = req_builder.build_split();
+        let mut request = request.map_err(Error::Http)?;
+        if let Some(path) = request.url().as_str().strip_prefix(self.base_urls[0].as_str()) {
+            let url = format!("{}{}", self.base_urls[index], path);
+            *request.url_mut() = url::Url::parse(&url)
+                .map_err(|e| Error::InvalidUrl(format!("Invalid failover URL {}: {}", url, e)))?;
+        }
+        Ok((reqwest::RequestBuilder::from_parts(http_client, request), index))
+    }
+    
+    /// Record the outcome and duration of a request sent to the base URL at `index`
+    pub(crate) fn record<T>(&self, index: usize, result: &Result<T>, elapsed: Duration) {
+        let failed = matches!(
+            result.as_ref().map_err(Error::inner),
+            Err(Error::Connect(_) | Error::Transport { unsent: true, .. } | Error::ServerError(..))
+        );
+        let mut health = self.lock();
+        let health = &mut health[index];
+        
+        health.failed_at = failed.then(Instant::now);
+        health.error_rate += EWMA_WEIGHT * (f64::from(u8::from(failed)) - health.error_rate);
+        if result.is_ok() {
+            health.latency = Some(match health.latency {
+                Some(average) => average.mul_f64(1.0 - EWMA_WEIGHT) + elapsed.mul_f64(EWMA_WEIGHT),
+                None => elapsed,
+            });
+        }
+    }
+    
+    /// Start timing a request sent to the base URL at `index`
+    pub(crate) fn start(&self, index: usize) -> InFlight<'_> {
+        InFlight {
+            failover: self,
+            index,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+    
+    /// Count a request to the base URL at `index` that was dropped after `elapsed`
+    ///
+    /// There is no outcome to count, but the request took at least this
+    /// long, so a slower time than the average still raises it.
+    fn record_cancelled(&self, index: usize, elapsed: Duration) {
+        let mut health = self.lock();
+        let health = &mut health[index];
+        if let Some(average) = health.latency.filter(|average| elapsed > *average) {
+            health.latency = Some(average.mul_f64(1.0 - EWMA_WEIGHT) + elapsed.mul_f64(EWMA_WEIGHT));
+        }
+    }
+    
+    /// Index of the base URL to use at `now`
+    ///
+    /// When every base URL is cooling down, the one that failed longest ago is used.
+    fn select_at(&self, routing: Routing, now: Instant) -> usize {
+        let health = self.lock();
+        let mut available = (0..health.len()).filter(|&index| !health[index].cooling_down(now, self.cool_down));
+        
+        let selected = match routing {
+            Routing::Ordered => available.next(),
+            Routing::Healthiest => available.min_by(|&a, &b| health[a].score().total_cmp(&health[b].score())),
+        };
+        selected.unwrap_or_else(|| {
+            (0..health.len())
+                .min_by_key(|&index| health[index].failed_at)
+                .unwrap_or(0)
+        })
+    }
+    
+    fn lock(&self) -> MutexGuard<'_, Vec<Health>> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A request in flight to one base URL, from [`Failover::start`]
+///
+/// If it is dropped before [`finish`](Self::finish), e.g. because a hedged
+/// duplicate won, the time it ran still counts towards the base URL's latency.
+pub(crate) struct InFlight<'a> {
+    failover: &'a Failover,
+    index: usize,
+    started: Instant,
+    finished: bool,
+}
+
+impl InFlight<'_> {
+    /// Record the outcome of the request
+    pub(crate) fn finish<T>(mut self, result: &Result<T>) {
+        self.finished = true;
+        self.failover.record(self.index, result, self.started.elapsed());
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.failover.record_cancelled(self.index, self.started.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn failover() -> Failover {
+        Failover::new(
+            "https://api.example.com".to_string(),
+            vec!["https://backup.example.com".to_string()],
+            Duration::from_secs(30),
+        )
+    }
+    
+    fn server_error() -> Result<()> {
+        Err(Error::ServerError(503, "Service Unavailable".to_string(), None))
+    }
+    
+    #[test]
+    fn test_fails_over_and_back() {
+        let failover = failover();
+        assert_eq!(failover.active(Routing::Ordered), "https://api.example.com");
+        
+        failover.record(0, &server_error(), Duration::ZERO);
+        assert_eq!(failover.active(Routing::Ordered), "https://backup.example.com");
+        
+        // The primary is tried again once the cool-down has elapsed
+        let later = Instant::now() + Duration::from_secs(31);
+        assert_eq!(failover.select_at(Routing::Ordered, later), 0);
+        
+        failover.record(0, &Ok(()), Duration::ZERO);
+        assert_eq!(failover.active(Routing::Ordered), "https://api.example.com");
+    }
+    
+    #[test]
+    fn test_client_errors_do_not_fail_over() {
+        let failover = failover();
+        failover.record(0, &Err::<(), _>(Error::NotFound("missing".to_string())), Duration::ZERO);
+        assert_eq!(failover.active(Routing::Ordered), "https://api.example.com");
+    }
+    
+    #[test]
+    fn test_all_failed_picks_oldest_failure() {
+        let failover = failover();
+        failover.record(0, &server_error(), Duration::ZERO);
+        failover.record(1, &server_error(), Duration::ZERO);
+        assert_eq!(failover.active(Routing::Ordered), "https://api.example.com");
+    }
+    
+    #[test]
+    fn test_route_rewrites_base_url() {
+        let failover = failover();
+        failover.record(0, &server_error(), Duration::ZERO);
+        
+        let req_builder = reqwest::Client::new().get("https://api.example.com/api_customer/orders?page=2");
+        let (req_builder, index) = failover.route(Routing::Ordered, req_builder).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(
+            req_builder.build().unwrap().url().as_str(),
+            "https://backup.example.com/api_customer/orders?page=2"
+        );
+    }
+    
+    #[test]
+    fn test_healthiest_routing() {
+        let failover = failover();
+        failover.record(0, &Ok(()), Duration::from_millis(200));
+        // Unmeasured base URLs are explored first
+        assert_eq!(failover.active(Routing::Healthiest), "https://backup.example.com");
+        
+        failover.record(1, &Ok(()), Duration::from_millis(50));
+        assert_eq!(failover.active(Routing::Healthiest), "https://backup.example.com");
+        assert_eq!(failover.active(Routing::Ordered), "https://api.example.com");
+        
+        // Errors outweigh the latency advantage once the cool-down is over
+        failover.record(1, &server_error(), Duration::ZERO);
+        failover.record(1, &server_error(), Duration::ZERO);
+        let later = Instant::now() + Duration::from_secs(31);
+        assert_eq!(failover.select_at(Routing::Healthiest, later), 0);
+        
+        let state = failover.state(Routing::Healthiest);
+        assert_eq!(state[0].latency, Some(Duration::from_millis(200)));
+        assert!(state[1].cooling_down);
+        assert!(state[1].error_rate > 0.3);
+        assert!(state[0].active);
+    }
+    
+    #[test]
+    fn test_cancelled_request_counts_latency() {
+        let failover = failover();
+        failover.record(0, &Ok(()), Duration::from_millis(100));
+        
+        let request = failover.start(0);
+        std::thread::sleep(Duration::from_millis(300));
+        drop(request);
+        let state = failover.state(Routing::Ordered);
+        assert!(state[0].latency.unwrap() > Duration::from_millis(100));
+        assert_eq!(state[0].error_rate, 0.0);
+        assert!(!state[0].cooling_down);
+    }
+}
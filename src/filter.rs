@@ -0,0 +1,235 @@
+//! Typed filters for list endpoints
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Whether an order has been invoiced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceState {
+    /// An invoice was issued
+    Invoiced,
+    /// No invoice yet
+    NotInvoiced,
+}
+
+/// Field to sort a listing by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SortBy {
+    /// Creation time
+    CreatedAt,
+    /// Time of the last update
+    UpdatedAt,
+    /// Gross order total
+    GrossTotal,
+}
+
+impl SortBy {
+    /// Value of the `sort_by` query parameter
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortBy::CreatedAt => "created_at",
+            SortBy::UpdatedAt => "updated_at",
+            SortBy::GrossTotal => "gross_total",
+        }
+    }
+}
+
+/// Sort order of a listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortDirection {
+    /// Smallest or oldest first
+    #[default]
+    Ascending,
+    /// Largest or newest first
+    Descending,
+}
+
+impl SortDirection {
+    /// Value of the `direction` query parameter
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "asc",
+            SortDirection::Descending => "desc",
+        }
+    }
+}
+
+/// Filter for [`Client::list_orders`](crate::Client::list_orders) and the
+/// other order listings
+///
+/// ```
+/// # use ecommerce_api_client::filter::{InvoiceState, OrderFilter, SortBy, SortDirection};
+/// # use std::time::{Duration, SystemTime};
+/// let filter = OrderFilter::new()
+///     .status(3)
+///     .created_from(SystemTime::now() - Duration::from_secs(7 * 86_400))
+///     .invoice_state(InvoiceState::NotInvoiced)
+///     .sort(SortBy::CreatedAt, SortDirection::Descending);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrderFilter {
+    status_order_id: Option<u64>,
+    created_from: Option<SystemTime>,
+    created_to: Option<SystemTime>,
+    updated_since: Option<SystemTime>,
+    customer_order_reference: Option<String>,
+    invoice_state: Option<InvoiceState>,
+    sort: Option<(SortBy, SortDirection)>,
+}
+
+impl OrderFilter {
+    /// A filter matching every order
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Only orders with this `status_order_id`
+    pub fn status(mut self, status_order_id: u64) -> Self {
+        self.status_order_id = Some(status_order_id);
+        self
+    }
+    
+    /// Only orders created at or after `time`
+    pub fn created_from(mut self, time: SystemTime) -> Self {
+        self.created_from = Some(time);
+        self
+    }
+    
+    /// Only orders created before `time`
+    pub fn created_to(mut self, time: SystemTime) -> Self {
+        self.created_to = Some(time);
+        self
+    }
+    
+    /// Only orders updated at or after `time`, e.g. for incremental syncs
+    pub fn updated_since(mut self, time: SystemTime) -> Self {
+        self.updated_since = Some(time);
+        self
+    }
+    
+    /// Only the order with this reference
+    pub fn customer_order_reference(mut self, reference: impl Into<String>) -> Self {
+        self.customer_order_reference = Some(reference.into());
+        self
+    }
+    
+    /// Only invoiced or only not yet invoiced orders
+    pub fn invoice_state(mut self, state: InvoiceState) -> Self {
+        self.invoice_state = Some(state);
+        self
+    }
+    
+    /// Sort the listing by `field` in `direction`
+    ///
+    /// Without a sort order the API returns orders in its default order.
+    /// Keep the sort order fixed while paging so pages do not overlap.
+    pub fn sort(mut self, field: SortBy, direction: SortDirection) -> Self {
+        self.sort = Some((field, direction));
+        self
+    }
+    
+    /// Query parameters of the filter
+    pub(crate) fn query(&self) -> Vec<(String, String)> {
+        let mut query = Vec::new();
+        let mut push = |name: &str, value: String| query.push((name.to_string(), value));
+        if let Some(status) = self.status_order_id {
+            push("status_order_id", status.to_string());
+        }
+        if let Some(time) = self.created_from {
+            push("created_from", rfc3339(time));
+        }
+        if let Some(time) = self.created_to {
+            push("created_to", rfc3339(time));
+        }
+        if let Some(time) = self.updated_since {
+            push("updated_since", rfc3339(time));
+        }
+        if let Some(reference) = &self.customer_order_reference {
+            push("customer_order_reference", reference.clone());
+        }
+        if let Some(state) = self.invoice_state {
+            push("invoiced", (state == InvoiceState::Invoiced).to_string());
+        }
+        if let Some((field, direction)) = self.sort {
+            push("sort_by", field.as_str().to_string());
+            push("direction", direction.as_str().to_string());
+        }
+        query
+    }
+}
+
+/// UTC timestamp such as `2024-06-01T12:30:00Z`, truncated to seconds
+fn rfc3339(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+    
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let days = days as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    #[test]
+    fn test_order_filter_query() {
+        let created = UNIX_EPOCH + Duration::from_secs(1_717_245_000);
+        let query = OrderFilter::new()
+            .status(3)
+            .created_from(created)
+            .customer_order_reference("ORDER-001")
+            .invoice_state(InvoiceState::NotInvoiced)
+            .query();
+        assert_eq!(
+            query,
+            vec![
+                ("status_order_id".to_string(), "3".to_string()),
+                ("created_from".to_string(), "2024-06-01T12:30:00Z".to_string()),
+                ("customer_order_reference".to_string(), "ORDER-001".to_string()),
+                ("invoiced".to_string(), "false".to_string()),
+            ]
+        );
+        assert!(OrderFilter::new().query().is_empty());
+    }
+    
+    #[test]
+    fn test_sort_query() {
+        let query = OrderFilter::new()
+            .sort(SortBy::GrossTotal, SortDirection::Descending)
+            .query();
+        assert_eq!(
+            query,
+            vec![
+                ("sort_by".to_string(), "gross_total".to_string()),
+                ("direction".to_string(), "desc".to_string()),
+            ]
+        );
+        assert_eq!(SortDirection::default().as_str(), "asc");
+    }
+    
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(1_735_689_599)), "2024-12-31T23:59:59Z");
+    }
+}
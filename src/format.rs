@@ -0,0 +1,216 @@
+//! Wire formats for request and response bodies
+
+use crate::envelope::{self, Envelope};
+use crate::error::Result;
+use crate::response::ApiResponse;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Body format negotiated with the API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// `application/json` (default)
+    #[default]
+    Json,
+    /// `application/x-www-form-urlencoded` request bodies with bracketed
+    /// nested keys (`order_products[0][quantity]=1`); responses stay JSON
+    Form,
+    /// `application/xml`, spoken by legacy endpoints
+    #[cfg(feature = "xml")]
+    Xml,
+}
+
+impl Format {
+    /// Content type used for request bodies in this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Form => "application/x-www-form-urlencoded",
+            #[cfg(feature = "xml")]
+            Format::Xml => "application/xml",
+        }
+    }
+    
+    /// `Accept` header value preferring this format
+    pub fn accept(&self) -> &'static str {
+        match self {
+            Format::Json | Format::Form => "application/json",
+            #[cfg(feature = "xml")]
+            Format::Xml => "application/xml, text/xml;q=0.9, application/json;q=0.5",
+        }
+    }
+    
+    /// Determine the format of a response from its `Content-Type` header
+    ///
+    /// Unknown or missing content types are treated as JSON.
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        #[cfg(feature = "xml")]
+        if let Some(content_type) = content_type {
+            let mime = content_type.split(';').next().unwrap_or_default().trim();
+            if mime.eq_ignore_ascii_case("application/xml") || mime.eq_ignore_ascii_case("text/xml") {
+                return Format::Xml;
+            }
+        }
+        
+        #[cfg(not(feature = "xml"))]
+        let _ = content_type;
+        
+        Format::Json
+    }
+    
+    /// Serialize a request body in this format
+    pub(crate) fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Format::Json => Ok(serde_json::to_vec(value)?),
+            Format::Form => {
+                let mut pairs = Vec::new();
+                form_pairs(None, &serde_json::to_value(value)?, &mut pairs);
+                
+                let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+                serializer.extend_pairs(pairs);
+                Ok(serializer.finish().into_bytes())
+            }
+            #[cfg(feature = "xml")]
+            Format::Xml => Ok(quick_xml::se::to_string(value)?.into_bytes()),
+        }
+    }
+    
+    /// Decode a response body in this format
+    ///
+    /// Envelopes only apply to JSON; XML payloads are never wrapped.
+    pub(crate) fn decode<T: DeserializeOwned>(&self, envelope: Envelope, body: &[u8]) -> Result<ApiResponse<T>> {
+        match self {
+            Format::Json | Format::Form => envelope::decode(envelope, body),
+            #[cfg(feature = "xml")]
+            Format::Xml => Ok(ApiResponse::new(quick_xml::de::from_reader(body)?)),
+        }
+    }
+}
+
+/// Flatten a JSON value into form pairs with bracketed nested keys
+fn form_pairs(prefix: Option<&str>, value: &Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Object(object) => {
+            for (key, value) in object {
+                let key = match prefix {
+                    Some(prefix) => format!("{}[{}]", prefix, key),
+                    None => key.clone(),
+                };
+                form_pairs(Some(&key), value, pairs);
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                let key = format!("{}[{}]", prefix.unwrap_or_default(), index);
+                form_pairs(Some(&key), value, pairs);
+            }
+        }
+        Value::String(text) => pairs.push((prefix.unwrap_or_default().to_string(), text.clone())),
+        Value::Bool(_) | Value::Number(_) => {
+            pairs.push((prefix.unwrap_or_default().to_string(), value.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_json_format() {
+        assert_eq!(Format::default(), Format::Json);
+        assert_eq!(Format::Json.content_type(), "application/json");
+        assert_eq!(Format::from_content_type(None), Format::Json);
+        assert_eq!(Format::from_content_type(Some("application/json; charset=utf-8")), Format::Json);
+    }
+    
+    #[test]
+    fn test_form_encoding() {
+        use crate::types::{Addressbook, CreateOrderProduct, CreateOrderRequest, ProductCode};
+        
+        let request = CreateOrderRequest {
+            customer_order_reference: Some("70000001".to_string()),
+            order_products: vec![CreateOrderProduct {
+                product_code: Some(ProductCode("274181".to_string())),
+                quantity: 2,
+                addressbook: Some(Addressbook {
+                    country: "GB".to_string(),
+                    address: Some("Covent Garden".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        
+        assert_eq!(Format::Form.content_type(), "application/x-www-form-urlencoded");
+        assert_eq!(Format::Form.accept(), "application/json");
+        
+        let body = String::from_utf8(Format::Form.encode(&request).unwrap()).unwrap();
+        let pairs: Vec<(String, String)> = url::form_urlencoded::parse(body.as_bytes())
+            .into_owned()
+            .collect();
+        
+        assert!(pairs.contains(&("customer_order_reference".to_string(), "70000001".to_string())));
+        assert!(pairs.contains(&("order_products[0][product_code]".to_string(), "274181".to_string())));
+        assert!(pairs.contains(&("order_products[0][quantity]".to_string(), "2".to_string())));
+        assert!(pairs.contains(&("order_products[0][addressbook][address]".to_string(), "Covent Garden".to_string())));
+        assert!(!pairs.iter().any(|(key, _)| key == "addressbook"));
+    }
+    
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_xml_format() {
+        assert_eq!(Format::Xml.content_type(), "application/xml");
+        assert_eq!(Format::from_content_type(Some("application/xml")), Format::Xml);
+        assert_eq!(Format::from_content_type(Some("text/xml; charset=ISO-8859-1")), Format::Xml);
+    }
+    
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_xml_round_trip() {
+        use crate::types::{CreateOrderProduct, CreateOrderRequest, CreateOrderResponse, ProductCode};
+        
+        let request = CreateOrderRequest {
+            customer_order_reference: Some("70000001".to_string()),
+            order_products: vec![CreateOrderProduct {
+                product_code: Some(ProductCode("274181".to_string())),
+                quantity: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        
+        let xml = String::from_utf8(Format::Xml.encode(&request).unwrap()).unwrap();
+        assert!(xml.starts_with("<order>"));
+        assert!(xml.contains("<customer_order_reference>70000001</customer_order_reference>"));
+        assert!(xml.contains("<product_code>274181</product_code><quantity>2</quantity>"));
+        
+        let body = br#"<order_response>
+            <order>
+                <id>70</id>
+                <status_order_id>1</status_order_id>
+                <customer_id>9</customer_id>
+                <customer_order_reference>74160086</customer_order_reference>
+                <gross_total>95.97</gross_total>
+                <addressbook_id>99</addressbook_id>
+            </order>
+            <order_products>
+                <id>108</id>
+                <order_id>70</order_id>
+                <product_id>12646</product_id>
+                <quantity>1.0</quantity>
+                <price>95.97</price>
+                <final_price>95.97</final_price>
+            </order_products>
+        </order_response>"#;
+        
+        let response: ApiResponse<CreateOrderResponse> = Format::Xml.decode(Envelope::Auto, body).unwrap();
+        assert_eq!(response.data.order.id, 70);
+        assert_eq!(response.data.order.gross_total, "95.97");
+        assert_eq!(response.data.order_products.len(), 1);
+        assert_eq!(response.data.order_products[0].product_id, 12646);
+    }
+}
@@ -0,0 +1,47 @@
+//! Durable file writes for the file-backed stores
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Replace the file at `path` with `contents` so that a crash leaves either
+/// the old or the new version
+///
+/// The contents go to a `.partial` file next to `path`, which is synced
+/// before being renamed over `path`. The directory is synced afterwards so
+/// the rename itself survives a power loss.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    
+    let mut file = File::create(&partial)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&partial, path)?;
+    
+    // Directories cannot be opened as files on Windows, where the rename is durable
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("ecommerce-fs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,117 @@
+//! Hedged requests for idempotent reads
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of recent latencies the hedge delay is computed from
+const SAMPLE_SIZE: usize = 100;
+
+/// When to send a duplicate of a slow GET request
+///
+/// A hedge is sent once the first request has been outstanding longer than
+/// the given percentile of recent response times; whichever response
+/// arrives first wins and the other request is cancelled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HedgePolicy {
+    /// Latency percentile (0.0 - 1.0) after which the hedge is sent
+    pub percentile: f64,
+    /// Hedge delay used until enough latencies have been observed
+    pub initial_delay: Duration,
+    /// Lower bound for the hedge delay
+    pub min_delay: Duration,
+    /// Number of observed latencies required before the percentile is used
+    pub min_samples: usize,
+}
+
+impl Default for HedgePolicy {
+    fn default() -> Self {
+        Self {
+            percentile: 0.95,
+            initial_delay: Duration::from_millis(500),
+            min_delay: Duration::from_millis(10),
+            min_samples: 20,
+        }
+    }
+}
+
+/// Tracks recent latencies to derive the hedge delay
+#[derive(Debug)]
+pub(crate) struct Hedger {
+    policy: HedgePolicy,
+    latencies: Mutex<VecDeque<Duration>>,
+}
+
+impl Hedger {
+    pub(crate) fn new(policy: HedgePolicy) -> Self {
+        Self {
+            policy,
+            latencies: Mutex::new(VecDeque::with_capacity(SAMPLE_SIZE)),
+        }
+    }
+    
+    /// How long to wait for the first response before hedging
+    pub(crate) fn delay(&self) -> Duration {
+        let latencies = self.lock();
+        if latencies.len() < self.policy.min_samples.max(1) {
+            return self.policy.initial_delay.max(self.policy.min_delay);
+        }
+        
+        let mut sorted: Vec<Duration> = latencies.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (self.policy.percentile.clamp(0.0, 1.0) * (sorted.len() - 1) as f64).round();
+        sorted[rank as usize].max(self.policy.min_delay)
+    }
+    
+    /// Record the latency of a successful response
+    pub(crate) fn record(&self, latency: Duration) {
+        let mut latencies = self.lock();
+        if latencies.len() == SAMPLE_SIZE {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Duration>> {
+        self.latencies.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_initial_delay_until_enough_samples() {
+        let hedger = Hedger::new(HedgePolicy {
+            min_samples: 3,
+            ..Default::default()
+        });
+        
+        hedger.record(Duration::from_millis(20));
+        hedger.record(Duration::from_millis(30));
+        assert_eq!(hedger.delay(), Duration::from_millis(500));
+        
+        hedger.record(Duration::from_millis(40));
+        assert_eq!(hedger.delay(), Duration::from_millis(40));
+    }
+    
+    #[test]
+    fn test_percentile_delay() {
+        let hedger = Hedger::new(HedgePolicy {
+            percentile: 0.9,
+            ..Default::default()
+        });
+        
+        for ms in 1..=100 {
+            hedger.record(Duration::from_millis(ms));
+        }
+        assert_eq!(hedger.delay(), Duration::from_millis(90));
+        
+        // Old samples are evicted once the window is full
+        for _ in 0..100 {
+            hedger.record(Duration::from_millis(5));
+        }
+        assert_eq!(hedger.delay(), Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,191 @@
+//! Idempotency keys that survive process restarts
+//!
+//! With an [`IdempotencyStore`] set through
+//! [`Client::with_idempotency_store`](crate::Client::with_idempotency_store),
+//! order creation records the key it sends for each customer order reference
+//! before sending it. Creating the same order again, even from a new process,
+//! then reuses the key, so the API recognises the retry instead of creating a
+//! second order.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// What came of the last attempt to create an order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Outcome {
+    /// Sent without a known result, e.g. interrupted or failed transiently
+    Pending,
+    /// Created as the order with this id
+    Created {
+        /// Id of the created order
+        order_id: u64,
+    },
+    /// Rejected by the API; the next attempt gets a new key
+    Rejected {
+        /// Why the order was rejected
+        message: String,
+    },
+}
+
+/// Idempotency key used for an order and what came of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct IdempotencyRecord {
+    /// Key sent in the `Idempotency-Key` header
+    pub key: String,
+    /// Outcome of the last attempt
+    pub outcome: Outcome,
+}
+
+impl IdempotencyRecord {
+    /// Record of an order about to be sent with `key`
+    pub fn pending(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            outcome: Outcome::Pending,
+        }
+    }
+}
+
+/// Maps customer order references to the idempotency keys used for them
+///
+/// The client does not send an order whose key could not be read or
+/// recorded; the call fails with [`Error::Storage`](crate::Error::Storage)
+/// instead, as sending it would risk a duplicate after a restart.
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync + 'static {
+    /// The record for the order with `reference`, if any
+    async fn get(&self, reference: &str) -> io::Result<Option<IdempotencyRecord>>;
+    
+    /// Replace the record for the order with `reference`
+    async fn put(&self, reference: &str, record: &IdempotencyRecord) -> io::Result<()>;
+}
+
+#[async_trait]
+impl<S: IdempotencyStore> IdempotencyStore for Arc<S> {
+    async fn get(&self, reference: &str) -> io::Result<Option<IdempotencyRecord>> {
+        (**self).get(reference).await
+    }
+    
+    async fn put(&self, reference: &str, record: &IdempotencyRecord) -> io::Result<()> {
+        (**self).put(reference, record).await
+    }
+}
+
+/// Keeps records in memory, so keys survive client restarts but not process restarts
+#[derive(Debug, Default)]
+pub struct MemoryIdempotencyStore {
+    records: Mutex<HashMap<String, IdempotencyRecord>>,
+}
+
+impl MemoryIdempotencyStore {
+    /// An empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, IdempotencyRecord>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for MemoryIdempotencyStore {
+    async fn get(&self, reference: &str) -> io::Result<Option<IdempotencyRecord>> {
+        Ok(self.lock().get(reference).cloned())
+    }
+    
+    async fn put(&self, reference: &str, record: &IdempotencyRecord) -> io::Result<()> {
+        self.lock().insert(reference.to_string(), record.clone());
+        Ok(())
+    }
+}
+
+/// Keeps records in a JSON file, read once when opened and rewritten on every change
+///
+/// Records are synced to disk before [`IdempotencyStore::put`] returns, so
+/// the key of an order being sent survives a crash.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct FileIdempotencyStore {
+    path: std::path::PathBuf,
+    records: Mutex<HashMap<String, IdempotencyRecord>>,
+    /// Held while writing, so a slow write never overtakes a later one
+    writing: tokio::sync::Mutex<()>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileIdempotencyStore {
+    /// Load the records in `path`, starting empty if the file does not exist
+    pub fn open(path: impl Into<std::path::PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let records = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            records: Mutex::new(records),
+            writing: tokio::sync::Mutex::new(()),
+        })
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, IdempotencyRecord>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl IdempotencyStore for FileIdempotencyStore {
+    async fn get(&self, reference: &str) -> io::Result<Option<IdempotencyRecord>> {
+        Ok(self.lock().get(reference).cloned())
+    }
+    
+    async fn put(&self, reference: &str, record: &IdempotencyRecord) -> io::Result<()> {
+        self.lock().insert(reference.to_string(), record.clone());
+        let _writing = self.writing.lock().await;
+        // Taken after waiting for earlier writes, so it includes their records
+        let json = serde_json::to_vec(&*self.lock())?;
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || crate::fs::write_atomic(&path, &json))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_record_serde() {
+        let record = IdempotencyRecord {
+            key: "key-1".to_string(),
+            outcome: Outcome::Created { order_id: 42 },
+        };
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(json, r#"{"key":"key-1","outcome":{"status":"created","order_id":42}}"#);
+        assert_eq!(serde_json::from_str::<IdempotencyRecord>(&json).unwrap(), record);
+    }
+    
+    #[tokio::test]
+    async fn test_file_store_reopened() {
+        let path = std::env::temp_dir().join(format!("ecommerce-api-idempotency-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        
+        let store = FileIdempotencyStore::open(&path).unwrap();
+        assert_eq!(store.get("ORDER-1").await.unwrap(), None);
+        store.put("ORDER-1", &IdempotencyRecord::pending("key-1")).await.unwrap();
+        
+        let reopened = FileIdempotencyStore::open(&path).unwrap();
+        assert_eq!(reopened.get("ORDER-1").await.unwrap(), Some(IdempotencyRecord::pending("key-1")));
+        let _ = std::fs::remove_file(&path);
+    }
+}
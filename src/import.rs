@@ -0,0 +1,325 @@
+//! Importing orders from CSV files
+//!
+//! Each row is one order line. Rows with the same `customer_order_reference`
+//! form one order, in the order the references first appear. Columns:
+//!
+//! | Column | Required | Notes |
+//! |---|---|---|
+//! | `customer_order_reference` | yes | Groups rows into orders |
+//! | `product_code` | yes | |
+//! | `quantity` | yes | Whole number above zero |
+//! | `unit_price` | no | Decimal with a `.` separator |
+//! | `currency` | no | |
+//! | `country` | yes | On the first row of each order |
+//! | `name`, `address`, `address2`, `city`, `province`, `postal_code`, `phone`, `email` | no | Shipping address |
+//! | `comments` | no | Order comment |
+//!
+//! The address and comment are taken from an order's first row; later rows
+//! may leave them empty but must not contradict them.
+
+use crate::bulk::{BulkOptions, BulkReport};
+use crate::client::Client;
+use crate::error::Result;
+use crate::types::{Addressbook, CreateOrderProduct, CreateOrderRequest, ProductCode};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+/// Columns every file must have
+const REQUIRED_COLUMNS: &[&str] = &["customer_order_reference", "product_code", "quantity", "country"];
+
+/// A row that could not be imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RowError {
+    /// Line of the row in the file, starting at 1 for the header
+    pub line: u64,
+    /// Order the row belongs to, if it has a reference
+    pub reference: Option<String>,
+    /// What is wrong with the row
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reference {
+            Some(reference) => write!(f, "line {} (order {}): {}", self.line, reference, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+/// Orders read from a CSV file
+///
+/// An order with any invalid row is left out entirely, so orders are never
+/// submitted with lines missing.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ImportBatch {
+    /// Orders whose rows were all valid
+    pub orders: Vec<CreateOrderRequest>,
+    /// Problems found, in file order
+    pub errors: Vec<RowError>,
+}
+
+impl ImportBatch {
+    /// Whether every row was valid
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+    
+    /// Create the valid orders with [`Client::create_orders_bulk`]
+    ///
+    /// The report's indices refer to [`ImportBatch::orders`].
+    pub async fn submit(self, client: &Client, options: BulkOptions) -> BulkReport {
+        client.create_orders_bulk(self.orders, options).await
+    }
+}
+
+/// One row of the file
+#[derive(Debug, Deserialize)]
+struct Row {
+    customer_order_reference: String,
+    product_code: String,
+    quantity: String,
+    #[serde(default)]
+    unit_price: String,
+    #[serde(default)]
+    currency: String,
+    #[serde(default)]
+    country: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    address: String,
+    #[serde(default)]
+    address2: String,
+    #[serde(default)]
+    city: String,
+    #[serde(default)]
+    province: String,
+    #[serde(default)]
+    postal_code: String,
+    #[serde(default)]
+    phone: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    comments: String,
+}
+
+impl Row {
+    /// Shipping address of the row, if it names a country
+    fn addressbook(&self) -> Option<Addressbook> {
+        let optional = |value: &str| (!value.is_empty()).then(|| value.to_string());
+        (!self.country.is_empty()).then(|| Addressbook {
+            country: self.country.clone(),
+            name: optional(&self.name),
+            address: optional(&self.address),
+            address2: optional(&self.address2),
+            city: optional(&self.city),
+            province: optional(&self.province),
+            postal_code: optional(&self.postal_code),
+            phone: optional(&self.phone),
+            email: optional(&self.email),
+            comments: None,
+        })
+    }
+    
+    /// Whether the row's address fields disagree with `first`
+    fn contradicts(&self, first: &Addressbook) -> bool {
+        let fields = [
+            (&self.name, &first.name),
+            (&self.address, &first.address),
+            (&self.address2, &first.address2),
+            (&self.city, &first.city),
+            (&self.province, &first.province),
+            (&self.postal_code, &first.postal_code),
+            (&self.phone, &first.phone),
+            (&self.email, &first.email),
+        ];
+        (!self.country.is_empty() && self.country != first.country)
+            || fields
+                .iter()
+                .any(|(value, first)| !value.is_empty() && first.as_deref() != Some(value.as_str()))
+    }
+    
+    /// The order line of the row
+    fn product(&self) -> std::result::Result<CreateOrderProduct, String> {
+        if self.product_code.is_empty() {
+            return Err("missing product_code".to_string());
+        }
+        let quantity = match self.quantity.parse::<u32>() {
+            Ok(quantity) if quantity > 0 => quantity,
+            _ => return Err(format!("invalid quantity `{}`", self.quantity)),
+        };
+        let unit_price = match self.unit_price.as_str() {
+            "" => None,
+            price => match price.parse::<f64>() {
+                Ok(price) if price.is_finite() && price >= 0.0 => Some(price),
+                _ => return Err(format!("invalid unit_price `{}`", price)),
+            },
+        };
+        Ok(CreateOrderProduct {
+            product_code: Some(ProductCode(self.product_code.clone())),
+            quantity,
+            unit_price,
+            currency: (!self.currency.is_empty()).then(|| self.currency.clone()),
+            ..Default::default()
+        })
+    }
+}
+
+/// An order being assembled from its rows
+struct Draft {
+    request: CreateOrderRequest,
+    valid: bool,
+}
+
+/// Read orders from a CSV file in the layout described in the [module docs](self)
+///
+/// Invalid rows are reported in [`ImportBatch::errors`]; only unreadable
+/// files and malformed CSV fail the whole import.
+///
+/// ```
+/// # use ecommerce_api_client::import::parse_orders;
+/// let csv = "customer_order_reference,product_code,quantity,country\n\
+///            ORDER-1,P-100,2,DE\n\
+///            ORDER-1,P-200,1,\n";
+/// let batch = parse_orders(csv.as_bytes()).unwrap();
+/// assert_eq!(batch.orders[0].order_products.len(), 2);
+/// ```
+pub fn parse_orders<R: Read>(reader: R) -> Result<ImportBatch> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    let mut batch = ImportBatch::default();
+    
+    let headers = reader.headers()?.clone();
+    let missing: Vec<_> = REQUIRED_COLUMNS
+        .iter()
+        .filter(|column| !headers.iter().any(|header| header == **column))
+        .collect();
+    if !missing.is_empty() {
+        batch.errors.push(RowError {
+            line: 1,
+            reference: None,
+            message: format!("missing columns {:?}", missing),
+        });
+        return Ok(batch);
+    }
+    
+    let mut drafts: Vec<Draft> = Vec::new();
+    let mut by_reference: HashMap<String, usize> = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        let fail = |reference: Option<String>, message: String| RowError { line, reference, message };
+        let row: Row = match record.deserialize(Some(&headers)) {
+            Ok(row) => row,
+            Err(e) => {
+                batch.errors.push(fail(None, e.to_string()));
+                continue;
+            }
+        };
+        let reference = row.customer_order_reference.clone();
+        if reference.is_empty() {
+            batch.errors.push(fail(None, "missing customer_order_reference".to_string()));
+            continue;
+        }
+        
+        let index = *by_reference.entry(reference.clone()).or_insert_with(|| {
+            drafts.push(Draft {
+                request: CreateOrderRequest {
+                    customer_order_reference: Some(reference.clone()),
+                    addressbook: row.addressbook(),
+                    order_products: Vec::new(),
+                    comments_customer: (!row.comments.is_empty()).then(|| row.comments.clone()),
+                },
+                valid: true,
+            });
+            drafts.len() - 1
+        });
+        let draft = &mut drafts[index];
+        
+        let problem = match &draft.request.addressbook {
+            None => Some("missing country".to_string()),
+            Some(first) if row.contradicts(first) => Some("address differs from the order's first row".to_string()),
+            Some(_) => None,
+        };
+        let result = match problem {
+            Some(problem) => Err(problem),
+            None => row.product(),
+        };
+        match result {
+            Ok(product) => draft.request.order_products.push(product),
+            Err(message) => {
+                draft.valid = false;
+                batch.errors.push(fail(Some(reference), message));
+            }
+        }
+    }
+    
+    batch.orders = drafts
+        .into_iter()
+        .filter(|draft| draft.valid)
+        .map(|draft| draft.request)
+        .collect();
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const HEADER: &str = "customer_order_reference,product_code,quantity,unit_price,currency,country,name,city,comments\n";
+    
+    #[test]
+    fn test_rows_grouped_by_reference() {
+        let csv = format!(
+            "{}{}{}{}",
+            HEADER,
+            "ORDER-1,P-100,2,9.50,EUR,DE,Jane Doe,Berlin,Leave at door\n",
+            "ORDER-2,P-300,1,,,AT,,,\n",
+            "ORDER-1,P-200,1,,,DE,,Berlin,\n"
+        );
+        let batch = parse_orders(csv.as_bytes()).unwrap();
+        assert!(batch.is_valid(), "{:?}", batch.errors);
+        assert_eq!(batch.orders.len(), 2);
+        
+        let first = &batch.orders[0];
+        assert_eq!(first.customer_order_reference.as_deref(), Some("ORDER-1"));
+        assert_eq!(first.comments_customer.as_deref(), Some("Leave at door"));
+        assert_eq!(first.addressbook.as_ref().unwrap().city.as_deref(), Some("Berlin"));
+        assert_eq!(first.order_products.len(), 2);
+        assert_eq!(first.order_products[0].unit_price, Some(9.5));
+        assert_eq!(first.order_products[1].product_code, Some(ProductCode("P-200".to_string())));
+        assert_eq!(batch.orders[1].addressbook.as_ref().unwrap().country, "AT");
+    }
+    
+    #[test]
+    fn test_row_errors() {
+        let csv = format!(
+            "{}{}{}{}{}",
+            HEADER,
+            "ORDER-1,P-100,0,,,DE,,,\n",
+            "ORDER-1,P-200,1,,,FR,,,\n",
+            "ORDER-2,P-300,1,,,,,,\n",
+            "ORDER-3,P-400,1,abc,,FR,,,\n"
+        );
+        let batch = parse_orders(csv.as_bytes()).unwrap();
+        assert!(batch.orders.is_empty());
+        let errors: Vec<_> = batch.errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "line 2 (order ORDER-1): invalid quantity `0`",
+                "line 3 (order ORDER-1): address differs from the order's first row",
+                "line 4 (order ORDER-2): missing country",
+                "line 5 (order ORDER-3): invalid unit_price `abc`",
+            ]
+        );
+        
+        let batch = parse_orders("customer_order_reference,quantity\n".as_bytes()).unwrap();
+        assert_eq!(batch.errors[0].message, r#"missing columns ["product_code", "country"]"#);
+    }
+}
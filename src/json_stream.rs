@@ -0,0 +1,305 @@
+//! Incremental decoding of large JSON arrays
+//!
+//! [`JsonArrayDecoder`] takes a response body chunk by chunk and yields each
+//! array item as soon as it is complete, so memory use is bounded by the
+//! largest item rather than the whole body.
+
+use crate::error::{Error, Result};
+use serde::de::{DeserializeOwned, Error as _};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Where the decoder is in the body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Looking for the array
+    Seeking,
+    /// Inside the array, whose items sit at `depth`
+    Items { depth: usize },
+    /// Past the end of the array
+    Done,
+}
+
+/// Decodes the items of a JSON array as its bytes arrive
+///
+/// The array is either the whole body or, for enveloped responses, the value
+/// of a top-level field such as `data`. Anything after the array is ignored.
+///
+/// ```
+/// # use ecommerce_api_client::json_stream::JsonArrayDecoder;
+/// let mut decoder = JsonArrayDecoder::<u32>::new();
+/// assert_eq!(decoder.push(b"[1, 2").unwrap(), [1]);
+/// assert_eq!(decoder.push(b"3, 4]").unwrap(), [23, 4]);
+/// decoder.finish().unwrap();
+/// ```
+pub struct JsonArrayDecoder<T> {
+    field: Option<&'static str>,
+    max_item_size: Option<u64>,
+    buffer: Vec<u8>,
+    /// Next byte of `buffer` to scan
+    position: usize,
+    state: State,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Start of the item being read
+    item_start: Option<usize>,
+    /// Start of the top-level key being read, while seeking
+    key_start: Option<usize>,
+    /// Last top-level key read, while seeking
+    key: Option<Vec<u8>>,
+    /// Whether the field's value comes next, while seeking
+    at_field: bool,
+    _items: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> JsonArrayDecoder<T> {
+    /// Decode a body that is a JSON array
+    pub fn new() -> Self {
+        Self::build(None)
+    }
+    
+    /// Decode a JSON array, or the array in the top-level `field` of an object
+    pub fn field(field: &'static str) -> Self {
+        Self::build(Some(field))
+    }
+    
+    fn build(field: Option<&'static str>) -> Self {
+        Self {
+            field,
+            max_item_size: None,
+            buffer: Vec::new(),
+            position: 0,
+            state: State::Seeking,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            item_start: None,
+            key_start: None,
+            key: None,
+            at_field: false,
+            _items: PhantomData,
+        }
+    }
+    
+    /// Fail with [`Error::ResponseTooLarge`] once a single item grows past `limit` bytes
+    pub fn max_item_size(mut self, limit: u64) -> Self {
+        self.max_item_size = Some(limit);
+        self
+    }
+    
+    /// Feed the next chunk of the body, returning the items it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<T>> {
+        if self.state == State::Done {
+            return Ok(Vec::new());
+        }
+        self.buffer.extend_from_slice(chunk);
+        let mut items = Vec::new();
+        while self.position < self.buffer.len() && self.state != State::Done {
+            let byte = self.buffer[self.position];
+            match self.state {
+                State::Seeking => self.seek(byte)?,
+                State::Items { depth } => {
+                    if let Some(item) = self.scan_item(byte, depth)? {
+                        items.push(item);
+                    }
+                }
+                State::Done => {}
+            }
+            self.position += 1;
+        }
+        self.compact()?;
+        Ok(items)
+    }
+    
+    /// Check that the body contained a complete array
+    pub fn finish(self) -> Result<()> {
+        match self.state {
+            State::Done => Ok(()),
+            _ => Err(serde_json::Error::custom("JSON array ended unexpectedly").into()),
+        }
+    }
+    
+    /// Step through the bytes before the array
+    fn seek(&mut self, byte: u8) -> Result<()> {
+        if self.in_string {
+            if self.string_ends(byte) {
+                if let Some(start) = self.key_start.take() {
+                    self.key = Some(self.buffer[start + 1..self.position].to_vec());
+                }
+            }
+            return Ok(());
+        }
+        match (self.depth, byte) {
+            (_, b' ' | b'\t' | b'\r' | b'\n') => {}
+            (0, b'[') => {
+                self.depth = 1;
+                self.state = State::Items { depth: 1 };
+            }
+            (0, b'{') if self.field.is_some() => self.depth = 1,
+            (0, _) => return Err(unexpected(byte)),
+            (1, b'"') => {
+                self.in_string = true;
+                self.key_start = Some(self.position);
+                self.at_field = false;
+            }
+            (1, b':') => {
+                let key = self.key.take();
+                self.at_field = key.as_deref() == self.field.map(str::as_bytes);
+            }
+            (1, b'[') if self.at_field => {
+                self.depth = 2;
+                self.state = State::Items { depth: 2 };
+            }
+            (_, b'"') => self.in_string = true,
+            (_, b'{' | b'[') => {
+                self.depth += 1;
+                self.at_field = false;
+            }
+            (_, b'}' | b']') => self.depth -= 1,
+            _ => self.at_field = false,
+        }
+        Ok(())
+    }
+    
+    /// Step through the array, returning an item once it is complete
+    fn scan_item(&mut self, byte: u8, items_depth: usize) -> Result<Option<T>> {
+        if self.in_string {
+            self.string_ends(byte);
+            return Ok(None);
+        }
+        let at_items = self.depth == items_depth;
+        match byte {
+            b' ' | b'\t' | b'\r' | b'\n' => Ok(None),
+            b',' | b']' if at_items => {
+                let item = match self.item_start.take() {
+                    Some(start) => Some(serde_json::from_slice(&self.buffer[start..self.position])?),
+                    None => None,
+                };
+                if byte == b']' {
+                    self.depth -= 1;
+                    self.state = State::Done;
+                }
+                Ok(item)
+            }
+            _ => {
+                if at_items && self.item_start.is_none() {
+                    self.item_start = Some(self.position);
+                }
+                match byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => self.depth += 1,
+                    b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                }
+                Ok(None)
+            }
+        }
+    }
+    
+    /// Track escapes inside a string, returning whether it just ended
+    fn string_ends(&mut self, byte: u8) -> bool {
+        if self.escaped {
+            self.escaped = false;
+        } else if byte == b'\\' {
+            self.escaped = true;
+        } else if byte == b'"' {
+            self.in_string = false;
+            return true;
+        }
+        false
+    }
+    
+    /// Drop the bytes that were scanned and are no longer needed
+    fn compact(&mut self) -> Result<()> {
+        let keep = [self.item_start, self.key_start]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(self.position);
+        self.buffer.drain(..keep);
+        self.position -= keep;
+        self.item_start = self.item_start.map(|start| start - keep);
+        self.key_start = self.key_start.map(|start| start - keep);
+        
+        let pending = self.buffer.len() as u64;
+        match self.max_item_size {
+            Some(limit) if pending > limit => Err(Error::ResponseTooLarge(limit)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Default for JsonArrayDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for JsonArrayDecoder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonArrayDecoder")
+            .field("field", &self.field)
+            .field("state", &self.state)
+            .field("buffered", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn unexpected(byte: u8) -> Error {
+    serde_json::Error::custom(format!("expected a JSON array, found `{}`", byte as char)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Item {
+        name: String,
+        tags: Vec<String>,
+    }
+    
+    #[test]
+    fn test_items_split_across_chunks() {
+        let body = br#" [ {"name": "a\"]", "tags": ["x", "{"]}, {"name": "b", "tags": []} ] "#;
+        for size in 1..body.len() {
+            let mut decoder = JsonArrayDecoder::<Item>::new();
+            let mut items = Vec::new();
+            for chunk in body.chunks(size) {
+                items.extend(decoder.push(chunk).unwrap());
+            }
+            decoder.finish().unwrap();
+            let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+            assert_eq!(names, ["a\"]", "b"], "chunk size {}", size);
+            assert_eq!(items[0].tags, ["x", "{"]);
+        }
+    }
+    
+    #[test]
+    fn test_enveloped_array() {
+        let body = br#"{"meta": {"data": [9]}, "links": {}, "data": [1, 2, 3], "more": [4]}"#;
+        let mut decoder = JsonArrayDecoder::<u32>::field("data");
+        let items: Vec<u32> = body.chunks(4).flat_map(|chunk| decoder.push(chunk).unwrap()).collect();
+        assert_eq!(items, [1, 2, 3]);
+        decoder.finish().unwrap();
+        
+        let mut unwrapped = JsonArrayDecoder::<u32>::new();
+        assert!(unwrapped.push(br#"{"data": [1]}"#).is_err());
+        
+        let mut empty = JsonArrayDecoder::<u32>::field("data");
+        assert!(empty.push(b"[]").unwrap().is_empty());
+        empty.finish().unwrap();
+    }
+    
+    #[test]
+    fn test_limits_and_truncation() {
+        let mut decoder = JsonArrayDecoder::<String>::new().max_item_size(8);
+        assert!(matches!(decoder.push(br#"["a very long item"#), Err(Error::ResponseTooLarge(8))));
+        
+        let mut truncated = JsonArrayDecoder::<u32>::new();
+        assert_eq!(truncated.push(b"[1, 2").unwrap(), [1]);
+        assert!(truncated.finish().is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! JWT assertion authentication
+
+use crate::error::Result;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use crate::runtime::{self, Instant};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Signed tokens are reused until this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(10);
+
+/// Short-lived JWTs signed locally and sent as `Authorization: Bearer <jwt>`
+///
+/// By default a token is reused for its lifetime; use
+/// [`JwtAuth::per_request`] to sign a fresh token for every request.
+#[derive(Clone)]
+pub struct JwtAuth {
+    algorithm: Algorithm,
+    key: Arc<EncodingKey>,
+    issuer: String,
+    subject: Option<String>,
+    audience: Option<String>,
+    key_id: Option<String>,
+    lifetime: Duration,
+    per_request: bool,
+    cache: Arc<Mutex<Option<(String, Instant)>>>,
+}
+
+/// Registered claims included in every token
+#[derive(Debug, Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<&'a str>,
+    iat: u64,
+    exp: u64,
+}
+
+impl JwtAuth {
+    /// Sign tokens with HS256 using a shared secret
+    pub fn hs256(secret: impl AsRef<[u8]>, issuer: impl Into<String>) -> Self {
+        Self::new(Algorithm::HS256, EncodingKey::from_secret(secret.as_ref()), issuer.into())
+    }
+    
+    /// Sign tokens with RS256 using a PEM-encoded RSA private key
+    pub fn rs256_from_pem(pem: &[u8], issuer: impl Into<String>) -> Result<Self> {
+        Ok(Self::new(Algorithm::RS256, EncodingKey::from_rsa_pem(pem)?, issuer.into()))
+    }
+    
+    fn new(algorithm: Algorithm, key: EncodingKey, issuer: String) -> Self {
+        Self {
+            algorithm,
+            key: Arc::new(key),
+            issuer,
+            subject: None,
+            audience: None,
+            key_id: None,
+            lifetime: Duration::from_secs(300),
+            per_request: false,
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+    
+    /// Set the `sub` claim
+    pub fn with_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+    
+    /// Set the `aud` claim
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+    
+    /// Set the `kid` header identifying the signing key
+    pub fn with_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+    
+    /// Set how long each token is valid (default 5 minutes)
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+    
+    /// Sign a fresh token for every request instead of reusing it
+    pub fn per_request(mut self, per_request: bool) -> Self {
+        self.per_request = per_request;
+        self
+    }
+    
+    /// Get a token to send, signing a new one if needed
+    pub(crate) fn token(&self) -> Result<String> {
+        if self.per_request {
+            return self.sign();
+        }
+        
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((token, expires_at)) = cache.as_ref() {
+            if Instant::now() + EXPIRY_MARGIN < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+        
+        let token = self.sign()?;
+        *cache = Some((token.clone(), Instant::now() + self.lifetime));
+        Ok(token)
+    }
+    
+    /// Drop the cached token so the next request signs a new one
+    pub(crate) fn invalidate(&self) {
+        *self.cache.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+    
+    /// Sign a new token
+    fn sign(&self) -> Result<String> {
+        let now = runtime::system_now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        
+        let claims = Claims {
+            iss: &self.issuer,
+            sub: self.subject.as_deref(),
+            aud: self.audience.as_deref(),
+            iat: now,
+            exp: now + self.lifetime.as_secs(),
+        };
+        
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.key_id.clone();
+        
+        Ok(jsonwebtoken::encode(&header, &claims, &self.key)?)
+    }
+}
+
+impl fmt::Debug for JwtAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtAuth")
+            .field("algorithm", &self.algorithm)
+            .field("key", &"<redacted>")
+            .field("issuer", &self.issuer)
+            .field("subject", &self.subject)
+            .field("audience", &self.audience)
+            .field("key_id", &self.key_id)
+            .field("lifetime", &self.lifetime)
+            .field("per_request", &self.per_request)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{DecodingKey, Validation};
+    
+    #[derive(Debug, serde::Deserialize)]
+    struct DecodedClaims {
+        iss: String,
+        sub: Option<String>,
+        aud: Option<String>,
+        iat: u64,
+        exp: u64,
+    }
+    
+    #[test]
+    fn test_hs256_token_claims() {
+        let jwt = JwtAuth::hs256("secret", "customer-9")
+            .with_subject("orders")
+            .with_audience("https://api.example.com")
+            .with_key_id("key-1")
+            .with_lifetime(Duration::from_secs(60));
+        
+        let token = jwt.token().unwrap();
+        let header = jsonwebtoken::decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::HS256);
+        assert_eq!(header.kid.as_deref(), Some("key-1"));
+        
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&["https://api.example.com"]);
+        let claims = jsonwebtoken::decode::<DecodedClaims>(&token, &DecodingKey::from_secret(b"secret"), &validation)
+            .unwrap()
+            .claims;
+        assert_eq!(claims.iss, "customer-9");
+        assert_eq!(claims.sub.as_deref(), Some("orders"));
+        assert_eq!(claims.aud.as_deref(), Some("https://api.example.com"));
+        assert_eq!(claims.exp - claims.iat, 60);
+    }
+    
+    #[test]
+    fn test_token_caching() {
+        let cached = JwtAuth::hs256("secret", "customer-9");
+        let token = cached.token().unwrap();
+        assert_eq!(cached.token().unwrap(), token);
+        
+        cached.invalidate();
+        assert!(cached.cache.lock().unwrap().is_none());
+        
+        let per_request = JwtAuth::hs256("secret", "customer-9").per_request(true);
+        per_request.token().unwrap();
+        assert!(per_request.cache.lock().unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_invalid_pem() {
+        assert!(JwtAuth::rs256_from_pem(b"not a pem", "customer-9").is_err());
+        assert!(!format!("{:?}", JwtAuth::hs256("s3cr3t", "customer-9")).contains("s3cr3t"));
+    }
+}
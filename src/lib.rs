@@ -43,19 +43,164 @@
 //! # }
 //! ```
 
+#[cfg(all(not(target_arch = "wasm32"), not(any(feature = "rustls", feature = "native-tls"))))]
+compile_error!("enable the `rustls` or `native-tls` feature to select a TLS backend");
+
+pub mod api_error;
+pub mod audit;
+pub mod auth;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+pub mod builder;
+pub mod bulk;
+mod cache;
+pub mod checkpoint;
+pub mod circuit_breaker;
 pub mod client;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod connector;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod debug_log;
+pub mod deprecation;
+pub mod diff;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod disk_cache;
+pub mod envelope;
 pub mod error;
+pub mod events;
+pub mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod failover;
+pub mod filter;
+pub mod format;
+#[cfg(not(target_arch = "wasm32"))]
+mod fs;
+pub mod hedge;
+pub mod idempotency;
+#[cfg(feature = "csv")]
+pub mod import;
+pub mod json_stream;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+#[cfg(feature = "log")]
+mod logging;
+pub mod metrics;
+pub mod options;
+pub mod outbox;
+pub mod pagination;
+pub mod progress;
+#[cfg(feature = "opentelemetry")]
+pub mod propagation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod proxy;
+pub mod rate_limit;
+pub mod reconcile;
+pub mod redact;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod redirect;
+pub mod replay;
+pub mod request_id;
+pub mod response;
+pub mod retry;
+pub mod router;
+mod runtime;
+pub mod saga;
+pub mod shutdown;
+mod single_flight;
+#[cfg(all(feature = "sse", not(target_arch = "wasm32")))]
+mod sse;
+pub mod store;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tls;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod transport;
 pub mod types;
+pub mod watch;
+#[cfg(all(feature = "webhook-server", not(target_arch = "wasm32")))]
+pub mod webhook_server;
+pub mod webhooks;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub mod websocket;
 
+/// Re-exported so [`CredentialProvider`] and other async traits can be implemented
+pub use api_error::{ApiErrorBody, ApiErrorCode};
+pub use async_trait::async_trait;
+pub use audit::AuditSink;
+pub use auth::{AuthScheme, CredentialProvider, Credentials, OAuth2, Session};
+pub use builder::ClientBuilder;
+pub use bulk::{BulkOptions, BulkReport};
+pub use checkpoint::CheckpointStore;
+pub use circuit_breaker::{CircuitBreakerConfig, CircuitState};
 pub use client::Client;
-pub use error::{Error, Result};
+#[cfg(not(target_arch = "wasm32"))]
+pub use config::Config;
+#[cfg(not(target_arch = "wasm32"))]
+pub use connector::Connector;
+pub use deprecation::DeprecationNotice;
+#[cfg(not(target_arch = "wasm32"))]
+pub use disk_cache::DiskCache;
+pub use envelope::{Envelope, Meta};
+pub use error::{Error, ErrorContext, ErrorKind, Result};
+pub use events::{ApiEvent, ApiEvents};
+#[cfg(not(target_arch = "wasm32"))]
+pub use failover::{RegionHealth, Routing};
+pub use filter::{InvoiceState, OrderFilter, SortBy, SortDirection};
+pub use format::Format;
+pub use hedge::HedgePolicy;
+pub use idempotency::IdempotencyStore;
+pub use metrics::MetricsObserver;
+pub use options::{Endpoint, IdempotencyKeys, RequestOptions};
+pub use outbox::Outbox;
+pub use pagination::{ListLimits, Page, PageRequest, Paginator};
+pub use progress::{Progress, ProgressObserver};
+#[cfg(feature = "opentelemetry")]
+pub use propagation::Propagation;
+#[cfg(not(target_arch = "wasm32"))]
+pub use proxy::ProxyConfig;
+pub use rate_limit::RateLimitStatus;
+pub use reconcile::ReconcileReport;
+#[cfg(not(target_arch = "wasm32"))]
+pub use redirect::RedirectPolicy;
+pub use response::{ApiResponse, Conditional};
+pub use retry::{RetryBudget, RetryPolicy};
+pub use router::EventRouter;
+pub use saga::SagaReport;
+pub use shutdown::ShutdownReport;
+pub use store::OrderStore;
+/// Re-exported so secret values in [`AuthScheme`] and [`Credentials`] can be built and read
+pub use secrecy::{ExposeSecret, SecretString};
+#[cfg(not(target_arch = "wasm32"))]
+pub use tls::{Certificate, CertificatePin, Identity, TlsBackend};
+#[cfg(not(target_arch = "wasm32"))]
+pub use transport::HttpTransport;
+pub use watch::OrderChange;
+
+/// HTTP types used by [`HttpTransport`]
+#[cfg(not(target_arch = "wasm32"))]
+pub use http;
+/// Re-exported so requests can be cancelled through [`RequestOptions::cancellation`]
+pub use tokio_util::sync::CancellationToken;
 
 /// Re-export commonly used types for convenience
 pub mod prelude {
+    pub use crate::auth::{AuthScheme, CredentialProvider, Credentials, OAuth2, Session};
+    pub use crate::builder::ClientBuilder;
     pub use crate::client::Client;
-    pub use crate::error::{Error, Result};
+    pub use crate::envelope::{Envelope, Meta};
+    pub use crate::error::{Error, ErrorKind, Result};
+    pub use crate::filter::OrderFilter;
+    pub use crate::format::Format;
+    pub use crate::options::{Endpoint, RequestOptions};
+    pub use crate::pagination::{Page, PageRequest};
+    pub use crate::rate_limit::RateLimitStatus;
+    pub use crate::response::{ApiResponse, Conditional};
+    pub use crate::retry::RetryPolicy;
     pub use crate::types::{
         CreateOrderRequest, CreateOrderResponse, CreateOrderProduct,
-        Addressbook, CustomerOrderReference, ProductCode, OrderId,
+        Addressbook, CustomerOrderReference, ProductCode, OrderId, Link, Links, FindOrCreate,
     };
 }
\ No newline at end of file
@@ -0,0 +1,102 @@
+//! `log` records for API calls, with credentials and personal data redacted
+
+use crate::error::Result;
+use crate::redact;
+use crate::response::ApiResponse;
+use reqwest::Method;
+use std::time::Duration;
+
+/// Target of all records, independent of the module layout
+const TARGET: &str = "ecommerce_api_client";
+
+/// A call being logged, from the first attempt to its outcome
+pub(crate) struct CallLog {
+    method: Method,
+    path: String,
+    request_id: String,
+}
+
+impl CallLog {
+    /// Log the start of a call at debug level, including its redacted body
+    pub(crate) fn start(method: &Method, request: Option<&reqwest::Request>, request_id: &str) -> Self {
+        let path = request.map_or("<streaming request>", |request| request.url().path());
+        if log::log_enabled!(target: TARGET, log::Level::Debug) {
+            let body = request
+                .and_then(|request| request.body())
+                .and_then(|body| body.as_bytes())
+                .map(describe_body)
+                .unwrap_or_default();
+            log::debug!(
+                target: TARGET,
+                method = method.as_str(),
+                path = path,
+                request_id = request_id,
+                body = body.as_str();
+                "sending {} {}", method, path
+            );
+        }
+        
+        Self {
+            method: method.clone(),
+            path: path.to_string(),
+            request_id: request_id.to_string(),
+        }
+    }
+    
+    /// Log the outcome of the call, at info level or as a warning if it failed
+    pub(crate) fn finish<T>(&self, result: &Result<ApiResponse<T>>, duration: Duration, attempts: u32) {
+        let duration_ms = duration.as_millis() as u64;
+        match result {
+            Ok(response) => log::info!(
+                target: TARGET,
+                method = self.method.as_str(),
+                path = self.path.as_str(),
+                request_id = self.request_id.as_str(),
+                status = response.status.map(|status| status.as_u16()),
+                duration_ms = duration_ms,
+                attempts = attempts;
+                "{} {} completed in {}ms", self.method, self.path, duration_ms
+            ),
+            Err(e) => {
+                let error = redact::redact_text(&e.message());
+                log::warn!(
+                    target: TARGET,
+                    method = self.method.as_str(),
+                    path = self.path.as_str(),
+                    request_id = self.request_id.as_str(),
+                    status = e.status_code(),
+                    duration_ms = duration_ms,
+                    attempts = attempts,
+                    error = error.as_str();
+                    "{} {} failed after {}ms: {}", self.method, self.path, duration_ms, error
+                )
+            }
+        }
+    }
+}
+
+/// Redacted JSON body, or its size for other formats
+fn describe_body(body: &[u8]) -> String {
+    match serde_json::from_slice(body) {
+        Ok(mut value) => {
+            redact::redact_json(&mut value);
+            value.to_string()
+        }
+        Err(_) => format!("<{} bytes>", body.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_describe_body() {
+        let body = br#"{"order_products":[{"addressbook":{"country":"US","phone":"555-0100"}}]}"#;
+        assert_eq!(
+            describe_body(body),
+            r#"{"order_products":[{"addressbook":{"country":"US","phone":"[REDACTED]"}}]}"#
+        );
+        assert_eq!(describe_body(b"<order/>"), "<8 bytes>");
+    }
+}
@@ -0,0 +1,131 @@
+//! Hooks for request metrics
+
+use crate::options::Endpoint;
+use reqwest::{Method, StatusCode};
+use std::time::Duration;
+
+/// A call about to be sent
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RequestStarted {
+    /// The endpoint called
+    pub endpoint: Endpoint,
+    /// HTTP method
+    pub method: Method,
+}
+
+/// A call that finished, after all of its retries
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RequestCompleted {
+    /// The endpoint called
+    pub endpoint: Endpoint,
+    /// HTTP method
+    pub method: Method,
+    /// Status of the last response, if one was received
+    pub status: Option<StatusCode>,
+    /// Whether the call succeeded
+    pub success: bool,
+    /// Time from the start of the first attempt to the final outcome
+    pub duration: Duration,
+    /// Number of retries after the first attempt
+    pub retries: u32,
+}
+
+/// Receives an event for every call the client makes
+///
+/// Implementations are called inline and should only record, e.g. by
+/// updating counters; anything slow belongs on another task.
+///
+/// ```
+/// use ecommerce_api_client::metrics::{MetricsObserver, RequestCompleted};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// #[derive(Default)]
+/// struct ErrorCounter(AtomicU64);
+///
+/// impl MetricsObserver for ErrorCounter {
+///     fn request_completed(&self, event: &RequestCompleted) {
+///         if !event.success {
+///             self.0.fetch_add(1, Ordering::Relaxed);
+///         }
+///     }
+/// }
+/// ```
+pub trait MetricsObserver: Send + Sync + 'static {
+    /// Called before the first attempt of a call
+    fn request_started(&self, _event: &RequestStarted) {}
+    
+    /// Called once the call has succeeded or finally failed
+    fn request_completed(&self, event: &RequestCompleted);
+}
+
+/// Reports to the [`metrics`](::metrics) facade, e.g. for a Prometheus exporter
+///
+/// Requires the `metrics` feature. Records:
+/// - `ecommerce_api_requests_total` counter, labelled by endpoint, method and status
+/// - `ecommerce_api_request_duration_seconds` histogram, labelled by endpoint and method
+/// - `ecommerce_api_retries_total` counter, labelled by endpoint
+/// - `ecommerce_api_requests_in_flight` gauge, labelled by endpoint
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsRecorder;
+
+#[cfg(feature = "metrics")]
+impl MetricsObserver for MetricsRecorder {
+    fn request_started(&self, event: &RequestStarted) {
+        ::metrics::gauge!("ecommerce_api_requests_in_flight", "endpoint" => event.endpoint.as_str())
+            .increment(1.0);
+    }
+    
+    fn request_completed(&self, event: &RequestCompleted) {
+        let endpoint = event.endpoint.as_str();
+        let status = match event.status {
+            Some(status) => status.as_u16().to_string(),
+            None => "error".to_string(),
+        };
+        
+        ::metrics::gauge!("ecommerce_api_requests_in_flight", "endpoint" => endpoint).decrement(1.0);
+        ::metrics::counter!(
+            "ecommerce_api_requests_total",
+            "endpoint" => endpoint,
+            "method" => event.method.to_string(),
+            "status" => status
+        )
+        .increment(1);
+        ::metrics::histogram!(
+            "ecommerce_api_request_duration_seconds",
+            "endpoint" => endpoint,
+            "method" => event.method.to_string()
+        )
+        .record(event.duration.as_secs_f64());
+        if event.retries > 0 {
+            ::metrics::counter!("ecommerce_api_retries_total", "endpoint" => endpoint)
+                .increment(u64::from(event.retries));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_recorder_without_exporter() {
+        // Without an installed recorder the facade discards everything
+        let recorder = MetricsRecorder;
+        recorder.request_started(&RequestStarted {
+            endpoint: Endpoint::CreateOrder,
+            method: Method::POST,
+        });
+        recorder.request_completed(&RequestCompleted {
+            endpoint: Endpoint::CreateOrder,
+            method: Method::POST,
+            status: None,
+            success: false,
+            duration: Duration::from_millis(250),
+            retries: 2,
+        });
+        assert_eq!(Endpoint::FollowLink.as_str(), "follow_link");
+    }
+}
@@ -0,0 +1,287 @@
+//! Per-request options
+
+use crate::error::{Error, Result};
+use crate::request_id::{self, REQUEST_ID};
+use crate::retry::IDEMPOTENCY_KEY;
+use crate::types::CreateOrderRequest;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Header carrying a caller-supplied trace id
+pub const TRACE_ID: HeaderName = HeaderName::from_static("x-trace-id");
+
+/// API endpoints that can be configured individually
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Endpoint {
+    /// `POST /api_customer/orders`
+    CreateOrder,
+    /// `GET /api_customer/orders`
+    ListOrders,
+    /// `GET /api_customer/orders/{id}`
+    GetOrder,
+    /// `DELETE /api_customer/orders/{id}`
+    CancelOrder,
+    /// `GET /api_customer/products`
+    ListProducts,
+    /// Export jobs under `/api_customer/exports`
+    Export,
+    /// `POST /api_customer/login`
+    Login,
+    /// Hypermedia links followed with [`Client::follow`](crate::Client::follow)
+    FollowLink,
+}
+
+impl Endpoint {
+    /// Every endpoint
+    pub(crate) const ALL: &'static [Endpoint] = &[
+        Endpoint::CreateOrder,
+        Endpoint::ListOrders,
+        Endpoint::GetOrder,
+        Endpoint::CancelOrder,
+        Endpoint::ListProducts,
+        Endpoint::Export,
+        Endpoint::Login,
+        Endpoint::FollowLink,
+    ];
+    
+    /// Stable snake_case name, e.g. for metric labels and audit records
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Endpoint::CreateOrder => "create_order",
+            Endpoint::ListOrders => "list_orders",
+            Endpoint::GetOrder => "get_order",
+            Endpoint::CancelOrder => "cancel_order",
+            Endpoint::ListProducts => "list_products",
+            Endpoint::Export => "export",
+            Endpoint::Login => "login",
+            Endpoint::FollowLink => "follow_link",
+        }
+    }
+}
+
+/// How order creation picks the `Idempotency-Key` it sends
+///
+/// A key set through [`RequestOptions::idempotency_key`] always takes
+/// precedence. The key sent is reported in [`ApiResponse::idempotency_key`](crate::ApiResponse::idempotency_key).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum IdempotencyKeys {
+    /// A random UUID per order, shared by all retries of the call
+    #[default]
+    Random,
+    /// `order-` followed by the SHA-256 of the customer order reference, so
+    /// that separate calls for the same order share a key; random for orders
+    /// without one
+    ///
+    /// The digest keeps the key a valid header value of bounded length,
+    /// whatever characters the reference contains.
+    FromReference,
+    /// Send no key unless one is set per request
+    Disabled,
+}
+
+impl IdempotencyKeys {
+    /// Key to send with `request`, if any
+    pub(crate) fn key_for(self, request: &CreateOrderRequest) -> Option<String> {
+        match (self, &request.customer_order_reference) {
+            (IdempotencyKeys::Disabled, _) => None,
+            (IdempotencyKeys::FromReference, Some(reference)) => {
+                let digest = Sha256::digest(reference.as_bytes());
+                let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+                Some(format!("order-{}", hex))
+            }
+            _ => Some(request_id::generate()),
+        }
+    }
+}
+
+/// One-off tweaks for a single call, without building a new [`Client`](crate::Client)
+///
+/// ```
+/// # use ecommerce_api_client::RequestOptions;
+/// # use std::time::Duration;
+/// # fn example() -> ecommerce_api_client::Result<()> {
+/// let options = RequestOptions::new()
+///     .timeout(Duration::from_secs(120))
+///     .idempotency_key("import-2024-06-01-0042")?
+///     .trace_id("batch-7f3a")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    headers: HeaderMap,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl RequestOptions {
+    /// Options that leave the request unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a header to this request, replacing any default value
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+    
+    /// Override the client's and the endpoint's timeout for this request
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    
+    /// Bound the whole call, including retries and backoff, to `deadline`
+    ///
+    /// Overrides the client's deadline. The call then fails with
+    /// [`Error::DeadlineExceeded`](crate::Error::DeadlineExceeded).
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    
+    /// Attach an `Idempotency-Key`, which also makes the request safe to retry
+    ///
+    /// Fails with [`Error::Config`] if the key is not a valid header value,
+    /// e.g. contains a line break, rather than sending the request without it.
+    pub fn idempotency_key(self, key: impl AsRef<str>) -> Result<Self> {
+        let value = HeaderValue::from_str(key.as_ref())
+            .map_err(|e| Error::Config(format!("Invalid idempotency key: {}", e)))?;
+        Ok(self.header(IDEMPOTENCY_KEY, value))
+    }
+    
+    /// Tag the request with a trace id sent as `X-Trace-Id`
+    ///
+    /// Fails with [`Error::Config`] if the id is not a valid header value.
+    pub fn trace_id(self, id: impl AsRef<str>) -> Result<Self> {
+        let value = HeaderValue::from_str(id.as_ref())
+            .map_err(|e| Error::Config(format!("Invalid trace id: {}", e)))?;
+        Ok(self.header(TRACE_ID, value))
+    }
+    
+    /// Send `id` as `X-Request-Id` instead of a generated id
+    ///
+    /// Fails with [`Error::Config`] if the id is not a valid header value.
+    pub fn request_id(self, id: impl AsRef<str>) -> Result<Self> {
+        let value = HeaderValue::from_str(id.as_ref())
+            .map_err(|e| Error::Config(format!("Invalid request id: {}", e)))?;
+        Ok(self.header(REQUEST_ID, value))
+    }
+    
+    /// Abort the call, including pending retries, once the token is cancelled
+    ///
+    /// The call then fails with [`Error::Cancelled`](crate::Error::Cancelled).
+    /// Dropping the returned future also cancels the request.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+    
+    /// Whether the options leave the request unchanged
+    pub(crate) fn is_empty(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.timeout.is_some() {
+            return false;
+        }
+        self.headers.is_empty() && self.deadline.is_none() && self.cancellation.is_none()
+    }
+    
+    /// Token cancelling this request, if any
+    pub(crate) fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+    
+    /// Deadline for the whole call, if set
+    pub(crate) fn deadline_budget(&self) -> Option<Duration> {
+        self.deadline
+    }
+    
+    /// Request id supplied by the caller, if any
+    pub(crate) fn request_id_header(&self) -> Option<&str> {
+        self.headers.get(REQUEST_ID).and_then(|value| value.to_str().ok())
+    }
+    
+    /// Idempotency key supplied by the caller, if any
+    pub(crate) fn idempotency_key_header(&self) -> Option<&str> {
+        self.headers.get(IDEMPOTENCY_KEY).and_then(|value| value.to_str().ok())
+    }
+    
+    /// Apply the options to a request
+    pub(crate) fn apply(&self, mut req_builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if !self.headers.is_empty() {
+            req_builder = req_builder.headers(self.headers.clone());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.timeout {
+            req_builder = req_builder.timeout(timeout);
+        }
+        req_builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_apply() {
+        let options = RequestOptions::new()
+            .header(HeaderName::from_static("x-tenant"), HeaderValue::from_static("acme"))
+            .idempotency_key("key-1")
+            .unwrap()
+            .trace_id("trace-1")
+            .unwrap()
+            .timeout(Duration::from_secs(3));
+        
+        let request = options
+            .apply(reqwest::Client::new().post("https://api.example.com/api_customer/orders"))
+            .build()
+            .unwrap();
+        
+        assert_eq!(request.headers()["x-tenant"], "acme");
+        assert_eq!(request.headers()[IDEMPOTENCY_KEY], "key-1");
+        assert_eq!(request.headers()[TRACE_ID], "trace-1");
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(3)));
+        assert!(crate::retry::is_idempotent(&request));
+    }
+    
+    #[test]
+    fn test_idempotency_keys() {
+        let request = CreateOrderRequest {
+            customer_order_reference: Some("ORDER-1".to_string()),
+            ..Default::default()
+        };
+        let key = IdempotencyKeys::FromReference.key_for(&request).unwrap();
+        assert_eq!(key, "order-c1d05a837876d68892cc978153fac336b25eb84ec265c67a9560c793a2da1d4e");
+        assert_eq!(IdempotencyKeys::Random.key_for(&request).unwrap().len(), 36);
+        assert_eq!(IdempotencyKeys::Disabled.key_for(&request), None);
+        assert_eq!(IdempotencyKeys::FromReference.key_for(&CreateOrderRequest::default()).unwrap().len(), 36);
+        
+        // References that are not valid header values still get a key
+        let request = CreateOrderRequest {
+            customer_order_reference: Some("Bestellung-Ä1".to_string()),
+            ..Default::default()
+        };
+        let key = IdempotencyKeys::FromReference.key_for(&request).unwrap();
+        assert_eq!(key.len(), "order-".len() + 64);
+        assert!(RequestOptions::new().idempotency_key(&key).is_ok());
+        assert_eq!(IdempotencyKeys::FromReference.key_for(&request), Some(key));
+    }
+    
+    #[test]
+    fn test_invalid_values_rejected() {
+        let error = RequestOptions::new().idempotency_key("order\r\n1").unwrap_err();
+        assert!(matches!(error, Error::Config(_)));
+        assert!(RequestOptions::new().request_id("ticket\n1").is_err());
+        let error = RequestOptions::new().trace_id("bad\nid").unwrap_err();
+        assert!(matches!(error, Error::Config(_)));
+    }
+}
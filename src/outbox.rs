@@ -0,0 +1,453 @@
+//! Durable queue of orders waiting to be sent
+//!
+//! Sites with unreliable connectivity enqueue orders in an [`Outbox`]
+//! instead of creating them directly. Each order is stored together with
+//! its idempotency key before anything is sent; [`Outbox::drain`] or
+//! [`Outbox::run`] then sends the queue once the API is reachable. Every
+//! order is created at least once, and the key keeps retries from creating
+//! it twice.
+
+use crate::client::Client;
+use crate::error::{Error, ErrorKind, Result};
+use crate::options::RequestOptions;
+use crate::types::{CreateOrderRequest, CreateOrderResponse};
+use crate::{request_id, runtime};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+
+/// An order in the outbox
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct OutboxEntry {
+    /// Id of the entry, also sent as the order's `Idempotency-Key`
+    pub id: String,
+    /// The order to create
+    pub request: CreateOrderRequest,
+    /// When the order was enqueued
+    pub enqueued_at: SystemTime,
+    /// Number of times sending was attempted
+    #[serde(default)]
+    pub attempts: u32,
+    /// Error of the last attempt
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// Whether the API rejected the order or sending it failed too often;
+    /// it is then no longer sent
+    #[serde(default)]
+    pub rejected: bool,
+}
+
+/// Persists the entries of an [`Outbox`]
+///
+/// Unlike other stores, failures are reported, since an order that cannot
+/// be stored must not be considered queued.
+#[async_trait]
+pub trait OutboxStore: Send + Sync + 'static {
+    /// Insert `entry`, or replace the entry with the same id
+    async fn put(&self, entry: &OutboxEntry) -> io::Result<()>;
+    
+    /// All entries, oldest first
+    async fn entries(&self) -> io::Result<Vec<OutboxEntry>>;
+    
+    /// Remove the entry with `id`, if present
+    async fn remove(&self, id: &str) -> io::Result<()>;
+}
+
+#[async_trait]
+impl<S: OutboxStore> OutboxStore for Arc<S> {
+    async fn put(&self, entry: &OutboxEntry) -> io::Result<()> {
+        (**self).put(entry).await
+    }
+    
+    async fn entries(&self) -> io::Result<Vec<OutboxEntry>> {
+        (**self).entries().await
+    }
+    
+    async fn remove(&self, id: &str) -> io::Result<()> {
+        (**self).remove(id).await
+    }
+}
+
+/// Keeps entries in memory, e.g. for tests
+#[derive(Debug, Default)]
+pub struct MemoryOutboxStore {
+    entries: Mutex<Vec<OutboxEntry>>,
+}
+
+impl MemoryOutboxStore {
+    /// An empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<OutboxEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl OutboxStore for MemoryOutboxStore {
+    async fn put(&self, entry: &OutboxEntry) -> io::Result<()> {
+        let mut entries = self.lock();
+        match entries.iter_mut().find(|stored| stored.id == entry.id) {
+            Some(stored) => *stored = entry.clone(),
+            None => entries.push(entry.clone()),
+        }
+        Ok(())
+    }
+    
+    async fn entries(&self) -> io::Result<Vec<OutboxEntry>> {
+        Ok(self.lock().clone())
+    }
+    
+    async fn remove(&self, id: &str) -> io::Result<()> {
+        self.lock().retain(|entry| entry.id != id);
+        Ok(())
+    }
+}
+
+/// Keeps each entry as a JSON file in a directory
+///
+/// Each entry is synced to disk before [`OutboxStore::put`] returns, and a
+/// crash while saving leaves its previous version intact.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct FileOutboxStore {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileOutboxStore {
+    /// Store entries in `dir`, creating it if needed
+    pub fn open(dir: impl Into<std::path::PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+    
+    /// File of the entry with `id`
+    fn path(&self, id: &str) -> std::path::PathBuf {
+        let name: String = id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl OutboxStore for FileOutboxStore {
+    async fn put(&self, entry: &OutboxEntry) -> io::Result<()> {
+        crate::fs::write_atomic(&self.path(&entry.id), &serde_json::to_vec(entry)?)
+    }
+    
+    async fn entries(&self) -> io::Result<Vec<OutboxEntry>> {
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                entries.push(serde_json::from_slice::<OutboxEntry>(&std::fs::read(&path)?)?);
+            }
+        }
+        entries.sort_by(|a, b| (a.enqueued_at, &a.id).cmp(&(b.enqueued_at, &b.id)));
+        Ok(entries)
+    }
+    
+    async fn remove(&self, id: &str) -> io::Result<()> {
+        match std::fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps entries in a [sled](https://docs.rs/sled) tree
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledOutboxStore {
+    tree: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledOutboxStore {
+    /// Store entries in `tree`, e.g. `db.open_tree("outbox")`
+    pub fn new(tree: sled::Tree) -> Self {
+        Self { tree }
+    }
+}
+
+#[cfg(feature = "sled")]
+#[async_trait]
+impl OutboxStore for SledOutboxStore {
+    async fn put(&self, entry: &OutboxEntry) -> io::Result<()> {
+        self.tree.insert(entry.id.as_bytes(), serde_json::to_vec(entry)?)?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+    
+    async fn entries(&self) -> io::Result<Vec<OutboxEntry>> {
+        let mut entries = Vec::new();
+        for item in self.tree.iter() {
+            let (_, value) = item?;
+            entries.push(serde_json::from_slice::<OutboxEntry>(&value)?);
+        }
+        entries.sort_by(|a, b| (a.enqueued_at, &a.id).cmp(&(b.enqueued_at, &b.id)));
+        Ok(entries)
+    }
+    
+    async fn remove(&self, id: &str) -> io::Result<()> {
+        self.tree.remove(id.as_bytes())?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+}
+
+/// Result of sending the queued orders once
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct DrainReport {
+    /// Orders created and removed from the outbox
+    pub created: Vec<CreateOrderResponse>,
+    /// Orders the API rejected or that failed [`Outbox::max_attempts`]
+    /// times; they stay in the outbox until discarded
+    pub rejected: Vec<OutboxEntry>,
+    /// Orders that failed for another reason than the API being unreachable,
+    /// skipped to be tried again on the next drain
+    pub failed: Vec<OutboxEntry>,
+    /// Orders left queued because the API could not be reached
+    pub pending: usize,
+}
+
+/// A durable queue of orders, sent with retries once the API is reachable
+///
+/// ```no_run
+/// # use ecommerce_api_client::Client;
+/// # use ecommerce_api_client::outbox::{FileOutboxStore, Outbox};
+/// # use ecommerce_api_client::types::CreateOrderRequest;
+/// # async fn example(client: Client, request: CreateOrderRequest) -> Result<(), Box<dyn std::error::Error>> {
+/// let outbox = Outbox::new(client, FileOutboxStore::open("outbox")?);
+/// outbox.enqueue(request).await?;
+/// let report = outbox.drain().await?;
+/// println!("{} created, {} still queued", report.created.len(), report.pending);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Outbox {
+    client: Client,
+    store: Arc<dyn OutboxStore>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    idle_interval: Duration,
+    max_attempts: u32,
+}
+
+impl Outbox {
+    /// A queue of orders for `client`, persisted in `store`
+    pub fn new(client: Client, store: impl OutboxStore) -> Self {
+        Self {
+            client,
+            store: Arc::new(store),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+            idle_interval: Duration::from_secs(30),
+            max_attempts: 5,
+        }
+    }
+    
+    /// Wait `initial` after a failed drain in [`Outbox::run`], doubling up to `max`
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+    
+    /// Drain every `interval` in [`Outbox::run`] while the API is reachable
+    pub fn idle_interval(mut self, interval: Duration) -> Self {
+        self.idle_interval = interval;
+        self
+    }
+    
+    /// Set aside an order that failed this many times, other than for the API
+    /// being unreachable, as rejected (default 5)
+    ///
+    /// Such failures, e.g. a `404` from a misrouted endpoint or a response
+    /// that cannot be decoded, are skipped so they do not hold up the
+    /// orders behind them.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+    
+    /// Store `request` for sending, returning its entry id
+    ///
+    /// The order is durably queued once this returns.
+    pub async fn enqueue(&self, request: CreateOrderRequest) -> Result<String> {
+        let entry = OutboxEntry {
+            id: request_id::generate(),
+            request,
+            enqueued_at: runtime::system_now(),
+            attempts: 0,
+            last_error: None,
+            rejected: false,
+        };
+        self.store.put(&entry).await.map_err(Error::Storage)?;
+        Ok(entry.id)
+    }
+    
+    /// Orders still to be sent, oldest first
+    pub async fn pending(&self) -> Result<Vec<OutboxEntry>> {
+        let entries = self.store.entries().await.map_err(Error::Storage)?;
+        Ok(entries.into_iter().filter(|entry| !entry.rejected).collect())
+    }
+    
+    /// Orders the API rejected, oldest first
+    pub async fn rejected(&self) -> Result<Vec<OutboxEntry>> {
+        let entries = self.store.entries().await.map_err(Error::Storage)?;
+        Ok(entries.into_iter().filter(|entry| entry.rejected).collect())
+    }
+    
+    /// Remove the entry with `id`, e.g. a rejected order that was handled
+    pub async fn discard(&self, id: &str) -> Result<()> {
+        self.store.remove(id).await.map_err(Error::Storage)
+    }
+    
+    /// Send the queued orders, oldest first
+    ///
+    /// Stops at the first order that fails because the API is unreachable,
+    /// overloaded or too slow; that order and the ones after it stay queued.
+    /// Orders the API rejects are set aside, and those failing for any other
+    /// reason are skipped until they have failed [`max_attempts`](Self::max_attempts)
+    /// times. Fails only if the store does.
+    pub async fn drain(&self) -> Result<DrainReport> {
+        let mut report = DrainReport::default();
+        let pending = self.pending().await?;
+        let total = pending.len();
+        for (sent, mut entry) in pending.into_iter().enumerate() {
+            let result = match RequestOptions::new().idempotency_key(&entry.id) {
+                Ok(options) => self.client.create_order_with(entry.request.clone(), &options).await,
+                Err(e) => Err(e),
+            };
+            entry.attempts += 1;
+            match result {
+                Ok(response) => {
+                    self.store.remove(&entry.id).await.map_err(Error::Storage)?;
+                    report.created.push(response.into_inner());
+                }
+                Err(e) => {
+                    let kind = e.kind();
+                    let unreachable = matches!(
+                        kind,
+                        ErrorKind::Network
+                            | ErrorKind::Timeout
+                            | ErrorKind::RateLimited
+                            | ErrorKind::ServerTransient
+                            | ErrorKind::Cancelled
+                    );
+                    entry.last_error = Some(e.message());
+                    entry.rejected = kind == ErrorKind::Validation || (!unreachable && entry.attempts >= self.max_attempts);
+                    self.store.put(&entry).await.map_err(Error::Storage)?;
+                    if entry.rejected {
+                        report.rejected.push(entry);
+                    } else if unreachable {
+                        report.pending = total - sent;
+                        break;
+                    } else {
+                        report.failed.push(entry);
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+    
+    /// Drain the outbox until `shutdown` is cancelled
+    ///
+    /// Drains every idle interval, and with exponential backoff while the
+    /// API cannot be reached. Orders enqueued meanwhile are picked up on the
+    /// next drain. Fails only if the store does.
+    pub async fn run(&self, shutdown: CancellationToken) -> Result<()> {
+        let mut backoff = self.initial_backoff;
+        while !shutdown.is_cancelled() {
+            let report = self.drain().await?;
+            let delay = if report.pending > 0 {
+                let delay = backoff;
+                backoff = backoff.saturating_mul(2).min(self.max_backoff);
+                delay
+            } else {
+                backoff = self.initial_backoff;
+                self.idle_interval
+            };
+            tokio::select! {
+                _ = shutdown.cancelled() => {}
+                _ = runtime::sleep(delay) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Outbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Outbox")
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("idle_interval", &self.idle_interval)
+            .field("max_attempts", &self.max_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn entry(id: &str, seconds: u64) -> OutboxEntry {
+        OutboxEntry {
+            id: id.to_string(),
+            request: CreateOrderRequest::default(),
+            enqueued_at: SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+            attempts: 0,
+            last_error: None,
+            rejected: false,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_file_store() {
+        let dir = std::env::temp_dir().join(format!("ecommerce-api-outbox-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = FileOutboxStore::open(&dir).unwrap();
+        
+        store.put(&entry("b", 2)).await.unwrap();
+        store.put(&entry("a", 3)).await.unwrap();
+        let mut retried = entry("b", 2);
+        retried.attempts = 1;
+        store.put(&retried).await.unwrap();
+        
+        let entries = store.entries().await.unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.id.as_str()).collect::<Vec<_>>(), ["b", "a"]);
+        assert_eq!(entries[0].attempts, 1);
+        store.remove("b").await.unwrap();
+        store.remove("b").await.unwrap();
+        assert_eq!(store.entries().await.unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[cfg(feature = "sled")]
+    #[tokio::test]
+    async fn test_sled_store() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let store = SledOutboxStore::new(db.open_tree("outbox").unwrap());
+        store.put(&entry("b", 2)).await.unwrap();
+        store.put(&entry("a", 3)).await.unwrap();
+        store.remove("a").await.unwrap();
+        assert_eq!(store.entries().await.unwrap(), [entry("b", 2)]);
+    }
+}
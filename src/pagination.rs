@@ -0,0 +1,519 @@
+//! Pagination of list endpoints, for both `page`/`per_page` and cursor styles
+
+use crate::checkpoint::{Checkpoint, CheckpointStore};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::options::Endpoint;
+use crate::progress::{Phase, ProgressObserver, ProgressTracker};
+use crate::response::ApiResponse;
+use crate::runtime::Instant;
+use futures_util::future::{self, Either};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Page size used unless another one is requested
+pub const DEFAULT_PER_PAGE: u64 = 50;
+
+/// Which page of a list endpoint to fetch
+///
+/// A `PageRequest` is also the resume point of a listing: persist
+/// [`Page::next`] after handling a page and pass it back as the first
+/// request to continue an interrupted sync.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PageRequest {
+    /// Offset pagination with `page` and `per_page`; pages are numbered from 1
+    Offset {
+        /// Page number
+        page: u64,
+        /// Items per page
+        per_page: u64,
+    },
+    /// Cursor pagination with `cursor` and `limit`
+    Cursor {
+        /// Cursor returned with the previous page, unset for the first page
+        cursor: Option<String>,
+        /// Items per page
+        limit: u64,
+    },
+}
+
+impl PageRequest {
+    /// First page of an offset-paginated listing
+    pub fn first_page(per_page: u64) -> Self {
+        PageRequest::Offset { page: 1, per_page }
+    }
+    
+    /// First page of a cursor-paginated listing
+    pub fn first_cursor(limit: u64) -> Self {
+        PageRequest::Cursor { cursor: None, limit }
+    }
+    
+    /// Query parameters selecting this page
+    pub(crate) fn query(&self) -> Vec<(&'static str, String)> {
+        match self {
+            PageRequest::Offset { page, per_page } => {
+                vec![("page", page.to_string()), ("per_page", per_page.to_string())]
+            }
+            PageRequest::Cursor { cursor, limit } => {
+                let mut query = vec![("limit", limit.to_string())];
+                if let Some(cursor) = cursor {
+                    query.push(("cursor", cursor.clone()));
+                }
+                query
+            }
+        }
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::first_page(DEFAULT_PER_PAGE)
+    }
+}
+
+/// Safety limits for collecting a whole listing into memory
+///
+/// Exceeding any of them fails the listing with
+/// [`Error::ListingTooLarge`] rather than returning a partial result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListLimits {
+    /// Items requested per page
+    pub per_page: u64,
+    /// Maximum number of pages to fetch
+    pub max_pages: u64,
+    /// Maximum number of items to collect
+    pub max_items: usize,
+    /// Maximum time to spend fetching pages
+    pub max_elapsed: Duration,
+}
+
+impl Default for ListLimits {
+    fn default() -> Self {
+        Self {
+            per_page: DEFAULT_PER_PAGE,
+            max_pages: 200,
+            max_items: 10_000,
+            max_elapsed: Duration::from_secs(300),
+        }
+    }
+}
+
+/// One page of a list endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Page<T> {
+    /// Items on this page
+    pub items: Vec<T>,
+    /// Total number of items, if the server reported it
+    pub total: Option<u64>,
+    /// Number of this page, for offset pagination
+    pub page: Option<u64>,
+    /// Cursor of the following page, for cursor pagination
+    pub next_cursor: Option<String>,
+    /// Request for the following page, unset on the last page
+    pub next: Option<PageRequest>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from a decoded list response
+    ///
+    /// Offset pages use `total_pages` or `total` from the envelope metadata
+    /// and otherwise assume more pages follow a full one. Cursor pages end
+    /// when no `next_cursor` is returned, either in the metadata or as the
+    /// `cursor` parameter of a `next` link.
+    pub(crate) fn from_response(response: ApiResponse<Vec<T>>, request: &PageRequest) -> Self {
+        let meta = response.meta.unwrap_or_default();
+        let items = response.data;
+        let next_link = response.links.as_ref().and_then(|links| links.get("next"));
+        
+        let (page, next_cursor, next) = match request {
+            PageRequest::Offset { page, per_page } => {
+                let page = meta.page.unwrap_or(*page);
+                let per_page = meta.per_page.unwrap_or(*per_page);
+                let has_more = match (meta.total_pages, meta.total) {
+                    (Some(total_pages), _) => page < total_pages,
+                    (None, Some(total)) => page.saturating_mul(per_page) < total,
+                    (None, None) => next_link.is_some() || items.len() as u64 >= per_page,
+                };
+                let next = (has_more && !items.is_empty()).then_some(PageRequest::Offset {
+                    page: page + 1,
+                    per_page,
+                });
+                (Some(page), None, next)
+            }
+            PageRequest::Cursor { limit, .. } => {
+                let next_cursor = meta
+                    .extra
+                    .get("next_cursor")
+                    .and_then(|cursor| cursor.as_str())
+                    .map(str::to_string)
+                    .or_else(|| next_link.and_then(|link| link_cursor(&link.href)))
+                    .filter(|cursor| !cursor.is_empty());
+                let next = next_cursor
+                    .clone()
+                    .filter(|_| !items.is_empty())
+                    .map(|cursor| PageRequest::Cursor {
+                        cursor: Some(cursor),
+                        limit: *limit,
+                    });
+                (None, next_cursor, next)
+            }
+        };
+        
+        Self {
+            items,
+            total: meta.total,
+            page,
+            next_cursor,
+            next,
+        }
+    }
+    
+    /// Whether this is the last page
+    pub fn is_last(&self) -> bool {
+        self.next.is_none()
+    }
+}
+
+impl<T> IntoIterator for Page<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+/// `cursor` query parameter of a link
+fn link_cursor(href: &str) -> Option<String> {
+    let url = url::Url::parse(href)
+        .or_else(|_| url::Url::parse("http://localhost").and_then(|base| base.join(href)))
+        .ok()?;
+    url.query_pairs()
+        .find(|(name, _)| name == "cursor")
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Fetches the pages of a list endpoint one after another
+///
+/// ```no_run
+/// # use ecommerce_api_client::Client;
+/// # use ecommerce_api_client::{OrderFilter, PageRequest};
+/// # async fn example(client: Client) -> ecommerce_api_client::Result<()> {
+/// let mut pages = client.order_pages(&OrderFilter::new(), PageRequest::first_page(100));
+/// while let Some(page) = pages.next_page().await? {
+///     for order in page {
+///         println!("{}", order.customer_order_reference);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Paginator<T> {
+    client: Client,
+    endpoint: Endpoint,
+    path: &'static str,
+    query: Vec<(String, String)>,
+    next: Option<PageRequest>,
+    pages: u64,
+    prefetch: usize,
+    items: u64,
+    progress: Option<ProgressTracker>,
+    checkpoint: Option<(Arc<dyn CheckpointStore>, String)>,
+    restored: bool,
+    _items: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Paginator<T> {
+    /// Page through `path`, starting at `first`
+    pub(crate) fn new(
+        client: Client,
+        endpoint: Endpoint,
+        path: &'static str,
+        query: Vec<(String, String)>,
+        first: PageRequest,
+    ) -> Self {
+        Self {
+            client,
+            endpoint,
+            path,
+            query,
+            next: Some(first),
+            pages: 0,
+            prefetch: 1,
+            items: 0,
+            progress: None,
+            checkpoint: None,
+            restored: false,
+            _items: PhantomData,
+        }
+    }
+    
+    /// Let the streams fetch up to `pages` offset pages concurrently
+    ///
+    /// Pages are still yielded in order. Near the end of the listing up to
+    /// `pages - 1` requests past the last page may be sent and discarded.
+    /// Cursor pagination needs each page's cursor first and is always
+    /// fetched one page at a time.
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.prefetch = pages.max(1);
+        self
+    }
+    
+    /// Report the number of items fetched to `observer` after every page
+    ///
+    /// The total is known once the server reports one in the page metadata.
+    pub fn progress(mut self, observer: impl ProgressObserver) -> Self {
+        self.progress = Some(ProgressTracker::start(Arc::new(observer), Phase::Fetching, None));
+        self
+    }
+    
+    /// Resume from the checkpoint saved in `store` under `key`, and keep it up to date
+    ///
+    /// Asking for a page saves the position after the previous one, so a
+    /// resumed listing starts with the first page that was not fully
+    /// handled. The checkpoint is cleared once the last page was handled.
+    /// Pages are then fetched one at a time, ignoring [`Paginator::prefetch`].
+    pub fn checkpoint(mut self, store: impl CheckpointStore, key: impl Into<String>) -> Self {
+        self.checkpoint = Some((Arc::new(store), key.into()));
+        self
+    }
+    
+    /// Fetch the next page, or `None` after the last one
+    ///
+    /// A failed fetch can be retried by calling this again, and so can a
+    /// checkpoint that could not be loaded, saved or cleared, which fails
+    /// with [`Error::Storage`](crate::Error::Storage).
+    pub async fn next_page(&mut self) -> Result<Option<Page<T>>> {
+        if let Some((store, key)) = &self.checkpoint {
+            if !self.restored {
+                let checkpoint = store.load(key).await.map_err(Error::Storage)?;
+                self.restored = true;
+                if let Some(Checkpoint { next_page: Some(next), items, .. }) = checkpoint {
+                    self.next = Some(next);
+                    self.items = items;
+                }
+            } else if self.pages > 0 {
+                match &self.next {
+                    Some(next) => {
+                        let checkpoint = Checkpoint {
+                            next_page: Some(next.clone()),
+                            items: self.items,
+                            ..Default::default()
+                        };
+                        store.save(key, &checkpoint).await.map_err(Error::Storage)?;
+                    }
+                    None => {
+                        store.clear(key).await.map_err(Error::Storage)?;
+                        self.checkpoint = None;
+                    }
+                }
+            }
+        }
+        let Some(request) = &self.next else {
+            return Ok(None);
+        };
+        let page = self
+            .client
+            .fetch_page(self.endpoint, self.path, &self.query, request)
+            .await?;
+        self.next = page.next.clone();
+        self.pages += 1;
+        self.items += page.items.len() as u64;
+        if let Some(progress) = &mut self.progress {
+            progress.set_total(page.total);
+            progress.report(self.items);
+        }
+        Ok(Some(page))
+    }
+    
+    /// The request for the next page, unset once all pages were fetched
+    pub fn next_request(&self) -> Option<&PageRequest> {
+        self.next.as_ref()
+    }
+    
+    /// Number of pages fetched so far
+    pub fn pages_fetched(&self) -> u64 {
+        self.pages
+    }
+    
+    /// Collect the items of all remaining pages within `limits`
+    ///
+    /// `limits.per_page` is ignored; it only applies to the first page
+    /// requested when the paginator is created.
+    pub async fn collect_all(mut self, limits: ListLimits) -> Result<Vec<T>> {
+        let started = Instant::now();
+        let mut items = Vec::new();
+        while self.next.is_some() {
+            let exceeded = self.pages >= limits.max_pages || started.elapsed() > limits.max_elapsed;
+            if exceeded {
+                return Err(self.too_large(items.len(), started));
+            }
+            if let Some(page) = self.next_page().await? {
+                items.extend(page.items);
+            }
+            if items.len() > limits.max_items {
+                return Err(self.too_large(items.len(), started));
+            }
+        }
+        Ok(items)
+    }
+    
+    fn too_large(&self, items: usize, started: Instant) -> Error {
+        Error::ListingTooLarge {
+            pages: self.pages,
+            items,
+            elapsed: started.elapsed(),
+        }
+    }
+    
+    /// The remaining pages, fetched lazily one after another
+    ///
+    /// Each page's [`Page::next`] is the point to resume from once its items
+    /// are handled. The stream ends after yielding the first error.
+    pub fn into_page_stream(self) -> impl Stream<Item = Result<Page<T>>> {
+        match self.next {
+            Some(PageRequest::Offset { page, per_page }) if self.prefetch > 1 && self.checkpoint.is_none() => {
+                Either::Left(self.prefetched(page, per_page))
+            }
+            _ => Either::Right(stream::unfold(Some(self), |state| async move {
+                let mut pages = state?;
+                match pages.next_page().await {
+                    Ok(Some(page)) => Some((Ok(page), Some(pages))),
+                    Ok(None) => None,
+                    Err(e) => Some((Err(e), None)),
+                }
+            })),
+        }
+    }
+    
+    /// Offset pages from `first` on, with up to `prefetch` requests in flight
+    fn prefetched(self, first: u64, per_page: u64) -> impl Stream<Item = Result<Page<T>>> {
+        let Self {
+            client,
+            endpoint,
+            path,
+            query,
+            prefetch,
+            mut items,
+            mut progress,
+            ..
+        } = self;
+        let query = Arc::new(query);
+        stream::iter(first..)
+            .map(move |page| {
+                let (client, query) = (client.clone(), query.clone());
+                async move {
+                    let request = PageRequest::Offset { page, per_page };
+                    client.fetch_page(endpoint, path, &query, &request).await
+                }
+            })
+            .buffered(prefetch)
+            .scan(false, move |done, page| {
+                if *done {
+                    return future::ready(None);
+                }
+                *done = page.as_ref().map_or(true, Page::is_last);
+                if let (Ok(page), Some(progress)) = (&page, &mut progress) {
+                    items += page.items.len() as u64;
+                    progress.set_total(page.total);
+                    progress.report(items);
+                }
+                future::ready(Some(page))
+            })
+    }
+    
+    /// The items of all remaining pages, fetched lazily page by page
+    ///
+    /// The stream ends after yielding the first error.
+    pub fn into_stream(self) -> impl Stream<Item = Result<T>> {
+        self.into_page_stream().flat_map(|page| {
+            let items: Vec<Result<T>> = match page {
+                Ok(page) => page.items.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(items)
+        })
+    }
+}
+
+impl<T> fmt::Debug for Paginator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Paginator")
+            .field("path", &self.path)
+            .field("next", &self.next)
+            .field("pages", &self.pages)
+            .field("prefetch", &self.prefetch)
+            .field("checkpoint", &self.checkpoint.as_ref().map(|(_, key)| key))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::Meta;
+    
+    fn response(items: Vec<u32>, meta: Option<Meta>) -> ApiResponse<Vec<u32>> {
+        let mut response = ApiResponse::new(items);
+        response.meta = meta;
+        response
+    }
+    
+    #[test]
+    fn test_offset_pages() {
+        let meta = Meta {
+            page: Some(2),
+            per_page: Some(2),
+            total: Some(5),
+            ..Default::default()
+        };
+        let page = Page::from_response(response(vec![3, 4], Some(meta)), &PageRequest::first_page(2));
+        assert_eq!(page.total, Some(5));
+        assert_eq!(page.next, Some(PageRequest::Offset { page: 3, per_page: 2 }));
+        
+        let last = Page::from_response(response(vec![5], None), &PageRequest::Offset { page: 3, per_page: 2 });
+        assert!(last.is_last());
+        assert_eq!(last.page, Some(3));
+        assert_eq!(PageRequest::first_page(2).query(), vec![("page", "1".to_string()), ("per_page", "2".to_string())]);
+    }
+    
+    #[test]
+    fn test_cursor_pages() {
+        let mut meta = Meta::default();
+        meta.extra.insert("next_cursor".to_string(), "c2".into());
+        let page = Page::from_response(response(vec![1, 2], Some(meta)), &PageRequest::first_cursor(2));
+        assert_eq!(page.next_cursor.as_deref(), Some("c2"));
+        assert_eq!(page.next.as_ref().unwrap().query(), vec![("limit", "2".to_string()), ("cursor", "c2".to_string())]);
+        
+        let mut linked = response(vec![3], None);
+        let mut links = crate::types::Links::default();
+        links.other.insert("next".to_string(), crate::types::Link::new("/api_customer/orders?cursor=c3&limit=2"));
+        linked.links = Some(links);
+        let page = Page::from_response(linked, &PageRequest::first_cursor(2));
+        assert_eq!(page.next_cursor.as_deref(), Some("c3"));
+        
+        let last = Page::from_response(response(vec![4], None), &PageRequest::first_cursor(2));
+        assert!(last.is_last());
+    }
+    
+    #[test]
+    fn test_resume_point_serde() {
+        let cursor = PageRequest::Cursor {
+            cursor: Some("c2".to_string()),
+            limit: 100,
+        };
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(json, r#"{"type":"cursor","cursor":"c2","limit":100}"#);
+        assert_eq!(serde_json::from_str::<PageRequest>(&json).unwrap(), cursor);
+        
+        let offset: PageRequest = serde_json::from_str(r#"{"type":"offset","page":7,"per_page":50}"#).unwrap();
+        assert_eq!(offset, PageRequest::Offset { page: 7, per_page: 50 });
+    }
+}
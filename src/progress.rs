@@ -0,0 +1,148 @@
+//! Progress reporting for long-running operations
+
+use crate::runtime::Instant;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What a long-running operation is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Phase {
+    /// Submitting a batch of requests; items are requests
+    Submitting,
+    /// Fetching the pages of a listing; items are listed items
+    Fetching,
+    /// Waiting for the server to finish a job; items are percent
+    Waiting,
+    /// Downloading a file; items are bytes
+    Downloading,
+}
+
+/// A progress update
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Progress {
+    /// Current phase
+    pub phase: Phase,
+    /// Items done so far in this phase
+    pub done: u64,
+    /// Items in this phase, if known
+    pub total: Option<u64>,
+    /// Time since the phase started
+    pub elapsed: Duration,
+    /// Estimated time until the phase ends, if the total is known
+    pub eta: Option<Duration>,
+}
+
+/// Receives progress updates from bulk submissions, listings and exports
+///
+/// Implementations are called inline and should return quickly. Closures
+/// taking a `&Progress` implement this trait.
+///
+/// ```
+/// # use ecommerce_api_client::BulkOptions;
+/// # use ecommerce_api_client::progress::Progress;
+/// let options = BulkOptions::new().progress(|progress: &Progress| {
+///     println!("{:?}: {}/{:?}, eta {:?}", progress.phase, progress.done, progress.total, progress.eta);
+/// });
+/// ```
+pub trait ProgressObserver: Send + Sync + 'static {
+    /// Called whenever more items are done
+    fn progress(&self, progress: &Progress);
+}
+
+impl<F: Fn(&Progress) + Send + Sync + 'static> ProgressObserver for F {
+    fn progress(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Reports the progress of one phase to an observer
+#[derive(Clone)]
+pub(crate) struct ProgressTracker {
+    observer: Arc<dyn ProgressObserver>,
+    phase: Phase,
+    started: Instant,
+    total: Option<u64>,
+}
+
+impl ProgressTracker {
+    /// Start tracking `phase` now
+    pub(crate) fn start(observer: Arc<dyn ProgressObserver>, phase: Phase, total: Option<u64>) -> Self {
+        Self {
+            observer,
+            phase,
+            started: Instant::now(),
+            total,
+        }
+    }
+    
+    /// Start tracking `phase` if there is an observer
+    pub(crate) fn maybe(observer: Option<&Arc<dyn ProgressObserver>>, phase: Phase, total: Option<u64>) -> Option<Self> {
+        observer.map(|observer| Self::start(observer.clone(), phase, total))
+    }
+    
+    /// Update the total once it is known
+    pub(crate) fn set_total(&mut self, total: Option<u64>) {
+        if total.is_some() {
+            self.total = total;
+        }
+    }
+    
+    /// Report that `done` items are done
+    pub(crate) fn report(&self, done: u64) {
+        let elapsed = self.started.elapsed();
+        let eta = match self.total {
+            Some(total) if done > 0 => {
+                let remaining = total.saturating_sub(done) as f64 / done as f64;
+                Some(elapsed.mul_f64(remaining))
+            }
+            _ => None,
+        };
+        self.observer.progress(&Progress {
+            phase: self.phase,
+            done,
+            total: self.total,
+            elapsed,
+            eta,
+        });
+    }
+}
+
+impl fmt::Debug for ProgressTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressTracker")
+            .field("phase", &self.phase)
+            .field("total", &self.total)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    
+    #[test]
+    fn test_report() {
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let recorded = updates.clone();
+        let observer: Arc<dyn ProgressObserver> = Arc::new(move |progress: &Progress| {
+            recorded.lock().unwrap().push(progress.clone());
+        });
+        
+        let mut tracker = ProgressTracker::start(observer, Phase::Fetching, None);
+        tracker.report(0);
+        tracker.set_total(Some(4));
+        tracker.report(1);
+        tracker.report(4);
+        
+        let updates = updates.lock().unwrap();
+        assert_eq!(updates.iter().map(|update| update.done).collect::<Vec<_>>(), [0, 1, 4]);
+        assert_eq!(updates[0].eta, None);
+        assert_eq!(updates[1].total, Some(4));
+        assert_eq!(updates[1].eta, Some(updates[1].elapsed.mul_f64(3.0)));
+        assert_eq!(updates[2].eta, Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,127 @@
+//! Trace context propagation to the API server
+
+use opentelemetry::trace::TraceContextExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// W3C `traceparent` header
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+/// W3C `tracestate` header
+pub const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+/// Single-header B3 format used by Zipkin
+pub const B3: HeaderName = HeaderName::from_static("b3");
+
+/// Trace context headers sent with each request
+///
+/// The context is taken from the current `tracing` span, which needs a
+/// `tracing-opentelemetry` layer to carry trace and span ids. Requests made
+/// outside a traced span are sent without these headers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Propagation {
+    /// Do not send trace context
+    None,
+    /// `traceparent` and `tracestate`
+    #[default]
+    W3c,
+    /// The single `b3` header
+    B3,
+    /// Both the W3C and the B3 headers
+    W3cAndB3,
+}
+
+impl Propagation {
+    /// Add the trace context of the current span to a request
+    pub(crate) fn inject(self, req_builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let headers = self.headers();
+        if headers.is_empty() {
+            return req_builder;
+        }
+        req_builder.headers(headers)
+    }
+    
+    /// Headers carrying the trace context of the current span
+    fn headers(self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if self == Propagation::None {
+            return headers;
+        }
+        
+        let context = tracing::Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return headers;
+        }
+        
+        let (trace_id, span_id) = (span_context.trace_id(), span_context.span_id());
+        let mut insert = |name, value: String| {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(name, value);
+            }
+        };
+        if matches!(self, Propagation::W3c | Propagation::W3cAndB3) {
+            let flags = span_context.trace_flags().to_u8();
+            insert(TRACEPARENT, format!("00-{}-{}-{:02x}", trace_id, span_id, flags));
+            let state = span_context.trace_state().header();
+            if !state.is_empty() {
+                insert(TRACESTATE, state);
+            }
+        }
+        if matches!(self, Propagation::B3 | Propagation::W3cAndB3) {
+            let sampled = u8::from(span_context.is_sampled());
+            insert(B3, format!("{}-{}-{}", trace_id, span_id, sampled));
+        }
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TracerProvider;
+    use tracing_subscriber::layer::SubscriberExt;
+    
+    /// Run `f` inside a span backed by an OpenTelemetry tracer
+    fn in_traced_span<R>(f: impl FnOnce() -> R) -> R {
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder().build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("test"));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("import_orders");
+            let _entered = span.enter();
+            f()
+        })
+    }
+    
+    #[test]
+    fn test_w3c_headers() {
+        let headers = in_traced_span(|| Propagation::W3c.headers());
+        
+        let traceparent = headers[TRACEPARENT].to_str().unwrap();
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0], "00");
+        assert_eq!(parts[1].len(), 32);
+        assert_eq!(parts[2].len(), 16);
+        assert_eq!(parts[3], "01");
+        assert!(!headers.contains_key(B3));
+    }
+    
+    #[test]
+    fn test_b3_headers() {
+        let headers = in_traced_span(|| Propagation::W3cAndB3.headers());
+        let traceparent = headers[TRACEPARENT].to_str().unwrap();
+        let b3 = headers[B3].to_str().unwrap();
+        // Both formats carry the same trace and span ids
+        assert_eq!(&traceparent[3..52], &b3[..49]);
+        assert!(b3.ends_with("-1"));
+        
+        assert!(in_traced_span(|| Propagation::None.headers()).is_empty());
+    }
+    
+    #[test]
+    fn test_untraced_requests_have_no_headers() {
+        assert!(Propagation::W3c.headers().is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//! Outbound proxy configuration
+
+use crate::error::{Error, Result};
+use secrecy::{ExposeSecret, SecretString};
+use std::fmt;
+
+/// Which requests are routed through a proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    All,
+    Http,
+    Https,
+}
+
+/// An HTTP, HTTPS or SOCKS proxy
+///
+/// SOCKS proxies (`socks5://` and `socks5h://` URLs) need the `socks` feature.
+///
+/// ```
+/// # use ecommerce_api_client::proxy::ProxyConfig;
+/// let proxy = ProxyConfig::all("http://proxy.internal:3128")
+///     .basic_auth("warehouse", "s3cret")
+///     .no_proxy(["localhost", ".internal.example.com"]);
+/// ```
+#[derive(Clone)]
+pub struct ProxyConfig {
+    url: String,
+    scope: Scope,
+    credentials: Option<(String, SecretString)>,
+    no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// Route all requests through the proxy
+    pub fn all(url: impl Into<String>) -> Self {
+        Self::new(url.into(), Scope::All)
+    }
+    
+    /// Route only plain HTTP requests through the proxy
+    pub fn http(url: impl Into<String>) -> Self {
+        Self::new(url.into(), Scope::Http)
+    }
+    
+    /// Route only HTTPS requests through the proxy
+    pub fn https(url: impl Into<String>) -> Self {
+        Self::new(url.into(), Scope::Https)
+    }
+    
+    fn new(url: String, scope: Scope) -> Self {
+        Self {
+            url,
+            scope,
+            credentials: None,
+            no_proxy: Vec::new(),
+        }
+    }
+    
+    /// Authenticate to the proxy with a username and password
+    pub fn basic_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((username.into(), SecretString::new(password.into())));
+        self
+    }
+    
+    /// Bypass the proxy for these hosts, domains (`.example.com`) or IP ranges
+    pub fn no_proxy<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.no_proxy.extend(hosts.into_iter().map(Into::into));
+        self
+    }
+    
+    /// Convert into a reqwest proxy
+    pub(crate) fn to_reqwest(&self) -> Result<reqwest::Proxy> {
+        if self.url.starts_with("socks") && !cfg!(feature = "socks") {
+            return Err(Error::Config(format!(
+                "SOCKS proxy {} requires the `socks` feature",
+                self.url
+            )));
+        }
+        
+        let proxy = match self.scope {
+            Scope::All => reqwest::Proxy::all(&self.url),
+            Scope::Http => reqwest::Proxy::http(&self.url),
+            Scope::Https => reqwest::Proxy::https(&self.url),
+        }
+        .map_err(|e| Error::Config(format!("Invalid proxy {}: {}", self.url, e)))?;
+        
+        let proxy = match &self.credentials {
+            Some((username, password)) => proxy.basic_auth(username, password.expose_secret()),
+            None => proxy,
+        };
+        Ok(proxy.no_proxy(reqwest::NoProxy::from_string(&self.no_proxy.join(","))))
+    }
+}
+
+impl fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("url", &self.url)
+            .field("scope", &self.scope)
+            .field("username", &self.credentials.as_ref().map(|(username, _)| username))
+            .field("no_proxy", &self.no_proxy)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_to_reqwest() {
+        let proxy = ProxyConfig::https("http://proxy.internal:3128")
+            .basic_auth("warehouse", "s3cret")
+            .no_proxy(["localhost"]);
+        assert!(proxy.to_reqwest().is_ok());
+        
+        let invalid = ProxyConfig::all("not a url");
+        assert!(matches!(invalid.to_reqwest().unwrap_err(), Error::Config(_)));
+    }
+    
+    #[test]
+    fn test_debug_redacts_password() {
+        let proxy = ProxyConfig::all("http://proxy.internal:3128").basic_auth("warehouse", "s3cret");
+        let debug = format!("{:?}", proxy);
+        assert!(debug.contains("warehouse"));
+        assert!(!debug.contains("s3cret"));
+    }
+    
+    #[test]
+    fn test_socks() {
+        let result = ProxyConfig::all("socks5h://127.0.0.1:1080").to_reqwest();
+        assert_eq!(result.is_ok(), cfg!(feature = "socks"));
+    }
+}
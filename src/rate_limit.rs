@@ -0,0 +1,276 @@
+//! Client-side rate limiting
+
+use crate::error::{Error, Result};
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use crate::runtime::{self, Instant};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Values of `X-RateLimit-Reset` above this are Unix timestamps rather than seconds
+const EPOCH_THRESHOLD: u64 = 1_000_000_000;
+
+/// Quota reported by the server in `X-RateLimit-*` response headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Requests allowed in the current window (`X-RateLimit-Limit`)
+    pub limit: Option<u32>,
+    /// Requests left in the current window (`X-RateLimit-Remaining`)
+    pub remaining: u32,
+    /// Time until the window resets (`X-RateLimit-Reset`)
+    pub reset: Option<Duration>,
+}
+
+impl RateLimitStatus {
+    /// Parse rate-limit headers, if the response carries them
+    ///
+    /// `X-RateLimit-Reset` may be either seconds until the reset or a Unix
+    /// timestamp.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+        
+        let remaining = number("x-ratelimit-remaining")?;
+        let reset = number("x-ratelimit-reset").map(|reset| {
+            if reset > EPOCH_THRESHOLD {
+                let reset = UNIX_EPOCH + Duration::from_secs(reset);
+                reset.duration_since(runtime::system_now()).unwrap_or_default()
+            } else {
+                Duration::from_secs(reset)
+            }
+        });
+        
+        Some(Self {
+            limit: number("x-ratelimit-limit").map(|limit| limit.min(u32::MAX as u64) as u32),
+            remaining: remaining.min(u32::MAX as u64) as u32,
+            reset,
+        })
+    }
+}
+
+/// Token-bucket rate limiter shared by all clones of a client
+///
+/// Tokens refill continuously at `requests_per_second` up to `burst`. A
+/// request that finds the bucket empty reserves the next token and waits
+/// for it, so concurrent callers are served in order.
+///
+/// Quota reported by the server through [`RateLimitStatus`] slows the
+/// limiter down further until the server's window resets.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens; negative when requests are queued
+    tokens: f64,
+    /// When tokens were last refilled; in the future while paused by the server
+    updated: Instant,
+    /// Reduced rate derived from server quota
+    quota: Option<Quota>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quota {
+    rate: f64,
+    until: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `requests_per_second` with bursts of up to `burst`
+    ///
+    /// Fails with [`Error::Config`] unless `requests_per_second` is a
+    /// positive, finite number and `burst` is at least 1.
+    pub fn new(requests_per_second: f64, burst: u32) -> Result<Self> {
+        if !(requests_per_second > 0.0 && requests_per_second.is_finite()) {
+            return Err(Error::Config(format!(
+                "Rate limit must be a positive number of requests per second, got {}",
+                requests_per_second
+            )));
+        }
+        if burst == 0 {
+            return Err(Error::Config("Rate limit burst must be at least 1".to_string()));
+        }
+        
+        Ok(Self {
+            rate: requests_per_second,
+            burst: burst as f64,
+            bucket: Mutex::new(Bucket {
+                tokens: burst as f64,
+                updated: Instant::now(),
+                quota: None,
+            }),
+        })
+    }
+    
+    /// Wait until a request may be sent
+    pub async fn acquire(&self) {
+        let delay = self.reserve(Instant::now());
+        if !delay.is_zero() {
+            runtime::sleep(delay).await;
+        }
+    }
+    
+    /// Adapt to the quota reported by the server
+    ///
+    /// The refill rate is lowered to spread the remaining requests evenly
+    /// over the rest of the window; an exhausted quota pauses the limiter
+    /// until the window resets.
+    pub fn observe(&self, status: &RateLimitStatus) {
+        self.observe_at(status, Instant::now());
+    }
+    
+    fn observe_at(&self, status: &RateLimitStatus, now: Instant) {
+        let Some(reset) = status.reset.filter(|reset| !reset.is_zero()) else {
+            return;
+        };
+        let mut bucket = self.lock();
+        let until = now + reset;
+        
+        if status.remaining == 0 {
+            bucket.tokens = bucket.tokens.min(0.0);
+            bucket.updated = bucket.updated.max(until);
+            bucket.quota = None;
+        } else {
+            bucket.tokens = bucket.tokens.min(status.remaining as f64);
+            bucket.quota = Some(Quota {
+                rate: status.remaining as f64 / reset.as_secs_f64(),
+                until,
+            });
+        }
+    }
+    
+    /// Take a token, returning how long to wait before it becomes available
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.lock();
+        let rate = match bucket.quota {
+            Some(quota) if now < quota.until => quota.rate.min(self.rate),
+            _ => self.rate,
+        };
+        
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(self.burst);
+        let paused = bucket.updated.saturating_duration_since(now);
+        bucket.updated = bucket.updated.max(now);
+        bucket.tokens -= 1.0;
+        
+        if bucket.tokens >= 0.0 {
+            paused
+        } else {
+            paused + Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_burst_is_free() {
+        let limiter = RateLimiter::new(10.0, 3).unwrap();
+        let now = Instant::now();
+        
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve(now), Duration::ZERO);
+        }
+        assert_eq!(limiter.reserve(now), Duration::from_millis(100));
+        assert_eq!(limiter.reserve(now), Duration::from_millis(200));
+    }
+    
+    #[test]
+    fn test_invalid_limits() {
+        for (requests_per_second, burst) in [(0.0, 5), (-1.0, 5), (f64::NAN, 5), (f64::INFINITY, 5), (5.0, 0)] {
+            let result = RateLimiter::new(requests_per_second, burst);
+            assert!(matches!(result.unwrap_err(), Error::Config(_)));
+        }
+    }
+    
+    #[test]
+    fn test_refill() {
+        let limiter = RateLimiter::new(2.0, 1).unwrap();
+        let now = Instant::now();
+        
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now + Duration::from_millis(500)), Duration::ZERO);
+        
+        // Refill never exceeds the burst size
+        let later = now + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::from_millis(500));
+    }
+    
+    #[test]
+    fn test_observe_lowers_rate() {
+        let limiter = RateLimiter::new(100.0, 1).unwrap();
+        let now = Instant::now();
+        
+        // 10 requests left for the next 10 seconds
+        limiter.observe_at(&status(10, 10), now);
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::from_secs(1));
+    }
+    
+    #[test]
+    fn test_observe_exhausted_pauses() {
+        let limiter = RateLimiter::new(10.0, 5).unwrap();
+        let now = Instant::now();
+        
+        limiter.observe_at(&status(0, 30), now);
+        assert_eq!(limiter.reserve(now), Duration::from_millis(30_100));
+        
+        // After the window resets the configured rate applies again
+        let later = now + Duration::from_secs(31);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+    }
+    
+    #[test]
+    fn test_status_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(RateLimitStatus::from_headers(&headers).is_none());
+        
+        headers.insert("x-ratelimit-limit", "100".parse().unwrap());
+        headers.insert("x-ratelimit-remaining", "42".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "60".parse().unwrap());
+        assert_eq!(
+            RateLimitStatus::from_headers(&headers),
+            Some(RateLimitStatus {
+                limit: Some(100),
+                ..status(42, 60)
+            })
+        );
+        
+        let epoch = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 120;
+        headers.insert("x-ratelimit-reset", epoch.into());
+        let reset = RateLimitStatus::from_headers(&headers).unwrap().reset.unwrap();
+        assert!(reset > Duration::from_secs(100) && reset <= Duration::from_secs(120));
+    }
+    
+    fn status(remaining: u32, reset: u64) -> RateLimitStatus {
+        RateLimitStatus {
+            limit: None,
+            remaining,
+            reset: Some(Duration::from_secs(reset)),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_acquire() {
+        let limiter = RateLimiter::new(1000.0, 1).unwrap();
+        let start = Instant::now();
+        
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,89 @@
+//! Comparison of the local order record with the API
+//!
+//! [`Client::reconcile_orders`](crate::Client::reconcile_orders) lists the
+//! orders created in a time range, compares them with the orders an
+//! [`OrderStore`](crate::store::OrderStore) recorded for the same range and
+//! reports every order that is missing on either side or whose status
+//! differs.
+
+use crate::diff::FieldChange;
+use crate::store::StoredOrder;
+use crate::types::Order;
+
+/// An order whose recorded status differs from the API's
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct StatusMismatch {
+    /// The order as recorded locally
+    pub local: StoredOrder,
+    /// The order as the API returns it
+    pub remote: Order,
+}
+
+impl StatusMismatch {
+    /// Every field that differs between the recorded and the remote order
+    pub fn changes(&self) -> Vec<FieldChange> {
+        self.local.order.diff(&self.remote)
+    }
+}
+
+/// Outcome of comparing the local order record with the API
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ReconcileReport {
+    /// Orders recorded locally that the API does not know
+    pub missing_remotely: Vec<StoredOrder>,
+    /// Orders the API returned that were not recorded locally
+    pub missing_locally: Vec<Order>,
+    /// Orders known on both sides with different statuses
+    pub status_mismatches: Vec<StatusMismatch>,
+    /// Number of orders that agree on both sides
+    pub matched: usize,
+}
+
+impl ReconcileReport {
+    /// Whether both sides agree on every order
+    pub fn is_consistent(&self) -> bool {
+        self.missing_remotely.is_empty() && self.missing_locally.is_empty() && self.status_mismatches.is_empty()
+    }
+    
+    /// Account for an order known on both sides
+    pub(crate) fn compare(&mut self, local: StoredOrder, remote: Order) {
+        if local.order.status_order_id == remote.status_order_id {
+            self.matched += 1;
+        } else {
+            self.status_mismatches.push(StatusMismatch { local, remote });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    
+    fn order(status: u64) -> Order {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "status_order_id": status,
+            "customer_id": 7,
+            "customer_order_reference": "ORDER-1",
+            "gross_total": "10.00",
+            "addressbook_id": 3,
+        }))
+        .unwrap()
+    }
+    
+    #[test]
+    fn test_compare() {
+        let mut report = ReconcileReport::default();
+        let local = StoredOrder::new(order(1), Vec::new(), SystemTime::UNIX_EPOCH);
+        report.compare(local.clone(), order(1));
+        assert!(report.is_consistent());
+        report.compare(local, order(4));
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.status_mismatches[0].remote.status_order_id, 4);
+        assert_eq!(report.status_mismatches[0].changes()[0].field, "status_order_id");
+        assert!(!report.is_consistent());
+    }
+}
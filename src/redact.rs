@@ -0,0 +1,179 @@
+//! Redaction of credentials and personal data before payloads are logged
+
+use serde_json::Value;
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Key fragments marking a field as a credential
+const CREDENTIALS: [&str; 7] = [
+    "password",
+    "secret",
+    "token",
+    "apikey",
+    "authorization",
+    "credential",
+    "cookie",
+];
+
+/// Key fragments marking a field as personal data
+const PERSONAL: [&str; 3] = ["email", "phone", "address"];
+
+/// Fields kept readable under a sensitive key, e.g. an `addressbook`
+const KEEP: [&str; 1] = ["country"];
+
+/// Whether a field named `key` holds a credential or personal data
+///
+/// Matching ignores case, `_` and `-`, so `customer_email` and `apiKey`
+/// are both sensitive.
+pub fn is_sensitive(key: &str) -> bool {
+    let key = normalize(key);
+    CREDENTIALS.iter().chain(&PERSONAL).any(|fragment| key.contains(fragment))
+}
+
+/// Whether a field or header named `key` holds a credential
+pub fn is_credential(key: &str) -> bool {
+    let key = normalize(key);
+    CREDENTIALS.iter().any(|fragment| key.contains(fragment))
+}
+
+fn normalize(key: &str) -> String {
+    key.chars()
+        .filter(|c| !matches!(c, '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Replace every sensitive field of a JSON document, at any depth
+///
+/// Under a sensitive key, such as an `addressbook`, every field is replaced
+/// whatever its name (the recipient's `name`, `city`, `postal_code`...),
+/// except `country`, which stays readable.
+pub fn redact_json(value: &mut Value) {
+    redact_where(value, &is_sensitive, false);
+}
+
+/// Replace only the credentials in a JSON document, returning whether there were any
+pub fn redact_credentials(value: &mut Value) -> bool {
+    redact_where(value, &is_credential, false)
+}
+
+/// `inside` is set under a sensitive key, where every field but those in [`KEEP`] is replaced
+fn redact_where(value: &mut Value, sensitive: &dyn Fn(&str) -> bool, inside: bool) -> bool {
+    let mut redacted = false;
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                let sensitive_key = sensitive(key);
+                match field {
+                    Value::Object(_) | Value::Array(_) => redacted |= redact_where(field, sensitive, inside || sensitive_key),
+                    _ if sensitive_key || (inside && !KEEP.contains(&normalize(key).as_str())) => {
+                        *field = Value::String(REDACTED.to_string());
+                        redacted = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redacted |= match item {
+                    Value::Object(_) | Value::Array(_) => redact_where(item, sensitive, inside),
+                    _ if inside => {
+                        *item = Value::String(REDACTED.to_string());
+                        true
+                    }
+                    _ => false,
+                };
+            }
+        }
+        _ => {}
+    }
+    redacted
+}
+
+/// Redact a JSON document embedded in a message, e.g. an error body
+///
+/// Text without a JSON object or array is returned unchanged.
+pub fn redact_text(text: &str) -> String {
+    let Some(start) = text.find(['{', '[']) else {
+        return text.to_string();
+    };
+    match serde_json::from_str::<Value>(&text[start..]) {
+        Ok(mut value) => {
+            redact_json(&mut value);
+            format!("{}{}", &text[..start], value)
+        }
+        Err(_) => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_redact_json() {
+        let mut order = json!({
+            "customer_order_reference": "ORDER-001",
+            "order_products": [{
+                "quantity": 1,
+                "addressbook": {
+                    "country": "US",
+                    "name": "Jane Doe",
+                    "city": "Springfield",
+                    "postal_code": "62704",
+                    "comments": "Leave with neighbour at no. 3",
+                    "address": "1 Main St",
+                    "address2": "Apt 4",
+                    "phone": "555-0100",
+                    "email": "jane@example.com"
+                }
+            }],
+            "billing_address": "1 Main St",
+            "apiKey": "k-123",
+            "access_token": null
+        });
+        redact_json(&mut order);
+        
+        let addressbook = &order["order_products"][0]["addressbook"];
+        assert_eq!(addressbook["country"], "US");
+        assert_eq!(addressbook["name"], REDACTED);
+        assert_eq!(addressbook["city"], REDACTED);
+        assert_eq!(addressbook["postal_code"], REDACTED);
+        assert_eq!(addressbook["comments"], REDACTED);
+        assert_eq!(addressbook["address"], REDACTED);
+        assert_eq!(addressbook["address2"], REDACTED);
+        assert_eq!(addressbook["phone"], REDACTED);
+        assert_eq!(addressbook["email"], REDACTED);
+        assert_eq!(order["billing_address"], REDACTED);
+        assert_eq!(order["apiKey"], REDACTED);
+        assert_eq!(order["access_token"], REDACTED);
+        assert_eq!(order["customer_order_reference"], "ORDER-001");
+    }
+    
+    #[test]
+    fn test_redact_credentials() {
+        let mut login = json!({"email": "jane@example.com", "password": "hunter2"});
+        assert!(redact_credentials(&mut login));
+        assert_eq!(login, json!({"email": "jane@example.com", "password": REDACTED}));
+        
+        assert!(!redact_credentials(&mut json!({"phone": "555-0100", "addressbook": {"name": "Jane Doe"}})));
+        let mut nested = json!({"credentials": {"username": "jane", "keys": ["k-1"]}});
+        assert!(redact_credentials(&mut nested));
+        assert_eq!(nested, json!({"credentials": {"username": REDACTED, "keys": [REDACTED]}}));
+        assert!(is_credential("X-Api-Key"));
+        assert!(!is_credential("x-request-id"));
+    }
+    
+    #[test]
+    fn test_redact_text() {
+        assert_eq!(
+            redact_text(r#"Bad request: {"email":"jane@example.com","field":"email"}"#),
+            r#"Bad request: {"email":"[REDACTED]","field":"email"}"#
+        );
+        assert_eq!(redact_text("Server error 502: Bad Gateway"), "Server error 502: Bad Gateway");
+        assert_eq!(redact_text("Bad request: {not json"), "Bad request: {not json");
+    }
+}
@@ -0,0 +1,159 @@
+//! Redirect handling with explicit rules for forwarding credentials
+
+use crate::error::{Error, Result};
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION};
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+/// Default number of redirects followed
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// How the client follows 3xx responses
+///
+/// Redirects are followed by the client itself rather than by the HTTP
+/// library, so credentials can be re-applied deliberately: they are sent to
+/// the origin of the original request and to explicitly trusted HTTPS hosts,
+/// and stripped everywhere else. 307 and 308 keep the method and body; 301
+/// and 302 turn a POST into a GET, and 303 always does.
+///
+/// ```
+/// use ecommerce_api_client::RedirectPolicy;
+///
+/// // The load balancer moves requests between regions
+/// let policy = RedirectPolicy::limited(3)
+///     .trust_host("eu.api.example.com")
+///     .trust_host("us.api.example.com");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    max_redirects: usize,
+    trusted_hosts: Vec<String>,
+}
+
+impl RedirectPolicy {
+    /// Never follow redirects; 3xx responses are returned as errors
+    pub fn none() -> Self {
+        Self::limited(0)
+    }
+    
+    /// Follow at most `max_redirects` redirects per request
+    pub fn limited(max_redirects: usize) -> Self {
+        Self {
+            max_redirects,
+            trusted_hosts: Vec::new(),
+        }
+    }
+    
+    /// Follow redirects up to the default limit of 10
+    pub fn follow() -> Self {
+        Self::limited(DEFAULT_MAX_REDIRECTS)
+    }
+    
+    /// Keep sending credentials when redirected to this host over HTTPS
+    pub fn trust_host(mut self, host: impl Into<String>) -> Self {
+        self.trusted_hosts.push(host.into().to_ascii_lowercase());
+        self
+    }
+    
+    /// Whether credentials for `origin` may be sent to `target`
+    pub(crate) fn forwards_credentials(&self, origin: &Url, target: &Url) -> bool {
+        if origin.origin() == target.origin() {
+            return true;
+        }
+        target.scheme() == "https"
+            && target
+                .host_str()
+                .is_some_and(|host| self.trusted_hosts.iter().any(|trusted| trusted == host))
+    }
+    
+    /// Turn a redirect response into the request to send next
+    ///
+    /// Returns `None` when the response is not a followable redirect, or the
+    /// request cannot be replayed.
+    pub(crate) fn next_request(
+        &self,
+        response: &reqwest::Response,
+        request: Option<reqwest::Request>,
+        redirects: usize,
+    ) -> Result<Option<reqwest::Request>> {
+        let status = response.status();
+        let location = response.headers().get(LOCATION).and_then(|value| value.to_str().ok());
+        let (Some(location), Some(mut request)) = (location, request) else {
+            return Ok(None);
+        };
+        if !is_followable(status) || self.max_redirects == 0 {
+            return Ok(None);
+        }
+        if redirects >= self.max_redirects {
+            return Err(Error::TooManyRedirects(redirects));
+        }
+        
+        let url = request
+            .url()
+            .join(location)
+            .map_err(|e| Error::InvalidUrl(format!("Invalid redirect location {:?}: {}", location, e)))?;
+        
+        let keep_body = matches!(status, StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT);
+        if !keep_body && request.method() != Method::HEAD {
+            *request.method_mut() = Method::GET;
+            *request.body_mut() = None;
+            strip_body_headers(request.headers_mut());
+        }
+        // Credentials are re-applied by the client when the target is trusted
+        request.headers_mut().remove(AUTHORIZATION);
+        request.headers_mut().remove(COOKIE);
+        *request.url_mut() = url;
+        Ok(Some(request))
+    }
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::follow()
+    }
+}
+
+fn is_followable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+fn strip_body_headers(headers: &mut HeaderMap) {
+    headers.remove(CONTENT_TYPE);
+    headers.remove(CONTENT_LENGTH);
+    headers.remove(CONTENT_ENCODING);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+    
+    #[test]
+    fn test_forwards_credentials() {
+        let policy = RedirectPolicy::default().trust_host("US.api.example.com");
+        let origin = url("https://eu.api.example.com/orders");
+        
+        assert!(policy.forwards_credentials(&origin, &url("https://eu.api.example.com/v2/orders")));
+        assert!(policy.forwards_credentials(&origin, &url("https://us.api.example.com/orders")));
+        // No downgrade to plain HTTP, and untrusted hosts never see credentials
+        assert!(!policy.forwards_credentials(&origin, &url("http://us.api.example.com/orders")));
+        assert!(!policy.forwards_credentials(&origin, &url("https://cdn.example.net/orders")));
+    }
+    
+    #[test]
+    fn test_constructors() {
+        assert_eq!(RedirectPolicy::none().max_redirects, 0);
+        assert_eq!(RedirectPolicy::limited(3).max_redirects, 3);
+        assert_eq!(RedirectPolicy::default(), RedirectPolicy::follow());
+    }
+}
@@ -0,0 +1,192 @@
+//! Re-issuing audited calls, e.g. for disaster-recovery drills and staging backfills
+
+use crate::audit::AuditRecord;
+use crate::client::Client;
+use crate::error::Result;
+use crate::response::ApiResponse;
+use reqwest::Method;
+
+/// Read the records of an audit log written by [`AuditFile`](crate::audit::AuditFile)
+///
+/// Only logs written [`with_raw_bodies`](crate::audit::AuditFile::with_raw_bodies)
+/// can replay calls that had a body.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_audit_log(path: impl AsRef<std::path::Path>) -> Result<Vec<AuditRecord>> {
+    let contents = std::fs::read_to_string(path.as_ref())
+        .map_err(|e| crate::Error::Config(format!("Failed to read {}: {}", path.as_ref().display(), e)))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| AuditRecord::from_json(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// Outcome of replaying one call
+#[derive(Debug)]
+pub enum ReplayOutcome {
+    /// Dry run: the request that would have been sent
+    DryRun {
+        /// HTTP method
+        method: Method,
+        /// URL on the target environment
+        url: String,
+    },
+    /// The call was sent; its response, decoded as JSON
+    Sent(Box<ApiResponse<serde_json::Value>>),
+}
+
+/// Re-sends audited calls through a client for another environment
+///
+/// Paths are resolved against the client's base URL, and its credentials
+/// are used instead of the original ones. Each call gets a new request id.
+///
+/// ```no_run
+/// # use ecommerce_api_client::{Client, Endpoint};
+/// # use ecommerce_api_client::replay::{self, Replayer};
+/// # async fn example() -> ecommerce_api_client::Result<()> {
+/// let records = replay::read_audit_log("orders-audit.jsonl")?;
+/// let staging = Client::new("https://staging.example.com")?;
+/// let replayer = Replayer::new(staging).dry_run(true);
+///
+/// let submitted = records
+///     .iter()
+///     .filter(|record| record.endpoint == Endpoint::CreateOrder && record.error.is_none());
+/// for outcome in replayer.replay_all(submitted).await {
+///     println!("{:?}", outcome?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    client: Client,
+    dry_run: bool,
+}
+
+impl Replayer {
+    /// Replay through `client`
+    pub fn new(client: Client) -> Self {
+        Self { client, dry_run: false }
+    }
+    
+    /// Only report what would be sent
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    
+    /// Replay a single call
+    pub async fn replay(&self, record: &AuditRecord) -> Result<ReplayOutcome> {
+        if self.dry_run {
+            return Ok(ReplayOutcome::DryRun {
+                method: record.method.clone(),
+                url: self.client.replay_url(record)?,
+            });
+        }
+        self.client
+            .replay(record)
+            .await
+            .map(|response| ReplayOutcome::Sent(Box::new(response)))
+    }
+    
+    /// Replay calls one after another, in the order given
+    ///
+    /// A failed call does not stop the others.
+    pub async fn replay_all<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a AuditRecord>,
+    ) -> Vec<Result<ReplayOutcome>> {
+        let mut outcomes = Vec::new();
+        for record in records {
+            outcomes.push(self.replay(record).await);
+        }
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditFile, AuditSink};
+    use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+    use crate::types::CreateOrderRequest;
+    use std::sync::{Arc, Mutex};
+    
+    /// Collects records in memory
+    #[derive(Default)]
+    struct Records(Mutex<Vec<AuditRecord>>);
+    
+    #[async_trait::async_trait]
+    impl AuditSink for Arc<Records> {
+        async fn record(&self, record: AuditRecord) {
+            self.0.lock().unwrap().push(record);
+        }
+    }
+    
+    /// Answers every request with its own body
+    struct Echo(Arc<Mutex<Vec<String>>>);
+    
+    #[async_trait::async_trait]
+    impl HttpTransport for Echo {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.0.lock().unwrap().push(request.uri().to_string());
+            Ok(http::Response::builder()
+                .status(201)
+                .header("content-type", "application/json")
+                .body(request.body().clone())
+                .unwrap())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_replay() {
+        let records = Arc::new(Records::default());
+        let production = Client::new("https://api.example.com")
+            .unwrap()
+            .with_transport(Echo(Arc::default()))
+            .with_audit_sink(records.clone());
+        let request = CreateOrderRequest {
+            customer_order_reference: Some("ORDER-001".to_string()),
+            ..Default::default()
+        };
+        let _ = production.create_order(request).await;
+        let record = records.0.lock().unwrap().pop().unwrap();
+        
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let staging = Client::new("https://staging.example.com")
+            .unwrap()
+            .with_transport(Echo(sent.clone()));
+        let replayer = Replayer::new(staging);
+        
+        let ReplayOutcome::DryRun { url, .. } = replayer.clone().dry_run(true).replay(&record).await.unwrap() else {
+            panic!("expected a dry run");
+        };
+        assert_eq!(url, "https://staging.example.com/api_customer/orders");
+        assert!(sent.lock().unwrap().is_empty());
+        
+        let ReplayOutcome::Sent(response) = replayer.replay(&record).await.unwrap() else {
+            panic!("expected the call to be sent");
+        };
+        assert_eq!(response.data["customer_order_reference"], "ORDER-001");
+        assert_eq!(sent.lock().unwrap()[0], url);
+        assert_ne!(response.request_id.as_deref(), Some(record.request_id.as_str()));
+    }
+    
+    #[tokio::test]
+    async fn test_replay_needs_raw_body() {
+        let path = std::env::temp_dir().join(format!("ecommerce-api-replay-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let client = Client::new("https://api.example.com")
+            .unwrap()
+            .with_transport(Echo(Arc::default()))
+            .with_audit_sink(AuditFile::open(&path).unwrap());
+        let _ = client.create_order(CreateOrderRequest::default()).await;
+        
+        let records = read_audit_log(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].raw_body.is_none());
+        let result = Replayer::new(client).replay(&records[0]).await;
+        assert!(matches!(result, Err(crate::Error::Config(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+//! Request ids correlating calls with the supplier's logs
+
+use reqwest::header::{HeaderMap, HeaderName};
+
+/// Header carrying the id of a request, sent by the client and often echoed by the server
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Headers the server may use to report its own id for a request
+const SERVER_REQUEST_IDS: [HeaderName; 2] = [REQUEST_ID, HeaderName::from_static("x-correlation-id")];
+
+/// A random UUID (version 4) identifying a call
+pub(crate) fn generate() -> String {
+    let bytes = fastrand::u128(..).to_le_bytes();
+    uuid::Builder::from_random_bytes(bytes)
+        .into_uuid()
+        .hyphenated()
+        .to_string()
+}
+
+/// The id the server reported for a request, if any
+pub(crate) fn from_headers(headers: &HeaderMap) -> Option<String> {
+    SERVER_REQUEST_IDS
+        .iter()
+        .find_map(|name| headers.get(name))
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    
+    #[test]
+    fn test_generate() {
+        let id = generate();
+        let parsed = uuid::Uuid::parse_str(&id).unwrap();
+        assert_eq!(parsed.get_version_num(), 4);
+        assert_eq!(id.len(), 36);
+        assert_ne!(id, generate());
+    }
+    
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(from_headers(&headers), None);
+        
+        headers.insert("x-correlation-id", HeaderValue::from_static("corr-7"));
+        assert_eq!(from_headers(&headers).as_deref(), Some("corr-7"));
+        
+        headers.insert(REQUEST_ID, HeaderValue::from_static("srv-42"));
+        assert_eq!(from_headers(&headers).as_deref(), Some("srv-42"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Response wrapper carrying metadata alongside the decoded payload
+
+use crate::deprecation::DeprecationNotice;
+use crate::envelope::Meta;
+use crate::error::{Error, Result};
+use crate::rate_limit::RateLimitStatus;
+use crate::types::Links;
+use bytes::Bytes;
+use futures_util::stream::{self, Stream};
+use reqwest::{StatusCode, Version};
+
+/// A decoded API response together with its metadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiResponse<T> {
+    /// The decoded payload
+    pub data: T,
+    /// Envelope metadata (pagination, counts), when the server sent any
+    pub meta: Option<Meta>,
+    /// Envelope-level links, when the server sent any
+    pub links: Option<Links>,
+    /// Quota reported in `X-RateLimit-*` headers, when the server sent any
+    pub rate_limit: Option<RateLimitStatus>,
+    /// HTTP status of the response, unset for payloads not received over HTTP
+    pub status: Option<StatusCode>,
+    /// HTTP version the response was received over, unknown on wasm
+    pub version: Option<Version>,
+    /// Id sent in the `X-Request-Id` header
+    pub request_id: Option<String>,
+    /// Id the server reported for the request
+    pub server_request_id: Option<String>,
+    /// Deprecation or sunset announced for the endpoint, if any
+    pub deprecation: Option<DeprecationNotice>,
+    /// `Idempotency-Key` sent with the request, for order creation
+    pub idempotency_key: Option<String>,
+    /// `ETag` the server reported for the payload, if any
+    pub etag: Option<String>,
+}
+
+impl<T> ApiResponse<T> {
+    /// Wrap a payload without any metadata
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            meta: None,
+            links: None,
+            rate_limit: None,
+            status: None,
+            version: None,
+            request_id: None,
+            server_request_id: None,
+            deprecation: None,
+            idempotency_key: None,
+            etag: None,
+        }
+    }
+    
+    /// Replace the payload, keeping the metadata
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ApiResponse<U> {
+        ApiResponse {
+            data: f(self.data),
+            meta: self.meta,
+            links: self.links,
+            rate_limit: self.rate_limit,
+            status: self.status,
+            version: self.version,
+            request_id: self.request_id,
+            server_request_id: self.server_request_id,
+            deprecation: self.deprecation,
+            idempotency_key: self.idempotency_key,
+            etag: self.etag,
+        }
+    }
+    
+    /// Whether the response was received over HTTP/2
+    pub fn is_http2(&self) -> bool {
+        self.version == Some(Version::HTTP_2)
+    }
+    
+    /// Consume the response, returning only the payload
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+/// Outcome of a conditional request
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conditional<T> {
+    /// Fetched without an `ETag`, or changed since the one given
+    Modified(T),
+    /// Unchanged since the `ETag` given, so no payload was sent
+    NotModified,
+}
+
+impl<T> Conditional<T> {
+    /// Whether a new payload was received
+    pub fn is_modified(&self) -> bool {
+        matches!(self, Conditional::Modified(_))
+    }
+    
+    /// The new payload, if any
+    pub fn modified(self) -> Option<T> {
+        match self {
+            Conditional::Modified(data) => Some(data),
+            Conditional::NotModified => None,
+        }
+    }
+}
+
+/// Read a response body, failing once it grows past `limit` bytes
+///
+/// A `Content-Length` over the limit fails before anything is read.
+pub(crate) async fn read_body(response: reqwest::Response, limit: Option<u64>) -> Result<Vec<u8>> {
+    let too_large = |size: u64| limit.filter(|limit| size > *limit).map(Error::ResponseTooLarge);
+    if let Some(error) = response.content_length().and_then(too_large) {
+        return Err(error);
+    }
+    
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut response = response;
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(Error::Http)? {
+            if let Some(error) = too_large((body.len() + chunk.len()) as u64) {
+                return Err(error);
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+    // `fetch` bodies can only be read in one piece
+    #[cfg(target_arch = "wasm32")]
+    {
+        let body = response.bytes().await.map_err(Error::Http)?;
+        match too_large(body.len() as u64) {
+            Some(error) => Err(error),
+            None => Ok(body.to_vec()),
+        }
+    }
+}
+
+/// Stream a response body chunk by chunk instead of buffering it
+pub(crate) fn body_stream(response: reqwest::Response) -> impl Stream<Item = Result<Bytes>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        stream::unfold(Some(response), |state| async move {
+            let mut response = state?;
+            match response.chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk), Some(response))),
+                Ok(None) => None,
+                Err(e) => Some((Err(Error::Http(e)), None)),
+            }
+        })
+    }
+    // `fetch` bodies can only be read in one piece
+    #[cfg(target_arch = "wasm32")]
+    {
+        stream::once(async move { response.bytes().await.map_err(Error::Http) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_is_http2() {
+        let mut response = ApiResponse::new(());
+        assert!(!response.is_http2());
+        
+        response.version = Some(Version::HTTP_11);
+        assert!(!response.is_http2());
+        
+        response.version = Some(Version::HTTP_2);
+        assert!(response.is_http2());
+    }
+    
+    #[test]
+    fn test_map_keeps_metadata() {
+        let mut response = ApiResponse::new(Some(1));
+        response.etag = Some("\"v1\"".to_string());
+        let mapped = response.map(|data| data.unwrap_or_default() + 1);
+        assert_eq!(mapped.data, 2);
+        assert_eq!(mapped.etag.as_deref(), Some("\"v1\""));
+        
+        assert_eq!(Conditional::Modified(1).modified(), Some(1));
+        assert!(!Conditional::<u32>::NotModified.is_modified());
+    }
+}
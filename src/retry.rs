@@ -0,0 +1,314 @@
+//! Automatic retries for transient failures
+
+use crate::error::Error;
+use reqwest::header::HeaderName;
+use reqwest::Method;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use crate::runtime::{self, Instant};
+use std::time::Duration;
+
+/// Header marking a non-idempotent request as safe to retry
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Decides whether and when a failed request is retried
+///
+/// The client only consults the policy for idempotent requests, requests
+/// carrying an `Idempotency-Key` header, and requests that failed before a
+/// connection was established, so orders are never submitted twice by
+/// accident.
+pub trait RetryPolicy: Send + Sync {
+    /// Return the delay before the next attempt, or `None` to give up
+    ///
+    /// `attempt` is the number of attempts made so far, starting at 1.
+    fn should_retry(&self, error: &Error, attempt: u32) -> Option<Duration>;
+}
+
+/// Exponential backoff with full jitter for retryable errors (default)
+///
+/// A `Retry-After` delay sent with a 429 or 503 response replaces the backoff when
+/// it is within `max_delay`; longer waits are left to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exponential {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for each further retry
+    pub base_delay: Duration,
+    /// Upper bound for the backoff between attempts
+    pub max_delay: Duration,
+}
+
+impl Default for Exponential {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Exponential {
+    /// Backoff ceiling before the given retry (1 for the first retry)
+    pub(crate) fn backoff_cap(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+impl RetryPolicy for Exponential {
+    fn should_retry(&self, error: &Error, attempt: u32) -> Option<Duration> {
+        if !error.is_retryable() || attempt >= self.max_attempts {
+            return None;
+        }
+        
+        match error.retry_after() {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff_cap(attempt).mul_f64(fastrand::f64())),
+        }
+    }
+}
+
+/// Constant delay between attempts for retryable errors
+///
+/// A longer `Retry-After` delay sent with a 429 or 503 response takes precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixed {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay between attempts
+    pub delay: Duration,
+}
+
+impl RetryPolicy for Fixed {
+    fn should_retry(&self, error: &Error, attempt: u32) -> Option<Duration> {
+        if !error.is_retryable() || attempt >= self.max_attempts {
+            return None;
+        }
+        
+        Some(error.retry_after().map_or(self.delay, |delay| delay.max(self.delay)))
+    }
+}
+
+/// Never retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Never;
+
+impl RetryPolicy for Never {
+    fn should_retry(&self, _error: &Error, _attempt: u32) -> Option<Duration> {
+        None
+    }
+}
+
+/// Caps retries at a fraction of requests over a sliding window
+///
+/// Shared by all clones of a client, so a degraded upstream sees at most
+/// `ratio` extra traffic however many requests are retrying concurrently.
+/// `min_retries` keeps retries possible when traffic is low.
+#[derive(Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_retries: u32,
+    window: Duration,
+    history: Mutex<History>,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    requests: VecDeque<Instant>,
+    retries: VecDeque<Instant>,
+}
+
+impl Default for RetryBudget {
+    /// Retries limited to 20% of requests over 10 seconds, with at least 10 allowed
+    fn default() -> Self {
+        Self::new(0.2, Duration::from_secs(10))
+    }
+}
+
+impl RetryBudget {
+    /// Allow retries up to `ratio` of the requests made within `window`
+    pub fn new(ratio: f64, window: Duration) -> Self {
+        Self {
+            ratio,
+            min_retries: 10,
+            window,
+            history: Mutex::default(),
+        }
+    }
+    
+    /// Number of retries allowed per window regardless of traffic
+    pub fn with_min_retries(mut self, min_retries: u32) -> Self {
+        self.min_retries = min_retries;
+        self
+    }
+    
+    /// Record a new (non-retry) request
+    pub(crate) fn deposit(&self) {
+        let now = Instant::now();
+        let mut history = self.lock(now);
+        history.requests.push_back(now);
+    }
+    
+    /// Spend budget on a retry, returning `false` when the budget is exhausted
+    pub(crate) fn withdraw(&self) -> bool {
+        self.withdraw_at(Instant::now())
+    }
+    
+    fn withdraw_at(&self, now: Instant) -> bool {
+        let mut history = self.lock(now);
+        let allowed = (history.requests.len() as f64 * self.ratio) as usize;
+        
+        if history.retries.len() >= allowed.max(self.min_retries as usize) {
+            return false;
+        }
+        history.retries.push_back(now);
+        true
+    }
+    
+    /// Lock the history, dropping entries that left the window
+    fn lock(&self, now: Instant) -> std::sync::MutexGuard<'_, History> {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let window = self.window;
+        let expired = |at: &Instant| now.saturating_duration_since(*at) > window;
+        
+        while history.requests.front().is_some_and(expired) {
+            history.requests.pop_front();
+        }
+        while history.retries.front().is_some_and(expired) {
+            history.retries.pop_front();
+        }
+        history
+    }
+}
+
+/// Parse a `Retry-After` header given in seconds or as an HTTP-date
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(runtime::system_now()).unwrap_or(Duration::ZERO))
+}
+
+/// Check whether a request may safely be sent more than once
+pub(crate) fn is_idempotent(request: &reqwest::Request) -> bool {
+    matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    ) || request.headers().contains_key(IDEMPOTENCY_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn server_error() -> Error {
+        Error::ServerError(503, "Service Unavailable".to_string(), None)
+    }
+    
+    #[test]
+    fn test_exponential_backoff_grows_and_caps() {
+        let policy = Exponential {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        
+        assert_eq!(policy.backoff_cap(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_cap(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_cap(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff_cap(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff_cap(64), Duration::from_secs(1));
+        
+        for attempt in 1..10 {
+            let delay = policy.should_retry(&server_error(), attempt).unwrap();
+            assert!(delay <= policy.backoff_cap(attempt));
+        }
+        assert!(policy.should_retry(&server_error(), 10).is_none());
+    }
+    
+    #[test]
+    fn test_exponential_skips_permanent_errors() {
+        let policy = Exponential::default();
+        assert!(policy.should_retry(&Error::BadRequest("invalid".into()), 1).is_none());
+        assert!(policy.should_retry(&server_error(), 2).is_some());
+        assert!(policy.should_retry(&server_error(), 3).is_none());
+    }
+    
+    #[test]
+    fn test_retry_after_honored() {
+        let policy = Exponential::default();
+        let limited = Error::RateLimit("Rate limit exceeded".to_string(), Some(Duration::from_secs(2)));
+        assert_eq!(policy.should_retry(&limited, 1), Some(Duration::from_secs(2)));
+        
+        let too_long = Error::RateLimit("Rate limit exceeded".to_string(), Some(Duration::from_secs(60)));
+        assert_eq!(policy.should_retry(&too_long, 1), None);
+        
+        let unavailable = Error::ServerError(503, "Service Unavailable".to_string(), Some(Duration::from_secs(3)));
+        assert_eq!(policy.should_retry(&unavailable, 1), Some(Duration::from_secs(3)));
+        
+        let fixed = Fixed {
+            max_attempts: 3,
+            delay: Duration::from_secs(1),
+        };
+        assert_eq!(fixed.should_retry(&limited, 1), Some(Duration::from_secs(2)));
+    }
+    
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+        
+        let future = httpdate::fmt_http_date(std::time::SystemTime::now() + Duration::from_secs(30));
+        let delay = parse_retry_after(&future).unwrap();
+        assert!(delay > Duration::from_secs(25) && delay <= Duration::from_secs(30));
+    }
+    
+    #[test]
+    fn test_fixed_and_never() {
+        let fixed = Fixed {
+            max_attempts: 2,
+            delay: Duration::from_millis(50),
+        };
+        assert_eq!(fixed.should_retry(&server_error(), 1), Some(Duration::from_millis(50)));
+        assert_eq!(fixed.should_retry(&server_error(), 2), None);
+        assert_eq!(fixed.should_retry(&Error::NotFound("gone".to_string()), 1), None);
+        
+        assert_eq!(Never.should_retry(&server_error(), 1), None);
+    }
+    
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(0.2, Duration::from_secs(10)).with_min_retries(1);
+        for _ in 0..10 {
+            budget.deposit();
+        }
+        
+        let now = Instant::now();
+        assert!(budget.withdraw_at(now));
+        assert!(budget.withdraw_at(now));
+        assert!(!budget.withdraw_at(now));
+        
+        // Requests and retries expire together once they leave the window
+        let later = now + Duration::from_secs(11);
+        assert!(budget.withdraw_at(later));
+        assert!(!budget.withdraw_at(later));
+    }
+    
+    #[test]
+    fn test_is_idempotent() {
+        let client = reqwest::Client::new();
+        let url = "https://api.example.com/api_customer/orders";
+        
+        assert!(is_idempotent(&client.get(url).build().unwrap()));
+        assert!(is_idempotent(&client.delete(url).build().unwrap()));
+        assert!(!is_idempotent(&client.post(url).build().unwrap()));
+        assert!(is_idempotent(&client.post(url).header(IDEMPOTENCY_KEY, "key-1").build().unwrap()));
+    }
+}
@@ -0,0 +1,452 @@
+//! Dispatch of API events to typed handlers
+//!
+//! An [`EventRouter`] holds the handlers for each kind of event and feeds
+//! them from any stream of [`ApiEvent`]s, such as
+//! [`ApiEvents`](crate::events::ApiEvents). It limits how many events are
+//! handled at once, retries or ignores failed handlers as each one's
+//! [`ErrorPolicy`] says, and hands events that could not be handled to a
+//! dead-letter callback.
+//!
+//! ```no_run
+//! # use ecommerce_api_client::events::ApiEvents;
+//! # use ecommerce_api_client::router::{ErrorPolicy, EventRouter};
+//! # use std::time::Duration;
+//! # async fn notify(order_id: u64, url: Option<String>) -> Result<(), std::io::Error> { Ok(()) }
+//! # async fn example(events: ApiEvents) {
+//! let router = EventRouter::new()
+//!     .on_order_shipped(|shipment| async move {
+//!         notify(shipment.order_id, shipment.tracking_url).await?;
+//!         Ok(())
+//!     })
+//!     .policy(ErrorPolicy::Retry { attempts: 3, delay: Duration::from_secs(5) })
+//!     .concurrency(8)
+//!     .dead_letter(|letter| eprintln!("{} failed for {:?}: {}", letter.handler, letter.event.id, letter.error));
+//! router.run(events).await;
+//! # }
+//! ```
+
+use crate::error::Error;
+use crate::events::ApiEvent;
+use crate::runtime;
+use crate::webhooks::{EventPayload, InvoiceIssued, OrderStatusChanged, ShipmentCreated, TrackingUpdated};
+use futures_util::future;
+use futures_util::stream::{Stream, StreamExt};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Error returned by an event handler
+pub type HandlerError = Box<dyn std::error::Error + Send + Sync>;
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Result<(), HandlerError>> + Send>>;
+
+/// Runs a handler for an event, or returns `None` if the event is not its kind
+type Handler = Arc<dyn Fn(&ApiEvent) -> Option<HandlerFuture> + Send + Sync>;
+
+/// Callback receiving the events handlers failed to handle
+type DeadLetterCallback = Arc<dyn Fn(DeadLetter) + Send + Sync>;
+
+/// Callback receiving the errors of the event stream
+type StreamErrorCallback = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// What to do when a handler fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ErrorPolicy {
+    /// Hand the event to the dead-letter callback
+    #[default]
+    DeadLetter,
+    /// Run the handler up to `attempts` times in all, `delay` apart, then hand the event to the dead-letter callback
+    Retry {
+        /// Runs of the handler in all, including the first
+        attempts: u32,
+        /// Pause between runs
+        delay: Duration,
+    },
+    /// Drop the event
+    Ignore,
+}
+
+/// An event a handler failed to handle
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct DeadLetter {
+    /// The event
+    pub event: ApiEvent,
+    /// Name of the handler, e.g. `order.shipped`
+    pub handler: String,
+    /// Error of the last run
+    pub error: HandlerError,
+    /// Times the handler ran
+    pub attempts: u32,
+}
+
+struct Route {
+    name: String,
+    handler: Handler,
+    policy: ErrorPolicy,
+}
+
+/// Dispatches events to the handlers registered for them
+///
+/// Every handler whose kind matches an event runs, concurrently with the
+/// others. Events no handler is registered for are dropped.
+pub struct EventRouter {
+    routes: Vec<Route>,
+    concurrency: usize,
+    dead_letter: Option<DeadLetterCallback>,
+    on_stream_error: Option<StreamErrorCallback>,
+}
+
+impl fmt::Debug for EventRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let handlers: Vec<_> = self.routes.iter().map(|route| route.name.as_str()).collect();
+        f.debug_struct("EventRouter")
+            .field("handlers", &handlers)
+            .field("concurrency", &self.concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for EventRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventRouter {
+    /// A router without handlers, handling one event at a time
+    pub fn new() -> Self {
+        Self {
+            routes: Vec::new(),
+            concurrency: 1,
+            dead_letter: None,
+            on_stream_error: None,
+        }
+    }
+    
+    /// Handle orders moving to another status
+    pub fn on_order_status_changed<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(OrderStatusChanged) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.route("order.status_changed", handler, |event| match &event.payload {
+            EventPayload::OrderStatusChanged(change) => Some(change.clone()),
+            _ => None,
+        })
+    }
+    
+    /// Handle goods of an order being handed to a carrier
+    pub fn on_order_shipped<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(ShipmentCreated) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.route("order.shipped", handler, |event| match &event.payload {
+            EventPayload::ShipmentCreated(shipment) => Some(shipment.clone()),
+            _ => None,
+        })
+    }
+    
+    /// Handle carriers reporting progress on a shipment, whichever carrier it is
+    pub fn on_tracking_updated<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(TrackingUpdated) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.route("shipment.tracking_updated", handler, |event| match &event.payload {
+            EventPayload::TrackingUpdated(update) => Some(update.clone()),
+            _ => None,
+        })
+    }
+    
+    /// Handle orders being invoiced
+    pub fn on_invoice_issued<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(InvoiceIssued) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.route("invoice.issued", handler, |event| match &event.payload {
+            EventPayload::InvoiceIssued(invoice) => Some(invoice.clone()),
+            _ => None,
+        })
+    }
+    
+    /// Handle events whose `type` is `event_type`, including types without a typed handler
+    pub fn on<F, Fut>(self, event_type: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(ApiEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        let event_type = event_type.into();
+        let name = event_type.clone();
+        self.route(name, handler, move |event| {
+            (event.payload.event_type() == event_type).then(|| event.clone())
+        })
+    }
+    
+    /// Handle every event
+    pub fn on_any<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(ApiEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.route("any", handler, |event| Some(event.clone()))
+    }
+    
+    /// Register `handler` for the events `extract` accepts
+    fn route<T, F, Fut>(
+        mut self,
+        name: impl Into<String>,
+        handler: F,
+        extract: impl Fn(&ApiEvent) -> Option<T> + Send + Sync + 'static,
+    ) -> Self
+    where
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        let handler: Handler = Arc::new(move |event| {
+            let payload = extract(event)?;
+            Some(Box::pin(handler(payload)) as HandlerFuture)
+        });
+        self.routes.push(Route {
+            name: name.into(),
+            handler,
+            policy: ErrorPolicy::default(),
+        });
+        self
+    }
+    
+    /// Set what happens when the handler registered last fails
+    ///
+    /// Handlers hand failed events to the dead-letter callback by default.
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        if let Some(route) = self.routes.last_mut() {
+            route.policy = policy;
+        }
+        self
+    }
+    
+    /// Handle up to `limit` events at once (default 1, in the order they arrive)
+    pub fn concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+    
+    /// Call `callback` with each event a handler failed to handle
+    pub fn dead_letter(mut self, callback: impl Fn(DeadLetter) + Send + Sync + 'static) -> Self {
+        self.dead_letter = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Call `callback` with the errors the event stream yields in between events
+    pub fn on_stream_error(mut self, callback: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.on_stream_error = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Run the handlers registered for `event`, returning how many ran
+    pub async fn dispatch(&self, event: &ApiEvent) -> usize {
+        self.dispatch_checked(event).await.0
+    }
+    
+    /// Run the handlers registered for `event`, returning how many ran and
+    /// the first failure neither dead-lettered nor ignored
+    async fn dispatch_checked(&self, event: &ApiEvent) -> (usize, Option<HandlerError>) {
+        let runs: Vec<_> = self
+            .routes
+            .iter()
+            .filter_map(|route| Some(self.handle(route, event, (route.handler)(event)?)))
+            .collect();
+        let handled = runs.len();
+        let unhandled = future::join_all(runs).await.into_iter().flatten().next();
+        (handled, unhandled)
+    }
+    
+    /// Dispatch every event of `events` until the stream ends
+    pub async fn run(&self, events: impl Stream<Item = crate::error::Result<ApiEvent>>) {
+        events
+            .for_each_concurrent(self.concurrency, |event| async move {
+                match event {
+                    Ok(event) => {
+                        self.dispatch(&event).await;
+                    }
+                    Err(e) => {
+                        if let Some(callback) = &self.on_stream_error {
+                            callback(&e);
+                        }
+                    }
+                }
+            })
+            .await;
+    }
+    
+    /// Run one handler as its policy says, starting with its first run
+    ///
+    /// Returns the error of a failed event that no dead-letter callback took.
+    async fn handle(&self, route: &Route, event: &ApiEvent, first: HandlerFuture) -> Option<HandlerError> {
+        let attempts = match route.policy {
+            ErrorPolicy::Retry { attempts, .. } => attempts.max(1),
+            _ => 1,
+        };
+        let mut run = Some(first);
+        let mut attempt = 1;
+        let error = loop {
+            let future = run.take().or_else(|| (route.handler)(event))?;
+            match future.await {
+                Ok(()) => return None,
+                Err(error) if attempt >= attempts => break error,
+                Err(_) => {
+                    if let ErrorPolicy::Retry { delay, .. } = route.policy {
+                        runtime::sleep(delay).await;
+                    }
+                    attempt += 1;
+                }
+            }
+        };
+        if route.policy == ErrorPolicy::Ignore {
+            return None;
+        }
+        let Some(callback) = &self.dead_letter else {
+            return Some(error);
+        };
+        callback(DeadLetter {
+            event: event.clone(),
+            handler: route.name.clone(),
+            error,
+            attempts: attempt,
+        });
+        None
+    }
+}
+
+/// Dispatches the events of verified callbacks
+///
+/// Failed handlers go to the dead-letter callback. Without one, the
+/// failure is returned, so the server answers `500` and the API delivers
+/// the event again.
+#[cfg(all(feature = "webhook-server", not(target_arch = "wasm32")))]
+#[async_trait::async_trait]
+impl crate::webhook_server::EventHandler for EventRouter {
+    async fn handle(&self, event: crate::webhooks::Event) -> Result<(), HandlerError> {
+        let (_, unhandled) = self.dispatch_checked(&ApiEvent::from(event)).await;
+        unhandled.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventSource;
+    use futures_util::stream;
+    use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    
+    fn event(event_type: &str, data: serde_json::Value) -> ApiEvent {
+        let body = serde_json::json!({"id": "evt", "type": event_type, "data": data});
+        ApiEvent::from(crate::webhooks::parse_event(body.to_string().as_bytes()).unwrap())
+    }
+    
+    #[tokio::test]
+    async fn test_dispatch_and_policies() {
+        let shipped = Arc::new(AtomicU32::new(0));
+        let letters = Arc::new(Mutex::new(Vec::new()));
+        let stream_errors = Arc::new(AtomicU32::new(0));
+        let router = {
+            let (shipped, letters, stream_errors) = (shipped.clone(), letters.clone(), stream_errors.clone());
+            EventRouter::new()
+                .on_order_shipped(move |shipment| {
+                    let attempt = shipped.fetch_add(1, Ordering::Relaxed);
+                    async move {
+                        match attempt {
+                            0 => Err("carrier API down".into()),
+                            _ if shipment.order_id == 42 => Ok(()),
+                            _ => Err("wrong order".into()),
+                        }
+                    }
+                })
+                .policy(ErrorPolicy::Retry {
+                    attempts: 2,
+                    delay: Duration::from_millis(1),
+                })
+                .on_invoice_issued(|_| async { Err("no printer".into()) })
+                .on("customer.updated", |_| async { Err("ignored".into()) })
+                .policy(ErrorPolicy::Ignore)
+                .dead_letter(move |letter| letters.lock().unwrap().push((letter.handler, letter.attempts)))
+                .on_stream_error(move |_| {
+                    stream_errors.fetch_add(1, Ordering::Relaxed);
+                })
+        };
+        
+        let events = stream::iter([
+            Ok(event("shipment.created", serde_json::json!({"shipment_id": "shp_1", "order_id": 42}))),
+            Ok(event("invoice.issued", serde_json::json!({"invoice_no": "INV-1", "order_id": 42}))),
+            Err(Error::Cancelled),
+            Ok(event("customer.updated", serde_json::json!({"customer_id": 7}))),
+            Ok(ApiEvent::new(EventSource::Push, EventPayload::Unknown {
+                event_type: "order.note_added".to_string(),
+                data: serde_json::Value::Null,
+            })),
+        ]);
+        router.run(events).await;
+        assert_eq!(shipped.load(Ordering::Relaxed), 2);
+        assert_eq!(*letters.lock().unwrap(), [("invoice.issued".to_string(), 1)]);
+        assert_eq!(stream_errors.load(Ordering::Relaxed), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let (running, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let router = {
+            let (running, most) = (running.clone(), most.clone());
+            EventRouter::new()
+                .on_any(move |_| {
+                    let (running, most) = (running.clone(), most.clone());
+                    async move {
+                        most.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(())
+                    }
+                })
+                .concurrency(2)
+        };
+        let events = (0..6).map(|_| Ok(event("customer.updated", serde_json::Value::Null)));
+        router.run(stream::iter(events)).await;
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+    
+    #[cfg(all(feature = "webhook-server", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_webhook_failures_without_dead_letter() {
+        use crate::webhook_server::WebhookReceiver;
+        use crate::webhooks::{sign, SIGNATURE_HEADER};
+        use hyper::{Body, Request, StatusCode};
+        
+        let body = serde_json::json!({
+            "id": "evt_1",
+            "type": "order.status_changed",
+            "data": {"order_id": 42, "customer_order_reference": "ORDER-42", "status_order_id": 4},
+        })
+        .to_string();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let request = || {
+            let signature = sign(b"secret", now, body.as_bytes());
+            Request::post("/").header(SIGNATURE_HEADER, signature).body(Body::from(body.clone())).unwrap()
+        };
+        let failing = || EventRouter::new().on_order_status_changed(|_| async { Err("database down".into()) });
+        
+        // Redelivered by the API
+        let receiver = WebhookReceiver::new(b"secret".to_vec(), failing());
+        assert_eq!(receiver.handle(request()).await.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        
+        // Taken by the dead-letter callback or ignored
+        let receiver = WebhookReceiver::new(b"secret".to_vec(), failing().dead_letter(|_| {}));
+        assert_eq!(receiver.handle(request()).await.status(), StatusCode::NO_CONTENT);
+        let receiver = WebhookReceiver::new(b"secret".to_vec(), failing().policy(ErrorPolicy::Ignore));
+        assert_eq!(receiver.handle(request()).await.status(), StatusCode::NO_CONTENT);
+    }
+}
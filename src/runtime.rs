@@ -0,0 +1,55 @@
+//! Clocks and timers that work natively and on wasm32
+//!
+//! `std::time::Instant` and `SystemTime` panic on `wasm32-unknown-unknown`
+//! and tokio's timers need a tokio runtime, so the browser's clock and
+//! `setTimeout` are used there instead.
+
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+/// Current wall-clock time
+pub(crate) fn system_now() -> SystemTime {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        SystemTime::now()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let since_epoch = web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .unwrap_or_default();
+        SystemTime::UNIX_EPOCH + since_epoch
+    }
+}
+
+/// Wait for `duration`
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(duration).await;
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Run `future` to completion unless `duration` elapses first
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    tokio::select! {
+        output = future => Some(output),
+        _ = sleep(duration) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_timeout() {
+        assert_eq!(timeout(Duration::from_secs(1), async { 7 }).await, Some(7));
+        assert_eq!(timeout(Duration::from_millis(1), std::future::pending::<()>()).await, None);
+    }
+}
@@ -0,0 +1,109 @@
+//! All-or-nothing submission of related orders
+//!
+//! A customer order split across warehouses becomes several API orders.
+//! [`Client::create_orders_saga`](crate::Client::create_orders_saga) submits
+//! them together and, if the API rejects any of them, cancels the ones it
+//! already created, so the customer is never left with half an order.
+
+use crate::bulk::BulkReport;
+use crate::error::Result;
+use crate::types::{Order, OrderId};
+
+/// A cancellation sent to undo an order of a failed group
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Compensation {
+    /// Position of the order in the submitted group
+    pub index: usize,
+    /// The order that was cancelled
+    pub order_id: OrderId,
+    /// The cancelled order, or why it could not be cancelled
+    pub result: Result<Order>,
+}
+
+impl Compensation {
+    /// Whether the order was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Outcome of an all-or-nothing submission
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SagaReport {
+    /// Outcome of creating each order
+    pub report: BulkReport,
+    /// Cancellations sent because an order was rejected, in index order
+    pub compensations: Vec<Compensation>,
+    /// Prefix of the orders' idempotency keys, to resubmit the group with
+    /// through [`BulkOptions::idempotency_prefix`](crate::BulkOptions::idempotency_prefix)
+    pub idempotency_prefix: String,
+}
+
+impl SagaReport {
+    /// Whether every order was created and nothing had to be undone
+    pub fn is_committed(&self) -> bool {
+        self.report.is_complete()
+    }
+    
+    /// Whether an order was rejected and every created order was cancelled again
+    ///
+    /// Not while an order failed transiently, as it may exist on the server.
+    pub fn is_rolled_back(&self) -> bool {
+        !self.report.permanent.is_empty()
+            && self.report.retryable.is_empty()
+            && self.compensations.iter().all(Compensation::is_cancelled)
+    }
+    
+    /// Cancellations that failed, leaving their orders to be undone by hand
+    pub fn failed_compensations(&self) -> impl Iterator<Item = &Compensation> {
+        self.compensations.iter().filter(|compensation| !compensation.is_cancelled())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    
+    fn compensation(index: usize, result: Result<Order>) -> Compensation {
+        Compensation {
+            index,
+            order_id: OrderId(index.to_string()),
+            result,
+        }
+    }
+    
+    #[test]
+    fn test_saga_outcome() {
+        let committed = SagaReport {
+            report: BulkReport::default(),
+            compensations: Vec::new(),
+            idempotency_prefix: "group-1".to_string(),
+        };
+        assert!(committed.is_committed());
+        assert!(!committed.is_rolled_back());
+        
+        let report = BulkReport::from_results(vec![(0, Err(Error::UnprocessableEntity("invalid".into())))]);
+        let stuck = SagaReport {
+            report,
+            compensations: vec![compensation(1, Err(Error::NotFound("gone".to_string())))],
+            idempotency_prefix: "group-1".to_string(),
+        };
+        assert!(!stuck.is_committed());
+        assert!(!stuck.is_rolled_back());
+        assert_eq!(stuck.failed_compensations().map(|failed| failed.index).collect::<Vec<_>>(), [1]);
+        
+        let report = BulkReport::from_results(vec![
+            (0, Err(Error::UnprocessableEntity("invalid".into()))),
+            (1, Err(Error::ServerError(503, "unavailable".to_string(), None))),
+        ]);
+        let unresolved = SagaReport {
+            report,
+            compensations: Vec::new(),
+            idempotency_prefix: "group-1".to_string(),
+        };
+        assert!(!unresolved.is_rolled_back());
+    }
+}
@@ -0,0 +1,173 @@
+//! Graceful shutdown with in-flight request draining
+
+use crate::error::{Error, Result};
+use reqwest::Method;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::runtime::{self, Instant};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+/// A call that was still running when the shutdown deadline passed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbandonedRequest {
+    /// HTTP method of the call
+    pub method: Method,
+    /// URL of the call
+    pub url: String,
+    /// How long the call had been running, including retries
+    pub elapsed: Duration,
+}
+
+/// Outcome of [`Client::shutdown`](crate::Client::shutdown)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Calls that finished while draining
+    pub drained: usize,
+    /// Calls that were cancelled because they outlived the deadline
+    pub abandoned: Vec<AbandonedRequest>,
+}
+
+impl ShutdownReport {
+    /// Check whether every in-flight call finished in time
+    pub fn is_clean(&self) -> bool {
+        self.abandoned.is_empty()
+    }
+}
+
+/// Tracks in-flight calls so a client can be shut down gracefully
+#[derive(Debug, Default)]
+pub(crate) struct Lifecycle {
+    state: Mutex<State>,
+    idle: Notify,
+    abandon: CancellationToken,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    closed: bool,
+    next_id: u64,
+    in_flight: HashMap<u64, (Method, String, Instant)>,
+}
+
+impl Lifecycle {
+    /// Register a call, failing once shutdown has started
+    pub(crate) fn enter(&self, method: Method, url: String) -> Result<InFlight<'_>> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(Error::Shutdown);
+        }
+        
+        let id = state.next_id;
+        state.next_id += 1;
+        state.in_flight.insert(id, (method, url, Instant::now()));
+        Ok(InFlight { lifecycle: self, id })
+    }
+    
+    /// Resolves when calls still running after the deadline are abandoned
+    pub(crate) async fn abandoned(&self) {
+        self.abandon.cancelled().await
+    }
+    
+    /// Stop accepting calls and wait up to `deadline` for running ones
+    pub(crate) async fn shutdown(&self, deadline: Duration) -> ShutdownReport {
+        let running = {
+            let mut state = self.lock();
+            state.closed = true;
+            state.in_flight.len()
+        };
+        
+        let _ = runtime::timeout(deadline, async {
+            loop {
+                let idle = self.idle.notified();
+                tokio::pin!(idle);
+                idle.as_mut().enable();
+                if self.lock().in_flight.is_empty() {
+                    break;
+                }
+                idle.await;
+            }
+        })
+        .await;
+        
+        let abandoned: Vec<AbandonedRequest> = self
+            .lock()
+            .in_flight
+            .values()
+            .map(|(method, url, started)| AbandonedRequest {
+                method: method.clone(),
+                url: url.clone(),
+                elapsed: started.elapsed(),
+            })
+            .collect();
+        self.abandon.cancel();
+        
+        ShutdownReport {
+            drained: running.saturating_sub(abandoned.len()),
+            abandoned,
+        }
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registration of a running call, removed on drop
+pub(crate) struct InFlight<'a> {
+    lifecycle: &'a Lifecycle,
+    id: u64,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        let mut state = self.lifecycle.lock();
+        state.in_flight.remove(&self.id);
+        if state.in_flight.is_empty() {
+            self.lifecycle.idle.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_shutdown_idle() {
+        let lifecycle = Lifecycle::default();
+        drop(lifecycle.enter(Method::GET, "https://api.example.com".to_string()).unwrap());
+        
+        let report = lifecycle.shutdown(Duration::from_secs(1)).await;
+        assert!(report.is_clean());
+        assert_eq!(report.drained, 0);
+        assert!(matches!(
+            lifecycle.enter(Method::GET, "https://api.example.com".to_string()),
+            Err(Error::Shutdown)
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_shutdown_drains_and_abandons() {
+        let lifecycle = std::sync::Arc::new(Lifecycle::default());
+        let quick = lifecycle.enter(Method::GET, "https://api.example.com/quick".to_string()).unwrap();
+        let _slow = lifecycle.enter(Method::POST, "https://api.example.com/slow".to_string()).unwrap();
+        
+        let draining = lifecycle.shutdown(Duration::from_millis(50));
+        tokio::pin!(draining);
+        tokio::select! {
+            _ = &mut draining => panic!("shutdown finished before the calls"),
+            _ = tokio::time::sleep(Duration::from_millis(10)) => drop(quick),
+        }
+        
+        let report = draining.await;
+        assert_eq!(report.drained, 1);
+        assert_eq!(report.abandoned.len(), 1);
+        assert_eq!(report.abandoned[0].method, Method::POST);
+        assert_eq!(report.abandoned[0].url, "https://api.example.com/slow");
+        
+        // Abandoned calls are told to stop
+        lifecycle.abandoned().await;
+    }
+}
@@ -0,0 +1,184 @@
+//! Coalescing of identical concurrent reads
+//!
+//! With [`Client::with_request_coalescing`](crate::Client::with_request_coalescing)
+//! set, a read that is already in flight is not sent again: later callers
+//! wait for the first one and share its result. Fifty workers asking for
+//! the same order at once then cost one request instead of fifty.
+
+use crate::error::{Error, Result};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Result of a call as seen by its waiters: unset while in flight, with no
+/// error if the error could not be copied
+type Outcome<R> = Option<std::result::Result<R, Option<Error>>>;
+
+/// Calls in flight, keyed by the type they return and a key naming the request
+type Calls = HashMap<(TypeId, String), Arc<dyn Any + Send + Sync>>;
+
+/// Calls in flight, shared between clones
+#[derive(Debug, Default)]
+pub(crate) struct SingleFlight {
+    calls: Mutex<Calls>,
+}
+
+impl SingleFlight {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, Calls> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Run `call`, or wait for the call already in flight under `key` and share its result
+    ///
+    /// Errors are shared too, unless they wrap an error from another crate
+    /// and cannot be copied. Then, or if the call in flight is cancelled,
+    /// one waiter runs `call` again and the others wait for it in turn.
+    pub(crate) async fn run<R, F>(&self, key: String, call: F) -> Result<R>
+    where
+        R: Clone + Send + Sync + 'static,
+        F: Future<Output = Result<R>>,
+    {
+        let key = (TypeId::of::<R>(), key);
+        loop {
+            let waiting = {
+                let mut calls = self.lock();
+                match calls.get(&key).and_then(|flight| flight.clone().downcast::<watch::Sender<Outcome<R>>>().ok()) {
+                    Some(sender) => Some(sender.subscribe()),
+                    None => {
+                        calls.insert(key.clone(), Arc::new(watch::channel::<Outcome<R>>(None).0));
+                        None
+                    }
+                }
+            };
+            let Some(mut receiver) = waiting else {
+                break;
+            };
+            let Ok(outcome) = receiver.wait_for(Option::is_some).await else {
+                continue;
+            };
+            match &*outcome {
+                Some(Ok(shared)) => return Ok(shared.clone()),
+                Some(Err(Some(error))) => {
+                    if let Some(error) = error.try_clone() {
+                        return Err(error);
+                    }
+                }
+                _ => {}
+            }
+        }
+        
+        let leader = Leader::<R> {
+            flight: self,
+            key: Some(key),
+            marker: std::marker::PhantomData,
+        };
+        let result = call.await;
+        leader.finish(result.as_ref().cloned().map_err(Error::try_clone));
+        result
+    }
+}
+
+/// Removes its call from the map when done, or when dropped because the call was cancelled
+struct Leader<'a, R: Send + Sync + 'static> {
+    flight: &'a SingleFlight,
+    key: Option<(TypeId, String)>,
+    marker: std::marker::PhantomData<R>,
+}
+
+impl<R: Send + Sync + 'static> Leader<'_, R> {
+    fn finish(mut self, result: std::result::Result<R, Option<Error>>) {
+        if let Some(sender) = self.remove() {
+            sender.send_replace(Some(result));
+        }
+    }
+    
+    fn remove(&mut self) -> Option<Arc<watch::Sender<Outcome<R>>>> {
+        let flight = self.flight.lock().remove(&self.key.take()?)?;
+        flight.downcast().ok()
+    }
+}
+
+impl<R: Send + Sync + 'static> Drop for Leader<'_, R> {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the waiters, one of which then runs the call again
+        self.remove();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one() {
+        let flight = SingleFlight::new();
+        let calls = AtomicU32::new(0);
+        let call = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+            Ok::<_, Error>(7)
+        };
+        
+        let (first, second, other) = tokio::join!(
+            flight.run("a".to_string(), call()),
+            flight.run("a".to_string(), call()),
+            flight.run("b".to_string(), call()),
+        );
+        assert_eq!((first.unwrap(), second.unwrap(), other.unwrap()), (7, 7, 7));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert!(flight.lock().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_failure_is_shared() {
+        let flight = SingleFlight::new();
+        let calls = AtomicU32::new(0);
+        let call = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+            Err::<u32, _>(Error::NotFound("Order 1".to_string()))
+        };
+        
+        let (first, second, third) = tokio::join!(
+            flight.run("a".to_string(), call()),
+            flight.run("a".to_string(), call()),
+            flight.run("a".to_string(), call()),
+        );
+        for result in [first, second, third] {
+            assert!(matches!(result, Err(Error::NotFound(message)) if message == "Order 1"));
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_uncopyable_failure_retried_once() {
+        let flight = SingleFlight::new();
+        let calls = AtomicU32::new(0);
+        let call = || async {
+            let attempt = calls.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+            match attempt {
+                0 => Err(Error::transport("connection reset")),
+                _ => Ok(attempt),
+            }
+        };
+        
+        // One waiter retries and the other shares its result
+        let (first, second, third) = tokio::join!(
+            flight.run("a".to_string(), call()),
+            flight.run("a".to_string(), call()),
+            flight.run("a".to_string(), call()),
+        );
+        assert!(matches!(first, Err(Error::Transport { .. })));
+        assert_eq!((second.unwrap(), third.unwrap()), (1, 1));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert!(flight.lock().is_empty());
+    }
+}
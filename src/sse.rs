@@ -0,0 +1,175 @@
+//! Server-Sent Events from the API's event stream (`sse` feature)
+//!
+//! [`Client::subscribe_events`](crate::Client::subscribe_events) keeps a
+//! `text/event-stream` connection to the events endpoint open and yields
+//! each message as an [`ApiEvent`]. A message names its type in the `event`
+//! field and carries the same JSON `data` as the webhook event of that type.
+//! Dropped connections are reopened with the `Last-Event-ID` of the last
+//! message received, so no event is missed in between.
+
+use crate::error::Result;
+use crate::events::{ApiEvent, EventSource};
+use crate::webhooks;
+use std::time::Duration;
+
+/// A complete message of an event stream
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Message {
+    /// Last event id set when the message was dispatched
+    pub(crate) id: Option<String>,
+    /// The `event` field, `message` if unset
+    pub(crate) event: String,
+    /// The `data` lines, joined with newlines
+    pub(crate) data: String,
+}
+
+impl Message {
+    /// The event the message carries
+    pub(crate) fn into_event(self) -> Result<ApiEvent> {
+        let data: serde_json::Value = serde_json::from_str(&self.data)?;
+        let raw = serde_json::json!({
+            "id": self.id.clone().unwrap_or_default(),
+            "type": self.event,
+            "data": data,
+        });
+        let mut event = ApiEvent::from(webhooks::parse_event(raw.to_string().as_bytes())?);
+        event.id = self.id;
+        event.source = EventSource::ServerSent;
+        Ok(event)
+    }
+}
+
+/// Splits the bytes of an event stream into messages
+///
+/// The last event id and reconnection delay outlive the connection, so one
+/// decoder is kept across reconnects and [`reset`](Self::reset) in between.
+#[derive(Debug, Default)]
+pub(crate) struct Decoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Option<String>,
+    /// Id set by the message being received
+    id: Option<String>,
+    /// Id of the last message, to send as `Last-Event-ID` when reconnecting
+    pub(crate) last_event_id: Option<String>,
+    /// Reconnection delay last asked for by the server
+    pub(crate) retry: Option<Duration>,
+}
+
+impl Decoder {
+    /// A decoder for a stream resuming after the message with `last_event_id`
+    pub(crate) fn new(last_event_id: Option<String>) -> Self {
+        Self {
+            id: last_event_id.clone(),
+            last_event_id,
+            ..Self::default()
+        }
+    }
+    
+    /// Drop a partly received message, e.g. after the connection was lost
+    pub(crate) fn reset(&mut self) {
+        self.buffer.clear();
+        self.event = None;
+        self.data = None;
+        self.id = self.last_event_id.clone();
+    }
+    
+    /// Feed the next chunk, returning the messages it completes
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<Message> {
+        self.buffer.extend_from_slice(chunk);
+        let mut messages = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if line.is_empty() {
+                messages.extend(self.dispatch());
+            } else {
+                self.field(line);
+            }
+        }
+        messages
+    }
+    
+    /// Apply one `field: value` line; lines starting with `:` are comments
+    fn field(&mut self, line: &str) {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match name {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()).filter(|id| !id.is_empty()),
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Finish the message at a blank line; blocks without data only set the id
+    fn dispatch(&mut self) -> Option<Message> {
+        self.last_event_id = self.id.clone();
+        let event = self.event.take();
+        let data = self.data.take()?;
+        Some(Message {
+            id: self.last_event_id.clone(),
+            event: event.unwrap_or_else(|| "message".to_string()),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::EventPayload;
+    
+    #[test]
+    fn test_decoder() {
+        let mut decoder = Decoder::new(None);
+        assert!(decoder.push(b": keep-alive\r\n\r\nretry: 2500\nid: 7\nevent: order.status_changed\ndata: {\"order_id\":").is_empty());
+        assert_eq!(decoder.retry, Some(Duration::from_millis(2500)));
+        assert_eq!(decoder.last_event_id, None);
+        let messages = decoder.push(b"42,\ndata:\"status_order_id\":3}\r\n\r\ndata: x\n");
+        assert_eq!(messages, [Message {
+            id: Some("7".to_string()),
+            event: "order.status_changed".to_string(),
+            data: "{\"order_id\":42,\n\"status_order_id\":3}".to_string(),
+        }]);
+        
+        decoder.reset();
+        assert!(decoder.push(b"id: 8\n").is_empty());
+        decoder.reset();
+        assert!(decoder.push(b"\n").is_empty());
+        assert_eq!(decoder.last_event_id.as_deref(), Some("7"));
+    }
+    
+    #[test]
+    fn test_into_event() {
+        let message = Message {
+            id: Some("7".to_string()),
+            event: "order.status_changed".to_string(),
+            data: r#"{"order_id":42,"status_order_id":3}"#.to_string(),
+        };
+        let event = message.into_event().unwrap();
+        assert_eq!((event.id.as_deref(), event.source), (Some("7"), EventSource::ServerSent));
+        assert!(matches!(event.payload, EventPayload::OrderStatusChanged(change) if change.order_id == 42));
+        
+        let message = Message {
+            id: None,
+            event: "message".to_string(),
+            data: "not json".to_string(),
+        };
+        assert!(message.into_event().is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Local record of the orders a client created
+//!
+//! With an [`OrderStore`] set through
+//! [`Client::with_order_store`](crate::Client::with_order_store), every order
+//! the client creates is recorded along with its line items. Reading or
+//! cancelling a recorded order updates its last known remote state, so the
+//! store can be queried offline and compared with the API later.
+
+use crate::types::{Order, OrderProduct};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// An order created through the client, as last seen from the API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct StoredOrder {
+    /// Last known remote state of the order
+    pub order: Order,
+    /// Line items returned when the order was created
+    #[serde(default)]
+    pub order_products: Vec<OrderProduct>,
+    /// When the order was created
+    pub recorded_at: SystemTime,
+    /// When the remote state was last read
+    pub refreshed_at: SystemTime,
+}
+
+impl StoredOrder {
+    /// Record of an order just created at `now`
+    pub fn new(order: Order, order_products: Vec<OrderProduct>, now: SystemTime) -> Self {
+        Self {
+            order,
+            order_products,
+            recorded_at: now,
+            refreshed_at: now,
+        }
+    }
+}
+
+/// Which stored orders to return from [`OrderStore::query`]
+///
+/// Every condition that is set must hold; an empty query matches every order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderQuery {
+    status_order_id: Option<u64>,
+    customer_order_reference: Option<String>,
+    recorded_from: Option<SystemTime>,
+    recorded_until: Option<SystemTime>,
+}
+
+impl OrderQuery {
+    /// A query matching every order
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Only orders last seen with this status
+    pub fn status(mut self, status_order_id: u64) -> Self {
+        self.status_order_id = Some(status_order_id);
+        self
+    }
+    
+    /// Only the order with this customer order reference
+    pub fn reference(mut self, reference: impl Into<String>) -> Self {
+        self.customer_order_reference = Some(reference.into());
+        self
+    }
+    
+    /// Only orders created at or after `from` and before `until`
+    pub fn recorded_between(mut self, from: SystemTime, until: SystemTime) -> Self {
+        self.recorded_from = Some(from);
+        self.recorded_until = Some(until);
+        self
+    }
+    
+    /// Whether `stored` matches the query
+    pub fn matches(&self, stored: &StoredOrder) -> bool {
+        self.status_order_id.is_none_or(|status| stored.order.status_order_id == status)
+            && self
+                .customer_order_reference
+                .as_ref()
+                .is_none_or(|reference| &stored.order.customer_order_reference == reference)
+            && self.recorded_from.is_none_or(|from| stored.recorded_at >= from)
+            && self.recorded_until.is_none_or(|until| stored.recorded_at < until)
+    }
+}
+
+/// Persists the orders a client created
+///
+/// Failures are reported to callers of the store, but the client does not
+/// fail calls because an order could not be recorded; it passes the failure
+/// to its [error callback](crate::Client::with_on_error) instead, and a
+/// recorded order is brought up to date the next time it is read.
+#[async_trait]
+pub trait OrderStore: Send + Sync + 'static {
+    /// Insert `stored`, or replace the order with the same id
+    async fn put(&self, stored: &StoredOrder) -> io::Result<()>;
+    
+    /// The order with `order_id`, if recorded
+    async fn get(&self, order_id: u64) -> io::Result<Option<StoredOrder>>;
+    
+    /// All recorded orders, by id
+    async fn orders(&self) -> io::Result<Vec<StoredOrder>>;
+    
+    /// The recorded orders matching `query`, by id
+    async fn query(&self, query: &OrderQuery) -> io::Result<Vec<StoredOrder>> {
+        let orders = self.orders().await?;
+        Ok(orders.into_iter().filter(|stored| query.matches(stored)).collect())
+    }
+}
+
+#[async_trait]
+impl<S: OrderStore> OrderStore for Arc<S> {
+    async fn put(&self, stored: &StoredOrder) -> io::Result<()> {
+        (**self).put(stored).await
+    }
+    
+    async fn get(&self, order_id: u64) -> io::Result<Option<StoredOrder>> {
+        (**self).get(order_id).await
+    }
+    
+    async fn orders(&self) -> io::Result<Vec<StoredOrder>> {
+        (**self).orders().await
+    }
+    
+    async fn query(&self, query: &OrderQuery) -> io::Result<Vec<StoredOrder>> {
+        (**self).query(query).await
+    }
+}
+
+/// Keeps orders in memory, e.g. for tests
+#[derive(Debug, Default)]
+pub struct MemoryOrderStore {
+    orders: Mutex<BTreeMap<u64, StoredOrder>>,
+}
+
+impl MemoryOrderStore {
+    /// An empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, StoredOrder>> {
+        self.orders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl OrderStore for MemoryOrderStore {
+    async fn put(&self, stored: &StoredOrder) -> io::Result<()> {
+        self.lock().insert(stored.order.id, stored.clone());
+        Ok(())
+    }
+    
+    async fn get(&self, order_id: u64) -> io::Result<Option<StoredOrder>> {
+        Ok(self.lock().get(&order_id).cloned())
+    }
+    
+    async fn orders(&self) -> io::Result<Vec<StoredOrder>> {
+        Ok(self.lock().values().cloned().collect())
+    }
+}
+
+/// Keeps orders in a [sled](https://docs.rs/sled) tree, keyed by id
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledOrderStore {
+    tree: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledOrderStore {
+    /// Store orders in `tree`, e.g. `db.open_tree("orders")`
+    pub fn new(tree: sled::Tree) -> Self {
+        Self { tree }
+    }
+}
+
+#[cfg(feature = "sled")]
+#[async_trait]
+impl OrderStore for SledOrderStore {
+    async fn put(&self, stored: &StoredOrder) -> io::Result<()> {
+        // Big-endian keys keep the tree sorted by id
+        self.tree.insert(stored.order.id.to_be_bytes(), serde_json::to_vec(stored)?)?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+    
+    async fn get(&self, order_id: u64) -> io::Result<Option<StoredOrder>> {
+        match self.tree.get(order_id.to_be_bytes())? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    async fn orders(&self) -> io::Result<Vec<StoredOrder>> {
+        let mut orders = Vec::new();
+        for item in self.tree.iter() {
+            let (_, value) = item?;
+            orders.push(serde_json::from_slice::<StoredOrder>(&value)?);
+        }
+        Ok(orders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    fn stored(id: u64, status: u64, seconds: u64) -> StoredOrder {
+        let order = serde_json::from_value(serde_json::json!({
+            "id": id,
+            "status_order_id": status,
+            "customer_id": 7,
+            "customer_order_reference": format!("ORDER-{}", id),
+            "gross_total": "10.00",
+            "addressbook_id": 3,
+        }))
+        .unwrap();
+        StoredOrder::new(order, Vec::new(), SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+    
+    #[tokio::test]
+    async fn test_query() {
+        let store = MemoryOrderStore::new();
+        store.put(&stored(2, 1, 20)).await.unwrap();
+        store.put(&stored(1, 4, 10)).await.unwrap();
+        store.put(&stored(3, 1, 30)).await.unwrap();
+        
+        let ids = |orders: Vec<StoredOrder>| orders.iter().map(|stored| stored.order.id).collect::<Vec<_>>();
+        assert_eq!(ids(store.orders().await.unwrap()), [1, 2, 3]);
+        assert_eq!(ids(store.query(&OrderQuery::new().status(1)).await.unwrap()), [2, 3]);
+        assert_eq!(ids(store.query(&OrderQuery::new().reference("ORDER-3")).await.unwrap()), [3]);
+        let from = SystemTime::UNIX_EPOCH + Duration::from_secs(10);
+        let until = SystemTime::UNIX_EPOCH + Duration::from_secs(30);
+        assert_eq!(ids(store.query(&OrderQuery::new().recorded_between(from, until)).await.unwrap()), [1, 2]);
+        assert_eq!(store.get(4).await.unwrap(), None);
+    }
+    
+    #[cfg(feature = "sled")]
+    #[tokio::test]
+    async fn test_sled_store() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let store = SledOrderStore::new(db.open_tree("orders").unwrap());
+        store.put(&stored(300, 1, 1)).await.unwrap();
+        store.put(&stored(2, 1, 2)).await.unwrap();
+        store.put(&stored(2, 4, 2)).await.unwrap();
+        assert_eq!(store.get(2).await.unwrap(), Some(stored(2, 4, 2)));
+        assert_eq!(store.orders().await.unwrap(), [stored(2, 4, 2), stored(300, 1, 1)]);
+    }
+}
@@ -0,0 +1,410 @@
+//! TLS configuration for the underlying HTTP client
+
+use crate::error::{Error, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+#[cfg(feature = "rustls")]
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use sha2::{Digest, Sha256};
+use std::fmt;
+#[cfg(feature = "rustls")]
+use std::sync::Arc;
+#[cfg(feature = "rustls")]
+use std::time::SystemTime;
+use zeroize::Zeroizing;
+
+/// TLS implementation used by the HTTP client
+///
+/// Selected at build time through the `rustls` (default) and `native-tls`
+/// features. With both enabled, rustls is used unless the builder asks for
+/// [`TlsBackend::NativeTls`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TlsBackend {
+    /// rustls with the bundled web PKI roots
+    #[cfg(feature = "rustls")]
+    #[default]
+    Rustls,
+    /// The platform's TLS library and certificate store (OpenSSL, Schannel,
+    /// Secure Transport), for corporate roots and MITM proxies
+    #[cfg(feature = "native-tls")]
+    #[cfg_attr(not(feature = "rustls"), default)]
+    NativeTls,
+}
+
+/// Client certificate and private key used for mutual TLS
+#[derive(Clone)]
+pub struct Identity {
+    /// DER certificate chain, leaf first
+    certs: Vec<Vec<u8>>,
+    /// DER private key
+    key: Zeroizing<Vec<u8>>,
+    key_format: KeyFormat,
+}
+
+/// Encoding of a private key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyFormat {
+    Pkcs8,
+    Rsa,
+    Sec1,
+}
+
+impl KeyFormat {
+    fn pem_label(self) -> &'static str {
+        match self {
+            KeyFormat::Pkcs8 => "PRIVATE KEY",
+            KeyFormat::Rsa => "RSA PRIVATE KEY",
+            KeyFormat::Sec1 => "EC PRIVATE KEY",
+        }
+    }
+}
+
+/// Additional trusted root certificate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    der: Vec<u8>,
+}
+
+/// SHA-256 fingerprint of a pinned server certificate
+///
+/// Pins are matched against the DER encoding of the server's end-entity
+/// certificate, after the normal chain verification has succeeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CertificatePin([u8; 32]);
+
+impl Identity {
+    /// Load an identity from a PEM certificate (chain) and PEM private key
+    ///
+    /// PKCS#8, RSA and SEC1 EC keys are accepted; the `native-tls` backend
+    /// only supports PKCS#8 keys.
+    pub fn from_pem(cert_pem: &[u8], key_pem: &[u8]) -> Result<Self> {
+        let mut pem = Zeroizing::new(Vec::with_capacity(cert_pem.len() + key_pem.len() + 1));
+        pem.extend_from_slice(cert_pem);
+        pem.push(b'\n');
+        pem.extend_from_slice(key_pem);
+        Self::from_pem_bundle(&pem)
+    }
+    
+    /// Load an identity from a single PEM buffer holding both certificate and key
+    pub fn from_pem_bundle(pem: &[u8]) -> Result<Self> {
+        let items = rustls_pemfile::read_all(&mut &pem[..])
+            .map_err(|e| Error::Tls(format!("Invalid client identity: {}", e)))?;
+        
+        let mut certs = Vec::new();
+        let mut key = None;
+        for item in items {
+            match item {
+                rustls_pemfile::Item::X509Certificate(der) => certs.push(der),
+                rustls_pemfile::Item::PKCS8Key(der) => key = key.or(Some((der, KeyFormat::Pkcs8))),
+                rustls_pemfile::Item::RSAKey(der) => key = key.or(Some((der, KeyFormat::Rsa))),
+                rustls_pemfile::Item::ECKey(der) => key = key.or(Some((der, KeyFormat::Sec1))),
+                _ => {}
+            }
+        }
+        
+        let (key, key_format) =
+            key.ok_or_else(|| Error::Tls("Client identity has no private key".to_string()))?;
+        let key = Zeroizing::new(key);
+        if certs.is_empty() {
+            return Err(Error::Tls("Client identity has no certificate".to_string()));
+        }
+        Ok(Self { certs, key, key_format })
+    }
+    
+    /// Load an identity from a DER-encoded PKCS#12 archive
+    #[cfg(feature = "pkcs12")]
+    pub fn from_pkcs12_der(der: &[u8], password: &str) -> Result<Self> {
+        let keystore = p12_keystore::KeyStore::from_pkcs12(der, password)
+            .map_err(|e| Error::Tls(format!("Invalid PKCS#12 identity: {}", e)))?;
+        let (_, chain) = keystore
+            .private_key_chain()
+            .ok_or_else(|| Error::Tls("PKCS#12 archive contains no private key".to_string()))?;
+        
+        Ok(Self {
+            certs: chain.chain().iter().map(|cert| cert.as_der().to_vec()).collect(),
+            key: Zeroizing::new(chain.key().to_vec()),
+            key_format: KeyFormat::Pkcs8,
+        })
+    }
+    
+    /// Convert into a reqwest identity for the given backend
+    pub(crate) fn to_reqwest(&self, backend: TlsBackend) -> Result<reqwest::Identity> {
+        let invalid = |e: reqwest::Error| Error::Tls(format!("Invalid client identity: {}", e));
+        let mut certs = Zeroizing::new(String::new());
+        for cert in &self.certs {
+            certs.push_str(&pem_encode("CERTIFICATE", cert));
+        }
+        let key = pem_encode(self.key_format.pem_label(), &self.key);
+        
+        match backend {
+            #[cfg(feature = "rustls")]
+            TlsBackend::Rustls => {
+                certs.push_str(&key);
+                reqwest::Identity::from_pem(certs.as_bytes()).map_err(invalid)
+            }
+            #[cfg(feature = "native-tls")]
+            TlsBackend::NativeTls => {
+                if self.key_format != KeyFormat::Pkcs8 {
+                    return Err(Error::Tls("native-tls requires a PKCS#8 private key".to_string()));
+                }
+                reqwest::Identity::from_pkcs8_pem(certs.as_bytes(), key.as_bytes()).map_err(invalid)
+            }
+        }
+    }
+    
+    /// Split the identity into a rustls certificate chain and private key
+    #[cfg(feature = "rustls")]
+    fn to_rustls(&self) -> (Vec<rustls::Certificate>, rustls::PrivateKey) {
+        let certs = self.certs.iter().cloned().map(rustls::Certificate).collect();
+        (certs, rustls::PrivateKey(self.key.to_vec()))
+    }
+}
+
+/// Encode DER data as a PEM block
+fn pem_encode(label: &str, der: &[u8]) -> Zeroizing<String> {
+    let encoded = Zeroizing::new(STANDARD.encode(der));
+    let mut pem = Zeroizing::new(format!("-----BEGIN {}-----\n", label));
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+impl Certificate {
+    /// Load a DER-encoded certificate
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        reqwest::Certificate::from_der(der)
+            .map_err(|e| Error::Tls(format!("Invalid certificate: {}", e)))?;
+        Ok(Self { der: der.to_vec() })
+    }
+    
+    /// Load the first certificate from a PEM buffer
+    pub fn from_pem(pem: &[u8]) -> Result<Self> {
+        Self::from_pem_bundle(pem)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Tls("No certificate found in PEM".to_string()))
+    }
+    
+    /// Load every certificate from a PEM buffer
+    pub fn from_pem_bundle(pem: &[u8]) -> Result<Vec<Self>> {
+        rustls_pemfile::certs(&mut &pem[..])
+            .map_err(|e| Error::Tls(format!("Invalid PEM certificate: {}", e)))
+            .map(|certs| certs.into_iter().map(|der| Self { der }).collect())
+    }
+    
+    /// The DER encoding of the certificate
+    pub fn as_der(&self) -> &[u8] {
+        &self.der
+    }
+    
+    pub(crate) fn to_reqwest(&self) -> Result<reqwest::Certificate> {
+        reqwest::Certificate::from_der(&self.der)
+            .map_err(|e| Error::Tls(format!("Invalid certificate: {}", e)))
+    }
+}
+
+impl CertificatePin {
+    /// Pin the given certificate
+    pub fn from_certificate(certificate: &Certificate) -> Self {
+        Self::of_der(certificate.as_der())
+    }
+    
+    /// Parse a hex SHA-256 fingerprint, with or without `:` separators
+    pub fn from_sha256_hex(fingerprint: &str) -> Result<Self> {
+        let hex: String = fingerprint.chars().filter(|c| *c != ':').collect();
+        let invalid = || Error::Tls(format!("Invalid SHA-256 fingerprint: {}", fingerprint));
+        
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        
+        let mut digest = [0u8; 32];
+        for (byte, chunk) in digest.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let chunk = std::str::from_utf8(chunk).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(chunk, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(digest))
+    }
+    
+    fn of_der(der: &[u8]) -> Self {
+        Self(Sha256::digest(der).into())
+    }
+}
+
+/// Verifier enforcing certificate pins on top of WebPKI chain validation
+#[cfg(feature = "rustls")]
+struct PinnedVerifier {
+    inner: WebPkiVerifier,
+    pins: Vec<CertificatePin>,
+}
+
+#[cfg(feature = "rustls")]
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        intermediates: &[rustls::Certificate],
+        server_name: &rustls::ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        self.inner
+            .verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+        
+        if self.pins.contains(&CertificatePin::of_der(&end_entity.0)) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General("server certificate does not match any pin".to_string()))
+        }
+    }
+}
+
+/// Build a rustls configuration trusting the web PKI plus extra roots and
+/// enforcing the given certificate pins
+#[cfg(feature = "rustls")]
+pub(crate) fn pinned_config(
+    root_certificates: &[Certificate],
+    pins: Vec<CertificatePin>,
+    identity: Option<&Identity>,
+) -> Result<rustls::ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+    for certificate in root_certificates {
+        roots
+            .add(&rustls::Certificate(certificate.der.clone()))
+            .map_err(|e| Error::Tls(format!("Invalid root certificate: {}", e)))?;
+    }
+    
+    let verifier = PinnedVerifier {
+        inner: WebPkiVerifier::new(roots, None),
+        pins,
+    };
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier));
+    
+    let mut config = match identity {
+        Some(identity) => {
+            let (certs, key) = identity.to_rustls();
+            builder
+                .with_client_auth_cert(certs, key)
+                .map_err(|e| Error::Tls(format!("Invalid client identity: {}", e)))?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Identity").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const CLIENT_CERT: &[u8] = include_bytes!("../tests/fixtures/client.crt");
+    const CLIENT_KEY: &[u8] = include_bytes!("../tests/fixtures/client.key");
+    
+    #[test]
+    fn test_identity_from_pem() {
+        assert!(Identity::from_pem(CLIENT_CERT, CLIENT_KEY).is_ok());
+    }
+    
+    #[test]
+    fn test_identity_without_key() {
+        let result = Identity::from_pem_bundle(CLIENT_CERT);
+        assert!(matches!(result.unwrap_err(), Error::Tls(_)));
+    }
+    
+    #[test]
+    fn test_certificate_from_pem() {
+        let certificate = Certificate::from_pem(include_bytes!("../tests/fixtures/ca.crt")).unwrap();
+        assert!(!certificate.as_der().is_empty());
+        assert!(certificate.to_reqwest().is_ok());
+        
+        assert!(Certificate::from_pem(b"not a certificate").is_err());
+    }
+    
+    #[test]
+    fn test_certificate_pin() {
+        let certificate = Certificate::from_pem(CLIENT_CERT).unwrap();
+        let pin = CertificatePin::from_certificate(&certificate);
+        
+        let hex: Vec<String> = pin.0.iter().map(|byte| format!("{:02X}", byte)).collect();
+        assert_eq!(CertificatePin::from_sha256_hex(&hex.join(":")).unwrap(), pin);
+        assert_eq!(CertificatePin::from_sha256_hex(&hex.concat().to_lowercase()).unwrap(), pin);
+        
+        assert!(CertificatePin::from_sha256_hex("abcd").is_err());
+        assert!(CertificatePin::from_sha256_hex(&"zz".repeat(32)).is_err());
+    }
+    
+    #[test]
+    fn test_identity_to_reqwest() {
+        let identity = Identity::from_pem(CLIENT_CERT, CLIENT_KEY).unwrap();
+        assert!(identity.to_reqwest(TlsBackend::default()).is_ok());
+        
+        #[cfg(feature = "native-tls")]
+        assert!(identity.to_reqwest(TlsBackend::NativeTls).is_ok());
+    }
+    
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn test_pinned_config_with_identity() {
+        let identity = Identity::from_pem(CLIENT_CERT, CLIENT_KEY).unwrap();
+        let root = Certificate::from_pem(include_bytes!("../tests/fixtures/ca.crt")).unwrap();
+        let pin = CertificatePin::from_certificate(&root);
+        
+        let config = pinned_config(&[root], vec![pin], Some(&identity)).unwrap();
+        assert!(config.client_auth_cert_resolver.has_certs());
+    }
+    
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn test_pinned_verifier() {
+        let root = Certificate::from_pem(include_bytes!("../tests/fixtures/server-ca.crt")).unwrap();
+        let server = Certificate::from_pem(include_bytes!("../tests/fixtures/server.crt")).unwrap();
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(&rustls::Certificate(root.der.clone())).unwrap();
+        let verify = |pins: Vec<CertificatePin>| {
+            let verifier = PinnedVerifier {
+                inner: WebPkiVerifier::new(roots.clone(), None),
+                pins,
+            };
+            verifier.verify_server_cert(
+                &rustls::Certificate(server.der.clone()),
+                &[],
+                &rustls::ServerName::try_from("localhost").unwrap(),
+                &mut std::iter::empty(),
+                &[],
+                SystemTime::now(),
+            )
+        };
+        
+        assert!(verify(vec![CertificatePin::from_certificate(&server)]).is_ok());
+        // Pins apply to the end-entity certificate, not the rest of the chain
+        assert!(verify(vec![CertificatePin::from_certificate(&root)]).is_err());
+        assert!(verify(vec![CertificatePin([0; 32])]).is_err());
+    }
+    
+    #[cfg(feature = "pkcs12")]
+    #[test]
+    fn test_identity_from_pkcs12() {
+        let der = include_bytes!("../tests/fixtures/client.p12");
+        assert!(Identity::from_pkcs12_der(der, "test").is_ok());
+        assert!(matches!(Identity::from_pkcs12_der(der, "wrong").unwrap_err(), Error::Tls(_)));
+    }
+}
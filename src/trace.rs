@@ -0,0 +1,63 @@
+//! `tracing` spans for API calls
+
+use crate::error::Result;
+use crate::options::Endpoint;
+use crate::response::ApiResponse;
+use reqwest::Method;
+use std::time::Duration;
+use tracing::field::Empty;
+use tracing::Span;
+
+/// Span covering one API call, including all retries
+pub(crate) fn request_span(endpoint: Endpoint, method: &Method, url: &str, request_id: &str) -> Span {
+    tracing::info_span!(
+        "ecommerce_api.request",
+        endpoint = ?endpoint,
+        method = %method,
+        url = %url,
+        request_id = %request_id,
+        status = Empty,
+        attempts = Empty,
+        duration_ms = Empty,
+        error = Empty,
+    )
+}
+
+/// Span covering a single attempt of a call
+pub(crate) fn attempt_span(attempt: u32) -> Span {
+    tracing::debug_span!("ecommerce_api.attempt", attempt, status = Empty, error = Empty)
+}
+
+/// Record the outcome of a call or attempt on its span
+pub(crate) fn record<T>(span: &Span, result: &Result<ApiResponse<T>>, elapsed: Option<Duration>) {
+    let status = match result {
+        Ok(response) => response.status.map(|status| status.as_u16()),
+        Err(e) => e.status_code(),
+    };
+    if let Some(status) = status {
+        span.record("status", status);
+    }
+    if let Err(e) = result {
+        span.record("error", e.message().as_str());
+    }
+    if let Some(elapsed) = elapsed {
+        span.record("duration_ms", elapsed.as_millis() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    
+    #[test]
+    fn test_record() {
+        // Recording on a disabled span is a no-op, but must not panic
+        let span = request_span(Endpoint::CreateOrder, &Method::POST, "https://api.example.com/api_customer/orders", "req-1");
+        record(&span, &Err::<ApiResponse<()>, _>(Error::NotFound("missing".to_string())), Some(Duration::from_millis(12)));
+        
+        let mut response = ApiResponse::new(());
+        response.status = Some(reqwest::StatusCode::CREATED);
+        record(&attempt_span(1), &Ok(response), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Pluggable HTTP transport
+
+use crate::error::{Error, Result};
+use crate::{response, runtime};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Request handed to an [`HttpTransport`], with a fully buffered body
+pub type HttpRequest = http::Request<Vec<u8>>;
+
+/// Response returned by an [`HttpTransport`], with a fully buffered body
+pub type HttpResponse = http::Response<Vec<u8>>;
+
+/// Sends HTTP requests on behalf of the client
+///
+/// By default the client talks to the network through `reqwest` directly.
+/// Implement this trait to route requests through another HTTP stack, e.g.
+/// hyper with a custom connector, or an in-memory fake in tests. Retries,
+/// redirects, authentication and rate limiting still happen in the client;
+/// the transport only performs single exchanges.
+///
+/// Report failures with [`Error::transport`], or [`Error::transport_unsent`]
+/// when the request is known not to have left the client. When the client
+/// limits response sizes, requests carry a [`MaxResponseSize`] extension;
+/// stop reading a body that grows past it, so a runaway response is not
+/// buffered whole.
+///
+/// ```
+/// use ecommerce_api_client::transport::{HttpRequest, HttpResponse, HttpTransport};
+/// use ecommerce_api_client::{async_trait, http, Result};
+///
+/// struct Canned;
+///
+/// #[async_trait]
+/// impl HttpTransport for Canned {
+///     async fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+///         Ok(http::Response::builder().status(404).body(Vec::new()).unwrap())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait HttpTransport: Send + Sync + 'static {
+    /// Perform one HTTP exchange
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+/// Largest response body the client accepts, in bytes
+///
+/// Set in the extensions of the requests handed to an [`HttpTransport`]
+/// when the client has a [`max_response_size`](crate::Client::with_max_response_size).
+/// A body growing past it should fail with [`Error::ResponseTooLarge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxResponseSize(pub u64);
+
+impl MaxResponseSize {
+    /// The limit set on `request`, if any
+    pub fn of<B>(request: &http::Request<B>) -> Option<u64> {
+        request.extensions().get::<MaxResponseSize>().map(|limit| limit.0)
+    }
+}
+
+/// The default transport, also usable to wrap or decorate `reqwest`
+#[async_trait]
+impl HttpTransport for reqwest::Client {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let limit = MaxResponseSize::of(&request);
+        let request = reqwest::Request::try_from(request).map_err(Error::Http)?;
+        let response = self.execute(request).await.map_err(Error::from_send)?;
+        
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let body = response::read_body(response, limit).await?;
+        builder.body(body).map_err(Error::transport)
+    }
+}
+
+/// A custom transport together with the client-wide settings it cannot see
+#[derive(Clone)]
+pub(crate) struct Transport {
+    inner: Arc<dyn HttpTransport>,
+    default_headers: HeaderMap,
+    timeout: Option<Duration>,
+}
+
+impl Transport {
+    /// Wrap a transport, adding `default_headers` to every request and
+    /// enforcing `timeout` unless the request sets its own
+    pub(crate) fn new(inner: Arc<dyn HttpTransport>, default_headers: HeaderMap, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            default_headers,
+            timeout,
+        }
+    }
+    
+    /// Send a request assembled with `reqwest` through the transport
+    ///
+    /// `max_response_size` is passed on as a [`MaxResponseSize`] extension,
+    /// and also checked here for transports that ignore it.
+    pub(crate) async fn send(&self, request: reqwest::Request, max_response_size: Option<u64>) -> Result<reqwest::Response> {
+        let timeout = request.timeout().copied().or(self.timeout);
+        let mut request = self.to_http(request)?;
+        if let Some(limit) = max_response_size {
+            request.extensions_mut().insert(MaxResponseSize(limit));
+        }
+        
+        let response = match timeout {
+            Some(timeout) => runtime::timeout(timeout, self.inner.send(request))
+                .await
+                .ok_or_else(|| Error::transport("request timed out"))??,
+            None => self.inner.send(request).await?,
+        };
+        if let Some(limit) = max_response_size.filter(|limit| response.body().len() as u64 > *limit) {
+            return Err(Error::ResponseTooLarge(limit));
+        }
+        Ok(reqwest::Response::from(response))
+    }
+    
+    fn to_http(&self, request: reqwest::Request) -> Result<HttpRequest> {
+        let body = match request.body() {
+            Some(body) => body
+                .as_bytes()
+                .ok_or_else(|| Error::Config("streaming bodies are not supported by custom transports".to_string()))?
+                .to_vec(),
+            None => Vec::new(),
+        };
+        
+        let mut builder = http::Request::builder()
+            .method(request.method().clone())
+            .uri(request.url().as_str())
+            .version(request.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = request.headers().clone();
+            for (name, value) in &self.default_headers {
+                headers.entry(name).or_insert_with(|| value.clone());
+            }
+        }
+        builder.body(body).map_err(Error::transport)
+    }
+}
+
+impl fmt::Debug for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transport")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, ACCEPT, USER_AGENT};
+    use std::sync::Mutex;
+    
+    /// Answers every request with 204 and keeps the requests it saw
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<HttpRequest>>);
+    
+    #[async_trait]
+    impl HttpTransport for Arc<Recorder> {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.0.lock().unwrap().push(request);
+            Ok(http::Response::builder().status(204).body(Vec::new()).unwrap())
+        }
+    }
+    
+    struct Stalled;
+    
+    #[async_trait]
+    impl HttpTransport for Stalled {
+        async fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            std::future::pending().await
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_send_adds_default_headers() {
+        let recorder = Arc::new(Recorder::default());
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(USER_AGENT, HeaderValue::from_static("order-importer/2.1"));
+        let transport = Transport::new(Arc::new(recorder.clone()), default_headers, None);
+        
+        let request = reqwest::Client::new()
+            .post("https://api.example.com/api_customer/orders")
+            .header(ACCEPT, "application/json")
+            .body("{}")
+            .build()
+            .unwrap();
+        let response = transport.send(request, Some(1024)).await.unwrap();
+        assert_eq!(response.status(), 204);
+        
+        let requests = recorder.0.lock().unwrap();
+        assert_eq!(MaxResponseSize::of(&requests[0]), Some(1024));
+        assert_eq!(requests[0].uri(), "https://api.example.com/api_customer/orders");
+        assert_eq!(requests[0].headers()[USER_AGENT], "order-importer/2.1");
+        assert_eq!(requests[0].headers()[ACCEPT], "application/json");
+        assert_eq!(requests[0].body(), b"{}");
+    }
+    
+    #[tokio::test]
+    async fn test_send_times_out() {
+        let transport = Transport::new(Arc::new(Stalled), HeaderMap::new(), Some(Duration::from_secs(30)));
+        let request = reqwest::Client::new()
+            .get("https://api.example.com/api_customer/orders")
+            .timeout(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        
+        let error = transport.send(request, None).await.unwrap_err();
+        assert!(matches!(error, Error::Transport { unsent: false, .. }));
+        assert!(error.is_retryable());
+    }
+}
@@ -1,9 +1,10 @@
 //! Type-safe data structures for the e-commerce API
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Strongly typed order ID wrapper
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct OrderId(pub String);
 
 /// Strongly typed customer order reference wrapper  
@@ -83,6 +84,7 @@ impl Default for CreateOrderProduct {
 
 /// Request payload for creating an order
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename = "order")]
 pub struct CreateOrderRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub customer_order_reference: Option<String>,
@@ -111,6 +113,8 @@ pub struct Order {
     pub comments_customer: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub invoice_no: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Links>,
 }
 
 /// Order product information from API response
@@ -130,11 +134,128 @@ pub struct OrderProduct {
     pub updated_at: Option<String>,
 }
 
+/// Product from the catalogue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    pub id: u64,
+    pub product_code: ProductCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Links>,
+}
+
 /// Response payload from order creation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CreateOrderResponse {
     pub order: Order,
     pub order_products: Vec<OrderProduct>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Links>,
+}
+
+/// Outcome of [`Client::find_or_create_order`](crate::Client::find_or_create_order)
+#[derive(Debug, Clone, PartialEq)]
+pub enum FindOrCreate {
+    /// No order had the reference, so it was created
+    Created(Box<CreateOrderResponse>),
+    /// An order with the reference already existed and was left as it is
+    Existing(Box<Order>),
+}
+
+impl FindOrCreate {
+    /// The created or existing order
+    pub fn order(&self) -> &Order {
+        match self {
+            FindOrCreate::Created(response) => &response.order,
+            FindOrCreate::Existing(order) => order,
+        }
+    }
+    
+    /// Whether the order was created by this call
+    pub fn is_created(&self) -> bool {
+        matches!(self, FindOrCreate::Created(_))
+    }
+}
+
+/// Hypermedia link to a related resource
+///
+/// Accepts both the plain `"rel": "https://..."` form and the
+/// `"rel": { "href": "https://..." }` object form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "LinkRepr")]
+pub struct Link {
+    pub href: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl Link {
+    /// Create a link pointing at the given URL
+    pub fn new(href: impl Into<String>) -> Self {
+        Self {
+            href: href.into(),
+            method: None,
+            title: None,
+        }
+    }
+}
+
+/// Wire representations accepted for a link
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LinkRepr {
+    Href(String),
+    Object {
+        href: String,
+        #[serde(default)]
+        method: Option<String>,
+        #[serde(default)]
+        title: Option<String>,
+    },
+}
+
+impl From<LinkRepr> for Link {
+    fn from(repr: LinkRepr) -> Self {
+        match repr {
+            LinkRepr::Href(href) => Link::new(href),
+            LinkRepr::Object { href, method, title } => Link { href, method, title },
+        }
+    }
+}
+
+/// Links to resources related to a response
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Links {
+    #[serde(rename = "self", skip_serializing_if = "Option::is_none")]
+    pub self_link: Option<Link>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice: Option<Link>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipments: Option<Link>,
+    /// Any other relations sent by the server
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Link>,
+}
+
+impl Links {
+    /// Look up a link by relation name
+    pub fn get(&self, rel: &str) -> Option<&Link> {
+        match rel {
+            "self" => self.self_link.as_ref(),
+            "invoice" => self.invoice.as_ref(),
+            "shipments" => self.shipments.as_ref(),
+            _ => self.other.get(rel),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -307,6 +428,22 @@ mod tests {
         assert_eq!(serde_json::from_str::<ProductCode>("\"SKU-789\"").unwrap().0, "SKU-789");
     }
     
+    #[test]
+    fn test_deserialize_links() {
+        let json = r#"{
+            "self": "https://api.example.com/api_customer/orders/70",
+            "invoice": { "href": "/api_customer/orders/70/invoice", "method": "GET" },
+            "tracking": { "href": "/api_customer/orders/70/tracking" }
+        }"#;
+        
+        let links: Links = serde_json::from_str(json).unwrap();
+        assert_eq!(links.self_link.as_ref().unwrap().href, "https://api.example.com/api_customer/orders/70");
+        assert_eq!(links.invoice.as_ref().unwrap().method.as_deref(), Some("GET"));
+        assert!(links.shipments.is_none());
+        assert_eq!(links.get("tracking").unwrap().href, "/api_customer/orders/70/tracking");
+        assert_eq!(links.get("invoice"), links.invoice.as_ref());
+    }
+    
     #[test]
     fn test_address_default() {
         let address = Addressbook::default();
@@ -0,0 +1,53 @@
+//! Following an order until it reaches a final status
+//!
+//! [`Client::watch_order`](crate::Client::watch_order) polls one order and
+//! yields an [`OrderChange`] each time the API reports it differently, so a
+//! notification service only sees the updates that matter. Unchanged polls
+//! cost a bodiless `304 Not Modified` when the API sends an `ETag`.
+//! [`Client::long_poll_order`](crate::Client::long_poll_order) follows an
+//! order the same way through a long-poll endpoint, which holds each request
+//! until the order changes.
+
+use crate::diff::FieldChange;
+use crate::types::Order;
+use std::time::Duration;
+
+/// How a watched order is read
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Polling {
+    /// Ask again after this pause
+    Interval(Duration),
+    /// Ask the API to hold each request for up to this long, and ask again at once
+    LongPoll(Duration),
+}
+
+/// A new state of a watched order
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct OrderChange {
+    /// The state yielded before, `None` for the first one
+    pub previous: Option<Order>,
+    /// The order as the API now returns it
+    pub order: Order,
+    /// Fields that differ from `previous`, empty for the first state
+    pub changes: Vec<FieldChange>,
+}
+
+impl OrderChange {
+    /// The first state of the order, or a change of `order` against `previous`
+    pub(crate) fn new(previous: Option<Order>, order: Order) -> Self {
+        let changes = previous.as_ref().map(|previous| previous.diff(&order)).unwrap_or_default();
+        Self {
+            previous,
+            order,
+            changes,
+        }
+    }
+    
+    /// Whether the status differs from the previous state
+    pub fn status_changed(&self) -> bool {
+        self.previous
+            .as_ref()
+            .is_some_and(|previous| previous.status_order_id != self.order.status_order_id)
+    }
+}
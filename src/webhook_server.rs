@@ -0,0 +1,320 @@
+//! Embedded HTTP receiver for webhook callbacks (`webhook-server` feature)
+//!
+//! [`WebhookReceiver`] answers the API's callbacks: it checks the signature,
+//! parses the [`Event`] and hands it to an [`EventHandler`], so a small
+//! integration can consume webhooks without writing its own server. It runs
+//! its own listener with [`WebhookReceiver::serve`], or answers requests
+//! routed to it by an existing hyper server with [`WebhookReceiver::handle`].
+//!
+//! ```no_run
+//! # use ecommerce_api_client::webhooks::EventPayload;
+//! # use ecommerce_api_client::webhook_server::WebhookReceiver;
+//! # use ecommerce_api_client::CancellationToken;
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let (events, mut received) = tokio::sync::mpsc::channel(64);
+//! let receiver = WebhookReceiver::new(b"whsec_secret".to_vec(), events).path("/webhooks");
+//! let listener = std::net::TcpListener::bind("0.0.0.0:8080")?;
+//! tokio::spawn(receiver.serve(listener, CancellationToken::new()));
+//! while let Some(event) = received.recv().await {
+//!     if let EventPayload::OrderStatusChanged(change) = &event.payload {
+//!         println!("order {} is now in status {}", change.order_id, change.status_order_id);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::webhooks::{self, Event, DEFAULT_TOLERANCE};
+use async_trait::async_trait;
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use secrecy::{ExposeSecret, SecretVec};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Default limit on the size of a callback body
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Receives the events of verified callbacks
+///
+/// The callback is acknowledged once `handle` returns. An error answers
+/// `500 Internal Server Error`, so the API delivers the event again later.
+///
+/// ```
+/// use ecommerce_api_client::async_trait;
+/// use ecommerce_api_client::webhook_server::EventHandler;
+/// use ecommerce_api_client::webhooks::Event;
+///
+/// struct Print;
+///
+/// #[async_trait]
+/// impl EventHandler for Print {
+///     async fn handle(&self, event: Event) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+///         println!("{} {}", event.id, event.payload.event_type());
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait EventHandler: Send + Sync + 'static {
+    /// Process one event
+    async fn handle(&self, event: Event) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+#[async_trait]
+impl<H: EventHandler> EventHandler for Arc<H> {
+    async fn handle(&self, event: Event) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        (**self).handle(event).await
+    }
+}
+
+/// Waits for room in the channel; fails once the receiving side is dropped
+#[async_trait]
+impl EventHandler for mpsc::Sender<Event> {
+    async fn handle(&self, event: Event) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.send(event).await.map_err(|_| "event channel closed".into())
+    }
+}
+
+/// Fails once the receiving side is dropped
+#[async_trait]
+impl EventHandler for mpsc::UnboundedSender<Event> {
+    async fn handle(&self, event: Event) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.send(event).map_err(|_| "event channel closed".into())
+    }
+}
+
+/// Handler calling `f` with every event, see [`handler_fn`]
+#[derive(Debug, Clone)]
+pub struct FnHandler<F>(F);
+
+/// Handle events with a synchronous callback
+pub fn handler_fn<F>(f: F) -> FnHandler<F>
+where
+    F: Fn(Event) + Send + Sync + 'static,
+{
+    FnHandler(f)
+}
+
+#[async_trait]
+impl<F> EventHandler for FnHandler<F>
+where
+    F: Fn(Event) + Send + Sync + 'static,
+{
+    async fn handle(&self, event: Event) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        (self.0)(event);
+        Ok(())
+    }
+}
+
+/// Verifies callbacks and hands their events to an [`EventHandler`]
+///
+/// Requests are answered with:
+/// - `204 No Content` once the handler accepted the event
+/// - `400 Bad Request` if the body is not an event
+/// - `401 Unauthorized` if the signature is missing, wrong or too old
+/// - `404 Not Found` outside the configured [`path`](Self::path)
+/// - `405 Method Not Allowed` for anything but `POST`
+/// - `413 Payload Too Large` above the [`max_body_size`](Self::max_body_size)
+/// - `500 Internal Server Error` if the handler failed
+#[derive(Clone)]
+pub struct WebhookReceiver {
+    secret: Arc<SecretVec<u8>>,
+    handler: Arc<dyn EventHandler>,
+    path: Option<String>,
+    tolerance: Duration,
+    max_body_size: usize,
+}
+
+impl std::fmt::Debug for WebhookReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookReceiver")
+            .field("path", &self.path)
+            .field("tolerance", &self.tolerance)
+            .field("max_body_size", &self.max_body_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebhookReceiver {
+    /// Accept callbacks signed with `secret` on any path and pass them to `handler`
+    pub fn new(secret: impl Into<Vec<u8>>, handler: impl EventHandler) -> Self {
+        Self {
+            secret: Arc::new(SecretVec::new(secret.into())),
+            handler: Arc::new(handler),
+            path: None,
+            tolerance: DEFAULT_TOLERANCE,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+    
+    /// Only accept callbacks posted to `path`, e.g. `/webhooks`
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+    
+    /// Reject callbacks signed longer than `tolerance` ago (default [`DEFAULT_TOLERANCE`])
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+    
+    /// Reject bodies larger than `bytes` (default [`DEFAULT_MAX_BODY_SIZE`])
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = bytes;
+        self
+    }
+    
+    /// Answer one request, for routing callbacks from an existing hyper server
+    pub async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        if self.path.as_deref().is_some_and(|path| path != request.uri().path()) {
+            return status(StatusCode::NOT_FOUND);
+        }
+        if request.method() != Method::POST {
+            return status(StatusCode::METHOD_NOT_ALLOWED);
+        }
+        
+        let (parts, body) = request.into_parts();
+        let body = match read_body(body, self.max_body_size).await {
+            Ok(body) => body,
+            Err(code) => return status(code),
+        };
+        let secret = self.secret.expose_secret();
+        if webhooks::verify_signature_at(secret, &parts.headers, &body, SystemTime::now(), self.tolerance).is_err() {
+            return status(StatusCode::UNAUTHORIZED);
+        }
+        let event = match webhooks::parse_event(&body) {
+            Ok(event) => event,
+            Err(_) => return status(StatusCode::BAD_REQUEST),
+        };
+        match self.handler.handle(event).await {
+            Ok(()) => status(StatusCode::NO_CONTENT),
+            Err(_) => status(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+    
+    /// Answer callbacks on `listener` until `shutdown` is cancelled
+    ///
+    /// Requests already being handled are finished before returning.
+    pub async fn serve(self, listener: std::net::TcpListener, shutdown: CancellationToken) -> Result<()> {
+        let server = Server::from_tcp(listener).map_err(Error::transport)?;
+        let make_service = make_service_fn(move |_| {
+            let receiver = self.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let receiver = receiver.clone();
+                    async move { Ok::<_, Infallible>(receiver.handle(request).await) }
+                }))
+            }
+        });
+        server
+            .serve(make_service)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await
+            .map_err(Error::transport)
+    }
+}
+
+/// Collect `body`, giving up once it exceeds `limit` bytes
+async fn read_body(mut body: Body, limit: usize) -> std::result::Result<Vec<u8>, StatusCode> {
+    if body.size_hint().lower() > limit as u64 {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if bytes.len() + chunk.len() > limit {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn status(code: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = code;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::{sign, SIGNATURE_HEADER};
+    
+    const SECRET: &[u8] = b"whsec_test";
+    
+    fn body() -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "id": "evt_1",
+            "created_at": "2024-05-01T10:00:00Z",
+            "type": "order.status_changed",
+            "data": {"order_id": 42, "customer_order_reference": "ORDER-42", "status_order_id": 4},
+        }))
+        .unwrap()
+    }
+    
+    fn now() -> u64 {
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs()
+    }
+    
+    #[tokio::test]
+    async fn test_handle() {
+        let (events, mut received) = mpsc::unbounded_channel();
+        let receiver = WebhookReceiver::new(SECRET, events).path("/webhooks").max_body_size(1024);
+        let request = |path: &str, signature: String, body: Vec<u8>| {
+            Request::post(path).header(SIGNATURE_HEADER, signature).body(Body::from(body)).unwrap()
+        };
+        
+        let signed = sign(SECRET, now(), &body());
+        let response = receiver.handle(request("/webhooks", signed.clone(), body())).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(received.try_recv().unwrap().id, "evt_1");
+        
+        let forged = sign(b"other", now(), &body());
+        assert_eq!(receiver.handle(request("/webhooks", forged, body())).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(receiver.handle(request("/other", signed, body())).await.status(), StatusCode::NOT_FOUND);
+        let large = vec![b' '; 2048];
+        let signed = sign(SECRET, now(), &large);
+        assert_eq!(receiver.handle(request("/webhooks", signed, large)).await.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let signed = sign(SECRET, now(), b"[]");
+        assert_eq!(receiver.handle(request("/webhooks", signed, b"[]".to_vec())).await.status(), StatusCode::BAD_REQUEST);
+        assert!(received.try_recv().is_err());
+        
+        drop(received);
+        let signed = sign(SECRET, now(), &body());
+        let response = receiver.handle(request("/webhooks", signed, body())).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    
+    #[tokio::test]
+    async fn test_serve() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let (events, mut received) = mpsc::channel(1);
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(WebhookReceiver::new(SECRET, events).serve(listener, shutdown.clone()));
+        
+        let response = reqwest::Client::new()
+            .post(&url)
+            .header(SIGNATURE_HEADER, sign(SECRET, now(), &body()))
+            .body(body())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        assert!(matches!(
+            received.recv().await.unwrap().payload,
+            webhooks::EventPayload::OrderStatusChanged(change) if change.order_id == 42
+        ));
+        
+        shutdown.cancel();
+        server.await.unwrap().unwrap();
+    }
+}
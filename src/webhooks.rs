@@ -0,0 +1,456 @@
+//! Webhook events sent by the API, and verification of their signatures
+//!
+//! The API calls back with a JSON [`Event`] and signs each callback with
+//! HMAC-SHA256 over the timestamp and the raw body, using the secret shared
+//! when the webhook was registered. Receivers check the signature with
+//! [`verify_signature`] before trusting the body, or do both at once with
+//! [`verify_event`].
+//!
+//! ```
+//! # use ecommerce_api_client::webhooks::{self, EventPayload};
+//! # fn handle(headers: &http::HeaderMap, body: &[u8]) -> ecommerce_api_client::Result<()> {
+//! let event = webhooks::verify_event(b"whsec_secret", headers, body)?;
+//! if let EventPayload::OrderStatusChanged(change) = &event.payload {
+//!     println!("order {} is now in status {}", change.order_id, change.status_order_id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Header carrying the timestamp and signatures of a callback
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// How far a callback's timestamp may be from the receiver's clock
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// A webhook callback
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawEvent", into = "RawEvent")]
+#[non_exhaustive]
+pub struct Event {
+    /// Id of the event, the same for redeliveries of one event
+    pub id: String,
+    /// When the event happened, as RFC 3339
+    pub created_at: Option<String>,
+    /// What happened
+    pub payload: EventPayload,
+}
+
+/// What a webhook event reports, by its `type`
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum EventPayload {
+    /// An order moved to another status
+    OrderStatusChanged(OrderStatusChanged),
+    /// Goods of an order were handed to a carrier
+    ShipmentCreated(ShipmentCreated),
+    /// An order was invoiced
+    InvoiceIssued(InvoiceIssued),
+    /// A carrier reported progress on a shipment
+    TrackingUpdated(TrackingUpdated),
+    /// An event type this version of the client does not know
+    Unknown {
+        /// The event's `type`
+        event_type: String,
+        /// The event's `data`, as sent
+        data: serde_json::Value,
+    },
+}
+
+impl EventPayload {
+    /// The event's `type`, e.g. `order.status_changed`
+    pub fn event_type(&self) -> &str {
+        match self {
+            EventPayload::OrderStatusChanged(_) => "order.status_changed",
+            EventPayload::ShipmentCreated(_) => "shipment.created",
+            EventPayload::InvoiceIssued(_) => "invoice.issued",
+            EventPayload::TrackingUpdated(_) => "shipment.tracking_updated",
+            EventPayload::Unknown { event_type, .. } => event_type,
+        }
+    }
+}
+
+/// An event as sent, before its `data` is interpreted by `type`
+#[derive(Serialize, Deserialize)]
+struct RawEvent {
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<String>,
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+impl TryFrom<RawEvent> for Event {
+    type Error = serde_json::Error;
+    
+    fn try_from(raw: RawEvent) -> std::result::Result<Self, Self::Error> {
+        let payload = match raw.event_type.as_str() {
+            "order.status_changed" => EventPayload::OrderStatusChanged(serde_json::from_value(raw.data)?),
+            "shipment.created" => EventPayload::ShipmentCreated(serde_json::from_value(raw.data)?),
+            "invoice.issued" => EventPayload::InvoiceIssued(serde_json::from_value(raw.data)?),
+            "shipment.tracking_updated" => EventPayload::TrackingUpdated(serde_json::from_value(raw.data)?),
+            _ => EventPayload::Unknown {
+                event_type: raw.event_type,
+                data: raw.data,
+            },
+        };
+        Ok(Event {
+            id: raw.id,
+            created_at: raw.created_at,
+            payload,
+        })
+    }
+}
+
+impl From<Event> for RawEvent {
+    fn from(event: Event) -> Self {
+        let event_type = event.payload.event_type().to_string();
+        let data = match event.payload {
+            EventPayload::OrderStatusChanged(payload) => serde_json::to_value(payload),
+            EventPayload::ShipmentCreated(payload) => serde_json::to_value(payload),
+            EventPayload::InvoiceIssued(payload) => serde_json::to_value(payload),
+            EventPayload::TrackingUpdated(payload) => serde_json::to_value(payload),
+            EventPayload::Unknown { data, .. } => Ok(data),
+        };
+        RawEvent {
+            id: event.id,
+            created_at: event.created_at,
+            event_type,
+            data: data.unwrap_or_default(),
+        }
+    }
+}
+
+/// Payload of an `order.status_changed` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct OrderStatusChanged {
+    /// Id of the order
+    pub order_id: u64,
+    /// Customer order reference of the order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_order_reference: Option<String>,
+    /// Status before the change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_status_order_id: Option<u64>,
+    /// Status after the change
+    pub status_order_id: u64,
+}
+
+/// Payload of a `shipment.created` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ShipmentCreated {
+    /// Id of the shipment
+    pub shipment_id: String,
+    /// Id of the shipped order
+    pub order_id: u64,
+    /// Name of the carrier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<String>,
+    /// Carrier's tracking number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracking_number: Option<String>,
+    /// Page where the shipment can be tracked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracking_url: Option<String>,
+}
+
+/// Payload of an `invoice.issued` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct InvoiceIssued {
+    /// Number of the invoice
+    pub invoice_no: String,
+    /// Id of the invoiced order
+    pub order_id: u64,
+    /// Invoiced amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gross_total: Option<String>,
+    /// Currency of the amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+/// Payload of a `shipment.tracking_updated` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct TrackingUpdated {
+    /// Id of the shipment
+    pub shipment_id: String,
+    /// Id of the shipped order
+    pub order_id: u64,
+    /// Name of the carrier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<String>,
+    /// Carrier's tracking number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracking_number: Option<String>,
+    /// Where the shipment stands, whatever the carrier
+    #[serde(rename = "status")]
+    pub milestone: TrackingMilestone,
+    /// The carrier's own description of the scan, e.g. `Arrived at sort facility`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Where the scan happened
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// When the carrier scanned the shipment, as RFC 3339
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub occurred_at: Option<String>,
+}
+
+/// Progress of a shipment, normalized across carriers
+///
+/// Carriers spell their statuses differently; [`TrackingMilestone::normalize`]
+/// maps the common spellings onto the same milestones, so notifications can
+/// be written once for every carrier. Statuses it does not recognize are
+/// kept in [`Other`](Self::Other).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+#[non_exhaustive]
+pub enum TrackingMilestone {
+    /// The carrier collected the shipment
+    PickedUp,
+    /// The shipment is moving through the carrier's network
+    InTransit,
+    /// The shipment is on the vehicle for final delivery
+    OutForDelivery,
+    /// The shipment reached the recipient
+    Delivered,
+    /// Delivery failed or is held up, e.g. a missed delivery, damage or a customs hold
+    Exception,
+    /// A status this client does not recognize, as sent
+    Other(String),
+}
+
+impl TrackingMilestone {
+    /// The milestone a carrier status stands for, e.g. `IN-TRANSIT` or `collected`
+    ///
+    /// Case, spaces, hyphens and underscores are ignored.
+    pub fn normalize(status: &str) -> Self {
+        let key: String = status
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .flat_map(char::to_lowercase)
+            .collect();
+        match key.as_str() {
+            "pickedup" | "pickup" | "collected" | "accepted" | "shipped" => TrackingMilestone::PickedUp,
+            "intransit" | "transit" | "departed" | "arrived" | "processing" => TrackingMilestone::InTransit,
+            "outfordelivery" | "ondelivery" | "withcourier" => TrackingMilestone::OutForDelivery,
+            "delivered" | "deliveredtoneighbour" | "deliveredtoneighbor" | "pickedupbyrecipient" => TrackingMilestone::Delivered,
+            "exception" | "failedattempt" | "deliveryfailed" | "undeliverable" | "damaged" | "lost" | "customshold"
+            | "returntosender" | "returned" => TrackingMilestone::Exception,
+            _ => TrackingMilestone::Other(status.to_string()),
+        }
+    }
+
+    /// The canonical name of the milestone, e.g. `in_transit`
+    pub fn as_str(&self) -> &str {
+        match self {
+            TrackingMilestone::PickedUp => "picked_up",
+            TrackingMilestone::InTransit => "in_transit",
+            TrackingMilestone::OutForDelivery => "out_for_delivery",
+            TrackingMilestone::Delivered => "delivered",
+            TrackingMilestone::Exception => "exception",
+            TrackingMilestone::Other(status) => status,
+        }
+    }
+
+    /// Whether the shipment will not move any further
+    pub fn is_final(&self) -> bool {
+        matches!(self, TrackingMilestone::Delivered)
+    }
+}
+
+impl From<String> for TrackingMilestone {
+    fn from(status: String) -> Self {
+        TrackingMilestone::normalize(&status)
+    }
+}
+
+impl From<TrackingMilestone> for String {
+    fn from(milestone: TrackingMilestone) -> Self {
+        milestone.as_str().to_string()
+    }
+}
+
+/// Parse a callback body without checking its signature
+pub fn parse_event(body: &[u8]) -> Result<Event> {
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Check the signature of a callback, then parse its body
+pub fn verify_event(secret: &[u8], headers: &http::HeaderMap, body: &[u8]) -> Result<Event> {
+    verify_signature(secret, headers, body)?;
+    parse_event(body)
+}
+
+/// Check that a callback was signed with `secret` within [`DEFAULT_TOLERANCE`]
+///
+/// The [`SIGNATURE_HEADER`] has the form `t=<unix seconds>,v1=<hex>`, where
+/// the hex digest is the HMAC-SHA256 of `<t>.<body>`. While the secret is
+/// rotated the header carries one `v1` per secret, and any of them may
+/// match. Fails with [`Error::InvalidSignature`].
+pub fn verify_signature(secret: &[u8], headers: &http::HeaderMap, body: &[u8]) -> Result<()> {
+    verify_signature_at(secret, headers, body, SystemTime::now(), DEFAULT_TOLERANCE)
+}
+
+/// Check a signature as [`verify_signature`] does, at `now` with a custom `tolerance`
+pub fn verify_signature_at(
+    secret: &[u8],
+    headers: &http::HeaderMap,
+    body: &[u8],
+    now: SystemTime,
+    tolerance: Duration,
+) -> Result<()> {
+    let invalid = |reason: &str| Error::InvalidSignature(reason.to_string());
+    let header = headers
+        .get(SIGNATURE_HEADER)
+        .ok_or_else(|| invalid("missing signature header"))?
+        .to_str()
+        .map_err(|_| invalid("malformed signature header"))?;
+    
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<u64>().ok(),
+            Some(("v1", value)) => signatures.extend(decode_hex(value)),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| invalid("missing timestamp"))?;
+    let signed_at = UNIX_EPOCH
+        .checked_add(Duration::from_secs(timestamp))
+        .ok_or_else(|| invalid("timestamp out of range"))?;
+    let skew = now.duration_since(signed_at).unwrap_or_else(|e| e.duration());
+    if skew > tolerance {
+        return Err(invalid("timestamp outside the tolerance"));
+    }
+    
+    let matches = signatures
+        .iter()
+        .any(|signature| mac(secret, timestamp, body).verify_slice(signature).is_ok());
+    if matches {
+        Ok(())
+    } else {
+        Err(invalid("no matching signature"))
+    }
+}
+
+/// Signature header value for `body` signed with `secret` at `timestamp`, e.g. for testing receivers
+pub fn sign(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let digest = mac(secret, timestamp, body).finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("t={},v1={}", timestamp, hex)
+}
+
+/// HMAC of the signed content of a callback
+fn mac(secret: &[u8], timestamp: u64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Bytes of a hex string, or `None` if it is not valid hex
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_tracking_milestones() {
+        let milestones: Vec<_> = ["picked_up", "Collected", "in transit", "OUT_FOR_DELIVERY", "delivered", "Failed-Attempt", "label_created"]
+            .into_iter()
+            .map(TrackingMilestone::normalize)
+            .collect();
+        assert_eq!(milestones, [
+            TrackingMilestone::PickedUp,
+            TrackingMilestone::PickedUp,
+            TrackingMilestone::InTransit,
+            TrackingMilestone::OutForDelivery,
+            TrackingMilestone::Delivered,
+            TrackingMilestone::Exception,
+            TrackingMilestone::Other("label_created".to_string()),
+        ]);
+        assert!(milestones[4].is_final() && !milestones[5].is_final());
+        assert_eq!(serde_json::to_string(&TrackingMilestone::normalize("Transit")).unwrap(), r#""in_transit""#);
+    }
+    
+    const BODY: &str = r#"{"id":"evt_1","type":"order.status_changed","created_at":"2024-06-01T12:00:00Z","data":{"order_id":42,"previous_status_order_id":1,"status_order_id":3}}"#;
+    
+    fn headers(signature: &str) -> http::HeaderMap {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+    
+    #[test]
+    fn test_parse_events() {
+        let event = parse_event(BODY.as_bytes()).unwrap();
+        assert_eq!(event.id, "evt_1");
+        let EventPayload::OrderStatusChanged(change) = event.payload else {
+            panic!("unexpected payload");
+        };
+        assert_eq!((change.order_id, change.status_order_id), (42, 3));
+        
+        let shipment = r#"{"id":"evt_2","type":"shipment.created","data":{"shipment_id":"shp_1","order_id":42,"tracking_number":"1Z999"}}"#;
+        assert!(matches!(
+            parse_event(shipment.as_bytes()).unwrap().payload,
+            EventPayload::ShipmentCreated(ShipmentCreated { order_id: 42, .. })
+        ));
+        let tracking = r#"{"id":"evt_4","type":"shipment.tracking_updated","data":{"shipment_id":"shp_1","order_id":42,"carrier":"DHL","status":"IN-TRANSIT","location":"Leipzig"}}"#;
+        let EventPayload::TrackingUpdated(update) = parse_event(tracking.as_bytes()).unwrap().payload else {
+            panic!("unexpected payload");
+        };
+        assert_eq!((update.order_id, update.milestone), (42, TrackingMilestone::InTransit));
+        let unknown = r#"{"id":"evt_3","type":"customer.updated","data":{"customer_id":7}}"#;
+        let event = parse_event(unknown.as_bytes()).unwrap();
+        assert_eq!(event.payload.event_type(), "customer.updated");
+        assert_eq!(serde_json::to_string(&event).unwrap(), unknown);
+    }
+    
+    #[test]
+    fn test_verify_signature() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_717_243_200);
+        let signed = sign(b"secret", 1_717_243_200, BODY.as_bytes());
+        let verify = |headers: &http::HeaderMap, body: &str| {
+            verify_signature_at(b"secret", headers, body.as_bytes(), now, DEFAULT_TOLERANCE)
+        };
+        assert!(verify(&headers(&signed), BODY).is_ok());
+        
+        // One of several signatures may match while the secret is rotated
+        let rotated = format!("{},v1={}", sign(b"old", 1_717_243_200, BODY.as_bytes()), &signed[signed.find("v1=").unwrap() + 3..]);
+        assert!(verify(&headers(&rotated), BODY).is_ok());
+        
+        let tampered = BODY.replace("42", "43");
+        assert!(matches!(verify(&headers(&signed), &tampered), Err(Error::InvalidSignature(_))));
+        let stale = sign(b"secret", 1_717_243_200 - 301, BODY.as_bytes());
+        assert!(verify(&headers(&stale), BODY).is_err());
+        assert!(verify(&http::HeaderMap::new(), BODY).is_err());
+        assert_eq!(verify(&headers("t=1,v1=zz"), BODY).unwrap_err().kind(), crate::ErrorKind::Auth);
+        // Timestamps past what `SystemTime` can hold are rejected, not a panic
+        let overflow = format!("t={},v1=00", u64::MAX);
+        assert!(matches!(verify(&headers(&overflow), BODY), Err(Error::InvalidSignature(_))));
+    }
+}
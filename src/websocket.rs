@@ -0,0 +1,96 @@
+//! WebSocket feed of API events (`websocket` feature)
+//!
+//! Deployments that push events over a WebSocket are followed with
+//! [`Client::subscribe_websocket`](crate::Client::subscribe_websocket). Each
+//! text frame carries one event in the same JSON format as a webhook
+//! callback and is yielded as an [`ApiEvent`]. The connection is checked
+//! with pings while it is quiet and reopened with a growing delay when it
+//! is lost.
+
+use crate::error::Result;
+use crate::events::{ApiEvent, EventSource};
+use crate::webhooks;
+use std::time::Duration;
+
+/// Settings of [`Client::subscribe_websocket`](crate::Client::subscribe_websocket)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebSocketOptions {
+    pub(crate) url: Option<String>,
+    pub(crate) ping_interval: Duration,
+    pub(crate) pong_timeout: Duration,
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        Self {
+            url: None,
+            ping_interval: Duration::from_secs(30),
+            pong_timeout: Duration::from_secs(10),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl WebSocketOptions {
+    /// Connect to the events endpoint of the base URL, with default timings
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Connect to `url`, e.g. `wss://feed.example.com/events`, instead of the base URL
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+    
+    /// Send a ping after the connection was quiet this long (default 30 seconds)
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+    
+    /// Reconnect if nothing arrives this long after a ping (default 10 seconds)
+    pub fn pong_timeout(mut self, timeout: Duration) -> Self {
+        self.pong_timeout = timeout;
+        self
+    }
+    
+    /// Wait `initial` before reconnecting, doubling up to `max` while connecting fails
+    ///
+    /// Defaults to 1 second, up to a minute.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+    
+    /// Pause before reconnecting after `failures` failed attempts in a row
+    pub(crate) fn reconnect_delay(&self, failures: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(failures))
+            .min(self.max_backoff)
+    }
+}
+
+/// The event a text or binary frame carries
+pub(crate) fn parse_frame(frame: &[u8]) -> Result<ApiEvent> {
+    let mut event = ApiEvent::from(webhooks::parse_event(frame)?);
+    event.source = EventSource::WebSocket;
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_reconnect_delay() {
+        let options = WebSocketOptions::new().backoff(Duration::from_millis(100), Duration::from_secs(1));
+        let delays: Vec<_> = (0..6).map(|failures| options.reconnect_delay(failures).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(options.reconnect_delay(u32::MAX), Duration::from_secs(1));
+    }
+}